@@ -0,0 +1,313 @@
+/*!
+The `abuse_filter` module reads AbuseFilter filters (`list=abusefilters`) and their hits (`list=abuselog`).
+Fields the current user is not allowed to see are returned as `None`.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use futures::Stream;
+use serde_json::Value;
+
+/// An abuse filter, as returned by `list=abusefilters`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AbuseFilter {
+    id: u64,
+    description: Option<String>,
+    actions: Vec<String>,
+    hits: Option<u64>,
+    enabled: bool,
+    deleted: bool,
+    private: bool,
+}
+
+impl AbuseFilter {
+    /// Creates a new filter from a `list=abusefilters` result entry (`formatversion=2`)
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            id: j["id"].as_u64()?,
+            description: j["description"].as_str().map(|s| s.to_string()),
+            actions: split_list(&j["actions"]),
+            hits: j["hits"].as_u64(),
+            enabled: j["enabled"].as_bool().unwrap_or(false),
+            deleted: j["deleted"].as_bool().unwrap_or(false),
+            private: j["private"].as_bool().unwrap_or(false),
+        })
+    }
+
+    /// Returns the filter ID
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the public description of the filter
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Returns the actions taken when the filter matches (e.g. `disallow`, `tag`)
+    pub fn actions(&self) -> &[String] {
+        &self.actions
+    }
+
+    /// Returns the number of hits
+    pub fn hits(&self) -> Option<u64> {
+        self.hits
+    }
+
+    /// Returns true if the filter is enabled
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Returns true if the filter is deleted
+    pub fn deleted(&self) -> bool {
+        self.deleted
+    }
+
+    /// Returns true if the filter is private
+    pub fn private(&self) -> bool {
+        self.private
+    }
+}
+
+/// An abuse log entry, as returned by `list=abuselog`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AbuseLogEntry {
+    id: u64,
+    filter_id: Option<String>,
+    filter: Option<String>,
+    user: Option<String>,
+    title: Option<Title>,
+    action: Option<String>,
+    result: Vec<String>,
+    timestamp: Option<String>,
+    revid: Option<u64>,
+}
+
+impl AbuseLogEntry {
+    /// Creates a new entry from a `list=abuselog` result entry (`formatversion=2`)
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            id: j["id"].as_u64()?,
+            // Global filters have IDs like "global-5"
+            filter_id: match &j["filter_id"] {
+                Value::String(s) if !s.is_empty() => Some(s.to_string()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            },
+            filter: j["filter"].as_str().map(|s| s.to_string()),
+            user: j["user"].as_str().map(|s| s.to_string()),
+            title: j["title"].as_str().map(|_| Title::new_from_api_result(j)),
+            action: j["action"].as_str().map(|s| s.to_string()),
+            result: split_list(&j["result"]),
+            timestamp: j["timestamp"].as_str().map(|s| s.to_string()),
+            revid: j["revid"].as_u64(),
+        })
+    }
+
+    /// Returns the log entry ID
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the ID of the filter that matched
+    pub fn filter_id(&self) -> Option<&str> {
+        self.filter_id.as_deref()
+    }
+
+    /// Returns the description of the filter that matched
+    pub fn filter(&self) -> Option<&str> {
+        self.filter.as_deref()
+    }
+
+    /// Returns the user who triggered the filter
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Returns the title of the page the action was performed on
+    pub fn title(&self) -> Option<&Title> {
+        self.title.as_ref()
+    }
+
+    /// Returns the action that triggered the filter (e.g. `edit`)
+    pub fn action(&self) -> Option<&str> {
+        self.action.as_deref()
+    }
+
+    /// Returns the actions taken by the filter
+    pub fn result(&self) -> &[String] {
+        &self.result
+    }
+
+    /// Returns the timestamp
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Returns the revision ID of the edit, if it was saved
+    pub fn revid(&self) -> Option<u64> {
+        self.revid
+    }
+}
+
+/// Filters for `Api::abuse_log`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AbuseLogOptions {
+    filters: Vec<String>,
+    user: Option<String>,
+    title: Option<String>,
+    start: Option<String>,
+    end: Option<String>,
+}
+
+impl AbuseLogOptions {
+    /// Returns empty options, matching all log entries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only returns hits of these filter IDs (`aflfilter`)
+    pub fn set_filters(&mut self, filters: &[&str]) {
+        self.filters = filters.iter().map(|s| s.to_string()).collect();
+    }
+
+    /// Only returns hits by this user (`afluser`)
+    pub fn set_user(&mut self, user: Option<&str>) {
+        self.user = user.map(|s| s.to_string());
+    }
+
+    /// Only returns hits on this page, given as a full title (`afltitle`)
+    pub fn set_title(&mut self, title: Option<&str>) {
+        self.title = title.map(|s| s.to_string());
+    }
+
+    /// Only returns hits before this timestamp; the log is listed newest first (`aflstart`)
+    pub fn set_start(&mut self, start: Option<&str>) {
+        self.start = start.map(|s| s.to_string());
+    }
+
+    /// Only returns hits after this timestamp (`aflend`)
+    pub fn set_end(&mut self, end: Option<&str>) {
+        self.end = end.map(|s| s.to_string());
+    }
+
+    /// Returns the API parameters for these options
+    pub fn params(&self) -> Vec<(String, String)> {
+        let mut ret = vec![];
+        if !self.filters.is_empty() {
+            ret.push(("aflfilter".to_string(), self.filters.join("|")));
+        }
+        let optional = [
+            ("afluser", &self.user),
+            ("afltitle", &self.title),
+            ("aflstart", &self.start),
+            ("aflend", &self.end),
+        ];
+        for (key, value) in optional {
+            if let Some(value) = value {
+                ret.push((key.to_string(), value.to_string()));
+            }
+        }
+        ret
+    }
+}
+
+/// Splits a comma-separated list, as used for filter actions and results
+fn split_list(v: &Value) -> Vec<String> {
+    v.as_str()
+        .map(|s| {
+            s.split(',')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl Api {
+    /// Returns a stream of all abuse filters, via `list=abusefilters`
+    pub async fn abuse_filters(
+        &self,
+    ) -> impl Stream<Item = Result<AbuseFilter, MediaWikiError>> + '_ {
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("list", "abusefilters"),
+            (
+                "abfprop",
+                "id|description|actions|hits|status|private|lastedittime",
+            ),
+            ("abflimit", "max"),
+            ("formatversion", "2"),
+        ]);
+        self.get_query_api_list_iter(&params, "abusefilters", AbuseFilter::from_json)
+            .await
+    }
+
+    /// Returns a stream of abuse log entries matching `options`, newest first, via `list=abuselog`
+    pub async fn abuse_log(
+        &self,
+        options: &AbuseLogOptions,
+    ) -> impl Stream<Item = Result<AbuseLogEntry, MediaWikiError>> + '_ {
+        let mut params = self.params_into(&[
+            ("action", "query"),
+            ("list", "abuselog"),
+            (
+                "aflprop",
+                "ids|filter|user|title|action|result|timestamp|revid",
+            ),
+            ("afllimit", "max"),
+            ("formatversion", "2"),
+        ]);
+        params.extend(options.params());
+        self.get_query_api_list_iter(&params, "abuselog", AbuseLogEntry::from_json)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abuse_filter_from_json() {
+        let filter = AbuseFilter::from_json(&json!({"id":12,"description":"Page blanking","actions":"disallow,tag","hits":345,"enabled":true,"deleted":false,"private":false})).unwrap();
+        assert_eq!(filter.id(), 12);
+        assert_eq!(filter.description(), Some("Page blanking"));
+        assert_eq!(
+            filter.actions(),
+            &["disallow".to_string(), "tag".to_string()]
+        );
+        assert!(filter.enabled());
+        assert!(!filter.private());
+        let hidden = AbuseFilter::from_json(&json!({"id":13,"private":true})).unwrap();
+        assert_eq!(hidden.description(), None);
+        assert!(hidden.actions().is_empty());
+    }
+
+    #[test]
+    fn abuse_log_entry_from_json() {
+        let entry = AbuseLogEntry::from_json(&json!({"id":1000,"filter_id":"12","filter":"Page blanking","user":"Foo","ns":0,"title":"Bar","action":"edit","result":"","timestamp":"2024-01-01T00:00:00Z"})).unwrap();
+        assert_eq!(entry.filter_id(), Some("12"));
+        assert_eq!(entry.title(), Some(&Title::new("Bar", 0)));
+        assert!(entry.result().is_empty());
+        assert_eq!(entry.revid(), None);
+    }
+
+    #[test]
+    fn abuse_log_options_params() {
+        let mut options = AbuseLogOptions::new();
+        options.set_filters(&["12", "global-5"]);
+        options.set_user(Some("Foo"));
+        assert_eq!(
+            options.params(),
+            vec![
+                ("aflfilter".to_string(), "12|global-5".to_string()),
+                ("afluser".to_string(), "Foo".to_string())
+            ]
+        );
+    }
+}
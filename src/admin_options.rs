@@ -0,0 +1,256 @@
+/*!
+The `admin_options` module exposes the wiki's configured deletion and protection reasons, protection levels
+and protection durations, e.g. to populate dropdowns in admin tools.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A group of reasons in a dropdown system message
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReasonGroup {
+    name: Option<String>,
+    reasons: Vec<String>,
+}
+
+impl ReasonGroup {
+    /// Returns the group name; `None` for reasons before the first group
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the reasons in the group
+    pub fn reasons(&self) -> &[String] {
+        &self.reasons
+    }
+}
+
+/// A protection duration option, from `MediaWiki:Protect-expiry-options`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiryOption {
+    label: String,
+    value: String,
+}
+
+impl ExpiryOption {
+    /// Returns the label to display
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the expiry value to pass to the API
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
+
+/// Protection types and levels, from siteinfo `restrictions`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Restrictions {
+    types: Vec<String>,
+    levels: Vec<String>,
+    cascading_levels: Vec<String>,
+    semiprotected_levels: Vec<String>,
+}
+
+impl Restrictions {
+    /// Creates the restrictions from a siteinfo result with `siprop=restrictions`
+    pub fn from_json(j: &Value) -> Self {
+        let r = &j["query"]["restrictions"];
+        Self {
+            types: string_array(&r["types"]),
+            levels: string_array(&r["levels"]),
+            cascading_levels: string_array(&r["cascadinglevels"]),
+            semiprotected_levels: string_array(&r["semiprotectedlevels"]),
+        }
+    }
+
+    /// Returns the protectable actions, e.g. `edit`, `move`
+    pub fn types(&self) -> &[String] {
+        &self.types
+    }
+
+    /// Returns the protection levels; the empty level means unprotected
+    pub fn levels(&self) -> &[String] {
+        &self.levels
+    }
+
+    /// Returns the levels that allow cascading protection
+    pub fn cascading_levels(&self) -> &[String] {
+        &self.cascading_levels
+    }
+
+    /// Returns the levels considered semi-protection
+    pub fn semiprotected_levels(&self) -> &[String] {
+        &self.semiprotected_levels
+    }
+
+    /// Checks if `kind` can be protected at `level` on this wiki
+    pub fn is_valid(&self, kind: &str, level: &str) -> bool {
+        self.types.iter().any(|t| t == kind) && self.levels.iter().any(|l| l == level)
+    }
+}
+
+/// Returns the strings in a JSON array
+fn string_array(v: &Value) -> Vec<String> {
+    v.as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parses a dropdown system message like `MediaWiki:Deletereason-dropdown`,
+/// where `*` lines start a group and `**` lines are reasons.
+pub fn parse_dropdown(text: &str) -> Vec<ReasonGroup> {
+    let mut ret: Vec<ReasonGroup> = vec![];
+    for line in text.lines().map(|l| l.trim()) {
+        if let Some(reason) = line.strip_prefix("**") {
+            let reason = reason.trim();
+            if reason.is_empty() {
+                continue;
+            }
+            if ret.is_empty() {
+                ret.push(ReasonGroup::default());
+            }
+            if let Some(group) = ret.last_mut() {
+                group.reasons.push(reason.to_string());
+            }
+        } else if let Some(name) = line.strip_prefix('*') {
+            ret.push(ReasonGroup {
+                name: Some(name.trim().to_string()),
+                reasons: vec![],
+            });
+        }
+    }
+    ret
+}
+
+/// Parses `MediaWiki:Protect-expiry-options`, a comma-separated list of `label:value` pairs
+pub fn parse_expiry_options(text: &str) -> Vec<ExpiryOption> {
+    text.split(',')
+        .filter_map(|option| {
+            let (label, value) = option.split_once(':').unwrap_or((option, option));
+            let (label, value) = (label.trim(), value.trim());
+            match label.is_empty() || value.is_empty() {
+                true => None,
+                false => Some(ExpiryOption {
+                    label: label.to_string(),
+                    value: value.to_string(),
+                }),
+            }
+        })
+        .collect()
+}
+
+impl Api {
+    /// Returns the raw content of system messages, via `meta=allmessages`
+    pub async fn system_messages(
+        &self,
+        names: &[&str],
+    ) -> Result<HashMap<String, String>, MediaWikiError> {
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("meta", "allmessages"),
+            ("ammessages", &names.join("|")),
+            ("formatversion", "2"),
+        ]);
+        let result = self.get_query_api_json(&params).await?;
+        Ok(result["query"]["allmessages"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|m| {
+                        Some((
+                            m["name"].as_str()?.to_string(),
+                            m["content"].as_str()?.to_string(),
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn system_message(&self, name: &str) -> Result<String, MediaWikiError> {
+        Ok(self
+            .system_messages(&[name])
+            .await?
+            .into_iter()
+            .next()
+            .map(|(_, content)| content)
+            .unwrap_or_default())
+    }
+
+    /// Returns the deletion reasons of `MediaWiki:Deletereason-dropdown`
+    pub async fn delete_reasons(&self) -> Result<Vec<ReasonGroup>, MediaWikiError> {
+        Ok(parse_dropdown(
+            &self.system_message("deletereason-dropdown").await?,
+        ))
+    }
+
+    /// Returns the protection reasons of `MediaWiki:Protect-dropdown`
+    pub async fn protect_reasons(&self) -> Result<Vec<ReasonGroup>, MediaWikiError> {
+        Ok(parse_dropdown(
+            &self.system_message("protect-dropdown").await?,
+        ))
+    }
+
+    /// Returns the protection durations of `MediaWiki:Protect-expiry-options`
+    pub async fn protection_expiry_options(&self) -> Result<Vec<ExpiryOption>, MediaWikiError> {
+        Ok(parse_expiry_options(
+            &self.system_message("protect-expiry-options").await?,
+        ))
+    }
+
+    /// Returns the protection types and levels, from the site info
+    pub fn restrictions(&self) -> Restrictions {
+        Restrictions::from_json(self.get_site_info())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropdown() {
+        let groups = parse_dropdown(
+            "** Orphan\n* Common reasons\n** [[WP:G1|G1]]: Nonsense\n**Vandalism\n*Other\n",
+        );
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups[0].name(), None);
+        assert_eq!(groups[0].reasons(), &["Orphan".to_string()]);
+        assert_eq!(groups[1].name(), Some("Common reasons"));
+        assert_eq!(groups[1].reasons().len(), 2);
+        assert_eq!(groups[1].reasons()[1], "Vandalism");
+        assert!(groups[2].reasons().is_empty());
+    }
+
+    #[test]
+    fn expiry_options() {
+        let options = parse_expiry_options("1 hour:1 hour,1 week:1 week,indefinite:infinite,");
+        assert_eq!(options.len(), 3);
+        assert_eq!(options[2].label(), "indefinite");
+        assert_eq!(options[2].value(), "infinite");
+    }
+
+    #[test]
+    fn restrictions() {
+        let r = Restrictions::from_json(&json!({"query":{"restrictions":{
+            "types":["create","edit","move","upload"],
+            "levels":["","autoconfirmed","sysop"],
+            "cascadinglevels":["sysop"],
+            "semiprotectedlevels":["autoconfirmed"]
+        }}}));
+        assert!(r.is_valid("edit", "sysop"));
+        assert!(!r.is_valid("read", "sysop"));
+        assert_eq!(r.cascading_levels(), &["sysop".to_string()]);
+    }
+}
@@ -4,7 +4,24 @@ The `Api` class serves as a universal interface to a MediaWiki API.
 
 #![deny(missing_docs)]
 
+use crate::cancellation::CancellationToken;
+use crate::continuation::Continuation;
+use crate::cookie_jar::CookieJar;
+use crate::edit_guard::DuplicateEditGuard;
+use crate::edit_summary::EditSummary;
+use crate::events::{ApiEvent, EventLog, EventObserver, EventObservers, RecordedEvent};
+use crate::hooks::Hooks;
+use crate::language::LanguageCache;
+use crate::login::LoginError;
 use crate::media_wiki_error::MediaWikiError;
+use crate::metrics::{Metrics, MetricsSink};
+use crate::mock::MockResponses;
+use crate::oauth2::OAuth2Client;
+use crate::rate_limit::{server_rate_limits, RateLimit, RateLimitKind, RateLimiter};
+use crate::relogin::Credentials;
+use crate::session::Session;
+use crate::site_health::{HealthPolicy, SiteHealth};
+use crate::site_info::SiteInfo;
 use crate::title::Title;
 use crate::user::User;
 use base64::prelude::*;
@@ -15,8 +32,11 @@ use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::StatusCode;
 use serde_json::Value;
 use std::collections::HashMap;
-use std::fmt::Write;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::fmt::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use url::Url;
 
 /// Alias for a namespace (could be -1 for Special pages etc.)
@@ -25,11 +45,117 @@ pub type NamespaceID = i64;
 const DEFAULT_USER_AGENT: &str = "Rust mediawiki API";
 const DEFAULT_MAXLAG: Option<u64> = Some(5);
 const DEFAULT_MAX_RETRY_ATTEMPTS: u64 = 5;
-const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+const DEFAULT_MAX_RELOGIN_ATTEMPTS: u64 = 1;
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
 const DEFAULT_DELAY_FOR_TOO_MANY_REQUESTS: u64 = 30;
+const DEFAULT_DELAY_FOR_RATELIMITED: Duration = Duration::from_secs(30);
 
 type HmacSha1 = Hmac<sha1::Sha1>;
 
+/// Types of tokens, for `meta=tokens`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenType {
+    /// Token for editing and most other write actions
+    Csrf,
+    /// Token for logging in
+    Login,
+    /// Token for watching and unwatching pages
+    Watch,
+    /// Token for patrolling changes
+    Patrol,
+    /// Token for rolling back edits
+    Rollback,
+    /// Token for changing user rights
+    UserRights,
+    /// Token for creating accounts
+    CreateAccount,
+}
+
+impl TokenType {
+    /// Returns the token type as used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Csrf => "csrf",
+            Self::Login => "login",
+            Self::Watch => "watch",
+            Self::Patrol => "patrol",
+            Self::Rollback => "rollback",
+            Self::UserRights => "userrights",
+            Self::CreateAccount => "createaccount",
+        }
+    }
+}
+
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TokenType {
+    type Err = MediaWikiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csrf" => Ok(Self::Csrf),
+            "login" => Ok(Self::Login),
+            "watch" => Ok(Self::Watch),
+            "patrol" => Ok(Self::Patrol),
+            "rollback" => Ok(Self::Rollback),
+            "userrights" => Ok(Self::UserRights),
+            "createaccount" => Ok(Self::CreateAccount),
+            other => Err(MediaWikiError::String(format!(
+                "Unknown token type: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// Levels for the `assert` parameter, which makes the API fail if the user is not as expected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AssertLevel {
+    /// The user must be logged in
+    User,
+    /// The user must have the bot right
+    Bot,
+    /// The user must not be logged in
+    Anon,
+}
+
+impl AssertLevel {
+    /// Returns the assert level as used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Bot => "bot",
+            Self::Anon => "anon",
+        }
+    }
+}
+
+impl fmt::Display for AssertLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AssertLevel {
+    type Err = MediaWikiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "user" => Ok(Self::User),
+            "bot" => Ok(Self::Bot),
+            "anon" => Ok(Self::Anon),
+            other => Err(MediaWikiError::String(format!(
+                "Unknown assert level: {}",
+                other
+            ))),
+        }
+    }
+}
+
 /// `OAuthParams` contains parameters for OAuth requests
 #[derive(Debug, Clone)]
 pub struct OAuthParams {
@@ -71,19 +197,116 @@ impl OAuthParams {
     }
 }
 
-/// `Api` is the main class to interact with a MediaWiki API
+/// `BasicAuth` contains HTTP basic auth credentials, for wikis behind a password-protected web server.
+/// The password is redacted in `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct BasicAuth {
+    user: String,
+    password: String,
+}
+
+impl BasicAuth {
+    /// Creates new basic auth credentials
+    pub fn new<S: Into<String>>(user: S, password: S) -> Self {
+        Self {
+            user: user.into(),
+            password: password.into(),
+        }
+    }
+
+    /// Returns the user name
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    /// Returns the `Authorization` header value, marked as sensitive
+    fn header_value(&self) -> Result<HeaderValue, MediaWikiError> {
+        let credentials = BASE64_STANDARD.encode(format!("{}:{}", self.user, self.password));
+        let mut value = HeaderValue::from_str(&format!("Basic {}", credentials))?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
+}
+
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("user", &self.user)
+            .field("password", &"[redacted]")
+            .finish()
+    }
+}
+
+/// OAuth credentials, changed through `&Api`
+#[derive(Debug, Clone, Default)]
+struct AuthState {
+    oauth: Option<OAuthParams>,
+    oauth2: Option<String>,
+    oauth2_client: Option<OAuth2Client>,
+}
+
+/// A file field of a `multipart/form-data` request, e.g. for `action=upload`
+struct FilePart<'a> {
+    field: &'a str,
+    name: &'a str,
+    data: Vec<u8>,
+}
+
+/// Locks for reading, ignoring poisoning; the protected state is always consistent
+pub(crate) fn read_lock<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Locks for writing, ignoring poisoning; the protected state is always consistent
+pub(crate) fn write_lock<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// `Api` is the main class to interact with a MediaWiki API.
+///
+/// Session state (user, tokens, OAuth credentials, cookies) uses interior mutability,
+/// so logging in and editing work through `&Api`, and an `Api` can be shared as `Arc<Api>`.
+/// Clones of an `Api` share the session.
 #[derive(Debug, Clone)]
 pub struct Api {
     api_url: String,
-    site_info: Value,
+    site_info: SiteInfo,
     client: reqwest::Client,
-    user: User,
+    user: Arc<RwLock<User>>,
     user_agent: String,
+    edit_summary: EditSummary,
+    edit_tags: Vec<String>,
     maxlag_seconds: Option<u64>,
     edit_delay_ms: Option<u64>,
     max_retry_attempts: u64,
-    oauth: Option<OAuthParams>,
-    oauth2: Option<String>,
+    max_read_retry_attempts: u64,
+    maxlag_on_reads: bool,
+    dry_run: bool,
+    dry_run_requests: Arc<Mutex<Vec<HashMap<String, String>>>>,
+    auth: Arc<RwLock<AuthState>>,
+    tokens: Arc<RwLock<HashMap<String, String>>>,
+    assert_level: Option<AssertLevel>,
+    credentials: Option<Credentials>,
+    max_relogin_attempts: u64,
+    raise_api_errors: bool,
+    rate_limiter: RateLimiter,
+    honor_server_rate_limits: bool,
+    duplicate_edit_guard: Option<Arc<Mutex<DuplicateEditGuard>>>,
+    cookie_jar: Arc<CookieJar>,
+    logged_out: Arc<AtomicBool>,
+    basic_auth: Option<BasicAuth>,
+    ratelimit_delay: Duration,
+    event_observers: EventObservers,
+    hooks: Hooks,
+    metrics: Metrics,
+    cancellation: Option<CancellationToken>,
+    mock: Option<MockResponses>,
+    event_log: Arc<Mutex<EventLog>>,
+    default_language: Option<String>,
+    default_variant: Option<String>,
+    language_cache: LanguageCache,
+    site_health: Arc<Mutex<SiteHealth>>,
+    health_policy: Option<HealthPolicy>,
 }
 
 impl Api {
@@ -108,40 +331,165 @@ impl Api {
         api_url: &str,
         builder: reqwest::ClientBuilder,
     ) -> Result<Api, MediaWikiError> {
-        let mut ret = Api {
+        Api::new_from_builder_with_basic_auth(api_url, builder, None).await
+    }
+
+    /// Returns a new `Api` element for a wiki behind HTTP basic auth.
+    /// The credentials are sent with every request to the wiki, including the initial site info request,
+    /// unless OAuth is used.
+    pub async fn new_with_basic_auth(
+        api_url: &str,
+        basic_auth: BasicAuth,
+    ) -> Result<Api, MediaWikiError> {
+        Api::new_from_builder_with_basic_auth(
+            api_url,
+            reqwest::Client::builder().timeout(DEFAULT_TIMEOUT),
+            Some(basic_auth),
+        )
+        .await
+    }
+
+    /// Returns a new `Api` element, using a bespoke reqwest::ClientBuilder and optional
+    /// HTTP basic auth credentials.
+    pub async fn new_from_builder_with_basic_auth(
+        api_url: &str,
+        builder: reqwest::ClientBuilder,
+        basic_auth: Option<BasicAuth>,
+    ) -> Result<Api, MediaWikiError> {
+        let mut ret = Api::new_without_site_info(api_url, builder, basic_auth)?;
+        ret.load_site_info().await?;
+        Ok(ret)
+    }
+
+    /// Returns a new `Api` element from site info loaded earlier, e.g. via `site_info_json()`,
+    /// without any network request
+    pub fn new_from_site_info(api_url: &str, site_info: &str) -> Result<Api, MediaWikiError> {
+        let mut ret = Api::new_without_site_info(
+            api_url,
+            reqwest::Client::builder().timeout(DEFAULT_TIMEOUT),
+            None,
+        )?;
+        ret.set_site_info(serde_json::from_str(site_info)?);
+        Ok(ret)
+    }
+
+    /// Returns a new `Api` element, without loading the site info
+    pub(crate) fn new_without_site_info(
+        api_url: &str,
+        builder: reqwest::ClientBuilder,
+        basic_auth: Option<BasicAuth>,
+    ) -> Result<Api, MediaWikiError> {
+        let cookie_jar = Arc::new(CookieJar::new());
+        Ok(Api {
             api_url: api_url.to_string(),
-            site_info: serde_json::from_str(r"{}")?,
-            client: builder.cookie_store(true).build()?,
-            user: User::new(),
+            site_info: SiteInfo::default(),
+            client: builder.cookie_provider(cookie_jar.clone()).build()?,
+            user: Arc::new(RwLock::new(User::new())),
             user_agent: DEFAULT_USER_AGENT.to_string(),
+            edit_summary: EditSummary::default(),
+            edit_tags: vec![],
             maxlag_seconds: DEFAULT_MAXLAG,
             max_retry_attempts: DEFAULT_MAX_RETRY_ATTEMPTS,
+            max_read_retry_attempts: DEFAULT_MAX_RETRY_ATTEMPTS,
+            maxlag_on_reads: false,
+            dry_run: false,
+            dry_run_requests: Arc::new(Mutex::new(vec![])),
             edit_delay_ms: None,
-            oauth: None,
-            oauth2: None,
-        };
-        ret.load_site_info().await?;
+            auth: Arc::new(RwLock::new(AuthState::default())),
+            tokens: Arc::new(RwLock::new(HashMap::new())),
+            assert_level: None,
+            credentials: None,
+            max_relogin_attempts: DEFAULT_MAX_RELOGIN_ATTEMPTS,
+            raise_api_errors: false,
+            rate_limiter: RateLimiter::new(),
+            honor_server_rate_limits: true,
+            duplicate_edit_guard: None,
+            cookie_jar,
+            logged_out: Arc::new(AtomicBool::new(false)),
+            basic_auth,
+            ratelimit_delay: DEFAULT_DELAY_FOR_RATELIMITED,
+            event_observers: EventObservers::default(),
+            hooks: Hooks::default(),
+            metrics: Metrics::default(),
+            cancellation: None,
+            mock: None,
+            event_log: Arc::new(Mutex::new(EventLog::default())),
+            default_language: None,
+            default_variant: None,
+            language_cache: LanguageCache::default(),
+            site_health: Arc::new(Mutex::new(SiteHealth::default())),
+            health_policy: None,
+        })
+    }
+
+    /// Returns a new `Api` element that serves canned responses instead of querying a wiki, for tests.
+    /// The site info is taken from a `meta=siteinfo` response, if there is one.
+    pub fn new_mocked(responses: MockResponses) -> Result<Api, MediaWikiError> {
+        let site_info_params = hashmap!["action".to_string()=>"query".to_string(),"meta".to_string()=>"siteinfo".to_string()];
+        let mut ret = Api::new_without_site_info(
+            "https://mock.invalid/w/api.php",
+            reqwest::Client::builder(),
+            None,
+        )?;
+        ret.site_info = SiteInfo::from_json(
+            responses
+                .response_for(&site_info_params)
+                .unwrap_or_else(|| serde_json::json!({})),
+        );
+        ret.maxlag_seconds = None;
+        ret.mock = Some(responses);
         Ok(ret)
     }
 
+    /// Returns the canned responses, if this `Api` was created by `new_mocked`
+    pub(crate) fn mock(&self) -> Option<&MockResponses> {
+        self.mock.as_ref()
+    }
+
     /// Returns the API url
     pub fn api_url(&self) -> &str {
         &self.api_url
     }
 
+    /// Returns the HTTP basic auth credentials, if set
+    pub fn basic_auth(&self) -> Option<&BasicAuth> {
+        self.basic_auth.as_ref()
+    }
+
     /// Sets the OAuth parameters
-    pub fn set_oauth(&mut self, oauth: Option<OAuthParams>) {
-        self.oauth = oauth;
+    pub fn set_oauth(&self, oauth: Option<OAuthParams>) {
+        if oauth.is_some() {
+            self.logged_out.store(false, Ordering::SeqCst);
+        }
+        write_lock(&self.auth).oauth = oauth;
     }
 
     /// Set an OAuth 2 access token
-    pub fn set_oauth2(&mut self, oauth2: &str) {
-        self.oauth2 = Some(oauth2.to_string());
+    pub fn set_oauth2(&self, oauth2: &str) {
+        self.logged_out.store(false, Ordering::SeqCst);
+        write_lock(&self.auth).oauth2 = Some(oauth2.to_string());
+    }
+
+    /// Returns the owner-only OAuth 2 client, if set via `set_oauth2_client`
+    pub fn oauth2_client(&self) -> Option<OAuth2Client> {
+        read_lock(&self.auth).oauth2_client.clone()
+    }
+
+    /// Sets the owner-only OAuth 2 client
+    pub(crate) fn set_oauth2_client_ref(&self, client: Option<OAuth2Client>) {
+        self.logged_out.store(false, Ordering::SeqCst);
+        write_lock(&self.auth).oauth2_client = client;
     }
 
-    /// Returns a reference to the current OAuth parameters
-    pub fn oauth(&self) -> &Option<OAuthParams> {
-        &self.oauth
+    /// Returns the current OAuth parameters
+    pub fn oauth(&self) -> Option<OAuthParams> {
+        read_lock(&self.auth).oauth.clone()
+    }
+
+    /// Returns true if requests are authorized via OAuth 1 or OAuth 2, rather than session cookies
+    pub(crate) fn uses_oauth(&self) -> bool {
+        let auth = read_lock(&self.auth);
+        auth.oauth.is_some() || auth.oauth2.is_some() || auth.oauth2_client.is_some()
     }
 
     /// Returns a reference to the reqwest client
@@ -154,37 +502,102 @@ impl Api {
         &mut self.client
     }
 
-    /// Returns a reference to the current user object
-    pub fn user(&self) -> &User {
-        &self.user
+    /// Returns a reference to the cookie jar of the reqwest client
+    pub fn cookie_jar(&self) -> &Arc<CookieJar> {
+        &self.cookie_jar
+    }
+
+    /// Returns the session (cookies, tokens and user) as JSON, to be restored with `restore_session`.
+    /// The session is as sensitive as a password.
+    pub fn save_session(&self) -> Result<String, MediaWikiError> {
+        Session {
+            api_url: self.api_url.clone(),
+            cookies: self.cookie_jar.to_json()?,
+            tokens: read_lock(&self.tokens).clone(),
+            user: self.user().clone(),
+        }
+        .to_json()
     }
 
-    /// Returns a mutable reference to the current user object
-    pub fn user_mut(&mut self) -> &mut User {
-        &mut self.user
+    /// Restores a session saved by `save_session` for the same API URL, replacing the current cookies, tokens and user
+    pub fn restore_session(&self, data: &str) -> Result<(), MediaWikiError> {
+        let session = Session::from_json(data)?;
+        if session.api_url != self.api_url {
+            return Err(MediaWikiError::String(format!(
+                "session is for {}, not {}",
+                session.api_url, self.api_url
+            )));
+        }
+        self.cookie_jar.load_json(&session.cookies)?;
+        *write_lock(&self.tokens) = session.tokens;
+        if session.user.logged_in() {
+            self.logged_out.store(false, Ordering::SeqCst);
+        }
+        *self.user_mut() = session.user;
+        self.apply_server_rate_limits();
+        Ok(())
+    }
+
+    /// Returns the current user object.
+    /// The returned guard should not be held across `.await`.
+    pub fn user(&self) -> RwLockReadGuard<'_, User> {
+        read_lock(&self.user)
+    }
+
+    /// Returns the current user object, for changing it.
+    /// The returned guard should not be held across `.await`.
+    pub fn user_mut(&self) -> RwLockWriteGuard<'_, User> {
+        write_lock(&self.user)
     }
 
     /// Loads the current user info; returns Ok(()) is successful
-    pub async fn load_current_user_info(&mut self) -> Result<(), MediaWikiError> {
-        let mut user = std::mem::take(&mut self.user);
+    pub async fn load_current_user_info(&self) -> Result<(), MediaWikiError> {
+        let mut user = self.user().clone();
         self.load_user_info(&mut user).await?;
-        self.user = user;
+        *self.user_mut() = user;
+        self.apply_server_rate_limits();
         Ok(())
     }
 
-    /// Returns the maximum number of retry attempts
+    /// Returns the maximum number of retry attempts for edits
     pub fn max_retry_attempts(&self) -> u64 {
         self.max_retry_attempts
     }
 
-    /// Sets the maximum number of retry attempts
+    /// Sets the maximum number of retry attempts for edits
     pub fn set_max_retry_attempts(&mut self, max_retry_attempts: u64) {
         self.max_retry_attempts = max_retry_attempts;
     }
 
+    /// Returns the maximum number of retry attempts for read queries
+    pub fn max_read_retry_attempts(&self) -> u64 {
+        self.max_read_retry_attempts
+    }
+
+    /// Sets the maximum number of retry attempts for read queries
+    pub fn set_max_read_retry_attempts(&mut self, max_read_retry_attempts: u64) {
+        self.max_read_retry_attempts = max_read_retry_attempts;
+    }
+
+    /// Returns the site info, as loaded by `new()` or `refresh_site_info()`
+    pub fn site_info(&self) -> &SiteInfo {
+        &self.site_info
+    }
+
+    /// Loads the site info again, e.g. after configuration changes on a long-running bot
+    pub async fn refresh_site_info(&mut self) -> Result<&SiteInfo, MediaWikiError> {
+        self.load_site_info().await?;
+        Ok(&self.site_info)
+    }
+
+    /// Returns the site info as a JSON string, to be passed to `new_from_site_info()` later
+    pub fn site_info_json(&self) -> String {
+        self.site_info.raw().to_string()
+    }
+
     /// Returns a reference to the serde_json Value containing the site info
     pub fn get_site_info(&self) -> &Value {
-        &self.site_info
+        self.site_info.raw()
     }
 
     /// Returns a serde_json Value in site info, within the `["query"]` object.
@@ -224,18 +637,73 @@ impl Api {
         info["*"].as_str().or_else(|| info["canonical"].as_str())
     }
 
+    /// Returns the raw `interwikimap` entry for an interwiki or language prefix (case-insensitive), if defined
+    pub fn get_interwiki_info(&self, prefix: &str) -> Option<&Value> {
+        let prefix = prefix.trim().to_lowercase();
+        self.get_site_info()["query"]["interwikimap"]
+            .as_array()?
+            .iter()
+            .find(|iw| iw["prefix"].as_str() == Some(prefix.as_str()))
+    }
+
+    /// Returns the API URL of the wiki an interwiki prefix links to.
+    /// Uses the `api` field of the interwiki map if present, otherwise guesses from a `/wiki/$1` URL
+    pub fn get_interwiki_api_url(&self, prefix: &str) -> Option<String> {
+        let info = self.get_interwiki_info(prefix)?;
+        if let Some(api) = info["api"].as_str() {
+            return Some(api.to_string());
+        }
+        let url = info["url"].as_str()?;
+        if let Some(base) = url.strip_suffix("/wiki/$1") {
+            return Some(format!("{}/w/api.php", base));
+        }
+        url.find("index.php")
+            .map(|pos| format!("{}api.php", &url[..pos]))
+    }
+
+    /// Returns the aliases of a magic word (e.g. `redirect`), localized aliases first
+    pub async fn get_magic_word_aliases(&self, name: &str) -> Result<Vec<String>, MediaWikiError> {
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("meta", "siteinfo"),
+            ("siprop", "magicwords"),
+        ]);
+        let result = self.get_query_api_json(&params).await?;
+        Ok(result["query"]["magicwords"]
+            .as_array()
+            .and_then(|arr| arr.iter().find(|mw| mw["name"].as_str() == Some(name)))
+            .and_then(|mw| mw["aliases"].as_array())
+            .map(|aliases| {
+                aliases
+                    .iter()
+                    .filter_map(|a| a.as_str())
+                    .map(|s| s.to_string())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
     /// Loads the site info.
-    /// Should only ever be called from `new()`
-    async fn load_site_info(&mut self) -> Result<&Value, MediaWikiError> {
-        let params = hashmap!["action".to_string()=>"query".to_string(),"meta".to_string()=>"siteinfo".to_string(),"siprop".to_string()=>"general|namespaces|namespacealiases|libraries|extensions|statistics".to_string()];
-        self.site_info = self.get_query_api_json(&params).await?;
-        Ok(&self.site_info)
+    /// Should only ever be called from `new()`, `ApiBuilder::build()` and `refresh_site_info()`
+    pub(crate) async fn load_site_info(&mut self) -> Result<&Value, MediaWikiError> {
+        let params = hashmap!["action".to_string()=>"query".to_string(),"meta".to_string()=>"siteinfo".to_string(),"siprop".to_string()=>"general|namespaces|namespacealiases|libraries|extensions|statistics|restrictions|interwikimap".to_string()];
+        let site_info = self.get_query_api_json(&params).await?;
+        self.set_site_info(site_info);
+        Ok(self.site_info.raw())
+    }
+
+    /// Sets the site info, from a `meta=siteinfo` result
+    pub(crate) fn set_site_info(&mut self, site_info: Value) {
+        self.site_info = SiteInfo::from_json(site_info);
+        if let Ok(mut health) = self.site_health.lock() {
+            health.update_from_general(&self.site_info.raw()["query"]["general"]);
+        }
     }
 
     /// Merges two JSON objects that are MediaWiki API results.
     /// If an array already exists in the `a` object, it will be expanded with the array from the `b` object
     /// This allows for combining multiple API results via the `continue` parameter
-    fn json_merge(a: &mut Value, b: Value) {
+    pub(crate) fn json_merge(a: &mut Value, b: Value) {
         match (a, b) {
             (a @ &mut Value::Object(_), Value::Object(b)) => {
                 if let Some(a) = a.as_object_mut() {
@@ -268,27 +736,48 @@ impl Api {
         HashMap::new()
     }
 
-    /// Returns a token of a `token_type`, such as `login` or `csrf` (for editing)
-    pub async fn get_token(&mut self, token_type: &str) -> Result<String, MediaWikiError> {
-        let mut params = hashmap!["action".to_string()=>"query".to_string(),"meta".to_string()=>"tokens".to_string()];
-        if !token_type.is_empty() {
-            params.insert("type".to_string(), token_type.to_string());
-        }
-        let mut key = token_type.to_string();
-        key += &"token";
-        if token_type.is_empty() {
-            key = "csrftoken".into()
+    /// Returns a token of a `token_type`, such as `login` or `csrf` (for editing).
+    /// Tokens other than `login` are cached until the session changes, or the API reports `badtoken`.
+    /// Prefer `get_token_of_type`, which cannot misspell the type.
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
+    pub async fn get_token(&self, token_type: &str) -> Result<String, MediaWikiError> {
+        let token_type = match token_type {
+            "" => TokenType::Csrf.as_str(),
+            t => t,
+        };
+        let cacheable = token_type != TokenType::Login.as_str();
+        if cacheable {
+            if let Some(token) = read_lock(&self.tokens).get(token_type) {
+                return Ok(token.to_owned());
+            }
         }
-        let x = self.query_api_json_mut(&params, "GET").await?;
+        let params = hashmap!["action".to_string()=>"query".to_string(),"meta".to_string()=>"tokens".to_string(),"type".to_string()=>token_type.to_string()];
+        let key = format!("{}token", token_type);
+        let x = self.query_api_json(&params, "GET").await?;
         match &x["query"]["tokens"][&key] {
-            Value::String(s) => Ok(s.to_string()),
+            Value::String(s) => {
+                if cacheable {
+                    write_lock(&self.tokens).insert(token_type.to_string(), s.to_string());
+                }
+                Ok(s.to_string())
+            }
             _ => Err(From::from(format!("Could not get token: {:?}", x))),
         }
     }
 
-    /// Calls `get_token()` to return an edit token
-    pub async fn get_edit_token(&mut self) -> Result<String, MediaWikiError> {
-        self.get_token("csrf").await
+    /// Returns a token of a `token_type`; see `get_token`
+    pub async fn get_token_of_type(&self, token_type: TokenType) -> Result<String, MediaWikiError> {
+        self.get_token(token_type.as_str()).await
+    }
+
+    /// Removes a token from the cache, so the next request for it fetches a new one
+    pub fn invalidate_token(&self, token_type: TokenType) {
+        write_lock(&self.tokens).remove(token_type.as_str());
+    }
+
+    /// Calls `get_token_of_type()` to return an edit token
+    pub async fn get_edit_token(&self) -> Result<String, MediaWikiError> {
+        self.get_token_of_type(TokenType::Csrf).await
     }
 
     /// Same as `get_query_api_json` but automatically loads all results via the `continue` parameter
@@ -332,25 +821,53 @@ impl Api {
             .await
     }
 
-    /// Same as `get_query_api_json` but automatically loads more results via the `continue` parameter.
+    /// Same as `get_query_api_json_limit`, but stops with `MediaWikiError::Cancelled` once `token` is cancelled,
+    /// also while waiting for a response or before a retry
+    pub async fn get_query_api_json_limit_cancellable(
+        &self,
+        params: &HashMap<String, String>,
+        max: Option<usize>,
+        token: &CancellationToken,
+    ) -> Result<Value, MediaWikiError> {
+        let mut api = self.clone();
+        api.set_cancellation(Some(token.clone()));
+        api.get_query_api_json_limit(params, max).await
+    }
+
+    /// Same as `get_query_api_json` but automatically loads more results via the `continue` parameter
+    /// (or the legacy `query-continue` of older MediaWiki versions).
     /// Returns a stream; each item is a "page" of results.
     pub async fn get_query_api_json_limit_iter<'a>(
         &'a self,
         params: &HashMap<String, String>,
         max: Option<usize>,
     ) -> impl Stream<Item = Result<Value, MediaWikiError>> + 'a {
+        self.get_query_api_json_resumable_iter(params, max, None)
+            .await
+            .map(|result| result.map(|(value, _)| value))
+    }
+
+    /// Same as `get_query_api_json_limit_iter`, but starts at the `start` continuation, if given,
+    /// and yields each "page" of results with the continuation for the next one (`None` after the last page).
+    /// Storing the continuation (see `Continuation::to_json`) allows a long scan to resume where it stopped.
+    pub async fn get_query_api_json_resumable_iter<'a>(
+        &'a self,
+        params: &HashMap<String, String>,
+        max: Option<usize>,
+        start: Option<Continuation>,
+    ) -> impl Stream<Item = Result<(Value, Option<Continuation>), MediaWikiError>> + 'a {
         struct QueryState<'a> {
             api: &'a Api,
             params: HashMap<String, String>,
             values_remaining: Option<usize>,
-            continue_params: Value,
+            continuation: Option<Continuation>,
         }
 
         let initial_query_state = QueryState {
             api: self,
             params: params.clone(),
             values_remaining: max,
-            continue_params: Value::Null,
+            continuation: start,
         };
 
         futures::stream::unfold(initial_query_state, |mut query_state| async move {
@@ -359,161 +876,655 @@ impl Api {
             }
 
             let mut current_params = query_state.params.clone();
-            if let Value::Object(obj) = &query_state.continue_params {
-                current_params.extend(
-                    obj.iter()
-                        // The default to_string() method for Value puts double-quotes around strings
-                        .map(|(k, v)| {
-                            (k.to_string(), v.as_str().map_or(v.to_string(), Into::into))
-                        }),
-                );
+            if let Some(continuation) = &query_state.continuation {
+                current_params.extend(continuation.params(&query_state.params));
             }
 
             let query_result = query_state.api.get_query_api_json(&current_params).await;
 
             let ret = match query_result {
                 Ok(mut result) => {
-                    query_state.continue_params = result["continue"].clone();
-                    if query_state.continue_params.is_null() {
+                    query_state.continuation = Continuation::from_result(&result);
+                    if query_state.continuation.is_none() {
                         query_state.values_remaining = Some(0);
                     } else if let Some(num) = query_state.values_remaining {
                         query_state.values_remaining =
                             Some(num.saturating_sub(query_state.api.query_result_count(&result)));
                     }
-                    result.as_object_mut().map(|r| r.remove("continue"));
-                    Ok(result)
+                    Continuation::remove_from(&mut result);
+                    Ok((result, query_state.continuation.clone()))
                 }
-                e @ Err(_) => {
+                Err(e) => {
                     query_state.values_remaining = Some(0);
-                    e
+                    Err(e)
                 }
             };
             Some((ret, query_state))
         })
     }
 
+    /// Same as `get_query_api_json_limit_iter`, but returns a stream of the individual
+    /// entries in `["query"][list_key]`, converted by `parse`. Entries that `parse` rejects are skipped.
+    pub async fn get_query_api_list_iter<'a, T, F>(
+        &'a self,
+        params: &HashMap<String, String>,
+        list_key: &'a str,
+        parse: F,
+    ) -> impl Stream<Item = Result<T, MediaWikiError>> + 'a
+    where
+        T: 'a,
+        F: Fn(&Value) -> Option<T> + 'a,
+    {
+        self.get_query_api_json_limit_iter(params, None)
+            .await
+            .flat_map(move |result| {
+                let items: Vec<Result<T, MediaWikiError>> = match result {
+                    Ok(result) => match result["query"][list_key].as_array() {
+                        Some(arr) => arr.iter().filter_map(&parse).map(Ok).collect(),
+                        None => match result["query"][list_key].as_object() {
+                            Some(obj) => obj.values().filter_map(&parse).map(Ok).collect(),
+                            None => vec![],
+                        },
+                    },
+                    Err(e) => vec![Err(e)],
+                };
+                futures::stream::iter(items)
+            })
+    }
+
     /// Runs a query against the MediaWiki API, using `method` GET or POST.
     /// Parameters are a hashmap; `format=json` is enforced.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(method = %method, action = params.get("action").map(String::as_str).unwrap_or_default())
+        )
+    )]
     pub async fn query_api_json(
         &self,
         params: &HashMap<String, String>,
         method: &str,
+    ) -> Result<Value, MediaWikiError> {
+        self.query_api_json_with_file(params, method, None).await
+    }
+
+    /// Runs a query like `query_api_json`, optionally POSTed as `multipart/form-data` with a file field.
+    /// Edits and uploads get the same checks, retries and error handling.
+    async fn query_api_json_with_file(
+        &self,
+        params: &HashMap<String, String>,
+        method: &str,
+        file: Option<&FilePart<'_>>,
     ) -> Result<Value, MediaWikiError> {
         let mut params = params.clone();
-        let mut attempts_left = self.max_retry_attempts;
+        let max_attempts = if self.is_edit_query(&params, method) {
+            self.max_retry_attempts
+        } else {
+            self.max_read_retry_attempts
+        };
+        let mut attempts_left = max_attempts;
         params.insert("format".to_string(), "json".to_string());
         let mut cumulative: u64 = 0;
+        let mut relogins_left = self.max_relogin_attempts;
+        let mut token_renewed = false;
+        self.set_language_params(&mut params);
+        self.set_assert_params(&mut params, method);
+        self.check_logged_out(&params, method)?;
+        self.check_duplicate_edit(&params, method)?;
+        if let Some(v) = self.simulate_edit(&params, method) {
+            return Ok(v);
+        }
+        let started = Instant::now();
         loop {
+            self.check_cancelled()?;
             self.set_cumulative_maxlag_params(&mut params, method, cumulative);
-            let t = self.query_api_raw(&params, method).await?;
-            let v: Value = serde_json::from_str(&t)?;
+            let v = self.query_api_value(&params, method, file).await?;
+            self.hooks.apply_response(&params, &v);
+            self.observe_site_health(&v);
             match self.check_maxlag(&v) {
                 Some(lag_seconds) => {
                     if attempts_left == 0 {
                         return Err(From::from(format!(
                             "Max attempts reached [MAXLAG] after {} attempts, cumulative maxlag {}",
-                            &self.max_retry_attempts, cumulative
+                            max_attempts, cumulative
                         )));
                     }
                     attempts_left -= 1;
                     cumulative += lag_seconds;
-                    tokio::time::sleep(Duration::from_millis(1000 * lag_seconds)).await;
+                    self.emit_event(&ApiEvent::MaxLag {
+                        action: params.get("action").cloned().unwrap_or_default(),
+                        wait: Duration::from_secs(lag_seconds),
+                        attempts_left,
+                    });
+                    self.sleep_cancellable(Duration::from_millis(1000 * lag_seconds))
+                        .await?;
+                }
+                None => {
+                    if let Some(wait) = self.check_ratelimited(&v, &params, method) {
+                        if attempts_left == 0 {
+                            return Err(From::from(format!(
+                                "Max attempts reached [RATELIMITED] after {} attempts",
+                                max_attempts
+                            )));
+                        }
+                        attempts_left -= 1;
+                        self.emit_event(&ApiEvent::RateLimited {
+                            action: params.get("action").cloned().unwrap_or_default(),
+                            wait,
+                            attempts_left,
+                        });
+                        self.sleep_cancellable(wait).await?;
+                        continue;
+                    }
+                    if v["error"]["code"].as_str() == Some("badtoken") {
+                        self.clear_tokens();
+                    }
+                    if relogins_left > 0 && self.needs_relogin(&params, &v) {
+                        relogins_left -= 1;
+                        self.emit_event(&ApiEvent::Relogin {
+                            action: params.get("action").cloned().unwrap_or_default(),
+                            code: v["error"]["code"].as_str().unwrap_or_default().to_string(),
+                        });
+                        self.relogin(&mut params).await?;
+                        continue;
+                    }
+                    if !token_renewed
+                        && Self::is_invalid_authorization(&v)
+                        && self.renew_oauth2_token().await?
+                    {
+                        token_renewed = true;
+                        continue;
+                    }
+                    if let Some(code) = v["error"]["code"].as_str() {
+                        self.emit_event(&ApiEvent::ApiError {
+                            action: params.get("action").cloned().unwrap_or_default(),
+                            code: code.to_string(),
+                        });
+                    }
+                    Self::check_session_expired(&v)?;
+                    if self.raise_api_errors {
+                        if let Some(error) = MediaWikiError::from_api_result(&v) {
+                            return Err(error);
+                        }
+                    }
+                    self.record_edit(&params, method, &v);
+                    if self.is_edit_query(&params, method) {
+                        self.metrics.edit(
+                            params.get("action").map(|s| s.as_str()).unwrap_or_default(),
+                            started.elapsed(),
+                            v["error"].is_null(),
+                        );
+                    }
+                    return Ok(v);
                 }
-                None => return Ok(v),
             }
         }
     }
 
-    /// Runs a query against the MediaWiki API, using `method` GET or POST.
-    /// Parameters are a hashmap; `format=json` is enforced.
-    async fn query_api_json_mut(
-        &mut self,
+    /// Returns the delay time after edits, in milliseconds, if set
+    pub fn edit_delay(&self) -> &Option<u64> {
+        &self.edit_delay_ms
+    }
+
+    /// Sets the delay time after edits in milliseconds (or `None`).
+    /// This is independent of, and additional to, MAXLAG
+    pub fn set_edit_delay(&mut self, edit_delay_ms: Option<u64>) {
+        self.edit_delay_ms = edit_delay_ms;
+    }
+
+    /// Returns the maxlag, in seconds, if set
+    pub fn maxlag(&self) -> &Option<u64> {
+        &self.maxlag_seconds
+    }
+
+    /// Sets the maxlag in seconds (or `None`)
+    pub fn set_maxlag(&mut self, maxlag_seconds: Option<u64>) {
+        self.maxlag_seconds = maxlag_seconds;
+    }
+
+    /// Returns true if `maxlag` is also sent with read queries
+    pub fn maxlag_on_reads(&self) -> bool {
+        self.maxlag_on_reads
+    }
+
+    /// Also sends `maxlag` with read queries, as Wikimedia asks of automated clients (default `false`).
+    /// Reads use their own retry budget; see `set_max_read_retry_attempts`
+    pub fn set_maxlag_on_reads(&mut self, maxlag_on_reads: bool) {
+        self.maxlag_on_reads = maxlag_on_reads;
+    }
+
+    /// Sets a guard that refuses to submit identical edits twice (or `None`).
+    /// The guard is shared between clones of this `Api`.
+    pub fn set_duplicate_edit_guard(&mut self, guard: Option<DuplicateEditGuard>) {
+        self.duplicate_edit_guard = guard.map(|g| Arc::new(Mutex::new(g)));
+    }
+
+    /// Returns the rate limiter; it is shared with all clones of this `Api`
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
+    }
+
+    /// Replaces the rate limiter, e.g. to share one between several `Api` objects
+    pub fn set_rate_limiter(&mut self, rate_limiter: RateLimiter) {
+        self.rate_limiter = rate_limiter;
+    }
+
+    /// Limits the rate of edits, e.g. `RateLimit::per_minute(90)`.
+    /// Use `rate_limiter().set_limit()` for uploads and reads.
+    pub fn set_rate_limit(&mut self, limit: RateLimit) {
+        self.rate_limiter
+            .set_limit(RateLimitKind::Edit, Some(limit));
+    }
+
+    /// Returns true if the edit and upload rates follow the `ratelimits` of the current user
+    pub fn honor_server_rate_limits(&self) -> bool {
+        self.honor_server_rate_limits
+    }
+
+    /// If set, the `ratelimits` from the user info of the logged-in user are used for edits and uploads
+    /// that have no explicit rate limit (default `true`). Users with `noratelimit` are never paced.
+    /// Turning this off removes the server limits already applied.
+    pub fn set_honor_server_rate_limits(&mut self, honor: bool) {
+        self.honor_server_rate_limits = honor;
+        self.apply_server_rate_limits();
+    }
+
+    /// Replaces the server edit and upload rate limits with those from the current user info;
+    /// explicitly set limits are kept
+    fn apply_server_rate_limits(&self) {
+        if self.honor_server_rate_limits {
+            self.rate_limiter
+                .set_server_limits(&server_rate_limits(&self.user()));
+        } else {
+            self.rate_limiter.clear_server_limits();
+        }
+    }
+
+    /// Returns the kind of rate limit that applies to a request
+    fn rate_limit_kind(&self, params: &HashMap<String, String>, method: &str) -> RateLimitKind {
+        if params.get("action").is_some_and(|a| a == "upload") {
+            RateLimitKind::Upload
+        } else if self.is_edit_query(params, method) {
+            RateLimitKind::Edit
+        } else {
+            RateLimitKind::Read
+        }
+    }
+
+    /// Returns true if API error responses are returned as `MediaWikiError::Api`
+    pub fn raise_api_errors(&self) -> bool {
+        self.raise_api_errors
+    }
+
+    /// If set, query methods return a response with an `error` member as `MediaWikiError::Api`,
+    /// instead of handing it back as `Ok` (default `false`)
+    pub fn set_raise_api_errors(&mut self, raise_api_errors: bool) {
+        self.raise_api_errors = raise_api_errors;
+    }
+
+    /// Returns the credentials used to log in again when the session expires, if set
+    pub fn credentials(&self) -> Option<&Credentials> {
+        self.credentials.as_ref()
+    }
+
+    /// Sets credentials, so that on `assertuserfailed`, `assertbotfailed` or `badtoken` errors
+    /// the `Api` logs in again, refreshes the token, and retries the request (default `None`)
+    pub fn set_credentials(&mut self, credentials: Option<Credentials>) {
+        self.credentials = credentials;
+    }
+
+    /// Returns the maximum number of re-logins per request
+    pub fn max_relogin_attempts(&self) -> u64 {
+        self.max_relogin_attempts
+    }
+
+    /// Sets the maximum number of re-logins per request (default 1)
+    pub fn set_max_relogin_attempts(&mut self, max_relogin_attempts: u64) {
+        self.max_relogin_attempts = max_relogin_attempts;
+    }
+
+    /// Returns the `assert` level added to every POST request with a token, if set
+    pub fn assert_level(&self) -> Option<AssertLevel> {
+        self.assert_level
+    }
+
+    /// Sets the `assert` level added to every POST request with a token (default `None`).
+    /// If the assertion fails, e.g. because the session expired, requests return `MediaWikiError::SessionExpired`.
+    pub fn set_assert(&mut self, assert_level: Option<AssertLevel>) {
+        self.assert_level = assert_level;
+    }
+
+    /// Adds the `assert` parameter to POST requests with a token, unless it is already set
+    fn set_assert_params(&self, params: &mut HashMap<String, String>, method: &str) {
+        if method != "POST" || !params.contains_key("token") {
+            return;
+        }
+        if let Some(level) = self.assert_level {
+            params
+                .entry("assert".to_string())
+                .or_insert_with(|| level.as_str().to_string());
+        }
+    }
+
+    /// Returns `MediaWikiError::SessionExpired` if a `user` or `bot` assertion failed
+    fn check_session_expired(v: &Value) -> Result<(), MediaWikiError> {
+        match v["error"]["code"].as_str() {
+            Some(code @ ("assertuserfailed" | "assertbotfailed")) => {
+                Err(MediaWikiError::SessionExpired(code.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns an error if the query is an edit, and `logout` was called
+    fn check_logged_out(
+        &self,
         params: &HashMap<String, String>,
         method: &str,
-    ) -> Result<Value, MediaWikiError> {
-        let mut params = params.clone();
-        let mut attempts_left = self.max_retry_attempts;
-        params.insert("format".to_string(), "json".to_string());
-        let mut cumulative: u64 = 0;
-        loop {
-            self.set_cumulative_maxlag_params(&mut params, method, cumulative);
-            let t = self.query_api_raw_mut(&params, method).await?;
-            let v: Value = serde_json::from_str(&t)?;
-            match self.check_maxlag(&v) {
-                Some(lag_seconds) => {
-                    if attempts_left == 0 {
-                        return Err(From::from(format!(
-                            "Max attempts reached [MAXLAG] after {} attempts, cumulative maxlag {}",
-                            &self.max_retry_attempts, cumulative
-                        )));
-                    }
-                    attempts_left -= 1;
-                    cumulative += lag_seconds;
-                    tokio::time::sleep(Duration::from_millis(1000 * lag_seconds)).await;
-                }
-                None => return Ok(v),
+    ) -> Result<(), MediaWikiError> {
+        match self.logged_out.load(Ordering::SeqCst) && self.is_edit_query(params, method) {
+            true => Err(MediaWikiError::NotLoggedIn),
+            false => Ok(()),
+        }
+    }
+
+    /// Returns an error if the query is an edit that the duplicate edit guard has seen recently
+    fn check_duplicate_edit(
+        &self,
+        params: &HashMap<String, String>,
+        method: &str,
+    ) -> Result<(), MediaWikiError> {
+        if !self.is_edit_query(params, method) {
+            return Ok(());
+        }
+        match &self.duplicate_edit_guard {
+            Some(guard) => guard
+                .lock()
+                .map_err(|e| MediaWikiError::String(e.to_string()))?
+                .check(params),
+            None => Ok(()),
+        }
+    }
+
+    /// Records a successful edit in the duplicate edit guard, if set
+    fn record_edit(&self, params: &HashMap<String, String>, method: &str, result: &Value) {
+        if !self.is_edit_query(params, method) || !result["error"].is_null() {
+            return;
+        }
+        if let Some(guard) = &self.duplicate_edit_guard {
+            if let Ok(mut guard) = guard.lock() {
+                guard.record(params);
             }
         }
     }
 
-    /// Returns the delay time after edits, in milliseconds, if set
-    pub fn edit_delay(&self) -> &Option<u64> {
-        &self.edit_delay_ms
+    /// Checks if a query is an edit, based on parameters and method (GET/POST)
+    fn is_edit_query(&self, params: &HashMap<String, String>, method: &str) -> bool {
+        // Editing only through POST (?)
+        if method != "POST" {
+            return false;
+        }
+        // Editing requires a token
+        if !params.contains_key("token") {
+            return false;
+        }
+        true
+    }
+
+    /// Returns true if edits are simulated instead of sent
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Sets dry-run mode. In dry-run mode, edits (POST requests with a token, and REST API page updates)
+    /// are not sent; they are recorded in `dry_run_requests`, reported as `ApiEvent::DryRun`, and get a
+    /// simulated successful response, see `is_dry_run_result`. Helpers that return IDs of new objects,
+    /// e.g. `create_item`, return placeholders for simulated edits. Read requests, including for tokens,
+    /// are sent as usual.
+    pub fn set_dry_run(&mut self, dry_run: bool) {
+        self.dry_run = dry_run;
+    }
+
+    /// Returns the parameters of the edits that were simulated in dry-run mode, oldest first, without tokens.
+    /// Clones of an `Api` share these.
+    pub fn dry_run_requests(&self) -> Vec<HashMap<String, String>> {
+        self.dry_run_requests
+            .lock()
+            .map(|requests| requests.clone())
+            .unwrap_or_default()
+    }
+
+    /// Clears the edits recorded in dry-run mode
+    pub fn clear_dry_run_requests(&self) {
+        if let Ok(mut requests) = self.dry_run_requests.lock() {
+            requests.clear();
+        }
+    }
+
+    /// In dry-run mode, records an edit and returns a simulated response, without sending it.
+    /// The response has `success` and `dryrun` set, and a `result` of `Success` for the action, e.g.
+    /// `{"success":1,"dryrun":true,"edit":{"result":"Success","dryrun":true}}`.
+    fn simulate_edit(&self, params: &HashMap<String, String>, method: &str) -> Option<Value> {
+        if !self.dry_run || !self.is_edit_query(params, method) {
+            return None;
+        }
+        let action = params.get("action").cloned().unwrap_or_default();
+        let mut recorded = params.clone();
+        recorded.remove("token");
+        self.record_dry_run(&action, recorded);
+        let v = serde_json::json!({
+            "success": 1,
+            "dryrun": true,
+            action: {"result": "Success", "dryrun": true},
+        });
+        self.hooks.apply_response(params, &v);
+        Some(v)
+    }
+
+    /// Records a simulated edit in `dry_run_requests`, and reports it as `ApiEvent::DryRun`
+    pub(crate) fn record_dry_run(&self, action: &str, request: HashMap<String, String>) {
+        if let Ok(mut requests) = self.dry_run_requests.lock() {
+            requests.push(request);
+        }
+        self.emit_event(&ApiEvent::DryRun {
+            action: action.to_string(),
+        });
+    }
+
+    /// Returns true if `result` is a response simulated in dry-run mode, rather than one from the wiki
+    pub fn is_dry_run_result(result: &Value) -> bool {
+        result["dryrun"].as_bool() == Some(true)
+    }
+
+    /// Returns true if the `maxlag` parameter is sent with a query
+    fn sends_maxlag(&self, params: &HashMap<String, String>, method: &str) -> bool {
+        self.maxlag_on_reads || self.is_edit_query(params, method)
+    }
+
+    /// Sets the maxlag parameter for a query, if necessary
+    fn _set_maxlag_params(&self, params: &mut HashMap<String, String>, method: &str) {
+        if !self.sends_maxlag(params, method) {
+            return;
+        }
+        if let Some(maxlag_seconds) = self.maxlag_seconds {
+            params.insert("maxlag".to_string(), maxlag_seconds.to_string());
+        }
+    }
+
+    /// Sets the maxlag parameter for a query, if necessary
+    fn set_cumulative_maxlag_params(
+        &self,
+        params: &mut HashMap<String, String>,
+        method: &str,
+        cumulative: u64,
+    ) {
+        if !self.sends_maxlag(params, method) {
+            return;
+        }
+        if let Some(maxlag_seconds) = self.maxlag_seconds {
+            let added = cumulative + maxlag_seconds;
+            params.insert("maxlag".to_string(), added.to_string());
+        }
+    }
+
+    /// Returns the language set via `set_default_language`
+    pub fn default_language(&self) -> Option<&str> {
+        self.default_language.as_deref()
+    }
+
+    /// Sets a language (e.g. `en`) that is sent as `uselang` with all requests, unless a request sets it.
+    /// This makes error messages and rendered content come back in a predictable language.
+    pub fn set_default_language(&mut self, language: Option<&str>) {
+        self.default_language = language.map(|s| s.to_string());
+    }
+
+    /// Returns the language variant set via `set_default_variant`
+    pub fn default_variant(&self) -> Option<&str> {
+        self.default_variant.as_deref()
+    }
+
+    /// Sets a language variant (e.g. `zh-hans`) that is sent as `variant` with all requests, unless a request sets it
+    pub fn set_default_variant(&mut self, variant: Option<&str>) {
+        self.default_variant = variant.map(|s| s.to_string());
+    }
+
+    /// Returns the cache for `Api::languages`
+    pub(crate) fn language_cache(&self) -> &LanguageCache {
+        &self.language_cache
+    }
+
+    /// Adds `uselang` and `variant` parameters, if defaults are set and the parameters are not
+    fn set_language_params(&self, params: &mut HashMap<String, String>) {
+        if let Some(language) = &self.default_language {
+            params
+                .entry("uselang".to_string())
+                .or_insert_with(|| language.to_string());
+        }
+        if let Some(variant) = &self.default_variant {
+            params
+                .entry("variant".to_string())
+                .or_insert_with(|| variant.to_string());
+        }
+    }
+
+    /// Returns the fallback delay after a `ratelimited` API error
+    pub fn ratelimit_delay(&self) -> Duration {
+        self.ratelimit_delay
+    }
+
+    /// Sets the fallback delay after a `ratelimited` API error, used if the user rate limits are unknown
+    pub fn set_ratelimit_delay(&mut self, ratelimit_delay: Duration) {
+        self.ratelimit_delay = ratelimit_delay;
+    }
+
+    /// Adds an observer that is called for events such as rate limiting
+    pub fn add_event_observer(&mut self, observer: EventObserver) {
+        self.event_observers.add(observer);
+    }
+
+    /// Adds a hook that can inspect and rewrite the parameters of each API request,
+    /// e.g. to add `bot=1` everywhere. Hooks run after the `Api` added its own parameters.
+    pub fn add_request_hook<F>(&mut self, hook: F)
+    where
+        F: Fn(&mut HashMap<String, String>) + Send + Sync + 'static,
+    {
+        self.hooks.add_request_hook(Arc::new(hook));
+    }
+
+    /// Adds a hook that is called with the parameters and the JSON result of each API request
+    pub fn add_response_hook<F>(&mut self, hook: F)
+    where
+        F: Fn(&HashMap<String, String>, &Value) + Send + Sync + 'static,
+    {
+        self.hooks.add_response_hook(Arc::new(hook));
+    }
+
+    /// Adds a sink that receives metrics about requests, retries and edits
+    pub fn add_metrics_sink(&mut self, sink: Arc<dyn MetricsSink>) {
+        self.metrics.add(sink);
     }
 
-    /// Sets the delay time after edits in milliseconds (or `None`).
-    /// This is independent of, and additional to, MAXLAG
-    pub fn set_edit_delay(&mut self, edit_delay_ms: Option<u64>) {
-        self.edit_delay_ms = edit_delay_ms;
+    /// Removes all metrics sinks
+    pub fn clear_metrics_sinks(&mut self) {
+        self.metrics.clear();
     }
 
-    /// Returns the maxlag, in seconds, if set
-    pub fn maxlag(&self) -> &Option<u64> {
-        &self.maxlag_seconds
+    /// Removes all request and response hooks
+    pub fn clear_hooks(&mut self) {
+        self.hooks.clear();
     }
 
-    /// Sets the maxlag in seconds (or `None`)
-    pub fn set_maxlag(&mut self, maxlag_seconds: Option<u64>) {
-        self.maxlag_seconds = maxlag_seconds;
+    /// Returns the most recent throttling, retry and error events, oldest first.
+    /// The result can be serialized, e.g. to attach it to a bug report.
+    pub fn recent_events(&self) -> Vec<RecordedEvent> {
+        self.event_log
+            .lock()
+            .map(|log| log.events())
+            .unwrap_or_default()
     }
 
-    /// Checks if a query is an edit, based on parameters and method (GET/POST)
-    fn is_edit_query(&self, params: &HashMap<String, String>, method: &str) -> bool {
-        // Editing only through POST (?)
-        if method != "POST" {
-            return false;
-        }
-        // Editing requires a token
-        if !params.contains_key("token") {
-            return false;
+    /// Sets the number of events kept for `recent_events` (default 100)
+    pub fn set_event_log_capacity(&mut self, capacity: usize) {
+        if let Ok(mut log) = self.event_log.lock() {
+            log.set_capacity(capacity);
         }
-        true
     }
 
-    /// Sets the maxlag parameter for a query, if necessary
-    fn _set_maxlag_params(&self, params: &mut HashMap<String, String>, method: &str) {
-        if !self.is_edit_query(params, method) {
-            return;
+    /// Returns the policy for pausing edits, if set
+    pub fn health_policy(&self) -> Option<&HealthPolicy> {
+        self.health_policy.as_ref()
+    }
+
+    /// Sets a policy that pauses edits while the site is unhealthy, resuming automatically (default `None`)
+    pub fn set_health_policy(&mut self, policy: Option<HealthPolicy>) {
+        self.health_policy = policy;
+    }
+
+    /// Returns the shared site health state
+    pub(crate) fn site_health_state(&self) -> &Mutex<SiteHealth> {
+        &self.site_health
+    }
+
+    /// Updates the site health from a `maxlag` or `readonly` error
+    fn observe_site_health(&self, v: &Value) {
+        if let Ok(mut health) = self.site_health.lock() {
+            health.update_from_result(v);
         }
-        if let Some(maxlag_seconds) = self.maxlag_seconds {
-            params.insert("maxlag".to_string(), maxlag_seconds.to_string());
+    }
+
+    /// Records an event in the event log, and sends it to all observers
+    pub(crate) fn emit_event(&self, event: &ApiEvent) {
+        if let Ok(mut log) = self.event_log.lock() {
+            log.push(RecordedEvent::new(event));
         }
+        #[cfg(feature = "tracing")]
+        event.trace();
+        self.event_observers.emit(event);
+        self.metrics.event(event);
     }
 
-    /// Sets the maxlag parameter for a query, if necessary
-    fn set_cumulative_maxlag_params(
+    /// Checks for a `ratelimited` error on an edit, and returns the time to wait if so.
+    /// The wait is derived from the user rate limits for the action if known, otherwise the fallback delay.
+    fn check_ratelimited(
         &self,
-        params: &mut HashMap<String, String>,
+        v: &Value,
+        params: &HashMap<String, String>,
         method: &str,
-        cumulative: u64,
-    ) {
-        if !self.is_edit_query(params, method) {
-            return;
-        }
-        if let Some(maxlag_seconds) = self.maxlag_seconds {
-            let added = cumulative + maxlag_seconds;
-            params.insert("maxlag".to_string(), added.to_string());
+    ) -> Option<Duration> {
+        if v["error"]["code"].as_str() != Some("ratelimited") || !self.is_edit_query(params, method)
+        {
+            return None;
         }
+        let action = params.get("action").map(|s| s.as_str()).unwrap_or_default();
+        Some(
+            self.user()
+                .ratelimit(action)
+                .filter(|(hits, _)| *hits > 0)
+                .map(|(hits, seconds)| Duration::from_secs(seconds.div_ceil(hits)))
+                .unwrap_or(self.ratelimit_delay),
+        )
     }
 
     /// Checks for a maxlag error, and returns the lag if so
@@ -541,12 +1552,16 @@ impl Api {
     }
 
     /// POST wrapper for `query_api_json`.
-    /// Requires `&mut self`, for session cookie storage
+    /// Same as `post_query_api_json`; session cookies are stored through `&self`.
+    #[deprecated(
+        since = "0.4.0",
+        note = "use `post_query_api_json`, which works through `&self`"
+    )]
     pub async fn post_query_api_json_mut(
         &mut self,
         params: &HashMap<String, String>,
     ) -> Result<Value, MediaWikiError> {
-        self.query_api_json_mut(params, "POST").await
+        self.query_api_json(params, "POST").await
     }
 
     /// Runs a query against the MediaWiki API, and returns a text.
@@ -556,18 +1571,58 @@ impl Api {
         params: &HashMap<String, String>,
         method: &str,
     ) -> Result<String, MediaWikiError> {
-        self.query_raw(&self.api_url, params, method).await
+        let hooked = self.hooks.apply_request(params);
+        let params = hooked.as_ref().unwrap_or(params);
+        let started = Instant::now();
+        let result = match &self.mock {
+            Some(mock) => mock.respond(params),
+            None => self.query_raw(&self.api_url, params, method).await,
+        };
+        self.record_request_metrics(params, method, started, result.is_ok());
+        result
     }
 
-    /// Runs a query against the MediaWiki API, and returns a text.
-    /// Uses `query_raw_mut`
-    async fn query_api_raw_mut(
-        &mut self,
+    /// Same as `query_api_raw`, but parses the JSON directly from the response bytes,
+    /// without an intermediate `String` copy of large responses
+    async fn query_api_value(
+        &self,
         params: &HashMap<String, String>,
         method: &str,
-    ) -> Result<String, MediaWikiError> {
-        self.query_raw_mut(&self.api_url.clone(), params, method)
-            .await
+        file: Option<&FilePart<'_>>,
+    ) -> Result<Value, MediaWikiError> {
+        let hooked = self.hooks.apply_request(params);
+        let params = hooked.as_ref().unwrap_or(params);
+        let started = Instant::now();
+        let result = match &self.mock {
+            Some(mock) => mock
+                .respond(params)
+                .and_then(|text| Ok(serde_json::from_str(&text)?)),
+            None => match self
+                .query_raw_response(&self.api_url, params, method, file)
+                .await
+            {
+                Ok(response) => match response.bytes().await {
+                    Ok(bytes) => serde_json::from_slice(&bytes).map_err(MediaWikiError::from),
+                    Err(e) => Err(MediaWikiError::Reqwest(e)),
+                },
+                Err(e) => Err(e),
+            },
+        };
+        self.record_request_metrics(params, method, started, result.is_ok());
+        result
+    }
+
+    /// Reports a request to the metrics sinks
+    fn record_request_metrics(
+        &self,
+        params: &HashMap<String, String>,
+        method: &str,
+        started: Instant,
+        success: bool,
+    ) {
+        let action = params.get("action").map(|s| s.as_str()).unwrap_or_default();
+        self.metrics
+            .request(action, method, started.elapsed(), success);
     }
 
     /// Generates a `RequestBuilder` for the API URL
@@ -589,6 +1644,26 @@ impl Api {
         self.user_agent = agent.into();
     }
 
+    /// Returns the tool name and hashtag that are appended to edit summaries
+    pub fn edit_summary(&self) -> &EditSummary {
+        &self.edit_summary
+    }
+
+    /// Sets the tool name and hashtag to append to the summaries of `Page` and Wikibase edits
+    pub fn set_edit_summary(&mut self, edit_summary: EditSummary) {
+        self.edit_summary = edit_summary;
+    }
+
+    /// Returns the change tags applied to `Page` and Wikibase edits that do not set their own
+    pub fn edit_tags(&self) -> &[String] {
+        &self.edit_tags
+    }
+
+    /// Sets the change tags applied to `Page` and Wikibase edits that do not set their own; see `Api::list_change_tags`
+    pub fn set_edit_tags(&mut self, tags: &[&str]) {
+        self.edit_tags = tags.iter().map(|s| s.to_string()).collect();
+    }
+
     /// Returns the user agent string, as it is passed to the API through a HTTP header
     pub fn user_agent_full(&self) -> String {
         format!(
@@ -612,7 +1687,7 @@ impl Api {
         to_sign: &HashMap<String, String>,
         oauth: &OAuthParams,
     ) -> Result<String, MediaWikiError> {
-        let mut keys: Vec<String> = to_sign.iter().map(|(k, _)| self.rawurlencode(k)).collect();
+        let mut keys: Vec<String> = to_sign.keys().map(|k| self.rawurlencode(k)).collect();
         keys.sort();
 
         let ret: Vec<String> = keys
@@ -658,14 +1733,14 @@ impl Api {
         Ok(ret)
     }
 
-    /// Returns a signed OAuth POST `RequestBuilder`
-    fn oauth_request_builder(
+    /// Returns the headers for a signed OAuth request
+    fn oauth_headers(
         &self,
         method: &str,
         api_url: &str,
         params: &HashMap<String, String>,
-    ) -> Result<reqwest::RequestBuilder, MediaWikiError> {
-        let oauth = match &self.oauth {
+    ) -> Result<HeaderMap, MediaWikiError> {
+        let oauth = match self.oauth() {
             Some(oauth) => oauth,
             None => {
                 return Err(From::from(
@@ -715,7 +1790,7 @@ impl Api {
 
         headers.insert(
             "oauth_signature",
-            self.sign_oauth_request(method, api_url, &to_sign, oauth)?
+            self.sign_oauth_request(method, api_url, &to_sign, &oauth)?
                 .parse()?,
         );
 
@@ -738,7 +1813,17 @@ impl Api {
             HeaderValue::from_str(header.as_str())?,
         );
         headers.insert(reqwest::header::USER_AGENT, self.user_agent_full().parse()?);
+        Ok(headers)
+    }
 
+    /// Returns a signed OAuth POST `RequestBuilder`
+    fn oauth_request_builder(
+        &self,
+        method: &str,
+        api_url: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<reqwest::RequestBuilder, MediaWikiError> {
+        let headers = self.oauth_headers(method, api_url, params)?;
         match method {
             "GET" => Ok(self.client.get(api_url).headers(headers).query(&params)),
             "POST" => Ok(self.client.post(api_url).headers(headers).form(&params)),
@@ -746,27 +1831,62 @@ impl Api {
         }
     }
 
-    /// Returns a `RequestBuilder` for a generic URL
-    fn request_builder(
+    /// Returns true if `url` has the same scheme, host and port as the API URL.
+    /// Credentials are only sent to such URLs, not e.g. to a SPARQL endpoint on another host.
+    fn is_wiki_url(&self, url: &str) -> bool {
+        match (Url::parse(url), Url::parse(&self.api_url)) {
+            (Ok(url), Ok(api_url)) => url.origin() == api_url.origin(),
+            _ => false,
+        }
+    }
+
+    /// Returns the headers (user agent, authorization) for a request.
+    /// Authorization is only added for URLs of the wiki; `params` are only used for OAuth signing.
+    pub(crate) fn request_headers(
         &self,
         api_url: &str,
         params: &HashMap<String, String>,
         method: &str,
-    ) -> Result<reqwest::RequestBuilder, MediaWikiError> {
-        // Use OAuth if set
-        if self.oauth.is_some() {
-            return self.oauth_request_builder(method, api_url, params);
-        }
-
+    ) -> Result<HeaderMap, MediaWikiError> {
         let mut headers = HeaderMap::new();
+        if !self.is_wiki_url(api_url) {
+            headers.insert(reqwest::header::USER_AGENT, self.user_agent_full().parse()?);
+            return Ok(headers);
+        }
+        if read_lock(&self.auth).oauth.is_some() {
+            return self.oauth_headers(method, api_url, params);
+        }
         headers.insert(reqwest::header::USER_AGENT, self.user_agent_full().parse()?);
-        if let Some(access_token) = &self.oauth2 {
+        let access_token = {
+            let auth = read_lock(&self.auth);
+            auth.oauth2
+                .clone()
+                .or_else(|| auth.oauth2_client.as_ref()?.access_token())
+        };
+        if let Some(access_token) = access_token {
             headers.insert(
                 reqwest::header::AUTHORIZATION,
                 format!("Bearer {}", access_token).parse()?,
             );
+        } else if let Some(basic_auth) = &self.basic_auth {
+            headers.insert(reqwest::header::AUTHORIZATION, basic_auth.header_value()?);
+        }
+        Ok(headers)
+    }
+
+    /// Returns a `RequestBuilder` for a generic URL
+    pub(crate) fn request_builder(
+        &self,
+        api_url: &str,
+        params: &HashMap<String, String>,
+        method: &str,
+    ) -> Result<reqwest::RequestBuilder, MediaWikiError> {
+        // Use OAuth if set, but only sign requests to the wiki itself
+        if read_lock(&self.auth).oauth.is_some() && self.is_wiki_url(api_url) {
+            return self.oauth_request_builder(method, api_url, params);
         }
 
+        let headers = self.request_headers(api_url, params, method)?;
         Ok(match method {
             "GET" => self.client.get(api_url).headers(headers).query(&params),
             "POST" => self.client.post(api_url).headers(headers).form(&params),
@@ -777,17 +1897,96 @@ impl Api {
         })
     }
 
+    /// POSTs a `multipart/form-data` query to the MediaWiki API, with `params` as text fields
+    /// and `data` as a file field named `file_field`. `format=json` is enforced.
+    /// Used for file uploads; like `post_query_api_json`, with assertions, maxlag, retries and error handling.
+    pub async fn post_query_api_multipart(
+        &self,
+        params: &HashMap<String, String>,
+        file_field: &str,
+        file_name: &str,
+        data: Vec<u8>,
+    ) -> Result<Value, MediaWikiError> {
+        let file = FilePart {
+            field: file_field,
+            name: file_name,
+            data,
+        };
+        self.query_api_json_with_file(params, "POST", Some(&file))
+            .await
+    }
+
+    /// Returns a `multipart/form-data` POST request with `params` as text fields, and a file field
+    fn multipart_request_builder(
+        &self,
+        api_url: &str,
+        params: &HashMap<String, String>,
+        file: &FilePart<'_>,
+    ) -> Result<reqwest::RequestBuilder, MediaWikiError> {
+        // Multipart bodies are not part of the OAuth signature
+        let headers = self.request_headers(api_url, &HashMap::new(), "POST")?;
+        let mut form = reqwest::multipart::Form::new();
+        for (k, v) in params {
+            form = form.text(k.to_owned(), v.to_owned());
+        }
+        let part =
+            reqwest::multipart::Part::bytes(file.data.clone()).file_name(file.name.to_string());
+        form = form.part(file.field.to_string(), part);
+        Ok(self.client.post(api_url).headers(headers).multipart(form))
+    }
+
     /// Performs a query, pauses if required, and returns the raw response
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(api_url = %api_url, method = %method, action = params.get("action").map(String::as_str).unwrap_or_default())
+        )
+    )]
     async fn query_raw_response(
         &self,
         api_url: &str,
         params: &HashMap<String, String>,
         method: &str,
+        file: Option<&FilePart<'_>>,
     ) -> Result<reqwest::Response, MediaWikiError> {
+        if self.is_edit_query(params, method) {
+            self.wait_for_site_health(params).await?;
+        }
+        self.refresh_oauth2_token().await?;
+        self.rate_limiter
+            .acquire(self.rate_limit_kind(params, method))
+            .await;
         let mut response;
+        let mut token_renewed = false;
         loop {
-            let req = self.request_builder(api_url, params, method)?;
-            response = req.send().await?;
+            #[cfg(feature = "tracing")]
+            let started = Instant::now();
+            let req = match file {
+                Some(file) => self.multipart_request_builder(api_url, params, file)?,
+                None => self.request_builder(api_url, params, method)?,
+            };
+            response = match &self.cancellation {
+                Some(token) => token.run(req.send()).await??,
+                None => req.send().await?,
+            };
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                status = response.status().as_u16(),
+                duration_ms = started.elapsed().as_millis() as u64,
+                "API response"
+            );
+            if let Some(lag) = response
+                .headers()
+                .get("X-Database-Lag")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|s| s.trim().parse::<f64>().ok())
+            {
+                if let Ok(mut health) = self.site_health.lock() {
+                    health.set_lag(Duration::from_secs_f64(lag.max(0.0)));
+                }
+            }
 
             // If the API is overloaded, wait the requested time and try again
             if response.status() == StatusCode::TOO_MANY_REQUESTS {
@@ -798,7 +1997,22 @@ impl Api {
                     .and_then(|bytes| std::str::from_utf8(bytes).ok())
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(DEFAULT_DELAY_FOR_TOO_MANY_REQUESTS); // Fallback value
-                tokio::time::sleep(Duration::from_secs(wait_sec)).await;
+                self.emit_event(&ApiEvent::TooManyRequests {
+                    action: params.get("action").cloned().unwrap_or_default(),
+                    wait: Duration::from_secs(wait_sec),
+                });
+                self.sleep_cancellable(Duration::from_secs(wait_sec))
+                    .await?;
+                continue;
+            }
+
+            // If the wiki rejects the OAuth 2 access token, e.g. because it was revoked, fetch a new one once
+            if response.status() == StatusCode::UNAUTHORIZED
+                && !token_renewed
+                && self.is_wiki_url(api_url)
+                && self.renew_oauth2_token().await?
+            {
+                token_renewed = true;
                 continue;
             }
 
@@ -808,6 +2022,36 @@ impl Api {
         Ok(response)
     }
 
+    /// Returns the cancellation token that aborts requests of this `Api`, if set
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    /// Sets a token that aborts requests, continuation loops and retry waits with `MediaWikiError::Cancelled` (or `None`).
+    /// To cancel only some requests, set the token on a clone of the `Api`
+    pub fn set_cancellation(&mut self, cancellation: Option<CancellationToken>) {
+        self.cancellation = cancellation;
+    }
+
+    /// Returns `MediaWikiError::Cancelled` if the cancellation token is cancelled
+    pub(crate) fn check_cancelled(&self) -> Result<(), MediaWikiError> {
+        match &self.cancellation {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
+    }
+
+    /// Sleeps for `duration`, unless the cancellation token is cancelled first
+    pub(crate) async fn sleep_cancellable(&self, duration: Duration) -> Result<(), MediaWikiError> {
+        match &self.cancellation {
+            Some(token) => token.sleep(duration).await,
+            None => {
+                tokio::time::sleep(duration).await;
+                Ok(())
+            }
+        }
+    }
+
     /// Delays the current thread, if the query performs an edit, and a delay time is set
     async fn enact_edit_delay(&self, params: &HashMap<String, String>, method: &str) {
         if !self.is_edit_query(params, method) {
@@ -818,49 +2062,79 @@ impl Api {
         }
     }
 
-    /// Runs a query against a generic URL, stores cookies, and returns a text
-    /// Used for non-stateless queries, such as logins
-    async fn query_raw_mut(
-        &mut self,
-        api_url: &str,
-        params: &HashMap<String, String>,
-        method: &str,
-    ) -> Result<String, MediaWikiError> {
-        let resp = self.query_raw_response(api_url, params, method).await?;
-        resp.text().await.map_err(MediaWikiError::Reqwest)
-    }
-
     /// Runs a query against a generic URL, and returns a text.
-    /// Does not store cookies, but also does not require `&self` to be mutable.
-    /// Used for simple queries
+    /// Cookies are stored in the shared cookie jar.
     pub async fn query_raw(
         &self,
         api_url: &str,
         params: &HashMap<String, String>,
         method: &str,
     ) -> Result<String, MediaWikiError> {
-        let resp = self.query_raw_response(api_url, params, method).await?;
+        let resp = self
+            .query_raw_response(api_url, params, method, None)
+            .await?;
         resp.text().await.map_err(MediaWikiError::Reqwest)
     }
 
-    /// Performs a login against the MediaWiki API.
-    /// If successful, user information is stored in `User`, and in the cookie jar
+    /// Performs a login against the MediaWiki API, e.g. with a bot password (`User@botname`).
+    /// If successful, user information is stored in `User`, and in the cookie jar.
+    ///
+    /// # Errors
+    /// A failed login returns `MediaWikiError::Login`, with the result and reason from the API.
     pub async fn login<S: Into<String>>(
-        &mut self,
+        &self,
         lgname: S,
         lgpassword: S,
     ) -> Result<(), MediaWikiError> {
-        let lgname: &str = &lgname.into();
-        let lgpassword: &str = &lgpassword.into();
-        let lgtoken = self.get_token("login").await?;
-        let params = hashmap!("action".to_string()=>"login".to_string(),"lgname".to_string()=>lgname.into(),"lgpassword".to_string()=>lgpassword.into(),"lgtoken".to_string()=>lgtoken);
-        let res = self.query_api_json_mut(&params, "POST").await?;
+        self.login_with_domain(lgname.into(), lgpassword.into(), None)
+            .await
+    }
+
+    /// Performs a login against the MediaWiki API, with an optional `lgdomain` for wikis using LDAP domains.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, lgpassword)))]
+    pub async fn login_with_domain(
+        &self,
+        lgname: String,
+        lgpassword: String,
+        lgdomain: Option<&str>,
+    ) -> Result<(), MediaWikiError> {
+        let lgtoken = self.get_token_of_type(TokenType::Login).await?;
+        let mut params = hashmap!("action".to_string()=>"login".to_string(),"lgname".to_string()=>lgname,"lgpassword".to_string()=>lgpassword,"lgtoken".to_string()=>lgtoken);
+        if let Some(lgdomain) = lgdomain {
+            params.insert("lgdomain".to_string(), lgdomain.to_string());
+        }
+        let res = self.query_api_json(&params, "POST").await?;
         if res["login"]["result"] == "Success" {
-            self.user.set_from_login(&res["login"])?;
+            self.logged_out.store(false, Ordering::SeqCst);
+            self.clear_tokens();
+            self.user_mut().set_from_login(&res["login"])?;
             self.load_current_user_info().await
         } else {
-            Err(From::from("Login failed"))
+            Err(MediaWikiError::Login(LoginError::from_json(&res["login"])))
+        }
+    }
+
+    /// Logs out via `action=logout`, and clears session cookies, OAuth credentials, and the `User`.
+    /// Subsequent edits fail with `MediaWikiError::NotLoggedIn`, until the next login.
+    pub async fn logout(&self) -> Result<(), MediaWikiError> {
+        let token = self.get_edit_token().await?;
+        let params = self.params_into(&[("action", "logout"), ("token", &token)]);
+        let res = self.query_api_json(&params, "POST").await?;
+        if !res["error"].is_null() {
+            return Err(MediaWikiError::BadResponse(res));
         }
+        self.cookie_jar.clear();
+        *write_lock(&self.auth) = AuthState::default();
+        self.clear_tokens();
+        *self.user_mut() = User::new();
+        self.rate_limiter.clear_server_limits();
+        self.logged_out.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Removes all cached tokens
+    pub fn clear_tokens(&self) {
+        write_lock(&self.tokens).clear();
     }
 
     /// From an API result that has a list of entries with "title" and "ns" (e.g. search), returns a vector of `Title` objects.
@@ -883,7 +2157,7 @@ impl Api {
         let query_api_url = self.get_site_info_string("general", "wikibase-sparql")?;
         let params = hashmap!["query".to_string()=>query.to_string(),"format".to_string()=>"json".to_string()];
         let response = self
-            .query_raw_response(query_api_url, &params, "POST")
+            .query_raw_response(query_api_url, &params, "POST", None)
             .await?;
         match response.json().await {
             Ok(json) => Ok(json),
@@ -900,7 +2174,7 @@ impl Api {
     ) -> Result<Value, MediaWikiError> {
         let params = hashmap!["query".to_string()=>query.to_string(),"format".to_string()=>"json".to_string()];
         let response = self
-            .query_raw_response(query_api_url, &params, "POST")
+            .query_raw_response(query_api_url, &params, "POST", None)
             .await?;
         let bytes = match response.bytes().await {
             Ok(bytes) => bytes,
@@ -969,7 +2243,458 @@ impl Api {
 
 #[cfg(test)]
 mod tests {
-    use super::{Api, Title};
+    use super::{
+        Api, ApiEvent, Arc, AssertLevel, BasicAuth, Continuation, MediaWikiError, Mutex, RateLimit,
+        RateLimitKind, StreamExt, Title, TokenType,
+    };
+
+    #[test]
+    fn basic_auth_redacted() {
+        let basic_auth = BasicAuth::new("user", "secret");
+        assert!(!format!("{:?}", basic_auth).contains("secret"));
+        let header = basic_auth.header_value().unwrap();
+        assert!(header.is_sensitive());
+        assert_eq!(header, "Basic dXNlcjpzZWNyZXQ=");
+    }
+
+    #[test]
+    fn credentials_only_for_wiki_urls() {
+        let api = Api::new_without_site_info(
+            "https://wiki.example.org/w/api.php",
+            reqwest::Client::builder(),
+            Some(BasicAuth::new("user", "secret")),
+        )
+        .unwrap();
+        let params = std::collections::HashMap::new();
+        let auth = reqwest::header::AUTHORIZATION;
+        let headers = api
+            .request_headers(
+                "https://wiki.example.org/w/rest.php/v1/page/Foo",
+                &params,
+                "GET",
+            )
+            .unwrap();
+        assert!(headers.contains_key(&auth));
+        for url in [
+            "https://query.wikidata.org/sparql",
+            "http://wiki.example.org/w/api.php",
+            "https://wiki.example.org:8443/w/api.php",
+        ] {
+            let headers = api.request_headers(url, &params, "POST").unwrap();
+            assert!(!headers.contains_key(&auth), "{}", url);
+            assert!(headers.contains_key(reqwest::header::USER_AGENT));
+        }
+    }
+
+    #[test]
+    fn token_type_round_trip() {
+        for token_type in [TokenType::Csrf, TokenType::Login, TokenType::UserRights] {
+            assert_eq!(
+                token_type.to_string().parse::<TokenType>().unwrap(),
+                token_type
+            );
+        }
+        assert_eq!("CSRF".parse::<TokenType>().unwrap(), TokenType::Csrf);
+        assert!("notatoken".parse::<TokenType>().is_err());
+    }
+
+    #[tokio::test]
+    async fn token_cache() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("meta", "tokens"), ("type", "patrol")],
+            json!({"query":{"tokens":{"patroltoken":"abc+\\"}}}),
+        );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let token_requests = || {
+            responses
+                .requests()
+                .iter()
+                .filter(|r| r.get("meta").is_some_and(|m| m == "tokens"))
+                .count()
+        };
+        assert_eq!(
+            api.get_token_of_type(TokenType::Patrol).await.unwrap(),
+            "abc+\\"
+        );
+        assert_eq!(api.get_token("patrol").await.unwrap(), "abc+\\");
+        assert_eq!(token_requests(), 1);
+        api.invalidate_token(TokenType::Patrol);
+        api.get_token_of_type(TokenType::Patrol).await.unwrap();
+        assert_eq!(token_requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn magic_word_aliases() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("siprop", "magicwords")],
+            json!({"query":{"magicwords":[
+                {"name":"redirect","aliases":["#WEITERLEITUNG","#REDIRECT"],"case-sensitive":false},
+                {"name":"notoc","aliases":["__KEIN_INHALTSVERZEICHNIS__","__NOTOC__"],"case-sensitive":true}]}}),
+        );
+        let api = Api::new_mocked(responses).unwrap();
+        assert_eq!(
+            api.get_magic_word_aliases("redirect").await.unwrap(),
+            ["#WEITERLEITUNG", "#REDIRECT"]
+        );
+        assert!(api
+            .get_magic_word_aliases("nosuchword")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn dry_run() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("meta", "tokens")],
+            json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+        );
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        api.set_dry_run(true);
+        let observed = Arc::new(Mutex::new(vec![]));
+        {
+            let observed = observed.clone();
+            api.add_event_observer(Arc::new(move |event: &ApiEvent| {
+                observed.lock().unwrap().push(event.kind().to_string())
+            }));
+        }
+        let token = api.get_edit_token().await.unwrap();
+        let params = api.params_into(&[
+            ("action", "edit"),
+            ("title", "Foo"),
+            ("text", "Bar"),
+            ("token", &token),
+        ]);
+        let v = api.post_query_api_json(&params).await.unwrap();
+        assert_eq!(v["edit"]["result"], "Success");
+        assert_eq!(v["success"], 1);
+        assert!(Api::is_dry_run_result(&v));
+        assert_eq!(responses.requests().len(), 1);
+        let recorded = api.dry_run_requests();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0]["title"], "Foo");
+        assert!(!recorded[0].contains_key("token"));
+        assert_eq!(*observed.lock().unwrap(), vec!["dry_run"]);
+        api.clear_dry_run_requests();
+        assert!(api.dry_run_requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn resumable_iter() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("apcontinue", "B")],
+                json!({"batchcomplete":"","query":{"allpages":[{"ns":0,"title":"B"}]}}),
+            )
+            .add(
+                &[("list", "allpages")],
+                json!({"continue":{"apcontinue":"B","continue":"-||"},"query":{"allpages":[{"ns":0,"title":"A"}]}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let params = api.params_into(&[("action", "query"), ("list", "allpages")]);
+        let pages: Vec<_> = api
+            .get_query_api_json_resumable_iter(&params, None, None)
+            .await
+            .collect()
+            .await;
+        let (first, checkpoint) = pages[0].as_ref().unwrap();
+        assert_eq!(first["query"]["allpages"][0]["title"], "A");
+        assert!(first.get("continue").is_none());
+        assert!(pages[1].as_ref().unwrap().1.is_none());
+
+        // Resume from a stored checkpoint
+        let stored = checkpoint.as_ref().unwrap().to_json().to_string();
+        let start = Continuation::from_result(&serde_json::from_str(&stored).unwrap());
+        let resumed: Vec<_> = api
+            .get_query_api_json_resumable_iter(&params, None, start)
+            .await
+            .collect()
+            .await;
+        assert_eq!(resumed.len(), 1);
+        let (page, next) = resumed[0].as_ref().unwrap();
+        assert_eq!(page["query"]["allpages"][0]["title"], "B");
+        assert!(next.is_none());
+        assert_eq!(responses.requests().last().unwrap()["apcontinue"], "B");
+    }
+
+    #[tokio::test]
+    async fn legacy_generator_continuation() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("gapcontinue", "C")],
+                json!({"query":{"pages":{"3":{"pageid":3,"ns":0,"title":"C"}}}}),
+            )
+            .add(
+                &[("plcontinue", "1|0|Y")],
+                json!({"query-continue":{"allpages":{"gapcontinue":"C"}},
+                    "query":{"pages":{"1":{"pageid":1,"ns":0,"title":"A","links":[{"ns":0,"title":"Y"}]},
+                    "2":{"pageid":2,"ns":0,"title":"B"}}}}),
+            )
+            .add(
+                &[("generator", "allpages")],
+                json!({"query-continue":{"links":{"plcontinue":"1|0|Y"},"allpages":{"gapcontinue":"C"}},
+                    "query":{"pages":{"1":{"pageid":1,"ns":0,"title":"A","links":[{"ns":0,"title":"X"}]},
+                    "2":{"pageid":2,"ns":0,"title":"B"}}}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let params = api.params_into(&[
+            ("action", "query"),
+            ("generator", "allpages"),
+            ("gaplimit", "2"),
+            ("prop", "links"),
+            ("pllimit", "1"),
+        ]);
+        let pages: Vec<_> = api
+            .get_query_api_json_limit_iter(&params, None)
+            .await
+            .collect()
+            .await;
+        assert_eq!(pages.len(), 3);
+        assert_eq!(
+            pages[1].as_ref().unwrap()["query"]["pages"]["1"]["links"][0]["title"],
+            "Y"
+        );
+        assert_eq!(
+            pages[2].as_ref().unwrap()["query"]["pages"]["3"]["title"],
+            "C"
+        );
+        let requests = responses.requests();
+        assert_eq!(requests[1]["plcontinue"], "1|0|Y");
+        assert!(!requests[1].contains_key("gapcontinue"));
+        assert_eq!(requests[2]["gapcontinue"], "C");
+        assert!(!requests[2].contains_key("plcontinue"));
+    }
+
+    #[tokio::test]
+    async fn ratelimited_retry() {
+        let ratelimited =
+            json!({"error":{"code":"ratelimited","info":"You've exceeded your rate limit"}});
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add_once(&[("action", "edit"), ("title", "A")], ratelimited.clone())
+            .add(
+                &[("action", "edit"), ("title", "A")],
+                json!({"edit":{"result":"Success"}}),
+            )
+            .add(&[("action", "edit"), ("title", "B")], ratelimited);
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        api.set_max_retry_attempts(1);
+        api.user_mut()
+            .set_user_info(Some(json!({"query":{"userinfo":{"ratelimits":{
+            "edit":{"user":{"hits":120,"seconds":60},"ip":{"hits":60,"seconds":60}}}}}})));
+        let observed = Arc::new(Mutex::new(vec![]));
+        {
+            let observed = observed.clone();
+            api.add_event_observer(Arc::new(move |event: &ApiEvent| {
+                observed.lock().unwrap().push(event.clone())
+            }));
+        }
+        let edit = |title: &str| {
+            api.params_into(&[("action", "edit"), ("title", title), ("token", "abc+\\")])
+        };
+
+        let v = api.post_query_api_json(&edit("A")).await.unwrap();
+        assert_eq!(v["edit"]["result"], "Success");
+        assert_eq!(responses.requests().len(), 2);
+        // The wait is derived from the strictest rate limit of the user, 60 edits per 60 seconds
+        let expected = ApiEvent::RateLimited {
+            action: "edit".to_string(),
+            wait: std::time::Duration::from_secs(1),
+            attempts_left: 0,
+        };
+        assert_eq!(*observed.lock().unwrap(), vec![expected.clone()]);
+
+        let error = api.post_query_api_json(&edit("B")).await.unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Max attempts reached [RATELIMITED]"));
+        assert_eq!(responses.requests().len(), 4);
+        assert_eq!(*observed.lock().unwrap(), vec![expected.clone(), expected]);
+    }
+
+    #[tokio::test]
+    async fn server_rate_limits_follow_user() {
+        let limited = json!({"query":{"userinfo":{"id":3,"name":"Foo","rights":["edit"],"ratelimits":{
+            "edit":{"user":{"hits":90,"seconds":60}},"upload":{"user":{"hits":8,"seconds":60}}}}}});
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add_once(&[("meta", "userinfo")], limited.clone())
+            .add_once(
+                &[("meta", "userinfo")],
+                json!({"query":{"userinfo":{"id":4,"name":"Bot","rights":["edit","noratelimit"]}}}),
+            )
+            .add(&[("meta", "userinfo")], limited)
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(&[("action", "logout")], json!({}));
+        let mut api = Api::new_mocked(responses).unwrap();
+        let limiter = api.rate_limiter().clone(); // Shares the limits
+        let upload_limit = || limiter.limit(RateLimitKind::Upload);
+        api.load_current_user_info().await.unwrap();
+        assert_eq!(upload_limit(), Some(RateLimit::per_minute(8)));
+        assert!(limiter.is_server_limit(RateLimitKind::Upload));
+
+        // Another user, with noratelimit
+        api.user_mut().set_user_info(None);
+        api.load_current_user_info().await.unwrap();
+        assert_eq!(upload_limit(), None);
+
+        api.set_rate_limit(RateLimit::per_minute(10));
+        api.user_mut().set_user_info(None);
+        api.load_current_user_info().await.unwrap();
+        assert_eq!(upload_limit(), Some(RateLimit::per_minute(8)));
+        assert_eq!(
+            limiter.limit(RateLimitKind::Edit),
+            Some(RateLimit::per_minute(10))
+        );
+
+        api.set_honor_server_rate_limits(false);
+        assert_eq!(upload_limit(), None);
+        api.set_honor_server_rate_limits(true);
+        assert_eq!(upload_limit(), Some(RateLimit::per_minute(8)));
+
+        api.logout().await.unwrap();
+        assert_eq!(upload_limit(), None);
+        assert_eq!(
+            limiter.limit(RateLimitKind::Edit),
+            Some(RateLimit::per_minute(10))
+        );
+    }
+
+    #[tokio::test]
+    async fn multipart_upload_checks() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add_once(
+                &[("action", "upload"), ("filename", "A.jpg")],
+                json!({"error":{"code":"maxlag","info":"Waiting for a database server","lag":0}}),
+            )
+            .add(
+                &[("action", "upload"), ("filename", "A.jpg")],
+                json!({"upload":{"result":"Success","filename":"A.jpg"}}),
+            )
+            .add(
+                &[("action", "upload"), ("filename", "B.jpg")],
+                json!({"error":{"code":"assertuserfailed","info":"You are no longer logged in"}}),
+            )
+            .add(&[("action", "logout")], json!({}));
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        api.set_maxlag(Some(5));
+        api.set_assert(Some(AssertLevel::User));
+        let upload = |filename: &str| {
+            let token = "abc+\\".to_string();
+            api.params_into(&[
+                ("action", "upload"),
+                ("filename", filename),
+                ("token", &token),
+            ])
+        };
+        let v = api
+            .post_query_api_multipart(&upload("A.jpg"), "file", "A.jpg", b"data".to_vec())
+            .await
+            .unwrap();
+        assert_eq!(v["upload"]["result"], "Success");
+        let requests = responses.requests();
+        let uploads: Vec<_> = requests
+            .iter()
+            .filter(|r| r.get("action").is_some_and(|a| a == "upload"))
+            .collect();
+        assert_eq!(uploads.len(), 2); // Retried after maxlag
+        assert_eq!(uploads[1]["maxlag"], "5");
+        assert_eq!(uploads[1]["assert"], "user");
+
+        assert!(matches!(
+            api.post_query_api_multipart(&upload("B.jpg"), "file", "B.jpg", b"data".to_vec())
+                .await,
+            Err(MediaWikiError::SessionExpired(code)) if code == "assertuserfailed"
+        ));
+
+        api.logout().await.unwrap();
+        let sent = responses.requests().len();
+        assert!(matches!(
+            api.post_query_api_multipart(&upload("A.jpg"), "file", "A.jpg", b"data".to_vec())
+                .await,
+            Err(MediaWikiError::NotLoggedIn)
+        ));
+        assert_eq!(responses.requests().len(), sent);
+    }
+
+    #[tokio::test]
+    async fn maxlag_on_reads() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("list", "allpages")],
+                json!({"error":{"code":"maxlag","info":"Waiting for a database server","lag":0}}),
+            )
+            .add(&[("meta", "userinfo")], json!({"query":{"userinfo":{}}}));
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        let params = api.params_into(&[("action", "query"), ("meta", "userinfo")]);
+        api.get_query_api_json(&params).await.unwrap();
+        assert!(!responses.requests().pop().unwrap().contains_key("maxlag"));
+        api.set_maxlag(Some(5));
+        api.set_maxlag_on_reads(true);
+        api.get_query_api_json(&params).await.unwrap();
+        assert_eq!(responses.requests().pop().unwrap()["maxlag"], "5");
+        api.set_max_read_retry_attempts(1);
+        let params = api.params_into(&[("action", "query"), ("list", "allpages")]);
+        assert!(api.get_query_api_json(&params).await.is_err());
+        assert_eq!(responses.requests().pop().unwrap()["maxlag"], "5");
+        assert_eq!(responses.requests().len(), 4); // Two userinfo reads, and allpages with one retry
+    }
+
+    #[test]
+    fn new_from_site_info() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("meta", "siteinfo")],
+            json!({"query":{"general":{"sitename":"Wikidata","lang":"en"},
+                "namespaces":{"120":{"id":120,"case":"case-sensitive","*":"Property","canonical":"Property"}}}}),
+        );
+        let json = Api::new_mocked(responses).unwrap().site_info_json();
+        let api = Api::new_from_site_info("https://www.wikidata.org/w/api.php", &json).unwrap();
+        assert_eq!(api.api_url(), "https://www.wikidata.org/w/api.php");
+        assert_eq!(api.site_info().site_name(), Some("Wikidata"));
+        assert_eq!(
+            Title::new_from_full("Property:P31", &api),
+            Title::new("P31", 120)
+        );
+        assert!(Api::new_from_site_info("https://www.wikidata.org/w/api.php", "{").is_err());
+    }
+
+    #[test]
+    fn api_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Api>();
+    }
+
+    #[test]
+    fn session_expired() {
+        assert!(matches!(
+            Api::check_session_expired(&json!({"error":{"code":"assertuserfailed"}})),
+            Err(MediaWikiError::SessionExpired(_))
+        ));
+        assert!(Api::check_session_expired(&json!({"error":{"code":"badtoken"}})).is_ok());
+    }
+
+    #[test]
+    fn assert_level_round_trip() {
+        for level in [AssertLevel::User, AssertLevel::Bot, AssertLevel::Anon] {
+            assert_eq!(level.to_string().parse::<AssertLevel>().unwrap(), level);
+        }
+        assert!("admin".parse::<AssertLevel>().is_err());
+    }
 
     #[tokio::test]
     async fn site_info() {
@@ -985,11 +2710,11 @@ mod tests {
 
     #[tokio::test]
     async fn get_token() {
-        let mut api = Api::new("https://www.wikidata.org/w/api.php")
+        let api = Api::new("https://www.wikidata.org/w/api.php")
             .await
             .unwrap();
         // Token for logged out users is always the same
-        assert!(!api.user.logged_in());
+        assert!(!api.user().logged_in());
         assert_eq!("+\\", api.get_token("csrf").await.unwrap());
         assert_eq!("+\\", api.get_edit_token().await.unwrap());
         assert!(api.get_token("notarealtokentype").await.is_err());
@@ -1055,18 +2780,18 @@ mod tests {
             .await
             .unwrap();
         assert_eq!(
-            api.extract_entity_from_uri(&"http://www.wikidata.org/entity/Q123")
+            api.extract_entity_from_uri("http://www.wikidata.org/entity/Q123")
                 .unwrap(),
             "Q123"
         );
         assert_eq!(
-            api.extract_entity_from_uri(&"http://www.wikidata.org/entity/P456")
+            api.extract_entity_from_uri("http://www.wikidata.org/entity/P456")
                 .unwrap(),
             "P456"
         );
         // Expect error ('/' missing):
         assert!(api
-            .extract_entity_from_uri(&"http:/www.wikidata.org/entity/Q123")
+            .extract_entity_from_uri("http:/www.wikidata.org/entity/Q123")
             .is_err());
     }
 
@@ -0,0 +1,237 @@
+/*!
+The `ApiBuilder` class configures an `Api` with fluent setters before it is created, via `Api::builder()`.
+
+```no_run
+# tokio::runtime::Runtime::new().unwrap().block_on(async {
+let api = mediawiki::api::Api::builder("https://en.wikipedia.org/w/api.php")
+    .user_agent("MyBot/1.0 (https://example.org/mybot)")
+    .maxlag(Some(5))
+    .edit_delay(Some(1000))
+    .build()
+    .await
+    .unwrap();
+# });
+```
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, AssertLevel, BasicAuth, OAuthParams, DEFAULT_TIMEOUT};
+use crate::edit_summary::EditSummary;
+use crate::media_wiki_error::MediaWikiError;
+
+/// Collects the options for a new `Api`; options that are not set keep the `Api` defaults
+#[derive(Debug)]
+pub struct ApiBuilder {
+    api_url: String,
+    client_builder: Option<reqwest::ClientBuilder>,
+    basic_auth: Option<BasicAuth>,
+    site_info: Option<String>,
+    user_agent: Option<String>,
+    edit_summary: Option<EditSummary>,
+    edit_tags: Option<Vec<String>>,
+    maxlag: Option<Option<u64>>,
+    maxlag_on_reads: Option<bool>,
+    edit_delay: Option<Option<u64>>,
+    max_retry_attempts: Option<u64>,
+    max_read_retry_attempts: Option<u64>,
+    oauth: Option<OAuthParams>,
+    oauth2: Option<String>,
+    assert_level: Option<AssertLevel>,
+}
+
+impl ApiBuilder {
+    /// Creates a builder for the API at `api_url`
+    pub fn new(api_url: &str) -> Self {
+        Self {
+            api_url: api_url.to_string(),
+            client_builder: None,
+            basic_auth: None,
+            site_info: None,
+            user_agent: None,
+            edit_summary: None,
+            edit_tags: None,
+            maxlag: None,
+            maxlag_on_reads: None,
+            edit_delay: None,
+            max_retry_attempts: None,
+            max_read_retry_attempts: None,
+            oauth: None,
+            oauth2: None,
+            assert_level: None,
+        }
+    }
+
+    /// Uses a bespoke `reqwest::ClientBuilder`, e.g. for proxies or timeouts
+    pub fn client_builder(mut self, client_builder: reqwest::ClientBuilder) -> Self {
+        self.client_builder = Some(client_builder);
+        self
+    }
+
+    /// Sends HTTP basic auth credentials with every request
+    pub fn basic_auth(mut self, basic_auth: BasicAuth) -> Self {
+        self.basic_auth = Some(basic_auth);
+        self
+    }
+
+    /// Uses site info saved earlier via `Api::site_info_json`, instead of loading it from the wiki
+    pub fn site_info(mut self, site_info: &str) -> Self {
+        self.site_info = Some(site_info.to_string());
+        self
+    }
+
+    /// Sets the user agent name; see `Api::set_user_agent`
+    pub fn user_agent<S: Into<String>>(mut self, agent: S) -> Self {
+        self.user_agent = Some(agent.into());
+        self
+    }
+
+    /// Sets the tool name and hashtag to append to edit summaries; see `Api::set_edit_summary`
+    pub fn edit_summary(mut self, edit_summary: EditSummary) -> Self {
+        self.edit_summary = Some(edit_summary);
+        self
+    }
+
+    /// Sets the change tags to apply to edits; see `Api::set_edit_tags`
+    pub fn edit_tags(mut self, tags: &[&str]) -> Self {
+        self.edit_tags = Some(tags.iter().map(|s| s.to_string()).collect());
+        self
+    }
+
+    /// Sets the maxlag in seconds (or `None`); see `Api::set_maxlag`
+    pub fn maxlag(mut self, maxlag_seconds: Option<u64>) -> Self {
+        self.maxlag = Some(maxlag_seconds);
+        self
+    }
+
+    /// Also sends maxlag with read queries; see `Api::set_maxlag_on_reads`
+    pub fn maxlag_on_reads(mut self, maxlag_on_reads: bool) -> Self {
+        self.maxlag_on_reads = Some(maxlag_on_reads);
+        self
+    }
+
+    /// Sets the delay after edits in milliseconds (or `None`); see `Api::set_edit_delay`
+    pub fn edit_delay(mut self, edit_delay_ms: Option<u64>) -> Self {
+        self.edit_delay = Some(edit_delay_ms);
+        self
+    }
+
+    /// Sets the maximum number of retry attempts for edits; see `Api::set_max_retry_attempts`
+    pub fn max_retry_attempts(mut self, max_retry_attempts: u64) -> Self {
+        self.max_retry_attempts = Some(max_retry_attempts);
+        self
+    }
+
+    /// Sets the maximum number of retry attempts for reads; see `Api::set_max_read_retry_attempts`
+    pub fn max_read_retry_attempts(mut self, max_read_retry_attempts: u64) -> Self {
+        self.max_read_retry_attempts = Some(max_read_retry_attempts);
+        self
+    }
+
+    /// Authorizes requests with OAuth 1; see `Api::set_oauth`
+    pub fn oauth(mut self, oauth: OAuthParams) -> Self {
+        self.oauth = Some(oauth);
+        self
+    }
+
+    /// Authorizes requests with an OAuth 2 access token; see `Api::set_oauth2`
+    pub fn oauth2(mut self, oauth2: &str) -> Self {
+        self.oauth2 = Some(oauth2.to_string());
+        self
+    }
+
+    /// Sets the `assert` level for edits; see `Api::set_assert`
+    pub fn assert(mut self, assert_level: AssertLevel) -> Self {
+        self.assert_level = Some(assert_level);
+        self
+    }
+
+    /// Creates the `Api`, and loads the site info unless it was given via `site_info`
+    pub async fn build(self) -> Result<Api, MediaWikiError> {
+        let client_builder = self
+            .client_builder
+            .unwrap_or_else(|| reqwest::Client::builder().timeout(DEFAULT_TIMEOUT));
+        let mut api = Api::new_without_site_info(&self.api_url, client_builder, self.basic_auth)?;
+        if let Some(agent) = self.user_agent {
+            api.set_user_agent(agent);
+        }
+        if let Some(edit_summary) = self.edit_summary {
+            api.set_edit_summary(edit_summary);
+        }
+        if let Some(tags) = &self.edit_tags {
+            let tags: Vec<&str> = tags.iter().map(|s| s.as_str()).collect();
+            api.set_edit_tags(&tags);
+        }
+        if let Some(maxlag) = self.maxlag {
+            api.set_maxlag(maxlag);
+        }
+        if let Some(maxlag_on_reads) = self.maxlag_on_reads {
+            api.set_maxlag_on_reads(maxlag_on_reads);
+        }
+        if let Some(edit_delay) = self.edit_delay {
+            api.set_edit_delay(edit_delay);
+        }
+        if let Some(attempts) = self.max_retry_attempts {
+            api.set_max_retry_attempts(attempts);
+        }
+        if let Some(attempts) = self.max_read_retry_attempts {
+            api.set_max_read_retry_attempts(attempts);
+        }
+        if let Some(oauth) = self.oauth {
+            api.set_oauth(Some(oauth));
+        }
+        if let Some(oauth2) = &self.oauth2 {
+            api.set_oauth2(oauth2);
+        }
+        if self.assert_level.is_some() {
+            api.set_assert(self.assert_level);
+        }
+        match self.site_info {
+            Some(site_info) => api.set_site_info(serde_json::from_str(&site_info)?),
+            None => {
+                api.load_site_info().await?;
+            }
+        }
+        Ok(api)
+    }
+}
+
+impl Api {
+    /// Returns a builder for an `Api` with custom options; see `ApiBuilder`
+    pub fn builder(api_url: &str) -> ApiBuilder {
+        ApiBuilder::new(api_url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn build() {
+        let site_info = json!({"query":{"general":{"sitename":"Test","lang":"en"}}}).to_string();
+        let api = Api::builder("https://test.example/w/api.php")
+            .site_info(&site_info)
+            .user_agent("TestBot/1.0")
+            .maxlag(None)
+            .edit_delay(Some(500))
+            .max_retry_attempts(2)
+            .assert(AssertLevel::Bot)
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(api.api_url(), "https://test.example/w/api.php");
+        assert_eq!(api.site_info().site_name(), Some("Test"));
+        assert_eq!(api.user_agent(), "TestBot/1.0");
+        assert_eq!(api.maxlag(), &None);
+        assert_eq!(api.edit_delay(), &Some(500));
+        assert_eq!(api.max_retry_attempts(), 2);
+        assert_eq!(api.max_read_retry_attempts(), 5);
+        assert_eq!(api.assert_level(), Some(AssertLevel::Bot));
+        assert!(Api::builder("https://test.example/w/api.php")
+            .site_info("not json")
+            .build()
+            .await
+            .is_err());
+    }
+}
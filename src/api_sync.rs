@@ -6,6 +6,7 @@ This sync version is kept for backwards compatibility.
 #![deny(missing_docs)]
 
 use crate::api::OAuthParams;
+use crate::login::LoginError;
 use crate::media_wiki_error::MediaWikiError;
 use crate::title::Title;
 use crate::user::User;
@@ -217,7 +218,7 @@ impl ApiSync {
             params.insert("type".to_string(), token_type.to_string());
         }
         let mut key = token_type.to_string();
-        key += &"token";
+        key += "token";
         if token_type.is_empty() {
             key = "csrftoken".into()
         }
@@ -546,7 +547,7 @@ impl ApiSync {
         to_sign: &HashMap<String, String>,
         oauth: &OAuthParams,
     ) -> Result<String, MediaWikiError> {
-        let mut keys: Vec<String> = to_sign.iter().map(|(k, _)| self.rawurlencode(k)).collect();
+        let mut keys: Vec<String> = to_sign.keys().map(|k| self.rawurlencode(k)).collect();
         keys.sort();
 
         let ret: Vec<String> = keys
@@ -761,7 +762,7 @@ impl ApiSync {
             self.user.set_from_login(&res["login"])?;
             self.load_current_user_info()
         } else {
-            Err(From::from("Login failed"))
+            Err(MediaWikiError::Login(LoginError::from_json(&res["login"])))
         }
     }
 
@@ -919,18 +920,18 @@ mod tests {
     fn extract_entity_from_uri() {
         let api = ApiSync::new("https://www.wikidata.org/w/api.php").unwrap();
         assert_eq!(
-            api.extract_entity_from_uri(&"http://www.wikidata.org/entity/Q123")
+            api.extract_entity_from_uri("http://www.wikidata.org/entity/Q123")
                 .unwrap(),
             "Q123"
         );
         assert_eq!(
-            api.extract_entity_from_uri(&"http://www.wikidata.org/entity/P456")
+            api.extract_entity_from_uri("http://www.wikidata.org/entity/P456")
                 .unwrap(),
             "P456"
         );
         // Expect error ('/' missing):
         assert!(api
-            .extract_entity_from_uri(&"http:/www.wikidata.org/entity/Q123")
+            .extract_entity_from_uri("http:/www.wikidata.org/entity/Q123")
             .is_err());
     }
 
@@ -0,0 +1,234 @@
+/*!
+The `backlinks` module lists the pages linking to a page via `list=backlinks`, and the pages
+transcluding a template via `list=embeddedin`, as streams that handle continuation.
+Unlike `Page::links_here`, the target page does not need to be loaded first.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+
+/// Which pages to return, depending on whether they are redirects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RedirectFilter {
+    /// Redirects and other pages
+    #[default]
+    All,
+    /// Redirects only
+    Redirects,
+    /// Pages that are not redirects only
+    NonRedirects,
+}
+
+impl RedirectFilter {
+    /// Returns the value of the `filterredir` parameters
+    pub fn as_str(&self) -> &str {
+        match self {
+            RedirectFilter::All => "all",
+            RedirectFilter::Redirects => "redirects",
+            RedirectFilter::NonRedirects => "nonredirects",
+        }
+    }
+}
+
+/// Options for `Api::backlinks` and `Api::embedded_in`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BacklinksOptions {
+    namespaces: Vec<NamespaceID>,
+    filter_redirects: RedirectFilter,
+    through_redirects: bool,
+}
+
+impl BacklinksOptions {
+    /// Creates options for all pages in all namespaces
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the namespaces pages are returned from; empty for all namespaces
+    pub fn namespaces(&self) -> &[NamespaceID] {
+        &self.namespaces
+    }
+
+    /// Only returns pages from these namespaces; empty for all namespaces
+    pub fn set_namespaces(&mut self, namespaces: &[NamespaceID]) {
+        self.namespaces = namespaces.to_vec();
+    }
+
+    /// Returns which pages are returned, depending on whether they are redirects
+    pub fn filter_redirects(&self) -> RedirectFilter {
+        self.filter_redirects
+    }
+
+    /// Sets which pages are returned, depending on whether they are redirects
+    pub fn set_filter_redirects(&mut self, filter_redirects: RedirectFilter) {
+        self.filter_redirects = filter_redirects;
+    }
+
+    /// Returns true if pages linking to redirects to the page are returned as well
+    pub fn through_redirects(&self) -> bool {
+        self.through_redirects
+    }
+
+    /// Also returns pages linking to redirects to the page (`blredirect`); `Api::backlinks` only
+    pub fn set_through_redirects(&mut self, through_redirects: bool) {
+        self.through_redirects = through_redirects;
+    }
+
+    /// Returns the API parameters for these options, with the parameter prefix, e.g. `bl`
+    fn params(&self, prefix: &str, redirect_param: bool) -> Vec<(String, String)> {
+        let mut ret = vec![(
+            format!("{}filterredir", prefix),
+            self.filter_redirects.as_str().to_string(),
+        )];
+        if !self.namespaces.is_empty() {
+            let namespaces: Vec<String> = self.namespaces.iter().map(|ns| ns.to_string()).collect();
+            ret.push((format!("{}namespace", prefix), namespaces.join("|")));
+        }
+        if redirect_param && self.through_redirects {
+            ret.push((format!("{}redirect", prefix), "1".to_string()));
+        }
+        ret
+    }
+}
+
+impl Api {
+    /// Returns the pages linking to `title` as a stream, via `list=backlinks`
+    pub fn backlinks<'a>(
+        &'a self,
+        title: &Title,
+        options: &BacklinksOptions,
+    ) -> Result<impl Stream<Item = Result<Title, MediaWikiError>> + 'a, MediaWikiError> {
+        self.backlinks_stream("backlinks", "bl", title, options)
+    }
+
+    /// Returns the pages transcluding `template_title` as a stream, via `list=embeddedin`.
+    /// `BacklinksOptions::through_redirects` does not apply, as transclusions already follow redirects.
+    pub fn embedded_in<'a>(
+        &'a self,
+        template_title: &Title,
+        options: &BacklinksOptions,
+    ) -> Result<impl Stream<Item = Result<Title, MediaWikiError>> + 'a, MediaWikiError> {
+        self.backlinks_stream("embeddedin", "ei", template_title, options)
+    }
+
+    /// Returns a stream of the titles in `["query"][list]`, including those of pages linking via redirects
+    fn backlinks_stream<'a>(
+        &'a self,
+        list: &'a str,
+        prefix: &str,
+        title: &Title,
+        options: &BacklinksOptions,
+    ) -> Result<impl Stream<Item = Result<Title, MediaWikiError>> + 'a, MediaWikiError> {
+        let full_title = title
+            .full_pretty(self)
+            .ok_or_else(|| MediaWikiError::BadTitle(title.clone()))?;
+        let mut params = self.params_into(&[
+            ("action", "query"),
+            ("list", list),
+            (&format!("{}title", prefix), &full_title),
+            (&format!("{}limit", prefix), "max"),
+        ]);
+        params.extend(options.params(prefix, list == "backlinks"));
+        Ok(futures::stream::once(
+            async move { self.get_query_api_json_limit_iter(&params, None).await },
+        )
+        .flatten()
+        .flat_map(move |result| {
+            let items: Vec<Result<Title, MediaWikiError>> = match result {
+                Ok(result) => result["query"][list]
+                    .as_array()
+                    .map(|pages| {
+                        pages
+                            .iter()
+                            .flat_map(Self::backlink_titles)
+                            .map(Ok)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(items)
+        }))
+    }
+
+    /// Returns the title of a backlink, followed by those of the pages linking to it if it is a redirect
+    fn backlink_titles(j: &Value) -> Vec<Title> {
+        let mut ret = vec![Title::new_from_api_result(j)];
+        if let Some(redirlinks) = j["redirlinks"].as_array() {
+            ret.extend(redirlinks.iter().map(Title::new_from_api_result));
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn backlinks() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"namespaces":{
+                    "0":{"id":0,"case":"first-letter","*":""},
+                    "10":{"id":10,"case":"first-letter","*":"Template","canonical":"Template"}}}}),
+            )
+            .add(
+                &[("list", "backlinks"), ("blcontinue", "0|5")],
+                json!({"batchcomplete":true,"query":{"backlinks":[{"pageid":5,"ns":0,"title":"Baz"}]}}),
+            )
+            .add(
+                &[("list", "backlinks")],
+                json!({"continue":{"blcontinue":"0|5","continue":"-||"},"query":{"backlinks":[
+                    {"pageid":3,"ns":0,"title":"Bar","redirect":true,
+                        "redirlinks":[{"pageid":4,"ns":0,"title":"Qux"}]}]}}),
+            )
+            .add(
+                &[("list", "embeddedin")],
+                json!({"batchcomplete":true,"query":{"embeddedin":[{"pageid":3,"ns":0,"title":"Bar"}]}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut options = BacklinksOptions::new();
+        options.set_namespaces(&[0]);
+        options.set_filter_redirects(RedirectFilter::Redirects);
+        options.set_through_redirects(true);
+        let titles: Vec<Title> = api
+            .backlinks(&Title::new("Foo", 0), &options)
+            .unwrap()
+            .map(|t| t.unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            titles,
+            vec![
+                Title::new("Bar", 0),
+                Title::new("Qux", 0),
+                Title::new("Baz", 0)
+            ]
+        );
+        let request = &responses.requests()[0];
+        assert_eq!(request["bltitle"], "Foo");
+        assert_eq!(request["blnamespace"], "0");
+        assert_eq!(request["blfilterredir"], "redirects");
+        assert_eq!(request["blredirect"], "1");
+
+        let titles: Vec<Title> = api
+            .embedded_in(&Title::new("Foo", 10), &options)
+            .unwrap()
+            .map(|t| t.unwrap())
+            .collect()
+            .await;
+        assert_eq!(titles, vec![Title::new("Bar", 0)]);
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["eititle"], "Template:Foo");
+        assert!(!request.contains_key("eiredirect"));
+    }
+}
@@ -8,7 +8,7 @@ use std::fs::File;
 use mediawiki::Api;
 use mediawiki::MediaWikiError;
 
-async fn edit_sandbox_item(api: &mut Api) -> Result<Value, MediaWikiError> {
+async fn edit_sandbox_item(api: &Api) -> Result<Value, MediaWikiError> {
     let q = "Q13406268"; // Second sandbox item
     let token = api.get_edit_token().await.unwrap();
     let params: HashMap<String, String> = vec![
@@ -28,7 +28,7 @@ async fn edit_sandbox_item(api: &mut Api) -> Result<Value, MediaWikiError> {
     api.post_query_api_json(&params).await
 }
 
-async fn login_api_from_config(api: &mut Api) {
+async fn login_api_from_config(api: &Api) {
     let settings = Config::builder()
         .add_source(config::File::with_name("test.ini"))
         .build()
@@ -38,7 +38,7 @@ async fn login_api_from_config(api: &mut Api) {
     api.login(lgname, lgpassword).await.unwrap();
 }
 
-async fn oauth_edit(api: &mut Api) {
+async fn oauth_edit(api: &Api) {
     let sandbox_item = "Q13406268";
     let file = File::open("oauth_test.json").expect("File oauth_test.json not found");
     let j =
@@ -66,7 +66,7 @@ async fn oauth_edit(api: &mut Api) {
             .expect("Could not get edit token"),
     );
 
-    match api.post_query_api_json_mut(&params).await {
+    match api.post_query_api_json(&params).await {
         Ok(_) => println!("Edited https://www.wikidata.org/wiki/{}", sandbox_item),
         Err(e) => panic!("{:?}", &e),
     }
@@ -102,12 +102,12 @@ async fn main() {
     // Wikidata
     // Deactivated, because editing...
     if false {
-        let mut api = Api::new("https://www.wikipedia.org/w/api.php")
+        let api = Api::new("https://www.wikipedia.org/w/api.php")
             .await
             .unwrap();
 
-        login_api_from_config(&mut api).await;
-        oauth_edit(&mut api).await;
-        edit_sandbox_item(&mut api).await.unwrap();
+        login_api_from_config(&api).await;
+        oauth_edit(&api).await;
+        edit_sandbox_item(&api).await.unwrap();
     }
 }
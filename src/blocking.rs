@@ -0,0 +1,435 @@
+/*!
+The `blocking` module offers a synchronous facade over `Api`, for simple scripts and build tools.
+It runs the async client on an internal tokio runtime, so it must not be used from within an async context.
+
+```no_run
+let api = mediawiki::blocking::Api::new("https://en.wikipedia.org/w/api.php").unwrap();
+let text = api.page_text(&mediawiki::Title::new("Rust (programming language)", 0)).unwrap();
+```
+*/
+
+#![deny(missing_docs)]
+
+use crate::backlinks::BacklinksOptions;
+use crate::langlinks::LangLink;
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::{ExtractOptions, Page};
+use crate::page_info::PageInfo;
+use crate::revision::{Comparison, Revision};
+use crate::section::Section;
+use crate::title::Title;
+use crate::user_contribs::{UserContrib, UserContribsOptions};
+use futures::TryStreamExt;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+/// A blocking wrapper around the async `Api`
+#[derive(Debug)]
+pub struct Api {
+    api: crate::api::Api,
+    runtime: Arc<Runtime>,
+}
+
+impl Api {
+    /// Returns a new blocking `Api`, and loads the MediaWiki site info from the `api_url` site.
+    pub fn new(api_url: &str) -> Result<Self, MediaWikiError> {
+        let runtime = Runtime::new()?;
+        let api = runtime.block_on(crate::api::Api::new(api_url))?;
+        Ok(Self::from_api(api, Arc::new(runtime)))
+    }
+
+    /// Wraps an existing async `Api`, using the given runtime
+    pub fn from_api(api: crate::api::Api, runtime: Arc<Runtime>) -> Self {
+        Self { api, runtime }
+    }
+
+    /// Returns the wrapped async `Api`
+    pub fn api(&self) -> &crate::api::Api {
+        &self.api
+    }
+
+    /// Returns the wrapped async `Api`, mutable
+    pub fn api_mut(&mut self) -> &mut crate::api::Api {
+        &mut self.api
+    }
+
+    /// Returns the internal runtime, to run other async `Api` methods.
+    ///
+    /// ```no_run
+    /// # let mut api = mediawiki::blocking::Api::new("https://en.wikipedia.org/w/api.php").unwrap();
+    /// let runtime = api.runtime();
+    /// runtime.block_on(api.api().load_current_user_info()).unwrap();
+    /// ```
+    pub fn runtime(&self) -> Arc<Runtime> {
+        self.runtime.clone()
+    }
+
+    /// Runs any future, e.g. one of the async `Api` methods, to completion
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Logs in with the given user name and password
+    pub fn login<S: Into<String>>(&self, lgname: S, lgpassword: S) -> Result<(), MediaWikiError> {
+        self.runtime.block_on(self.api.login(lgname, lgpassword))
+    }
+
+    /// Runs a GET query, see `Api::get_query_api_json`
+    pub fn get_query_api_json(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Result<Value, MediaWikiError> {
+        self.block_on(self.api.get_query_api_json(params))
+    }
+
+    /// Runs a GET query and loads all continued results, see `Api::get_query_api_json_all`
+    pub fn get_query_api_json_all(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Result<Value, MediaWikiError> {
+        self.block_on(self.api.get_query_api_json_all(params))
+    }
+
+    /// Runs a POST query, see `Api::post_query_api_json`
+    pub fn post_query_api_json(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Result<Value, MediaWikiError> {
+        self.runtime.block_on(self.api.post_query_api_json(params))
+    }
+
+    /// Runs a GET query, and deserializes the result, see `Api::get_query_typed`
+    pub fn get_query_typed<T: DeserializeOwned>(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Result<T, MediaWikiError> {
+        self.block_on(self.api.get_query_typed(params))
+    }
+
+    /// Runs a GET query with continuation, and deserializes the pages, see `Api::get_query_pages_typed`
+    pub fn get_query_pages_typed<T: DeserializeOwned>(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Result<Vec<T>, MediaWikiError> {
+        self.block_on(self.api.get_query_pages_typed(params))
+    }
+
+    /// Fetches a single revision by ID, see `Api::revision`
+    pub fn revision(&self, revid: u64) -> Result<Revision, MediaWikiError> {
+        self.block_on(self.api.revision(revid))
+    }
+
+    /// Fetches revisions by ID, see `Api::revisions`
+    pub fn revisions(&self, revids: &[u64]) -> Result<Vec<Revision>, MediaWikiError> {
+        self.block_on(self.api.revisions(revids))
+    }
+
+    /// Compares two revisions, see `Api::compare`
+    pub fn compare(&self, old_revid: u64, new_revid: u64) -> Result<Comparison, MediaWikiError> {
+        self.block_on(self.api.compare(old_revid, new_revid))
+    }
+
+    /// Returns information about a page, see `Page::info`
+    pub fn page_info(&self, title: &Title) -> Result<PageInfo, MediaWikiError> {
+        let page = Page::new(title.clone());
+        self.block_on(page.info(&self.api))
+    }
+
+    /// Returns true if the page exists
+    pub fn page_exists(&self, title: &Title) -> Result<bool, MediaWikiError> {
+        let page = Page::new(title.clone());
+        self.block_on(page.exists(&self.api))
+    }
+
+    /// Returns the page properties of a page, see `Page::pageprops`
+    pub fn page_props(&self, title: &Title) -> Result<HashMap<String, String>, MediaWikiError> {
+        let page = Page::new(title.clone());
+        self.block_on(page.pageprops(&self.api))
+    }
+
+    /// Returns the interlanguage links of a page, see `Page::langlinks`
+    pub fn langlinks(&self, title: &Title) -> Result<Vec<LangLink>, MediaWikiError> {
+        let page = Page::new(title.clone());
+        self.block_on(page.langlinks(&self.api))
+    }
+
+    /// Returns the Wikidata item ID of a page, if any, see `Page::wikidata_item`
+    pub fn wikidata_item(&self, title: &Title) -> Result<Option<String>, MediaWikiError> {
+        let page = Page::new(title.clone());
+        self.block_on(page.wikidata_item(&self.api))
+    }
+
+    /// Returns the sections of a page, see `Page::sections`
+    pub fn page_sections(&self, title: &Title) -> Result<Vec<Section>, MediaWikiError> {
+        let page = Page::new(title.clone());
+        self.block_on(page.sections(&self.api))
+    }
+
+    /// Returns the rendered HTML of a page, see `Page::html`
+    pub fn page_html(
+        &self,
+        title: &Title,
+        variant: Option<&str>,
+    ) -> Result<String, MediaWikiError> {
+        let page = Page::new(title.clone());
+        self.block_on(page.html(&self.api, variant))
+    }
+
+    /// Returns all pages linking to `title`, see `Api::backlinks`
+    pub fn backlinks(
+        &self,
+        title: &Title,
+        options: &BacklinksOptions,
+    ) -> Result<Vec<Title>, MediaWikiError> {
+        self.block_on(self.api.backlinks(title, options)?.try_collect())
+    }
+
+    /// Returns all pages transcluding `template_title`, see `Api::embedded_in`
+    pub fn embedded_in(
+        &self,
+        template_title: &Title,
+        options: &BacklinksOptions,
+    ) -> Result<Vec<Title>, MediaWikiError> {
+        self.block_on(self.api.embedded_in(template_title, options)?.try_collect())
+    }
+
+    /// Returns all contributions of a user or IP address, see `Api::user_contribs`
+    pub fn user_contribs(
+        &self,
+        username: &str,
+        options: &UserContribsOptions,
+    ) -> Result<Vec<UserContrib>, MediaWikiError> {
+        self.block_on(async {
+            self.api
+                .user_contribs(username, options)
+                .await
+                .try_collect()
+                .await
+        })
+    }
+
+    /// Returns the wikitext of the current revision of a page
+    pub fn page_text(&self, title: &Title) -> Result<String, MediaWikiError> {
+        let mut page = Page::new(title.clone());
+        self.block_on(async { page.text(&self.api).await.map(|s| s.to_string()) })
+    }
+
+    /// Returns a summary of a page, see `Page::extract`
+    pub fn page_extract(
+        &self,
+        title: &Title,
+        options: &ExtractOptions,
+    ) -> Result<String, MediaWikiError> {
+        let page = Page::new(title.clone());
+        self.block_on(page.extract(&self.api, options))
+    }
+
+    /// Replaces the text of a page, see `Page::edit_text`
+    pub fn edit_text(
+        &self,
+        title: &Title,
+        text: impl Into<String>,
+        summary: impl Into<String>,
+    ) -> Result<(), MediaWikiError> {
+        let page = Page::new(title.clone());
+        self.runtime
+            .block_on(page.edit_text(&self.api, text, summary))
+    }
+
+    /// Returns up to `limit` titles matching a full-text search
+    pub fn search(&self, query: &str, limit: Option<usize>) -> Result<Vec<Title>, MediaWikiError> {
+        let params = self.api.params_into(&[
+            ("action", "query"),
+            ("list", "search"),
+            ("srsearch", query),
+            ("srlimit", "max"),
+            ("srprop", ""),
+        ]);
+        let result = self.block_on(self.api.get_query_api_json_limit(&params, limit))?;
+        Ok(crate::api::Api::result_array_to_titles(
+            &result["query"]["search"],
+        ))
+    }
+
+    /// Returns all members of a category
+    pub fn category_members(&self, category: &Title) -> Result<Vec<Title>, MediaWikiError> {
+        let category = category
+            .full_pretty(&self.api)
+            .ok_or_else(|| MediaWikiError::BadTitle(category.clone()))?;
+        let params = self.api.params_into(&[
+            ("action", "query"),
+            ("list", "categorymembers"),
+            ("cmtitle", &category),
+            ("cmlimit", "max"),
+        ]);
+        let result = self.get_query_api_json_all(&params)?;
+        Ok(crate::api::Api::result_array_to_titles(
+            &result["query"]["categorymembers"],
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    fn new_api(mut responses: MockResponses) -> Api {
+        responses.add(
+            &[("meta", "siteinfo")],
+            json!({"query":{"namespaces":{
+                "0":{"id":0,"case":"first-letter","*":""},
+                "10":{"id":10,"case":"first-letter","*":"Template","canonical":"Template"},
+                "14":{"id":14,"case":"first-letter","*":"Category","canonical":"Category"}}}}),
+        );
+        let api = crate::api::Api::new_mocked(responses).unwrap();
+        Api::from_api(api, Arc::new(Runtime::new().unwrap()))
+    }
+
+    #[test]
+    fn pages_and_edits() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("prop", "revisions"), ("titles", "Foo")],
+                json!({"curtimestamp":"2024-01-02T03:04:05Z","query":{"pages":[{"pageid":1,"ns":0,"title":"Foo",
+                    "revisions":[{"revid":5,"timestamp":"2024-01-01T00:00:00Z","slots":{"main":{"content":"Bar"}}}]}]}}),
+            )
+            .add(&[("meta", "tokens")], json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}))
+            .add(&[("action", "edit")], json!({"edit":{"result":"Success","newrevid":6}}))
+            .add(
+                &[("list", "search")],
+                json!({"query":{"search":[{"ns":0,"title":"Foo"}]}}),
+            )
+            .add(
+                &[("list", "categorymembers")],
+                json!({"query":{"categorymembers":[{"ns":0,"title":"Foo"},{"ns":0,"title":"Bar"}]}}),
+            );
+        let api = new_api(responses);
+        let title = Title::new("Foo", 0);
+        assert_eq!(api.page_text(&title).unwrap(), "Bar");
+        api.edit_text(&title, "Baz", "Test").unwrap();
+        assert_eq!(api.search("Foo", Some(10)).unwrap(), vec![title.clone()]);
+        let members = api.category_members(&Title::new("Things", 14)).unwrap();
+        assert_eq!(members.len(), 2);
+    }
+
+    #[test]
+    fn typed_queries() {
+        #[derive(Deserialize)]
+        struct PageStub {
+            pageid: u64,
+        }
+        let mut responses = MockResponses::new();
+        responses.add(
+            &[("titles", "Foo")],
+            json!({"batchcomplete":"","query":{"pages":{"12":{"pageid":12,"ns":0,"title":"Foo"}}}}),
+        );
+        let api = new_api(responses);
+        let params = api
+            .api()
+            .params_into(&[("action", "query"), ("titles", "Foo")]);
+        let result: Value = api.get_query_typed(&params).unwrap();
+        assert_eq!(result["query"]["pages"]["12"]["title"], "Foo");
+        let pages: Vec<PageStub> = api.get_query_pages_typed(&params).unwrap();
+        assert_eq!(pages[0].pageid, 12);
+    }
+
+    #[test]
+    fn revisions() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("action", "compare")],
+                json!({"compare":{"fromid":1,"fromrevid":122,"fromns":0,"fromtitle":"Foo",
+                    "toid":1,"torevid":123,"tons":0,"totitle":"Foo","body":"<tr><td>diff</td></tr>"}}),
+            )
+            .add(
+                &[("prop", "revisions")],
+                json!({"batchcomplete":true,"query":{"pages":[{"pageid":1,"ns":0,"title":"Foo","revisions":[
+                    {"revid":122,"parentid":0,"slots":{"main":{"content":"Old"}}},
+                    {"revid":123,"parentid":122,"slots":{"main":{"content":"New"}}}]}]}}),
+            );
+        let api = new_api(responses);
+        assert_eq!(api.revision(123).unwrap().wikitext(), Some("New"));
+        assert_eq!(api.revisions(&[122, 123]).unwrap().len(), 2);
+        assert_eq!(api.compare(122, 123).unwrap().to_revid(), 123);
+    }
+
+    #[test]
+    fn page_data() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("prop", "info")],
+                json!({"query":{"pages":{"123":{"pageid":123,"ns":0,"title":"Paris",
+                    "contentmodel":"wikitext","lastrevid":456,"length":789,"protection":[]}}}}),
+            )
+            .add(
+                &[("prop", "langlinks")],
+                json!({"query":{"pages":{"123":{"pageid":123,"ns":0,"title":"Paris","langlinks":[
+                    {"lang":"de","url":"https://de.wikipedia.org/wiki/Paris","*":"Paris"}]}}}}),
+            )
+            .add(
+                &[("prop", "pageprops")],
+                json!({"query":{"pages":{"123":{"pageid":123,"ns":0,"title":"Paris","pageprops":{"wikibase_item":"Q90"}}}}}),
+            )
+            .add(
+                &[("action", "parse"), ("prop", "sections")],
+                json!({"parse":{"title":"Paris","sections":[{"level":"2","line":"History","number":"1","index":"1"}]}}),
+            )
+            .add(
+                &[("action", "parse"), ("prop", "text")],
+                json!({"parse":{"title":"Paris","text":"<p>Paris</p>"}}),
+            );
+        let api = new_api(responses);
+        let title = Title::new("Paris", 0);
+        assert_eq!(api.page_info(&title).unwrap().length(), Some(789));
+        assert!(api.page_exists(&title).unwrap());
+        assert_eq!(api.page_props(&title).unwrap()["wikibase_item"], "Q90");
+        assert_eq!(api.langlinks(&title).unwrap()[0].lang(), "de");
+        assert_eq!(api.wikidata_item(&title).unwrap(), Some("Q90".to_string()));
+        assert_eq!(api.page_sections(&title).unwrap()[0].line(), "History");
+        assert_eq!(api.page_html(&title, None).unwrap(), "<p>Paris</p>");
+    }
+
+    #[test]
+    fn lists() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("list", "backlinks")],
+                json!({"batchcomplete":true,"query":{"backlinks":[{"pageid":5,"ns":0,"title":"Baz"}]}}),
+            )
+            .add(
+                &[("list", "embeddedin")],
+                json!({"batchcomplete":true,"query":{"embeddedin":[{"pageid":3,"ns":0,"title":"Bar"}]}}),
+            )
+            .add(
+                &[("list", "usercontribs")],
+                json!({"batchcomplete":true,"query":{"usercontribs":[
+                    {"pageid":3,"revid":7,"parentid":6,"ns":0,"title":"Bar","timestamp":"2024-01-02T00:00:00Z"}]}}),
+            );
+        let api = new_api(responses);
+        let options = BacklinksOptions::new();
+        assert_eq!(
+            api.backlinks(&Title::new("Foo", 0), &options).unwrap(),
+            vec![Title::new("Baz", 0)]
+        );
+        assert_eq!(
+            api.embedded_in(&Title::new("Infobox", 10), &options)
+                .unwrap(),
+            vec![Title::new("Bar", 0)]
+        );
+        let contribs = api
+            .user_contribs("Alice", &UserContribsOptions::new())
+            .unwrap();
+        assert_eq!(contribs[0].revid(), 7);
+    }
+}
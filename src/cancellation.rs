@@ -0,0 +1,155 @@
+/*!
+The `cancellation` module provides `CancellationToken`, which lets callers abort long-running
+queries, continuation loops, and retry waits, either explicitly or after a deadline.
+*/
+
+#![deny(missing_docs)]
+
+use crate::media_wiki_error::MediaWikiError;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+    deadline: Option<Instant>,
+}
+
+/// A token to cancel requests cooperatively; clones share the cancellation state
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is cancelled only via `cancel()`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a token that is cancelled at `deadline`, or via `cancel()`
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                deadline: Some(deadline),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Creates a token that is cancelled after `timeout`, or via `cancel()`
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    /// Returns the deadline, if any
+    pub fn deadline(&self) -> Option<Instant> {
+        self.inner.deadline
+    }
+
+    /// Cancels the token, and wakes up everything waiting on it
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Returns true if the token was cancelled, or its deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+            || self.inner.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    /// Returns `MediaWikiError::Cancelled` if the token is cancelled
+    pub fn check(&self) -> Result<(), MediaWikiError> {
+        match self.is_cancelled() {
+            true => Err(MediaWikiError::Cancelled),
+            false => Ok(()),
+        }
+    }
+
+    /// Completes when the token is cancelled, or its deadline has passed
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            match self.inner.deadline {
+                Some(deadline) => {
+                    let deadline = tokio::time::Instant::from_std(deadline);
+                    tokio::select! {
+                        _ = notified => {}
+                        _ = tokio::time::sleep_until(deadline) => return,
+                    }
+                }
+                None => notified.await,
+            }
+        }
+    }
+
+    /// Runs `future` to completion, unless the token is cancelled first
+    pub async fn run<F: Future>(&self, future: F) -> Result<F::Output, MediaWikiError> {
+        tokio::select! {
+            biased;
+            _ = self.cancelled() => Err(MediaWikiError::Cancelled),
+            output = future => Ok(output),
+        }
+    }
+
+    /// Sleeps for `duration`, unless the token is cancelled first
+    pub async fn sleep(&self, duration: Duration) -> Result<(), MediaWikiError> {
+        self.run(tokio::time::sleep(duration)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Api;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn cancel() {
+        let token = CancellationToken::new();
+        assert!(token.check().is_ok());
+        let waiter = {
+            let token = token.clone();
+            tokio::spawn(async move { token.sleep(Duration::from_secs(60)).await })
+        };
+        token.cancel();
+        assert!(matches!(
+            waiter.await.unwrap(),
+            Err(MediaWikiError::Cancelled)
+        ));
+        assert!(token.is_cancelled());
+        let token = CancellationToken::with_timeout(Duration::from_millis(10));
+        token.cancelled().await;
+        assert!(token.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn cancel_maxlag_wait() {
+        let mut responses = MockResponses::new();
+        responses.add(
+            &[("list", "allpages")],
+            json!({"error":{"code":"maxlag","info":"Waiting for a database server","lag":60}}),
+        );
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        api.set_maxlag(Some(5));
+        api.set_maxlag_on_reads(true);
+        let params = api.params_into(&[("action", "query"), ("list", "allpages")]);
+        let token = CancellationToken::with_timeout(Duration::from_millis(50));
+        let started = Instant::now();
+        assert!(matches!(
+            api.get_query_api_json_limit_cancellable(&params, None, &token)
+                .await,
+            Err(MediaWikiError::Cancelled)
+        ));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(responses.requests().len(), 1);
+    }
+}
@@ -0,0 +1,161 @@
+/*!
+The `Captcha` class represents a ConfirmEdit captcha, as returned for edits and account creation.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+use std::collections::HashMap;
+use url::Url;
+
+/// A captcha that has to be solved before an edit or account creation can be resubmitted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captcha {
+    id: String,
+    kind: Option<String>,
+    question: Option<String>,
+    url: Option<String>,
+    mime: Option<String>,
+}
+
+impl Captcha {
+    /// Creates a captcha from a failed `action=edit` result, if it contains one
+    pub fn from_edit_response(result: &Value) -> Option<Self> {
+        let captcha = &result["edit"]["captcha"];
+        let id = match &captcha["id"] {
+            Value::String(s) => s.to_string(),
+            Value::Number(n) => n.to_string(),
+            _ => return None,
+        };
+        Some(Self {
+            id,
+            kind: captcha["type"].as_str().map(|s| s.to_string()),
+            question: captcha["question"].as_str().map(|s| s.to_string()),
+            url: captcha["url"].as_str().map(|s| s.to_string()),
+            mime: captcha["mime"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    /// Creates a captcha from an `action=createaccount` result (or `meta=authmanagerinfo`),
+    /// if it contains a `CaptchaAuthenticationRequest`
+    pub fn from_createaccount_response(result: &Value) -> Option<Self> {
+        let requests = result["createaccount"]["requests"]
+            .as_array()
+            .or_else(|| result["query"]["authmanagerinfo"]["requests"].as_array())?;
+        let request = requests
+            .iter()
+            .find(|r| r["id"].as_str() == Some("CaptchaAuthenticationRequest"))?;
+        let fields = &request["fields"];
+        let info = fields["captchaInfo"]["value"]
+            .as_str()
+            .map(|s| s.to_string());
+        let kind = fields["captchaType"]["value"]
+            .as_str()
+            .map(|s| s.to_string());
+        let is_image = kind.as_deref() == Some("image")
+            || info
+                .as_deref()
+                .is_some_and(|i| i.contains("Special:Captcha/image"));
+        let (question, url) = match is_image {
+            true => (None, info),
+            false => (info, None),
+        };
+        Some(Self {
+            id: fields["captchaId"]["value"].as_str()?.to_string(),
+            kind,
+            question,
+            url,
+            mime: fields["captchaInfo"]["mime"]
+                .as_str()
+                .map(|s| s.to_string()),
+        })
+    }
+
+    /// Returns the captcha ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the captcha type (e.g. `image`, `simple`, `question`), if given
+    pub fn kind(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    /// Returns the question for text captchas
+    pub fn question(&self) -> Option<&str> {
+        self.question.as_deref()
+    }
+
+    /// Returns the (possibly relative) image URL for image captchas
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    /// Returns the MIME type of the captcha image, if given
+    pub fn mime(&self) -> Option<&str> {
+        self.mime.as_deref()
+    }
+
+    /// Adds the solution to the parameters of an `action=edit` request, for resubmission
+    pub fn apply_to_edit(&self, params: &mut HashMap<String, String>, answer: &str) {
+        params.insert("captchaid".to_string(), self.id.to_string());
+        params.insert("captchaword".to_string(), answer.to_string());
+    }
+
+    /// Adds the solution to the parameters of an `action=createaccount` request, for resubmission
+    pub fn apply_to_createaccount(&self, params: &mut HashMap<String, String>, answer: &str) {
+        params.insert("captchaId".to_string(), self.id.to_string());
+        params.insert("captchaWord".to_string(), answer.to_string());
+    }
+}
+
+impl Api {
+    /// Fetches the image of an image captcha, through the client of this `Api`
+    pub async fn fetch_captcha_image(&self, captcha: &Captcha) -> Result<Vec<u8>, MediaWikiError> {
+        let url = captcha.url().ok_or_else(|| {
+            MediaWikiError::String(format!("Captcha {} has no image", captcha.id()))
+        })?;
+        let url = Url::parse(self.api_url())?.join(url)?;
+        let headers = self.request_headers(url.as_str(), &HashMap::new(), "GET")?;
+        let response = self.client().get(url).headers(headers).send().await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_captcha() {
+        let result = json!({"edit":{"result":"Failure","captcha":{"type":"image","mime":"image/png","id":"1234","url":"/w/index.php?title=Special:Captcha/image&wpCaptchaId=1234"}}});
+        let captcha = Captcha::from_edit_response(&result).unwrap();
+        assert_eq!(captcha.id(), "1234");
+        assert_eq!(captcha.kind(), Some("image"));
+        assert!(captcha.url().unwrap().contains("wpCaptchaId=1234"));
+        let mut params = HashMap::new();
+        captcha.apply_to_edit(&mut params, "answer");
+        assert_eq!(params["captchaid"], "1234");
+        assert_eq!(params["captchaword"], "answer");
+        assert!(Captcha::from_edit_response(&json!({"edit":{"result":"Success"}})).is_none());
+    }
+
+    #[test]
+    fn createaccount_captcha() {
+        let result = json!({"createaccount":{"status":"UI","requests":[{"id":"CaptchaAuthenticationRequest","fields":{
+            "captchaId":{"type":"hidden","value":"42"},
+            "captchaInfo":{"type":"null","value":"What is 1 + 2?"},
+            "captchaWord":{"type":"string"}
+        }}]}});
+        let captcha = Captcha::from_createaccount_response(&result).unwrap();
+        assert_eq!(captcha.id(), "42");
+        assert_eq!(captcha.question(), Some("What is 1 + 2?"));
+        assert_eq!(captcha.url(), None);
+        let mut params = HashMap::new();
+        captcha.apply_to_createaccount(&mut params, "3");
+        assert_eq!(params["captchaId"], "42");
+        assert_eq!(params["captchaWord"], "3");
+    }
+}
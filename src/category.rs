@@ -0,0 +1,255 @@
+/*!
+The `category` module lists the members of a category via `list=categorymembers`,
+optionally walking subcategories recursively.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::continuation::Continuation;
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::Page;
+use crate::title::Title;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::collections::{HashSet, VecDeque};
+
+/// The namespace of categories
+const CATEGORY_NAMESPACE: NamespaceID = 14;
+
+/// Options for `Page::category_members`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CategoryMembersOptions {
+    namespaces: Vec<NamespaceID>,
+    recursive: bool,
+    max_depth: Option<usize>,
+}
+
+impl CategoryMembersOptions {
+    /// Creates options for direct members in all namespaces
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the namespaces members are returned from; empty for all namespaces
+    pub fn namespaces(&self) -> &[NamespaceID] {
+        &self.namespaces
+    }
+
+    /// Only returns members from these namespaces; empty for all namespaces.
+    /// Subcategories are still walked in recursive mode.
+    pub fn set_namespaces(&mut self, namespaces: &[NamespaceID]) {
+        self.namespaces = namespaces.to_vec();
+    }
+
+    /// Returns true if subcategories are walked
+    pub fn recursive(&self) -> bool {
+        self.recursive
+    }
+
+    /// Walks subcategories, each at most once
+    pub fn set_recursive(&mut self, recursive: bool) {
+        self.recursive = recursive;
+    }
+
+    /// Returns the maximum depth of subcategories to walk; `None` for no limit
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Sets the maximum depth of subcategories to walk, where 1 means direct subcategories only
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// Returns the `cmnamespace` parameter, including categories in recursive mode
+    fn cmnamespace(&self) -> Option<String> {
+        if self.namespaces.is_empty() {
+            return None;
+        }
+        let mut namespaces = self.namespaces.clone();
+        if self.recursive && !namespaces.contains(&CATEGORY_NAMESPACE) {
+            namespaces.push(CATEGORY_NAMESPACE);
+        }
+        Some(
+            namespaces
+                .iter()
+                .map(|ns| ns.to_string())
+                .collect::<Vec<_>>()
+                .join("|"),
+        )
+    }
+
+    /// Returns true if members of this namespace are returned
+    fn returns_namespace(&self, namespace_id: NamespaceID) -> bool {
+        self.namespaces.is_empty() || self.namespaces.contains(&namespace_id)
+    }
+
+    /// Returns true if subcategories found at `depth` are walked
+    fn walks_subcategories_at(&self, depth: usize) -> bool {
+        self.recursive && self.max_depth.is_none_or(|max| depth < max)
+    }
+}
+
+struct CategoryWalk<'a> {
+    api: &'a Api,
+    options: CategoryMembersOptions,
+    pending: VecDeque<(String, usize)>,
+    visited: HashSet<String>,
+    current: Option<(String, usize, Option<Continuation>)>,
+}
+
+impl CategoryWalk<'_> {
+    /// Loads the next batch of members, or returns `None` when all categories are done
+    async fn next_batch(&mut self) -> Option<Result<Vec<Title>, MediaWikiError>> {
+        if self.current.is_none() {
+            let (category, depth) = self.pending.pop_front()?;
+            self.current = Some((category, depth, None));
+        }
+        let (category, depth, continuation) = self.current.take()?;
+        let mut params = self.api.params_into(&[
+            ("action", "query"),
+            ("list", "categorymembers"),
+            ("cmtitle", &category),
+            ("cmlimit", "max"),
+        ]);
+        if let Some(cmnamespace) = self.options.cmnamespace() {
+            params.insert("cmnamespace".to_string(), cmnamespace);
+        }
+        if let Some(continuation) = &continuation {
+            params.extend(continuation.params(&params));
+        }
+        let result = match self.api.get_query_api_json(&params).await {
+            Ok(result) => result,
+            Err(e) => {
+                self.pending.clear();
+                return Some(Err(e));
+            }
+        };
+        let mut titles = vec![];
+        for (full_title, namespace_id) in Page::category_members_from_api_result(&result) {
+            if namespace_id == CATEGORY_NAMESPACE
+                && self.options.walks_subcategories_at(depth)
+                && self.visited.insert(full_title.to_owned())
+            {
+                self.pending.push_back((full_title.to_owned(), depth + 1));
+            }
+            if self.options.returns_namespace(namespace_id) {
+                titles.push(Title::new_from_full(&full_title, self.api));
+            }
+        }
+        if let Some(continuation) = Continuation::from_result(&result) {
+            self.current = Some((category, depth, Some(continuation)));
+        }
+        Some(Ok(titles))
+    }
+}
+
+impl Page {
+    /// Returns the members of this category page as a stream, handling continuation.
+    /// In recursive mode, subcategories are walked breadth-first, each at most once.
+    pub fn category_members<'a>(
+        &self,
+        api: &'a Api,
+        options: &CategoryMembersOptions,
+    ) -> Result<impl Stream<Item = Result<Title, MediaWikiError>> + 'a, MediaWikiError> {
+        let category = self
+            .title()
+            .full_pretty(api)
+            .ok_or_else(|| MediaWikiError::BadTitle(self.title().clone()))?;
+        let walk = CategoryWalk {
+            api,
+            options: options.to_owned(),
+            pending: VecDeque::from([(category.to_owned(), 0)]),
+            visited: HashSet::from([category]),
+            current: None,
+        };
+        Ok(futures::stream::unfold(walk, |mut walk| async move {
+            let batch = walk.next_batch().await?;
+            Some((batch, walk))
+        })
+        .flat_map(|batch| {
+            let items: Vec<Result<Title, MediaWikiError>> = match batch {
+                Ok(titles) => titles.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(items)
+        }))
+    }
+
+    /// Returns the full titles and namespaces of a `list=categorymembers` result
+    fn category_members_from_api_result(result: &Value) -> Vec<(String, NamespaceID)> {
+        result["query"]["categorymembers"]
+            .as_array()
+            .map(|members| {
+                members
+                    .iter()
+                    .filter_map(|m| Some((m["title"].as_str()?.to_string(), m["ns"].as_i64()?)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options() {
+        let mut options = CategoryMembersOptions::new();
+        assert_eq!(options.cmnamespace(), None);
+        assert!(options.returns_namespace(CATEGORY_NAMESPACE));
+        assert!(!options.walks_subcategories_at(0));
+        options.set_namespaces(&[0]);
+        options.set_recursive(true);
+        options.set_max_depth(Some(2));
+        assert_eq!(options.cmnamespace(), Some("0|14".to_string()));
+        assert!(!options.returns_namespace(CATEGORY_NAMESPACE));
+        assert!(options.walks_subcategories_at(1));
+        assert!(!options.walks_subcategories_at(2));
+    }
+
+    #[tokio::test]
+    async fn recursive_walk() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"namespaces":{"0":{"id":0,"name":""},"14":{"id":14,"name":"Category","canonical":"Category"}}}}),
+            )
+            .add(
+                &[("cmtitle", "Category:A")],
+                json!({"query":{"categorymembers":[{"ns":0,"title":"One"},{"ns":14,"title":"Category:B"}]}}),
+            )
+            .add(
+                &[("cmtitle", "Category:B")],
+                json!({"query":{"categorymembers":[{"ns":0,"title":"Two"},{"ns":14,"title":"Category:A"}]}}),
+            );
+        let api = Api::new_mocked(responses).unwrap();
+        let page = Page::new(Title::new("A", CATEGORY_NAMESPACE));
+        let mut options = CategoryMembersOptions::new();
+        options.set_namespaces(&[0]);
+        options.set_recursive(true);
+        let titles: Vec<Title> = page
+            .category_members(&api, &options)
+            .unwrap()
+            .map(|t| t.unwrap())
+            .collect()
+            .await;
+        assert_eq!(titles, vec![Title::new("One", 0), Title::new("Two", 0)]);
+    }
+
+    #[test]
+    fn members_from_api_result() {
+        let result = json!({"batchcomplete":"","query":{"categorymembers":[
+            {"pageid":1,"ns":0,"title":"Cologne"},{"pageid":2,"ns":14,"title":"Category:Districts of Cologne"}]}});
+        assert_eq!(
+            Page::category_members_from_api_result(&result),
+            vec![
+                ("Cologne".to_string(), 0),
+                ("Category:Districts of Cologne".to_string(), 14)
+            ]
+        );
+    }
+}
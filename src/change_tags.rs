@@ -0,0 +1,137 @@
+/*!
+The `change_tags` module lists the change tags of a wiki via `list=tags`, e.g. to find the tags
+that can be applied to edits via the `tags` parameter.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+
+/// A change tag, as returned by `list=tags`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeTag {
+    name: String,
+    display_name: Option<String>,
+    description: Option<String>,
+    defined: bool,
+    active: bool,
+    sources: Vec<String>,
+    hit_count: Option<u64>,
+}
+
+impl ChangeTag {
+    /// Creates a new change tag from a `list=tags` result entry (`formatversion=2`)
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            name: j["name"].as_str()?.to_string(),
+            display_name: j["displayname"].as_str().map(|s| s.to_string()),
+            description: j["description"].as_str().map(|s| s.to_string()),
+            defined: j["defined"].as_bool().unwrap_or(false),
+            active: j["active"].as_bool().unwrap_or(false),
+            sources: j["source"]
+                .as_array()
+                .map(|sources| {
+                    sources
+                        .iter()
+                        .filter_map(|s| s.as_str())
+                        .map(|s| s.to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            hit_count: j["hitcount"].as_u64(),
+        })
+    }
+
+    /// Returns the tag name, as used in the `tags` parameter
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the display name, as parsed HTML; `None` if the tag is hidden
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    /// Returns the description, as parsed HTML
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Returns true if the tag is defined by an extension or on the wiki
+    pub fn is_defined(&self) -> bool {
+        self.defined
+    }
+
+    /// Returns true if the tag can still be applied
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Returns where the tag is defined, `extension` and/or `manual`
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// Returns the number of revisions and log entries with the tag
+    pub fn hit_count(&self) -> Option<u64> {
+        self.hit_count
+    }
+
+    /// Returns true if the tag can be applied to edits by users and bots, i.e. it is an active manual tag
+    pub fn is_applicable(&self) -> bool {
+        self.active && self.sources.iter().any(|s| s == "manual")
+    }
+}
+
+impl Api {
+    /// Returns all change tags of the wiki; use `ChangeTag::is_applicable` to find those that can be
+    /// passed in the `tags` parameter of edits
+    pub async fn list_change_tags(&self) -> Result<Vec<ChangeTag>, MediaWikiError> {
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("list", "tags"),
+            ("tglimit", "max"),
+            (
+                "tgprop",
+                "displayname|description|defined|active|source|hitcount",
+            ),
+            ("formatversion", "2"),
+        ]);
+        let result = self.get_query_api_json_all(&params).await?;
+        Ok(result["query"]["tags"]
+            .as_array()
+            .map(|tags| tags.iter().filter_map(ChangeTag::from_json).collect())
+            .unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn list_change_tags() {
+        let mut responses = MockResponses::new();
+        responses.add(
+            &[("list", "tags")],
+            json!({"batchcomplete":true,"query":{"tags":[
+                {"name":"bot-edit","displayname":"Bot edit","defined":true,"active":true,"source":["manual"],"hitcount":12},
+                {"name":"mw-undo","displayname":"Undo","defined":true,"active":true,"source":["software"]},
+                {"name":"old-tag","defined":true,"active":false,"source":["manual"]}
+            ]}}),
+        );
+        let api = Api::new_mocked(responses).unwrap();
+        let tags = api.list_change_tags().await.unwrap();
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags[0].hit_count(), Some(12));
+        let applicable: Vec<&str> = tags
+            .iter()
+            .filter(|t| t.is_applicable())
+            .map(|t| t.name())
+            .collect();
+        assert_eq!(applicable, ["bot-edit"]);
+    }
+}
@@ -0,0 +1,171 @@
+/*!
+The `Collation` class orders titles the way a wiki sorts category members.
+*/
+
+#![deny(missing_docs)]
+
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+#[cfg(feature = "uca-collation")]
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+
+/// The shared UCA collator, created on first use; it keeps buffers that are reused between comparisons
+#[cfg(feature = "uca-collation")]
+static UCA_COLLATOR: OnceLock<Mutex<feruca::Collator>> = OnceLock::new();
+
+/// A category collation, as configured by `$wgCategoryCollation`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Collation {
+    /// Sort by the uppercased title (the MediaWiki default)
+    #[default]
+    Uppercase,
+    /// Sort by the title as-is, by code point
+    Identity,
+    /// Sort by the Unicode Collation Algorithm, using the CLDR root order
+    #[cfg(feature = "uca-collation")]
+    Uca,
+}
+
+impl Collation {
+    /// Returns the sort key of a title for `Uppercase` and `Identity`, or the title text for `Uca`
+    pub fn sort_key(&self, title: &Title) -> String {
+        match self {
+            Self::Uppercase => title.pretty().to_uppercase(),
+            _ => title.pretty().to_string(),
+        }
+    }
+
+    /// Compares two titles by this collation; ties are broken by namespace ID and raw title
+    pub fn compare(&self, a: &Title, b: &Title) -> Ordering {
+        match self {
+            #[cfg(feature = "uca-collation")]
+            Self::Uca => Self::compare_uca(&mut Self::uca_collator(), a, b),
+            _ => self
+                .sort_key(a)
+                .cmp(&self.sort_key(b))
+                .then_with(|| a.cmp(b)),
+        }
+    }
+
+    /// Sorts titles by this collation
+    pub fn sort(&self, titles: &mut [Title]) {
+        match self {
+            #[cfg(feature = "uca-collation")]
+            Self::Uca => {
+                let mut collator = Self::uca_collator();
+                titles.sort_by(|a, b| Self::compare_uca(&mut collator, a, b));
+            }
+            _ => titles.sort_by_cached_key(|t| (self.sort_key(t), t.clone())),
+        }
+    }
+
+    /// Returns the shared UCA collator, locked for the caller
+    #[cfg(feature = "uca-collation")]
+    fn uca_collator() -> MutexGuard<'static, feruca::Collator> {
+        UCA_COLLATOR
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    #[cfg(feature = "uca-collation")]
+    fn compare_uca(collator: &mut feruca::Collator, a: &Title, b: &Title) -> Ordering {
+        collator
+            .collate(a.pretty(), b.pretty())
+            .then_with(|| a.cmp(b))
+    }
+}
+
+impl fmt::Display for Collation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Uppercase => write!(f, "uppercase"),
+            Self::Identity => write!(f, "identity"),
+            #[cfg(feature = "uca-collation")]
+            Self::Uca => write!(f, "uca-default"),
+        }
+    }
+}
+
+impl FromStr for Collation {
+    type Err = MediaWikiError;
+
+    /// Parses a `$wgCategoryCollation` value; all `uca-*` collations map to `Uca`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uppercase" => Ok(Self::Uppercase),
+            "identity" => Ok(Self::Identity),
+            #[cfg(feature = "uca-collation")]
+            s if s.starts_with("uca-") => Ok(Self::Uca),
+            other => Err(MediaWikiError::String(format!(
+                "Unsupported collation: {}",
+                other
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uppercase_and_identity() {
+        let mut titles = vec![
+            Title::new("beta", 0),
+            Title::new("Alpha", 0),
+            Title::new("Beta", 0),
+        ];
+        Collation::Uppercase.sort(&mut titles);
+        assert_eq!(
+            titles,
+            vec![
+                Title::new("Alpha", 0),
+                Title::new("Beta", 0),
+                Title::new("beta", 0)
+            ]
+        );
+        Collation::Identity.sort(&mut titles);
+        assert_eq!(titles[2], Title::new("beta", 0));
+        assert_eq!(
+            Collation::Uppercase.compare(&Title::new("a", 0), &Title::new("B", 0)),
+            Ordering::Less
+        );
+        assert_eq!(
+            Collation::Identity.compare(&Title::new("a", 0), &Title::new("B", 0)),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            "uppercase".parse::<Collation>().unwrap(),
+            Collation::Uppercase
+        );
+        assert_eq!(
+            Collation::Identity
+                .to_string()
+                .parse::<Collation>()
+                .unwrap(),
+            Collation::Identity
+        );
+        assert!("numeric".parse::<Collation>().is_err());
+    }
+
+    #[cfg(feature = "uca-collation")]
+    #[test]
+    fn uca() {
+        let mut titles = vec![Title::new("Ötzi", 0), Title::new("Overton", 0)];
+        Collation::Uca.sort(&mut titles);
+        assert_eq!(titles[0], Title::new("Ötzi", 0));
+        assert_eq!(
+            Collation::Uca.compare(&Title::new("Ötzi", 0), &Title::new("Overton", 0)),
+            Ordering::Less
+        );
+        assert_eq!("uca-de".parse::<Collation>().unwrap(), Collation::Uca);
+    }
+}
@@ -0,0 +1,345 @@
+/*!
+The `config` module loads the settings and credentials of a single bot from a TOML or INI file,
+and/or environment variables, and constructs a logged-in `Api` from them.
+
+Example `bot.toml`:
+```toml
+api_url = "https://www.wikidata.org/w/api.php"
+user_agent = "MyBot/1.0 (https://example.org/mybot)"
+maxlag = 5
+tags = ["my-bot"]
+
+[user]
+user = "MyBot@mybot"
+pass_env = "MYBOT_PASSWORD"
+```
+
+The password can be given as `pass`, or read from the environment variable named by `pass_env`.
+Instead of `[user]`, an `[oauth]` section with `consumer_key`, `consumer_secret`, `token_key`
+and `token_secret`, or an `[oauth2]` section with `access_token`, can be used.
+Every value can be set or overridden by an environment variable `MEDIAWIKI_CONFIG__<KEY>`,
+e.g. `MEDIAWIKI_CONFIG__USER__PASS`, so secrets do not need to be stored in the file.
+Lists such as `tags` are comma-separated in environment variables.
+See `Profiles` for files with several named configurations.
+
+```no_run
+# tokio::runtime::Runtime::new().unwrap().block_on(async {
+let api = mediawiki::api::Api::from_config_file("bot.toml").await.unwrap();
+# });
+```
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, OAuthParams};
+use crate::api_builder::ApiBuilder;
+use crate::media_wiki_error::MediaWikiError;
+use ::config::{Config, Environment, File, FileFormat};
+use serde::{Deserialize, Deserializer};
+use std::fmt;
+use std::path::Path;
+
+const ENV_PREFIX: &str = "MEDIAWIKI_CONFIG";
+const ENV_SEPARATOR: &str = "__";
+
+/// Login credentials for a user name and (bot) password
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+pub struct UserCredentials {
+    user: String,
+    pass: Option<String>,
+    pass_env: Option<String>,
+}
+
+impl UserCredentials {
+    /// Returns the user name, e.g. `MyBot@mybot` for a bot password
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    /// Returns the password, as given by `pass`, or read from the environment variable named by `pass_env`
+    pub fn password(&self) -> Result<String, MediaWikiError> {
+        if let Some(pass) = &self.pass {
+            return Ok(pass.to_owned());
+        }
+        match &self.pass_env {
+            Some(var) => std::env::var(var).map_err(|e| {
+                MediaWikiError::String(format!("Environment variable {}: {}", var, e))
+            }),
+            None => Err(MediaWikiError::String(format!(
+                "No password for user {}",
+                self.user
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for UserCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UserCredentials")
+            .field("user", &self.user)
+            .field("pass", &self.pass.as_ref().map(|_| "[redacted]"))
+            .field("pass_env", &self.pass_env)
+            .finish()
+    }
+}
+
+/// OAuth 1.0a consumer and access token
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+pub struct OAuthCredentials {
+    consumer_key: String,
+    consumer_secret: String,
+    token_key: String,
+    token_secret: String,
+}
+
+impl OAuthCredentials {
+    /// Returns the credentials as `OAuthParams`
+    pub fn params(&self) -> OAuthParams {
+        OAuthParams::new_from_json(&serde_json::json!({
+            "gConsumerKey": self.consumer_key,
+            "gConsumerSecret": self.consumer_secret,
+            "gTokenKey": self.token_key,
+            "gTokenSecret": self.token_secret,
+        }))
+    }
+}
+
+impl fmt::Debug for OAuthCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OAuthCredentials")
+            .field("consumer_key", &self.consumer_key)
+            .field("consumer_secret", &"[redacted]")
+            .field("token_key", &self.token_key)
+            .field("token_secret", &"[redacted]")
+            .finish()
+    }
+}
+
+/// An OAuth 2.0 owner-only access token
+#[derive(Clone, PartialEq, Eq, Deserialize)]
+pub struct OAuth2Credentials {
+    access_token: String,
+}
+
+impl fmt::Debug for OAuth2Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OAuth2Credentials")
+            .field("access_token", &"[redacted]")
+            .finish()
+    }
+}
+
+/// The settings and credentials of a bot
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ApiConfig {
+    api_url: String,
+    user_agent: Option<String>,
+    maxlag: Option<u64>,
+    edit_delay_ms: Option<u64>,
+    #[serde(default, deserialize_with = "deserialize_list")]
+    tags: Vec<String>,
+    user: Option<UserCredentials>,
+    oauth: Option<OAuthCredentials>,
+    oauth2: Option<OAuth2Credentials>,
+}
+
+impl ApiConfig {
+    /// Loads the configuration from a file, with environment variable overrides.
+    /// The format is derived from the file extension, e.g. `.toml` or `.ini`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, MediaWikiError> {
+        Self::load(Some(File::from(path.as_ref())))
+    }
+
+    /// Loads the configuration from environment variables only
+    pub fn from_env() -> Result<Self, MediaWikiError> {
+        Self::load(None::<File<::config::FileSourceString, FileFormat>>)
+    }
+
+    /// Loads the configuration from a TOML string, with environment variable overrides
+    pub fn from_toml_str(toml: &str) -> Result<Self, MediaWikiError> {
+        Self::load(Some(File::from_str(toml, FileFormat::Toml)))
+    }
+
+    /// Loads the configuration from an INI string, with environment variable overrides
+    pub fn from_ini_str(ini: &str) -> Result<Self, MediaWikiError> {
+        Self::load(Some(File::from_str(ini, FileFormat::Ini)))
+    }
+
+    fn load<S>(source: Option<S>) -> Result<Self, MediaWikiError>
+    where
+        S: ::config::Source + Send + Sync + 'static,
+    {
+        load_config(source)?
+            .try_deserialize()
+            .map_err(|e| MediaWikiError::String(format!("Could not load config: {}", e)))
+    }
+
+    /// Returns the API URL
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    /// Returns the user agent, if set
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    /// Returns the maxlag in seconds, if set
+    pub fn maxlag(&self) -> Option<u64> {
+        self.maxlag
+    }
+
+    /// Returns the edit delay in milliseconds, if set
+    pub fn edit_delay_ms(&self) -> Option<u64> {
+        self.edit_delay_ms
+    }
+
+    /// Returns the change tags to apply to edits
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns the user name and password, if set
+    pub fn user(&self) -> Option<&UserCredentials> {
+        self.user.as_ref()
+    }
+
+    /// Returns the OAuth 1.0a credentials, if set
+    pub fn oauth(&self) -> Option<&OAuthCredentials> {
+        self.oauth.as_ref()
+    }
+
+    /// Returns the OAuth 2.0 credentials, if set
+    pub fn oauth2(&self) -> Option<&OAuth2Credentials> {
+        self.oauth2.as_ref()
+    }
+
+    /// Returns an `ApiBuilder` with the settings and OAuth credentials of this configuration.
+    /// User name and password are not applied; see `api`.
+    pub fn builder(&self) -> ApiBuilder {
+        let mut builder = Api::builder(&self.api_url);
+        if let Some(user_agent) = &self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if self.maxlag.is_some() {
+            builder = builder.maxlag(self.maxlag);
+        }
+        if self.edit_delay_ms.is_some() {
+            builder = builder.edit_delay(self.edit_delay_ms);
+        }
+        if !self.tags.is_empty() {
+            let tags: Vec<&str> = self.tags.iter().map(|s| s.as_str()).collect();
+            builder = builder.edit_tags(&tags);
+        }
+        if let Some(oauth) = &self.oauth {
+            builder = builder.oauth(oauth.params());
+        }
+        if let Some(oauth2) = &self.oauth2 {
+            builder = builder.oauth2(&oauth2.access_token);
+        }
+        builder
+    }
+
+    /// Constructs an `Api` from this configuration, and logs in if a user name and password are given
+    pub async fn api(&self) -> Result<Api, MediaWikiError> {
+        let api = self.builder().build().await?;
+        if let Some(user) = &self.user {
+            api.login(user.user.to_owned(), user.password()?).await?;
+        }
+        Ok(api)
+    }
+}
+
+/// Loads a file or string `source`, with environment variable overrides `MEDIAWIKI_CONFIG__<KEY>`
+pub(crate) fn load_config<S>(source: Option<S>) -> Result<Config, MediaWikiError>
+where
+    S: ::config::Source + Send + Sync + 'static,
+{
+    let mut builder = Config::builder();
+    if let Some(source) = source {
+        builder = builder.add_source(source);
+    }
+    builder
+        .add_source(
+            Environment::with_prefix(ENV_PREFIX)
+                .separator(ENV_SEPARATOR)
+                .try_parsing(true),
+        )
+        .build()
+        .map_err(|e| MediaWikiError::String(format!("Could not load config: {}", e)))
+}
+
+/// Deserializes a list, or a comma-separated string as set in environment variables
+fn deserialize_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        List(Vec<String>),
+        String(String),
+    }
+    Ok(match List::deserialize(deserializer)? {
+        List::List(list) => list,
+        List::String(s) => s
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+    })
+}
+
+impl Api {
+    /// Constructs an `Api` from a TOML or INI configuration file; see `ApiConfig`
+    pub async fn from_config_file<P: AsRef<Path>>(path: P) -> Result<Api, MediaWikiError> {
+        ApiConfig::from_file(path)?.api().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn load_config() {
+        let config = ApiConfig::from_toml_str(
+            r#"
+            api_url = "https://test.wikidata.org/w/api.php"
+            user_agent = "TestBot/1.0"
+            maxlag = 5
+
+            tags = ["test-bot"]
+
+            [user]
+            user = "TestBot@test"
+            pass = "secret"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.user().unwrap().user(), "TestBot@test");
+        assert!(!format!("{:?}", config).contains("secret"));
+        let site_info = json!({"query":{"general":{"sitename":"Test"}}}).to_string();
+        let api = config
+            .builder()
+            .site_info(&site_info)
+            .build()
+            .await
+            .unwrap();
+        assert_eq!(api.user_agent(), "TestBot/1.0");
+        assert_eq!(*api.maxlag(), Some(5));
+        assert_eq!(api.edit_tags(), &["test-bot".to_string()]);
+        assert_eq!(config.user().unwrap().password().unwrap(), "secret");
+
+        let config = ApiConfig::from_ini_str(
+            "api_url = https://test.wikidata.org/w/api.php\nedit_delay_ms = 500\ntags = a, b\n\n\
+             [oauth]\nconsumer_key = ck\nconsumer_secret = cs\ntoken_key = tk\ntoken_secret = ts\n",
+        )
+        .unwrap();
+        assert_eq!(config.edit_delay_ms(), Some(500));
+        assert_eq!(config.tags(), &["a".to_string(), "b".to_string()]);
+        assert_eq!(
+            config.oauth().unwrap().params().g_token_key.as_deref(),
+            Some("tk")
+        );
+        assert!(config.user().is_none());
+        assert!(ApiConfig::from_toml_str("maxlag = 5").is_err());
+    }
+}
@@ -0,0 +1,156 @@
+/*!
+The `Conflict` class holds the three versions of a page involved in an edit conflict.
+*/
+
+#![deny(missing_docs)]
+
+use serde_json::Value;
+
+/// Represents an edit conflict, as returned by `action=edit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    base_rev: Option<u64>,
+    base_text: Option<String>,
+    current_rev: Option<u64>,
+    current_text: Option<String>,
+    current_timestamp: Option<String>,
+    attempted_text: String,
+}
+
+impl Conflict {
+    /// Returns true if the API result is an edit conflict error
+    pub fn is_conflict(result: &Value) -> bool {
+        result["error"]["code"].as_str() == Some("editconflict")
+            || result["edit"]["result"].as_str() == Some("Conflict")
+    }
+
+    /// Creates a new conflict from an API result, if that result is an edit conflict.
+    /// `base_rev` and `base_text` describe the revision the attempted edit was based on.
+    /// The `editconflict` error does not include the current version of the page;
+    /// load it and use `set_current` to complete the conflict.
+    pub fn from_response(
+        result: &Value,
+        base_rev: Option<u64>,
+        base_text: Option<&str>,
+        attempted_text: &str,
+    ) -> Option<Self> {
+        Self::is_conflict(result).then(|| Self::new(base_rev, base_text, attempted_text))
+    }
+
+    /// Creates a conflict where the current version of the page is unknown, e.g. from a REST API response
+    pub fn new(base_rev: Option<u64>, base_text: Option<&str>, attempted_text: &str) -> Self {
+        Self {
+            base_rev,
+            base_text: base_text.map(|s| s.to_string()),
+            current_rev: None,
+            current_text: None,
+            current_timestamp: None,
+            attempted_text: attempted_text.to_string(),
+        }
+    }
+
+    /// Returns the revision ID the attempted edit was based on
+    pub fn base_rev(&self) -> Option<u64> {
+        self.base_rev
+    }
+
+    /// Returns the text of the revision the attempted edit was based on, if known
+    pub fn base_text(&self) -> Option<&str> {
+        self.base_text.as_deref()
+    }
+
+    /// Returns the current revision ID of the page, if set via `set_current`
+    pub fn current_rev(&self) -> Option<u64> {
+        self.current_rev
+    }
+
+    /// Returns the current text of the page, if set via `set_current`
+    pub fn current_text(&self) -> Option<&str> {
+        self.current_text.as_deref()
+    }
+
+    /// Returns the timestamp of the current revision, if set via `set_current`
+    pub fn current_timestamp(&self) -> Option<&str> {
+        self.current_timestamp.as_deref()
+    }
+
+    /// Sets the current version of the page, e.g. from its latest revision loaded after the conflict
+    pub fn set_current(&mut self, rev: Option<u64>, text: Option<&str>, timestamp: Option<&str>) {
+        self.current_rev = rev;
+        self.current_text = text.map(|s| s.to_string());
+        self.current_timestamp = timestamp.map(|s| s.to_string());
+    }
+
+    /// Returns the text that was attempted to be saved
+    pub fn attempted_text(&self) -> &str {
+        &self.attempted_text
+    }
+
+    /// Returns true if all three versions are known, so the conflict can be resolved without another request
+    pub fn is_complete(&self) -> bool {
+        self.base_text.is_some() && self.current_text.is_some()
+    }
+
+    /// Returns the conflict in diff3 format, with the three versions as conflict blocks.
+    /// Unknown versions are left empty.
+    pub fn to_diff3(&self) -> String {
+        let block = |s: &str| {
+            if s.is_empty() || s.ends_with('\n') {
+                s.to_string()
+            } else {
+                format!("{}\n", s)
+            }
+        };
+        let base_label = self
+            .base_rev
+            .map(|r| r.to_string())
+            .unwrap_or_else(|| "base".to_string());
+        let current_label = self
+            .current_rev
+            .map(|r| r.to_string())
+            .unwrap_or_else(|| "current".to_string());
+        format!(
+            "<<<<<<< {}\n{}||||||| {}\n{}=======\n{}>>>>>>> attempted\n",
+            current_label,
+            block(self.current_text().unwrap_or_default()),
+            base_label,
+            block(self.base_text().unwrap_or_default()),
+            block(&self.attempted_text),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_conflict() {
+        let result = json!({"edit":{"result":"Success"}});
+        assert!(Conflict::from_response(&result, Some(1), None, "x").is_none());
+    }
+
+    #[test]
+    fn conflict_from_error() {
+        let result = json!({"error":{"code":"editconflict","info":"Edit conflict.",
+            "docref":"See https://en.wikipedia.org/w/api.php for API usage."},"servedby":"mw1234"});
+        let mut conflict = Conflict::from_response(&result, Some(11), Some("A"), "C").unwrap();
+        assert_eq!(conflict.base_rev(), Some(11));
+        assert_eq!(conflict.current_rev(), None);
+        assert!(!conflict.is_complete());
+        assert_eq!(
+            conflict.to_diff3(),
+            "<<<<<<< current\n||||||| 11\nA\n=======\nC\n>>>>>>> attempted\n"
+        );
+        conflict.set_current(Some(12), Some("B"), Some("2020-01-01T00:00:00Z"));
+        assert_eq!(conflict.current_rev(), Some(12));
+        assert_eq!(conflict.current_text(), Some("B"));
+        assert_eq!(conflict.current_timestamp(), Some("2020-01-01T00:00:00Z"));
+        assert_eq!(conflict.attempted_text(), "C");
+        assert!(conflict.is_complete());
+        assert_eq!(
+            conflict.to_diff3(),
+            "<<<<<<< 12\nB\n||||||| 11\nA\n=======\nC\n>>>>>>> attempted\n"
+        );
+    }
+}
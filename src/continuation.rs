@@ -0,0 +1,135 @@
+/*!
+The `Continuation` class represents the continuation of a query, in the current (`continue`)
+or the legacy (`query-continue`) format returned by older MediaWiki versions.
+*/
+
+#![deny(missing_docs)]
+
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// The continuation of a query result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Continuation {
+    /// The `continue` format, used since MediaWiki 1.26
+    Continue(Map<String, Value>),
+    /// The legacy `query-continue` format, with one object of parameters per module
+    QueryContinue(Map<String, Value>),
+}
+
+impl Continuation {
+    /// Returns the continuation of a query result, if there is one.
+    /// `continue` takes precedence over `query-continue`.
+    pub fn from_result(result: &Value) -> Option<Self> {
+        if let Some(obj) = result["continue"].as_object() {
+            return Some(Self::Continue(obj.to_owned()));
+        }
+        result["query-continue"]
+            .as_object()
+            .map(|obj| Self::QueryContinue(obj.to_owned()))
+    }
+
+    /// Removes the continuation from a query result
+    pub fn remove_from(result: &mut Value) {
+        if let Some(obj) = result.as_object_mut() {
+            obj.remove("continue");
+            obj.remove("query-continue");
+        }
+    }
+
+    /// Returns true for the legacy `query-continue` format
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, Self::QueryContinue(_))
+    }
+
+    /// Returns the parameters to add to `request` for the next request.
+    /// For `query-continue` with a `generator` in `request`, the other modules (e.g. `prop`) are continued first,
+    /// with the current set of generated pages; the generator is continued once they are done.
+    pub fn params(&self, request: &HashMap<String, String>) -> Vec<(String, String)> {
+        let params: Vec<(&String, &Value)> = match self {
+            Self::Continue(obj) => obj.iter().collect(),
+            Self::QueryContinue(modules) => {
+                let generator = request.get("generator");
+                let is_generator = |module: &String| Some(module) == generator;
+                let others_pending = modules.keys().any(|module| !is_generator(module));
+                modules
+                    .iter()
+                    .filter(|(module, _)| !(others_pending && is_generator(module)))
+                    .filter_map(|(_, params)| params.as_object())
+                    .flat_map(|obj| obj.iter())
+                    .collect()
+            }
+        };
+        params
+            .into_iter()
+            // The default to_string() method for Value puts double-quotes around strings
+            .map(|(k, v)| (k.to_string(), v.as_str().map_or(v.to_string(), Into::into)))
+            .collect()
+    }
+
+    /// Returns the continuation as a JSON object in the format it was received in,
+    /// e.g. for storing it; `from_result` reads it back
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::Continue(obj) => serde_json::json!({ "continue": obj }),
+            Self::QueryContinue(obj) => serde_json::json!({ "query-continue": obj }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modern() {
+        let result = json!({"batchcomplete":"","continue":{"apcontinue":"B","continue":"-||"},"query":{"allpages":[{"title":"A"}]}});
+        let continuation = Continuation::from_result(&result).unwrap();
+        assert!(!continuation.is_legacy());
+        let mut params = continuation.params(&HashMap::new());
+        params.sort();
+        assert_eq!(
+            params,
+            vec![
+                ("apcontinue".to_string(), "B".to_string()),
+                ("continue".to_string(), "-||".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn legacy() {
+        let mut result = json!({"query-continue":{"links":{"plcontinue":"1|0|Foo"},"allpages":{"gapcontinue":"B"}},
+            "query":{"pages":{"1":{"pageid":1,"ns":0,"title":"A","links":[{"ns":0,"title":"Bar"}]}}}});
+        let continuation = Continuation::from_result(&result).unwrap();
+        assert!(continuation.is_legacy());
+        let generator: HashMap<String, String> =
+            [("generator".to_string(), "allpages".to_string())].into();
+        assert_eq!(
+            continuation.params(&generator),
+            vec![("plcontinue".to_string(), "1|0|Foo".to_string())]
+        );
+        let mut params = continuation.params(&HashMap::new());
+        params.sort();
+        assert_eq!(
+            params,
+            vec![
+                ("gapcontinue".to_string(), "B".to_string()),
+                ("plcontinue".to_string(), "1|0|Foo".to_string())
+            ]
+        );
+        let generator_only = json!({"query-continue":{"allpages":{"gapcontinue":"B"}}});
+        assert_eq!(
+            Continuation::from_result(&generator_only)
+                .unwrap()
+                .params(&generator),
+            vec![("gapcontinue".to_string(), "B".to_string())]
+        );
+        assert_eq!(
+            Continuation::from_result(&continuation.to_json()),
+            Some(continuation)
+        );
+        Continuation::remove_from(&mut result);
+        assert_eq!(Continuation::from_result(&result), None);
+    }
+}
@@ -0,0 +1,103 @@
+/*!
+The `CookieJar` class stores the session cookies of an `Api`, and allows clearing, saving and restoring them.
+*/
+
+#![deny(missing_docs)]
+
+use crate::media_wiki_error::MediaWikiError;
+use reqwest::cookie::CookieStore;
+use reqwest::header::HeaderValue;
+use std::sync::{PoisonError, RwLock};
+use url::Url;
+
+/// A cookie store that can be cleared, e.g. on logout, and serialized to persist a session
+#[derive(Debug, Default)]
+pub struct CookieJar {
+    store: RwLock<cookie_store::CookieStore>,
+}
+
+impl CookieJar {
+    /// Returns a new, empty cookie jar
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes all cookies
+    pub fn clear(&self) {
+        self.store
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Returns all cookies as JSON, including session cookies without an expiry date
+    pub fn to_json(&self) -> Result<String, MediaWikiError> {
+        let store = self.store.read().unwrap_or_else(PoisonError::into_inner);
+        let mut buffer = vec![];
+        cookie_store::serde::json::save_incl_expired_and_nonpersistent(&store, &mut buffer)
+            .map_err(|e| MediaWikiError::String(format!("cannot save cookies: {}", e)))?;
+        String::from_utf8(buffer).map_err(|e| MediaWikiError::String(e.to_string()))
+    }
+
+    /// Replaces all cookies with the ones from `to_json`
+    pub fn load_json(&self, json: &str) -> Result<(), MediaWikiError> {
+        let store = cookie_store::serde::json::load_all(json.as_bytes())
+            .map_err(|e| MediaWikiError::String(format!("cannot load cookies: {}", e)))?;
+        *self.store.write().unwrap_or_else(PoisonError::into_inner) = store;
+        Ok(())
+    }
+}
+
+impl CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let mut store = self.store.write().unwrap_or_else(PoisonError::into_inner);
+        for header in cookie_headers {
+            if let Ok(cookie) = header.to_str() {
+                let _ = store.parse(cookie, url); // Invalid cookies are ignored, like by browsers
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let store = self.store.read().unwrap_or_else(PoisonError::into_inner);
+        let cookies: Vec<String> = store
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        if cookies.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&cookies.join("; ")).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear() {
+        let jar = CookieJar::new();
+        let url = Url::parse("https://www.wikidata.org/w/api.php").unwrap();
+        let cookie = HeaderValue::from_static("session=abc; Path=/");
+        jar.set_cookies(&mut std::iter::once(&cookie), &url);
+        assert_eq!(jar.cookies(&url).unwrap(), "session=abc");
+        jar.clear();
+        assert!(jar.cookies(&url).is_none());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let jar = CookieJar::new();
+        let url = Url::parse("https://www.wikidata.org/w/api.php").unwrap();
+        let cookie = HeaderValue::from_static("wikidatawikiSession=abc; Path=/; Secure; HttpOnly");
+        jar.set_cookies(&mut std::iter::once(&cookie), &url);
+        let json = jar.to_json().unwrap();
+        let restored = CookieJar::new();
+        restored.load_json(&json).unwrap();
+        assert_eq!(restored.cookies(&url).unwrap(), "wikidatawikiSession=abc");
+        let other = Url::parse("https://en.wikipedia.org/w/api.php").unwrap();
+        assert!(restored.cookies(&other).is_none());
+        assert!(restored.load_json("not json").is_err());
+    }
+}
@@ -0,0 +1,164 @@
+/*!
+The `deleted_revisions` module reads revisions of deleted pages via `prop=deletedrevisions` and
+`list=alldeletedrevisions`. Both require the `deletedhistory` right, and `deletedtext` for the wikitext.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::Page;
+use crate::revision::{Revision, RVPROP};
+use crate::title::Title;
+use futures::StreamExt;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Revision properties for `list=alldeletedrevisions`; the wikitext is left out, as it limits requests to 50 revisions
+const ADRPROP: &str = "ids|flags|timestamp|size|sha1|comment|tags|user|userid";
+
+impl Api {
+    /// Returns the deleted revisions of all pages, optionally only those by `user`, and in `namespaces`
+    /// (all if empty), newest first, each with the title of its page. The wikitext is not included.
+    pub async fn all_deleted_revisions(
+        &self,
+        user: Option<&str>,
+        namespaces: &[NamespaceID],
+    ) -> Result<Vec<(Title, Revision)>, MediaWikiError> {
+        let mut params = self.params_into(&[
+            ("action", "query"),
+            ("list", "alldeletedrevisions"),
+            ("adrprop", ADRPROP),
+            ("adrlimit", "max"),
+            ("formatversion", "2"),
+        ]);
+        if let Some(user) = user {
+            params.insert("adruser".to_string(), user.to_string());
+        }
+        if !namespaces.is_empty() {
+            let namespaces: Vec<String> = namespaces.iter().map(|ns| ns.to_string()).collect();
+            params.insert("adrnamespace".to_string(), namespaces.join("|"));
+        }
+        self.deleted_revisions_query(&params, "alldeletedrevisions", "revisions")
+            .await
+    }
+
+    /// Runs a query with continuation, and returns the deleted revisions of all pages in `["query"][list_key]`
+    async fn deleted_revisions_query(
+        &self,
+        params: &HashMap<String, String>,
+        list_key: &str,
+        revisions_key: &str,
+    ) -> Result<Vec<(Title, Revision)>, MediaWikiError> {
+        let results: Vec<_> = self
+            .get_query_api_json_limit_iter(params, None)
+            .await
+            .collect()
+            .await;
+        let mut ret = vec![];
+        for result in results {
+            let result = result?;
+            let pages = result["query"][list_key].as_array().into_iter().flatten();
+            for page in pages {
+                let revisions = page[revisions_key].as_array().into_iter().flatten();
+                for revision in revisions {
+                    ret.push((
+                        Title::new_from_api_result(page),
+                        Self::deleted_revision(revision)?,
+                    ));
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    fn deleted_revision(j: &Value) -> Result<Revision, MediaWikiError> {
+        let mut revision = Revision::from_json(j)?;
+        revision.set_deleted(true);
+        Ok(revision)
+    }
+}
+
+impl Page {
+    /// Returns the deleted revisions of this page, including their wikitext, newest first.
+    /// The page itself may exist, if it was recreated after deletion.
+    pub async fn deleted_revisions(&self, api: &Api) -> Result<Vec<Revision>, MediaWikiError> {
+        let title = self
+            .title()
+            .full_pretty(api)
+            .ok_or_else(|| MediaWikiError::BadTitle(self.title().clone()))?;
+        let params = api.params_into(&[
+            ("action", "query"),
+            ("prop", "deletedrevisions"),
+            ("titles", &title),
+            ("drvprop", RVPROP),
+            ("drvslots", "main"),
+            ("drvlimit", "max"),
+            ("formatversion", "2"),
+        ]);
+        Ok(api
+            .deleted_revisions_query(&params, "pages", "deletedrevisions")
+            .await?
+            .into_iter()
+            .map(|(_, revision)| revision)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn deleted_revisions() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"namespaces":{"0":{"id":0,"case":"first-letter","*":""}}}}),
+            )
+            .add(
+                &[("adrcontinue", "20240101000000|5")],
+                json!({"batchcomplete":true,"query":{"alldeletedrevisions":[{"ns":0,"title":"Bar",
+                    "revisions":[{"revid":5,"parentid":0,"user":"Vandal"}]}]}}),
+            )
+            .add(
+                &[("list", "alldeletedrevisions")],
+                json!({"continue":{"adrcontinue":"20240101000000|5","continue":"-||"},
+                    "query":{"alldeletedrevisions":[{"ns":0,"title":"Foo",
+                    "revisions":[{"revid":7,"parentid":6,"user":"Vandal"},{"revid":6,"parentid":0,"user":"Vandal"}]}]}}),
+            )
+            .add(
+                &[("prop", "deletedrevisions")],
+                json!({"batchcomplete":true,"query":{"pages":[{"ns":0,"title":"Foo","missing":true,
+                    "deletedrevisions":[{"revid":7,"parentid":6,"minor":true,"slots":{"main":{"content":"Spam"}}}]}]}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let all = api
+            .all_deleted_revisions(Some("Vandal"), &[0])
+            .await
+            .unwrap();
+        let ids: Vec<(String, u64)> = all
+            .iter()
+            .filter_map(|(title, rev)| Some((title.pretty().to_string(), rev.id()?)))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![
+                ("Foo".to_string(), 7),
+                ("Foo".to_string(), 6),
+                ("Bar".to_string(), 5)
+            ]
+        );
+        assert!(all.iter().all(|(_, rev)| rev.is_deleted()));
+        assert_eq!(responses.requests()[0]["adruser"], "Vandal");
+
+        let page = Page::new(Title::new("Foo", 0));
+        let revisions = page.deleted_revisions(&api).await.unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert!(revisions[0].is_deleted());
+        assert!(revisions[0].is_minor());
+        assert_eq!(revisions[0].wikitext(), Some("Spam"));
+    }
+}
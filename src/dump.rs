@@ -0,0 +1,304 @@
+/*!
+The `dump` module reads MediaWiki XML dumps (e.g. `pages-articles.xml`), as produced by `Special:Export`
+and `dumpBackup.php`, as an iterator or a stream of `(Title, Revision)` items.
+Requires the `dump` feature.
+
+```no_run
+use mediawiki::dump::DumpReader;
+for item in DumpReader::from_path("dewiki-latest-pages-articles.xml.gz").unwrap() {
+    let (title, revision) = item.unwrap();
+    println!("{} {:?}", title.pretty(), revision.id());
+}
+```
+*/
+
+#![deny(missing_docs)]
+
+use crate::media_wiki_error::MediaWikiError;
+use crate::revision::Revision;
+use crate::title::Title;
+use futures::Stream;
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+
+/// Number of parsed items buffered ahead of a slow consumer by `DumpReader::into_stream`
+const STREAM_BUFFER: usize = 64;
+
+/// Reads the pages and revisions of an XML dump
+pub struct DumpReader<R: Read> {
+    events: EventReader<BufReader<R>>,
+    stack: Vec<String>,
+    text: String,
+    page_title: Option<String>,
+    page_ns: i64,
+    revision: Option<Map<String, Value>>,
+    done: bool,
+}
+
+impl<R: Read> std::fmt::Debug for DumpReader<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("DumpReader")
+            .field("page_title", &self.page_title)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl DumpReader<Box<dyn Read + Send>> {
+    /// Opens a dump file; files ending in `.gz` are decompressed
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, MediaWikiError> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let reader: Box<dyn Read + Send> = match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => Box::new(flate2::read::MultiGzDecoder::new(file)),
+            _ => Box::new(file),
+        };
+        Ok(Self::new(reader))
+    }
+}
+
+impl<R: Read> DumpReader<R> {
+    /// Creates a reader for an uncompressed XML dump
+    pub fn new(reader: R) -> Self {
+        Self {
+            events: EventReader::new(BufReader::new(reader)),
+            stack: vec![],
+            text: String::new(),
+            page_title: None,
+            page_ns: 0,
+            revision: None,
+            done: false,
+        }
+    }
+
+    /// Returns true if `attributes` mark the element as removed by revision deletion
+    fn is_deleted(attributes: &[OwnedAttribute]) -> bool {
+        attributes
+            .iter()
+            .any(|a| a.name.local_name == "deleted" && a.value == "deleted")
+    }
+
+    /// Handles the start of an element within a revision
+    fn start_revision_element(&mut self, name: &str, attributes: &[OwnedAttribute]) {
+        let Some(revision) = self.revision.as_mut() else {
+            return;
+        };
+        let mut set = |key: &str, value: Value| {
+            revision.insert(key.to_string(), value);
+        };
+        match name {
+            "contributor" if Self::is_deleted(attributes) => set("userhidden", json!(true)),
+            "comment" if Self::is_deleted(attributes) => set("commenthidden", json!(true)),
+            "text" if Self::is_deleted(attributes) => set("texthidden", json!(true)),
+            "minor" => set("minor", json!(true)),
+            _ => {}
+        }
+        if name == "text" {
+            if let Some(bytes) = attributes
+                .iter()
+                .find(|a| a.name.local_name == "bytes")
+                .and_then(|a| a.value.parse::<u64>().ok())
+            {
+                set("size", json!(bytes));
+            }
+        }
+    }
+
+    /// Handles the end of an element with its text; returns an item at the end of a revision
+    fn end_element(&mut self, name: &str) -> Option<Result<(Title, Revision), MediaWikiError>> {
+        let text = std::mem::take(&mut self.text);
+        let parent = self.stack.last().map(|s| s.as_str()).unwrap_or_default();
+        let key = match (parent, name) {
+            ("page", "title") => {
+                self.page_title = Some(text);
+                return None;
+            }
+            ("page", "ns") => {
+                self.page_ns = text.trim().parse().unwrap_or_default();
+                return None;
+            }
+            ("page", "revision") => {
+                let revision = Value::Object(self.revision.take()?);
+                let title = json!({"title": self.page_title.as_deref().unwrap_or_default(), "ns": self.page_ns});
+                return Some(
+                    Revision::from_json(&revision)
+                        .map(|revision| (Title::new_from_api_result(&title), revision)),
+                );
+            }
+            ("revision", "id") => "revid",
+            ("revision", "parentid") => "parentid",
+            ("revision", "timestamp") => "timestamp",
+            ("revision", "comment") => "comment",
+            ("revision", "model") => "contentmodel",
+            ("revision", "sha1") => "sha1",
+            ("revision", "text") => "*",
+            ("contributor", "username") | ("contributor", "ip") => "user",
+            ("contributor", "id") => "userid",
+            _ => return None,
+        };
+        let value = match key {
+            "revid" | "parentid" | "userid" => match text.trim().parse::<u64>() {
+                Ok(id) => json!(id),
+                Err(_) => return None,
+            },
+            _ => json!(text),
+        };
+        if let Some(revision) = self.revision.as_mut() {
+            revision.insert(key.to_string(), value);
+        }
+        None
+    }
+
+    /// Returns the items as a stream, parsing on a blocking thread of the tokio runtime
+    pub fn into_stream(self) -> impl Stream<Item = Result<(Title, Revision), MediaWikiError>>
+    where
+        R: Send + 'static,
+    {
+        let (tx, rx) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+        tokio::task::spawn_blocking(move || {
+            for item in self {
+                if tx.blocking_send(item).is_err() {
+                    break; // Stream was dropped
+                }
+            }
+        });
+        futures::stream::unfold(rx, |mut rx| async move {
+            let item = rx.recv().await?;
+            Some((item, rx))
+        })
+    }
+}
+
+impl<R: Read> Iterator for DumpReader<R> {
+    type Item = Result<(Title, Revision), MediaWikiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            match self.events.next() {
+                Ok(XmlEvent::StartElement {
+                    name, attributes, ..
+                }) => {
+                    let name = name.local_name;
+                    self.text.clear();
+                    match name.as_str() {
+                        "page" => {
+                            self.page_title = None;
+                            self.page_ns = 0;
+                        }
+                        "revision" => self.revision = Some(Map::new()),
+                        _ => self.start_revision_element(&name, &attributes),
+                    }
+                    self.stack.push(name);
+                }
+                Ok(XmlEvent::Characters(s))
+                | Ok(XmlEvent::CData(s))
+                | Ok(XmlEvent::Whitespace(s)) => self.text.push_str(&s),
+                Ok(XmlEvent::EndElement { name }) => {
+                    self.stack.pop();
+                    if let Some(item) = self.end_element(&name.local_name) {
+                        return Some(item);
+                    }
+                }
+                Ok(XmlEvent::EndDocument) => self.done = true,
+                Ok(_) => {}
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(MediaWikiError::String(format!(
+                        "XML dump error: {}",
+                        e
+                    ))));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    const DUMP: &str = r#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/" version="0.11" xml:lang="en">
+  <siteinfo>
+    <sitename>Wikipedia</sitename>
+    <namespaces><namespace key="0" case="first-letter" /><namespace key="10" case="first-letter">Template</namespace></namespaces>
+  </siteinfo>
+  <page>
+    <title>Foo &amp; bar</title>
+    <ns>0</ns>
+    <id>12</id>
+    <revision>
+      <id>100</id>
+      <timestamp>2024-01-02T03:04:05Z</timestamp>
+      <contributor><username>Alice</username><id>7</id></contributor>
+      <minor />
+      <comment>first</comment>
+      <model>wikitext</model>
+      <format>text/x-wiki</format>
+      <text bytes="11" xml:space="preserve">Hello '''x'''</text>
+      <sha1>abc</sha1>
+    </revision>
+    <revision>
+      <id>101</id>
+      <parentid>100</parentid>
+      <timestamp>2024-01-03T03:04:05Z</timestamp>
+      <contributor deleted="deleted" />
+      <comment deleted="deleted" />
+      <text bytes="3" xml:space="preserve">  a</text>
+    </revision>
+  </page>
+  <page>
+    <title>Template:Infobox</title>
+    <ns>10</ns>
+    <id>13</id>
+    <revision>
+      <id>200</id>
+      <timestamp>2024-01-04T03:04:05Z</timestamp>
+      <contributor><ip>127.0.0.1</ip></contributor>
+      <text bytes="0" xml:space="preserve" />
+    </revision>
+  </page>
+</mediawiki>"#;
+
+    #[test]
+    fn read_dump() {
+        let items: Vec<(Title, Revision)> = DumpReader::new(DUMP.as_bytes())
+            .map(|item| item.unwrap())
+            .collect();
+        assert_eq!(items.len(), 3);
+        let (title, revision) = &items[0];
+        assert_eq!(title, &Title::new("Foo & bar", 0));
+        assert_eq!(revision.id(), Some(100));
+        assert_eq!(revision.wikitext(), Some("Hello '''x'''"));
+        assert_eq!(revision.user().present(), Some("Alice"));
+        assert_eq!(revision.userid().present(), Some(7));
+        assert_eq!(revision.size(), Some(11));
+        let (_, revision) = &items[1];
+        assert_eq!(revision.parent_id(), Some(100));
+        assert_eq!(revision.wikitext(), Some("  a"));
+        assert!(revision.user().is_hidden());
+        assert!(revision.comment().is_hidden());
+        let (title, revision) = &items[2];
+        assert_eq!(title, &Title::new("Infobox", 10));
+        assert_eq!(revision.user().present(), Some("127.0.0.1"));
+        assert!(DumpReader::new("<mediawiki><page>".as_bytes())
+            .last()
+            .unwrap()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn stream_dump() {
+        let ids: Vec<Option<u64>> = DumpReader::new(DUMP.as_bytes())
+            .into_stream()
+            .map(|item| item.unwrap().1.id())
+            .collect()
+            .await;
+        assert_eq!(ids, vec![Some(100), Some(101), Some(200)]);
+    }
+}
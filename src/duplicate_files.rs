@@ -0,0 +1,128 @@
+/*!
+The `duplicate_files` module finds files with identical content, via `prop=duplicatefiles` and SHA1 lookups.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use futures::Stream;
+use serde_json::Value;
+
+/// Length of a SHA1 hash in base 36, as used in the database
+const SHA1_BASE36_LEN: usize = 31;
+
+/// A file with identical content to another file
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DuplicateFile {
+    name: String,
+    user: Option<String>,
+    timestamp: Option<String>,
+    shared: bool,
+}
+
+impl DuplicateFile {
+    /// Creates a new entry from a `prop=duplicatefiles` result entry
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            name: j["name"].as_str()?.replace('_', " "),
+            user: j["user"].as_str().map(|s| s.to_string()),
+            timestamp: j["timestamp"].as_str().map(|s| s.to_string()),
+            // `formatversion=2` uses a boolean, version 1 an empty string
+            shared: match &j["shared"] {
+                Value::Bool(b) => *b,
+                Value::Null => false,
+                _ => true,
+            },
+        })
+    }
+
+    /// Creates a new local entry from a `list=allimages` result entry
+    pub fn from_allimages_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            name: j["name"].as_str()?.replace('_', " "),
+            user: j["user"].as_str().map(|s| s.to_string()),
+            timestamp: j["timestamp"].as_str().map(|s| s.to_string()),
+            shared: false,
+        })
+    }
+
+    /// Returns the file name, without namespace prefix
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the user who uploaded the current version
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Returns the upload timestamp of the current version
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Returns true if the file is on a shared repository (e.g. Wikimedia Commons)
+    pub fn is_shared(&self) -> bool {
+        self.shared
+    }
+
+    /// Returns true if the file is on the local wiki
+    pub fn is_local(&self) -> bool {
+        !self.shared
+    }
+}
+
+impl Api {
+    /// Returns a stream of local files with the given SHA1 content hash, via `list=allimages`.
+    /// `sha1` can be hexadecimal (as returned by `prop=imageinfo`) or base 36.
+    pub async fn files_by_sha1(
+        &self,
+        sha1: &str,
+    ) -> impl Stream<Item = Result<DuplicateFile, MediaWikiError>> + '_ {
+        let sha1 = sha1.trim().to_lowercase();
+        let key = match sha1.len() {
+            SHA1_BASE36_LEN => "aisha1base36",
+            _ => "aisha1",
+        };
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("list", "allimages"),
+            (key, &sha1),
+            ("aiprop", "user|timestamp"),
+            ("ailimit", "max"),
+        ]);
+        self.get_query_api_list_iter(&params, "allimages", DuplicateFile::from_allimages_json)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicate_file_from_json() {
+        let local = DuplicateFile::from_json(
+            &json!({"name":"Foo_bar.jpg","user":"Alice","timestamp":"2020-01-01T00:00:00Z"}),
+        )
+        .unwrap();
+        assert_eq!(local.name(), "Foo bar.jpg");
+        assert!(local.is_local());
+        let shared = DuplicateFile::from_json(&json!({"name":"Foo.jpg","shared":""})).unwrap();
+        assert!(shared.is_shared());
+        let shared = DuplicateFile::from_json(&json!({"name":"Foo.jpg","shared":true})).unwrap();
+        assert!(shared.is_shared());
+        assert!(DuplicateFile::from_json(&json!({})).is_none());
+    }
+
+    #[test]
+    fn allimages_from_json() {
+        let file = DuplicateFile::from_allimages_json(
+            &json!({"name":"Foo.jpg","title":"File:Foo.jpg","user":"Alice"}),
+        )
+        .unwrap();
+        assert_eq!(file.user(), Some("Alice"));
+        assert!(file.is_local());
+    }
+}
@@ -0,0 +1,136 @@
+/*!
+The `DuplicateEditGuard` class refuses to submit the same edit twice within a time window,
+to protect against bot loops re-saving identical content.
+*/
+
+#![deny(missing_docs)]
+
+use crate::media_wiki_error::MediaWikiError;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// Parameters that identify the edited page or entity
+const TARGET_PARAMS: &[&str] = &["title", "pageid", "id", "entity", "claim"];
+
+/// Parameters that contain the edit content
+const CONTENT_PARAMS: &[&str] = &["text", "appendtext", "prependtext", "data", "value"];
+
+/// Remembers hashes of the last edits, and rejects identical ones within a time window
+#[derive(Debug, Clone)]
+pub struct DuplicateEditGuard {
+    capacity: usize,
+    window: Duration,
+    entries: VecDeque<(u64, Instant)>,
+}
+
+impl DuplicateEditGuard {
+    /// Creates a new guard, remembering up to `capacity` edits for `window`
+    pub fn new(capacity: usize, window: Duration) -> Self {
+        Self {
+            capacity,
+            window,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// Returns the target and a hash of the content of an edit, if the parameters describe one
+    fn edit_key(params: &HashMap<String, String>) -> Option<(String, u64)> {
+        let target = TARGET_PARAMS
+            .iter()
+            .find_map(|k| params.get(*k).map(|v| format!("{}={}", k, v)))?;
+        let mut hasher = DefaultHasher::new();
+        params.get("action").hash(&mut hasher);
+        target.hash(&mut hasher);
+        let mut has_content = false;
+        for k in CONTENT_PARAMS {
+            if let Some(v) = params.get(*k) {
+                (k, v).hash(&mut hasher);
+                has_content = true;
+            }
+        }
+        match has_content {
+            true => Some((target, hasher.finish())),
+            false => None,
+        }
+    }
+
+    /// Forgets edits that are older than the window
+    fn expire(&mut self, now: Instant) {
+        while let Some((_, time)) = self.entries.front() {
+            if now.duration_since(*time) <= self.window {
+                break;
+            }
+            self.entries.pop_front();
+        }
+    }
+
+    /// Returns an error if an identical edit was recorded within the window
+    pub fn check(&mut self, params: &HashMap<String, String>) -> Result<(), MediaWikiError> {
+        self.expire(Instant::now());
+        if let Some((target, hash)) = Self::edit_key(params) {
+            if self.entries.iter().any(|(h, _)| *h == hash) {
+                return Err(MediaWikiError::DuplicateEdit(target));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records an edit
+    pub fn record(&mut self, params: &HashMap<String, String>) {
+        if let Some((_, hash)) = Self::edit_key(params) {
+            self.entries.push_back((hash, Instant::now()));
+            while self.entries.len() > self.capacity {
+                self.entries.pop_front();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(title: &str, text: &str) -> HashMap<String, String> {
+        hashmap![
+            "action".to_string() => "edit".to_string(),
+            "title".to_string() => title.to_string(),
+            "text".to_string() => text.to_string()
+        ]
+    }
+
+    #[test]
+    fn duplicate_rejected() {
+        let mut guard = DuplicateEditGuard::new(10, Duration::from_secs(60));
+        guard.check(&edit("Foo", "a")).unwrap();
+        guard.record(&edit("Foo", "a"));
+        assert!(matches!(
+            guard.check(&edit("Foo", "a")),
+            Err(MediaWikiError::DuplicateEdit(_))
+        ));
+        assert!(guard.check(&edit("Foo", "b")).is_ok());
+        assert!(guard.check(&edit("Bar", "a")).is_ok());
+    }
+
+    #[test]
+    fn capacity_and_window() {
+        let mut guard = DuplicateEditGuard::new(1, Duration::from_secs(60));
+        guard.record(&edit("Foo", "a"));
+        guard.record(&edit("Bar", "a"));
+        assert!(guard.check(&edit("Foo", "a")).is_ok());
+
+        let mut guard = DuplicateEditGuard::new(10, Duration::ZERO);
+        guard.record(&edit("Foo", "a"));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(guard.check(&edit("Foo", "a")).is_ok());
+    }
+
+    #[test]
+    fn non_edits_ignored() {
+        let mut guard = DuplicateEditGuard::new(10, Duration::from_secs(60));
+        let params = hashmap!["action".to_string() => "query".to_string()];
+        guard.record(&params);
+        assert!(guard.check(&params).is_ok());
+    }
+}
@@ -0,0 +1,131 @@
+/*!
+The `EditSummary` class adds a tool name and version, and an optional campaign hashtag, to edit summaries,
+e.g. `Fix typo #mytool v1.2 #campaign`, so edits can be attributed to the tool that made them.
+Summaries are truncated to the limit of MediaWiki, keeping the suffix intact.
+*/
+
+#![deny(missing_docs)]
+
+/// Maximum length of an edit summary, in characters
+pub const MAX_SUMMARY_LENGTH: usize = 500;
+
+/// Appended to summary text that was shortened
+const ELLIPSIS: char = '…';
+
+/// A tool name and version, and an optional campaign hashtag, to append to edit summaries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditSummary {
+    tool: Option<String>,
+    version: Option<String>,
+    hashtag: Option<String>,
+}
+
+impl EditSummary {
+    /// Creates an `EditSummary` without suffix
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an `EditSummary` with the tool name and version, e.g. `mytool` and `1.2`
+    pub fn with_tool(tool: &str, version: Option<&str>) -> Self {
+        let mut ret = Self::new();
+        ret.set_tool(Some(tool), version);
+        ret
+    }
+
+    /// Returns the tool name
+    pub fn tool(&self) -> Option<&str> {
+        self.tool.as_deref()
+    }
+
+    /// Returns the tool version
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Sets the tool name and version; a leading `#` of the name is optional
+    pub fn set_tool(&mut self, tool: Option<&str>, version: Option<&str>) {
+        self.tool = tool.map(|s| s.trim_start_matches('#').to_string());
+        self.version = version.map(|s| s.trim_start_matches('v').to_string());
+    }
+
+    /// Returns the campaign hashtag, without `#`
+    pub fn hashtag(&self) -> Option<&str> {
+        self.hashtag.as_deref()
+    }
+
+    /// Sets the campaign hashtag; a leading `#` is optional
+    pub fn set_hashtag(&mut self, hashtag: Option<&str>) {
+        self.hashtag = hashtag.map(|s| s.trim_start_matches('#').to_string());
+    }
+
+    /// Returns the suffix, e.g. `#mytool v1.2 #campaign`, or an empty string
+    pub fn suffix(&self) -> String {
+        let mut parts = vec![];
+        if let Some(tool) = &self.tool {
+            parts.push(format!("#{}", tool));
+            if let Some(version) = &self.version {
+                parts.push(format!("v{}", version));
+            }
+        }
+        if let Some(hashtag) = &self.hashtag {
+            parts.push(format!("#{}", hashtag));
+        }
+        parts.join(" ")
+    }
+
+    /// Returns `text` with the suffix, shortening `text` if the result would exceed `MAX_SUMMARY_LENGTH`
+    pub fn apply(&self, text: &str) -> String {
+        let text = text.trim();
+        let suffix = self.suffix();
+        if suffix.is_empty() {
+            return truncate_summary(text, MAX_SUMMARY_LENGTH);
+        }
+        if text.is_empty() {
+            return truncate_summary(&suffix, MAX_SUMMARY_LENGTH);
+        }
+        let available = MAX_SUMMARY_LENGTH.saturating_sub(suffix.chars().count() + 1);
+        if available == 0 {
+            return truncate_summary(&suffix, MAX_SUMMARY_LENGTH);
+        }
+        format!("{} {}", truncate_summary(text, available), suffix)
+    }
+}
+
+/// Shortens `text` to at most `max_chars` characters, ending it with an ellipsis if it was shortened.
+/// Counts characters, not bytes, so multi-byte characters are never split.
+pub fn truncate_summary(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut ret: String = text
+        .chars()
+        .take(max_chars.saturating_sub(1))
+        .collect::<String>()
+        .trim_end()
+        .to_string();
+    if max_chars > 0 {
+        ret.push(ELLIPSIS);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply() {
+        let mut summary = EditSummary::with_tool("#mytool", Some("v1.2"));
+        assert_eq!(summary.apply("Fix typo"), "Fix typo #mytool v1.2");
+        summary.set_hashtag(Some("campaign"));
+        assert_eq!(summary.apply(" "), "#mytool v1.2 #campaign");
+        assert_eq!(EditSummary::new().apply("Fix typo"), "Fix typo");
+        let long = "ä".repeat(600);
+        let applied = summary.apply(&long);
+        assert_eq!(applied.chars().count(), MAX_SUMMARY_LENGTH);
+        assert!(applied.ends_with("ä… #mytool v1.2 #campaign"));
+        assert_eq!(truncate_summary("abc", 3), "abc");
+        assert_eq!(truncate_summary("ab cd", 4), "ab…");
+    }
+}
@@ -0,0 +1,534 @@
+/*!
+The `entity_container` module loads and caches Wikibase entities, fetching batches concurrently.
+With an `EntityStore`, loaded entities are kept on disk instead of in memory.
+A container can be shared between tasks as `Arc<EntityContainer>`; concurrent requests for the same entity
+are coalesced into a single fetch.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{read_lock, write_lock, Api};
+use crate::entity_store::EntityStore;
+use crate::media_wiki_error::MediaWikiError;
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use tokio::sync::watch;
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Entities being fetched, with a receiver that turns `true` when the fetch succeeded
+type Pending = Mutex<HashMap<String, watch::Receiver<bool>>>;
+
+/// Marks entities as being fetched; dropping it unmarks them, and wakes up waiting tasks
+struct PendingGuard<'a> {
+    pending: &'a Pending,
+    ids: Vec<String>,
+    senders: Vec<watch::Sender<bool>>,
+}
+
+impl PendingGuard<'_> {
+    /// Tells waiting tasks that the entities were fetched; without this, they fetch the entities themselves
+    fn complete(&self) {
+        for sender in &self.senders {
+            sender.send_replace(true);
+        }
+    }
+
+    /// Unmarks the entities in `done`, which were loaded in the meantime, and tells waiting tasks
+    fn release(&mut self, done: &HashSet<String>) {
+        if done.is_empty() {
+            return;
+        }
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        let claimed = std::mem::take(&mut self.ids).into_iter();
+        let senders = std::mem::take(&mut self.senders).into_iter();
+        for (id, sender) in claimed.zip(senders) {
+            if done.contains(&id) {
+                sender.send_replace(true);
+                pending.remove(&id);
+            } else {
+                self.ids.push(id);
+                self.senders.push(sender);
+            }
+        }
+    }
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+        for id in &self.ids {
+            pending.remove(id);
+        }
+    }
+}
+
+/// A cache of Wikibase entities as JSON, keyed by entity ID
+#[derive(Debug)]
+pub struct EntityContainer {
+    entities: RwLock<HashMap<String, Arc<Value>>>,
+    pending: Pending,
+    concurrency: usize,
+    store: Option<Arc<dyn EntityStore>>,
+}
+
+impl Default for EntityContainer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EntityContainer {
+    /// Creates an empty container
+    pub fn new() -> Self {
+        Self {
+            entities: RwLock::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            concurrency: DEFAULT_CONCURRENCY,
+            store: None,
+        }
+    }
+
+    /// Creates an empty container that keeps loaded entities in `store`
+    pub fn with_store(store: Arc<dyn EntityStore>) -> Self {
+        let mut ret = Self::new();
+        ret.set_store(Some(store));
+        ret
+    }
+
+    /// Returns the persistent store, if any
+    pub fn store(&self) -> Option<&Arc<dyn EntityStore>> {
+        self.store.as_ref()
+    }
+
+    /// Sets the persistent store; entities loaded afterwards are written to the store instead of memory
+    pub fn set_store(&mut self, store: Option<Arc<dyn EntityStore>>) {
+        self.store = store;
+    }
+
+    /// Returns the maximum number of concurrent requests (default 4)
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Sets the maximum number of concurrent requests; at least one request is always made
+    pub fn set_concurrency(&mut self, concurrency: usize) {
+        self.concurrency = concurrency.max(1);
+    }
+
+    /// Loads all entities in `ids` that are not in the container yet.
+    /// Entities that another task is loading already are waited for, not fetched again,
+    /// unless that task fails. Missing entities are skipped.
+    pub async fn load_entities<S: AsRef<str>>(
+        &self,
+        api: &Api,
+        ids: &[S],
+    ) -> Result<(), MediaWikiError> {
+        let mut ids: Vec<String> = ids.iter().map(|id| id.as_ref().to_string()).collect();
+        while !ids.is_empty() {
+            let (guard, waiting) = self.claim(&ids).await?;
+            let to_load: Vec<&str> = guard.ids.iter().map(|id| id.as_str()).collect();
+            let entities = self.fetch_entities(api, &to_load, None).await?;
+            self.insert_entities(entities).await?;
+            guard.complete();
+            drop(guard);
+            ids.clear();
+            for (id, mut receiver) in waiting {
+                // Fails if the sender was dropped without completing, i.e. the other task failed
+                if receiver.wait_for(|done| *done).await.is_err() {
+                    ids.push(id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Marks the entities in `ids` that are neither loaded nor being loaded as being loaded by the caller,
+    /// and returns receivers for those being loaded by other tasks
+    async fn claim<S: AsRef<str>>(
+        &self,
+        ids: &[S],
+    ) -> Result<(PendingGuard<'_>, Vec<(String, watch::Receiver<bool>)>), MediaWikiError> {
+        let mut ids: Vec<String> = ids.iter().map(|id| id.as_ref().to_string()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        // The store does blocking I/O, so it is checked outside the lock
+        let stored = self.stored_ids(ids.clone()).await?;
+        let mut guard = PendingGuard {
+            pending: &self.pending,
+            ids: vec![],
+            senders: vec![],
+        };
+        let mut waiting = vec![];
+        // Memory is checked under the lock, because a fetching task inserts its entities before unmarking them
+        {
+            let mut pending = self.pending.lock().unwrap_or_else(PoisonError::into_inner);
+            for id in ids {
+                if let Some(receiver) = pending.get(&id) {
+                    waiting.push((id, receiver.clone()));
+                } else if !stored.contains(&id) && self.get_entity(&id).is_none() {
+                    let (sender, receiver) = watch::channel(false);
+                    pending.insert(id.clone(), receiver);
+                    guard.ids.push(id);
+                    guard.senders.push(sender);
+                }
+            }
+        }
+        // Another task may have stored some entities between the check and the lock
+        let stored = self.stored_ids(guard.ids.clone()).await?;
+        guard.release(&stored);
+        Ok((guard, waiting))
+    }
+
+    /// Returns those of `ids` that are in the store
+    async fn stored_ids(&self, ids: Vec<String>) -> Result<HashSet<String>, MediaWikiError> {
+        self.on_store(move |store| Ok(ids.into_iter().filter(|id| store.contains(id)).collect()))
+            .await
+    }
+
+    /// Checks the current revision of all entities in the container via `wbgetentities` with `props=info`,
+    /// and reloads those that changed since they were loaded. Entities that no longer exist are removed.
+    /// Returns the IDs of the reloaded and removed entities.
+    pub async fn refresh_stale(&self, api: &Api) -> Result<Vec<String>, MediaWikiError> {
+        let ids = self.all_entity_ids().await?;
+        let id_refs: Vec<&str> = ids.iter().map(|id| id.as_str()).collect();
+        let current = self.fetch_entities(api, &id_refs, Some("info")).await?;
+        let mut stale = vec![];
+        for id in ids {
+            let current_revid = current.get(&id).and_then(|e| e["lastrevid"].as_u64());
+            if current_revid.is_none() || current_revid != self.lastrevid(&id).await? {
+                stale.push(id);
+            }
+        }
+        for id in &stale {
+            write_lock(&self.entities).remove(id);
+        }
+        let removed = stale.clone();
+        self.on_store(move |store| removed.iter().try_for_each(|id| store.remove(id)))
+            .await?;
+        let to_load: Vec<&str> = stale
+            .iter()
+            .filter(|id| current.contains_key(id.as_str()))
+            .map(|id| id.as_str())
+            .collect();
+        let entities = self.fetch_entities(api, &to_load, None).await?;
+        self.insert_entities(entities).await?;
+        Ok(stale)
+    }
+
+    /// Adds loaded entities to the store if there is one, or to memory otherwise
+    async fn insert_entities(
+        &self,
+        entities: HashMap<String, Value>,
+    ) -> Result<(), MediaWikiError> {
+        match &self.store {
+            Some(_) => {
+                self.on_store(move |store| {
+                    entities
+                        .iter()
+                        .try_for_each(|(id, entity)| store.set(id, entity))
+                })
+                .await
+            }
+            None => {
+                write_lock(&self.entities).extend(
+                    entities
+                        .into_iter()
+                        .map(|(id, entity)| (id, Arc::new(entity))),
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Runs `f` on the store on a blocking thread of the tokio runtime; without a store, returns the default
+    async fn on_store<T, F>(&self, f: F) -> Result<T, MediaWikiError>
+    where
+        T: Default + Send + 'static,
+        F: FnOnce(&dyn EntityStore) -> Result<T, MediaWikiError> + Send + 'static,
+    {
+        let Some(store) = self.store.clone() else {
+            return Ok(T::default());
+        };
+        tokio::task::spawn_blocking(move || f(store.as_ref()))
+            .await
+            .map_err(|e| MediaWikiError::String(e.to_string()))?
+    }
+
+    /// Fetches entities in concurrent batches of the size allowed for the user, optionally with only some `props`
+    async fn fetch_entities(
+        &self,
+        api: &Api,
+        ids: &[&str],
+        props: Option<&str>,
+    ) -> Result<HashMap<String, Value>, MediaWikiError> {
+        let mut entities = HashMap::new();
+        let mut chunks = ids.chunks(api.multi_value_limit());
+        let mut running = FuturesUnordered::new();
+        loop {
+            while running.len() < self.concurrency {
+                match chunks.next() {
+                    Some(chunk) => running.push(Self::load_chunk(api, chunk, props)),
+                    None => break,
+                }
+            }
+            match running.next().await {
+                Some(result) => entities.extend(result?),
+                None => break,
+            }
+        }
+        Ok(entities)
+    }
+
+    /// Loads a single entity, unless it is in the container already, and returns it
+    pub async fn load_entity(
+        &self,
+        api: &Api,
+        id: &str,
+    ) -> Result<Option<Arc<Value>>, MediaWikiError> {
+        self.load_entities(api, &[id]).await?;
+        self.entity(id).await
+    }
+
+    /// Fetches one batch of entities
+    async fn load_chunk(
+        api: &Api,
+        ids: &[&str],
+        props: Option<&str>,
+    ) -> Result<HashMap<String, Value>, MediaWikiError> {
+        let ids = ids.join("|");
+        let mut params = api.params_into(&[("action", "wbgetentities"), ("ids", &ids)]);
+        if let Some(props) = props {
+            params.insert("props".to_string(), props.to_string());
+        }
+        let result = api.get_query_api_json(&params).await?;
+        if !result["error"].is_null() {
+            return Err(MediaWikiError::BadResponse(result));
+        }
+        Ok(Self::entities_from_api_result(&result))
+    }
+
+    /// Returns the existing entities of a `wbgetentities` result, keyed by ID
+    fn entities_from_api_result(result: &Value) -> HashMap<String, Value> {
+        result["entities"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, entity)| entity.get("missing").is_none())
+            .map(|(id, entity)| (id.to_owned(), entity.to_owned()))
+            .collect()
+    }
+
+    /// Returns an entity from memory, if loaded
+    pub fn get_entity(&self, id: &str) -> Option<Arc<Value>> {
+        read_lock(&self.entities).get(id).cloned()
+    }
+
+    /// Returns an entity from memory or the store, if loaded
+    pub async fn entity(&self, id: &str) -> Result<Option<Arc<Value>>, MediaWikiError> {
+        if let Some(entity) = self.get_entity(id) {
+            return Ok(Some(entity));
+        }
+        let id = id.to_string();
+        let entity = self.on_store(move |store| store.get(&id)).await?;
+        Ok(entity.map(Arc::new))
+    }
+
+    /// Returns the revision ID of an entity when it was loaded, from its `lastrevid`
+    pub async fn lastrevid(&self, id: &str) -> Result<Option<u64>, MediaWikiError> {
+        Ok(self
+            .entity(id)
+            .await?
+            .and_then(|entity| entity["lastrevid"].as_u64()))
+    }
+
+    /// Returns true if the entity is in memory or the store
+    pub async fn has_entity(&self, id: &str) -> Result<bool, MediaWikiError> {
+        if read_lock(&self.entities).contains_key(id) {
+            return Ok(true);
+        }
+        let id = id.to_string();
+        self.on_store(move |store| Ok(store.contains(&id))).await
+    }
+
+    /// Adds an entity to the container, replacing any existing one with the same ID
+    pub fn set_entity(&self, id: &str, entity: Value) {
+        write_lock(&self.entities).insert(id.to_string(), Arc::new(entity));
+    }
+
+    /// Removes an entity from memory, and returns it
+    pub fn remove_entity(&self, id: &str) -> Option<Arc<Value>> {
+        write_lock(&self.entities).remove(id)
+    }
+
+    /// Returns the IDs of all entities in memory
+    pub fn entity_ids(&self) -> Vec<String> {
+        read_lock(&self.entities).keys().cloned().collect()
+    }
+
+    /// Returns the sorted IDs of all entities in memory or the store
+    pub async fn all_entity_ids(&self) -> Result<Vec<String>, MediaWikiError> {
+        let mut ids = self.entity_ids();
+        ids.append(&mut self.on_store(|store| store.ids()).await?);
+        ids.sort_unstable();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    /// Returns the number of entities in memory
+    pub fn len(&self) -> usize {
+        read_lock(&self.entities).len()
+    }
+
+    /// Returns true if there are no entities in memory
+    pub fn is_empty(&self) -> bool {
+        read_lock(&self.entities).is_empty()
+    }
+
+    /// Removes all entities from memory; the store is left as it is
+    pub fn clear(&self) {
+        write_lock(&self.entities).clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_from_api_result() {
+        let result = json!({"entities":{
+            "Q42":{"id":"Q42","type":"item"},
+            "Q0":{"id":"Q0","missing":""}
+        }});
+        let entities = EntityContainer::entities_from_api_result(&result);
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities["Q42"]["type"], "item");
+    }
+
+    #[tokio::test]
+    async fn refresh_stale() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("props", "info")],
+                json!({"entities":{"Q1":{"id":"Q1","lastrevid":5},"Q2":{"id":"Q2","lastrevid":8},
+                    "Q3":{"id":"Q3","missing":""}}}),
+            )
+            .add(
+                &[("ids", "Q2")],
+                json!({"entities":{"Q2":{"id":"Q2","lastrevid":8,"labels":{}}}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let container = EntityContainer::new();
+        container.set_entity("Q1", json!({"id":"Q1","lastrevid":5}));
+        container.set_entity("Q2", json!({"id":"Q2","lastrevid":7}));
+        container.set_entity("Q3", json!({"id":"Q3","lastrevid":1}));
+        let changed = container.refresh_stale(&api).await.unwrap();
+        assert_eq!(changed, vec!["Q2", "Q3"]);
+        assert_eq!(container.lastrevid("Q2").await.unwrap(), Some(8));
+        assert!(container.get_entity("Q2").unwrap()["labels"].is_object());
+        assert!(!container.has_entity("Q3").await.unwrap());
+        assert_eq!(container.len(), 2);
+        assert_eq!(responses.requests()[0]["ids"], "Q1|Q2|Q3");
+    }
+
+    #[tokio::test]
+    async fn load_into_store() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("action", "wbgetentities")],
+            json!({"entities":{"Q1":{"id":"Q1","lastrevid":5}}}),
+        );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let dir =
+            std::env::temp_dir().join(format!("mediawiki-entity-container-{}", std::process::id()));
+        let store = Arc::new(crate::entity_store::FileEntityStore::new(&dir).unwrap());
+        let container = EntityContainer::with_store(store.clone());
+        container.load_entities(&api, &["Q1"]).await.unwrap();
+        assert!(container.is_empty());
+        assert!(container.has_entity("Q1").await.unwrap());
+        assert_eq!(container.lastrevid("Q1").await.unwrap(), Some(5));
+        let restarted = EntityContainer::with_store(store.clone());
+        assert_eq!(restarted.all_entity_ids().await.unwrap(), vec!["Q1"]);
+        assert_eq!(restarted.entity("Q1").await.unwrap().unwrap()["id"], "Q1");
+        // Entities in the store are not fetched again
+        restarted.load_entity(&api, "Q1").await.unwrap();
+        assert_eq!(responses.requests().len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn shared_container() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("action", "wbgetentities")],
+            json!({"entities":{"Q42":{"id":"Q42","lastrevid":3}}}),
+        );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let container = Arc::new(EntityContainer::new());
+        let (guard, waiting) = container.claim(&["Q42"]).await.unwrap();
+        assert!(waiting.is_empty());
+        let task = {
+            let (api, container) = (api.clone(), container.clone());
+            tokio::spawn(async move { container.load_entity(&api, "Q42").await })
+        };
+        tokio::task::yield_now().await;
+        assert!(!task.is_finished());
+        container.set_entity("Q42", json!({"id":"Q42","lastrevid":2}));
+        drop(guard);
+        let entity = task.await.unwrap().unwrap().unwrap();
+        assert_eq!(entity["lastrevid"], 2);
+        assert!(responses.requests().is_empty());
+        container.load_entity(&api, "Q42").await.unwrap();
+        container.remove_entity("Q42");
+        let entity = container.load_entity(&api, "Q42").await.unwrap().unwrap();
+        assert_eq!(entity["lastrevid"], 3);
+        assert_eq!(responses.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn waiter_fetches_after_failed_fetch() {
+        let mut failing = crate::mock::MockResponses::new();
+        failing.add(
+            &[("action", "wbgetentities")],
+            json!({"error":{"code":"internal_api_error_DBQueryError","info":"Database query error"}}),
+        );
+        let failing_api = Api::new_mocked(failing).unwrap();
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("action", "wbgetentities")],
+            json!({"entities":{"Q42":{"id":"Q42","lastrevid":3}}}),
+        );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let container = EntityContainer::new();
+        let owner = async {
+            let (guard, _) = container.claim(&["Q42"]).await.unwrap();
+            tokio::task::yield_now().await;
+            let result = container.fetch_entities(&failing_api, &["Q42"], None).await;
+            drop(guard);
+            result
+        };
+        let (owner, waiter) = tokio::join!(owner, container.load_entity(&api, "Q42"));
+        assert!(owner.is_err());
+        assert_eq!(waiter.unwrap().unwrap()["lastrevid"], 3);
+        assert_eq!(responses.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn container() {
+        let mut container = EntityContainer::new();
+        container.set_concurrency(0);
+        assert_eq!(container.concurrency(), 1);
+        container.set_entity("Q42", json!({"id":"Q42"}));
+        assert!(container.has_entity("Q42").await.unwrap());
+        assert_eq!(container.entity_ids(), vec!["Q42"]);
+        assert_eq!(
+            container.remove_entity("Q42"),
+            Some(Arc::new(json!({"id":"Q42"})))
+        );
+        assert!(container.is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+/*!
+The `entity_store` module provides persistent storage backends for `EntityContainer`,
+so large jobs can keep entities on disk instead of in memory, and reuse them after a restart.
+*/
+
+#![deny(missing_docs)]
+
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// A storage backend for Wikibase entities as JSON, keyed by entity ID.
+/// Methods may block; `EntityContainer` only calls them via `tokio::task::spawn_blocking`.
+pub trait EntityStore: Send + Sync + fmt::Debug {
+    /// Returns an entity, if stored
+    fn get(&self, id: &str) -> Result<Option<Value>, MediaWikiError>;
+
+    /// Stores an entity, replacing any existing one with the same ID
+    fn set(&self, id: &str, entity: &Value) -> Result<(), MediaWikiError>;
+
+    /// Removes an entity, if stored
+    fn remove(&self, id: &str) -> Result<(), MediaWikiError>;
+
+    /// Returns true if the entity is stored
+    fn contains(&self, id: &str) -> bool;
+
+    /// Returns the IDs of all stored entities
+    fn ids(&self) -> Result<Vec<String>, MediaWikiError>;
+
+    /// Removes all entities
+    fn clear(&self) -> Result<(), MediaWikiError> {
+        for id in self.ids()? {
+            self.remove(&id)?;
+        }
+        Ok(())
+    }
+}
+
+/// An `EntityStore` that keeps each entity as a JSON file in a directory.
+/// The files are spread over 256 subdirectories by a hash of the ID, so no single directory grows too large.
+/// Its methods do blocking file I/O; `EntityContainer` runs them via `tokio::task::spawn_blocking`.
+#[derive(Debug, Clone)]
+pub struct FileEntityStore {
+    dir: PathBuf,
+}
+
+impl FileEntityStore {
+    /// Uses `dir` for the entity files, creating it if necessary; existing files are reused
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self, MediaWikiError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Returns the directory of the entity files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the file for an entity ID; IDs with characters other than letters, digits, `-` and `_` are rejected
+    fn path(&self, id: &str) -> Result<PathBuf, MediaWikiError> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        match valid {
+            true => Ok(self.dir.join(Self::shard(id)).join(format!("{}.json", id))),
+            false => Err(MediaWikiError::String(format!("invalid entity ID: {}", id))),
+        }
+    }
+
+    /// Returns the subdirectory for an entity ID, the first two hex digits of its MD5 hash
+    fn shard(id: &str) -> String {
+        format!("{:02x}", md5::compute(id).0[0])
+    }
+}
+
+impl EntityStore for FileEntityStore {
+    fn get(&self, id: &str) -> Result<Option<Value>, MediaWikiError> {
+        match fs::read(self.path(id)?) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, id: &str, entity: &Value) -> Result<(), MediaWikiError> {
+        // Write to a temporary file first, so an interrupted job does not leave a truncated entity
+        let path = self.path(id)?;
+        if let Some(shard) = path.parent() {
+            fs::create_dir_all(shard)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(entity)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    fn remove(&self, id: &str) -> Result<(), MediaWikiError> {
+        match fs::remove_file(self.path(id)?) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.path(id).is_ok_and(|path| path.is_file())
+    }
+
+    fn ids(&self) -> Result<Vec<String>, MediaWikiError> {
+        let mut ids = vec![];
+        for shard in fs::read_dir(&self.dir)? {
+            let shard = shard?;
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(shard.path())? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    if let Some(id) = path.file_stem().and_then(|s| s.to_str()) {
+                        ids.push(id.to_string());
+                    }
+                }
+            }
+        }
+        ids.sort_unstable();
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_store() {
+        let dir =
+            std::env::temp_dir().join(format!("mediawiki-entity-store-{}", std::process::id()));
+        let store = FileEntityStore::new(&dir).unwrap();
+        store
+            .set("Q42", &json!({"id":"Q42","lastrevid":3}))
+            .unwrap();
+        store.set("P31", &json!({"id":"P31"})).unwrap();
+        assert!(store.contains("Q42"));
+        assert_eq!(store.get("Q42").unwrap().unwrap()["lastrevid"], 3);
+        assert_eq!(store.get("Q1").unwrap(), None);
+        assert_eq!(store.ids().unwrap(), vec!["P31", "Q42"]);
+        assert!(dir
+            .join(FileEntityStore::shard("Q42"))
+            .join("Q42.json")
+            .is_file());
+        assert!(store.set("../evil", &json!({})).is_err());
+        let reopened = FileEntityStore::new(&dir).unwrap();
+        assert!(reopened.contains("P31"));
+        reopened.remove("P31").unwrap();
+        reopened.remove("P31").unwrap();
+        reopened.clear().unwrap();
+        assert!(reopened.ids().unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,343 @@
+/*!
+The `events` module lets callers observe notable events during API requests, such as rate limiting,
+and keeps a bounded log of recent events for postmortems.
+*/
+
+#![deny(missing_docs)]
+
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Default number of events kept in the `EventLog`
+pub const DEFAULT_EVENT_LOG_CAPACITY: usize = 100;
+
+/// An event that occurred while performing an API request
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ApiEvent {
+    /// The API returned a `ratelimited` error; the request will be retried after `wait`
+    RateLimited {
+        /// The `action` parameter of the request
+        action: String,
+        /// The time waited before retrying
+        wait: Duration,
+        /// The number of retry attempts left after this one
+        attempts_left: u64,
+    },
+    /// The API returned a `maxlag` error; the request will be retried after `wait`
+    MaxLag {
+        /// The `action` parameter of the request
+        action: String,
+        /// The time waited before retrying
+        wait: Duration,
+        /// The number of retry attempts left after this one
+        attempts_left: u64,
+    },
+    /// The server returned HTTP 429; the request will be retried after `wait`
+    TooManyRequests {
+        /// The `action` parameter of the request
+        action: String,
+        /// The time waited before retrying, from the `Retry-After` header or a default
+        wait: Duration,
+    },
+    /// The API returned an error that was passed on to the caller
+    ApiError {
+        /// The `action` parameter of the request
+        action: String,
+        /// The API error code
+        code: String,
+    },
+    /// The session was lost; the `Api` logs in again with its stored credentials and retries
+    Relogin {
+        /// The `action` parameter of the request
+        action: String,
+        /// The API error code that caused the re-login
+        code: String,
+    },
+    /// An edit is paused because the site is read-only or lagged; health is checked again after `wait`
+    SiteUnhealthy {
+        /// The `action` parameter of the request
+        action: String,
+        /// The time waited before checking again
+        wait: Duration,
+    },
+    /// An edit was not sent because the `Api` is in dry-run mode; a successful response was simulated
+    DryRun {
+        /// The `action` parameter of the request
+        action: String,
+    },
+}
+
+impl ApiEvent {
+    /// Returns a short name for the kind of event
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::RateLimited { .. } => "ratelimited",
+            Self::MaxLag { .. } => "maxlag",
+            Self::TooManyRequests { .. } => "too_many_requests",
+            Self::ApiError { .. } => "api_error",
+            Self::SiteUnhealthy { .. } => "site_unhealthy",
+            Self::Relogin { .. } => "relogin",
+            Self::DryRun { .. } => "dry_run",
+        }
+    }
+
+    /// Returns the `action` parameter of the request
+    pub fn action(&self) -> &str {
+        match self {
+            Self::RateLimited { action, .. }
+            | Self::MaxLag { action, .. }
+            | Self::TooManyRequests { action, .. }
+            | Self::ApiError { action, .. }
+            | Self::SiteUnhealthy { action, .. }
+            | Self::Relogin { action, .. }
+            | Self::DryRun { action } => action,
+        }
+    }
+
+    /// Returns the time waited before retrying, if any
+    pub fn wait(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { wait, .. }
+            | Self::MaxLag { wait, .. }
+            | Self::TooManyRequests { wait, .. }
+            | Self::SiteUnhealthy { wait, .. } => Some(*wait),
+            Self::ApiError { .. } | Self::Relogin { .. } | Self::DryRun { .. } => None,
+        }
+    }
+
+    /// Returns the API error code, if any
+    pub fn error_code(&self) -> Option<&str> {
+        match self {
+            Self::RateLimited { .. } => Some("ratelimited"),
+            Self::MaxLag { .. } => Some("maxlag"),
+            Self::TooManyRequests { .. } | Self::SiteUnhealthy { .. } | Self::DryRun { .. } => None,
+            Self::ApiError { code, .. } | Self::Relogin { code, .. } => Some(code),
+        }
+    }
+
+    /// Returns the number of retry attempts left, for retried requests
+    pub fn attempts_left(&self) -> Option<u64> {
+        match self {
+            Self::RateLimited { attempts_left, .. } | Self::MaxLag { attempts_left, .. } => {
+                Some(*attempts_left)
+            }
+            _ => None,
+        }
+    }
+
+    /// Emits the event via `tracing`
+    #[cfg(feature = "tracing")]
+    pub(crate) fn trace(&self) {
+        tracing::warn!(
+            kind = self.kind(),
+            action = self.action(),
+            wait_ms = self.wait().map(|w| w.as_millis() as u64),
+            attempts_left = self.attempts_left(),
+            error_code = self.error_code(),
+            "API event"
+        );
+    }
+}
+
+/// An event in the `EventLog`, with the time it occurred
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecordedEvent {
+    timestamp: String,
+    kind: String,
+    action: String,
+    wait_ms: Option<u64>,
+    error_code: Option<String>,
+}
+
+impl RecordedEvent {
+    /// Records an event as occurring now
+    pub fn new(event: &ApiEvent) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            kind: event.kind().to_string(),
+            action: event.action().to_string(),
+            wait_ms: event.wait().map(|w| w.as_millis() as u64),
+            error_code: event.error_code().map(|s| s.to_string()),
+        }
+    }
+
+    /// Returns the time of the event, in RFC 3339 format
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    /// Returns the kind of event, see `ApiEvent::kind`
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns the `action` parameter of the request
+    pub fn action(&self) -> &str {
+        &self.action
+    }
+
+    /// Returns the time waited in milliseconds, if any
+    pub fn wait_ms(&self) -> Option<u64> {
+        self.wait_ms
+    }
+
+    /// Returns the API error code, if any
+    pub fn error_code(&self) -> Option<&str> {
+        self.error_code.as_deref()
+    }
+}
+
+/// A ring buffer of the most recent events
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    capacity: usize,
+    events: VecDeque<RecordedEvent>,
+}
+
+impl Default for EventLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_LOG_CAPACITY)
+    }
+}
+
+impl EventLog {
+    /// Creates a log that keeps the last `capacity` events
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Adds an event, dropping the oldest one if the log is full
+    pub fn push(&mut self, event: RecordedEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// Returns the events, oldest first
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        self.events.iter().cloned().collect()
+    }
+
+    /// Sets the capacity, dropping the oldest events if required
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.events.len() > capacity {
+            self.events.pop_front();
+        }
+    }
+
+    /// Removes all events
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+
+/// A callback that is called for each `ApiEvent`
+pub type EventObserver = Arc<dyn Fn(&ApiEvent) + Send + Sync>;
+
+/// A list of event observers
+#[derive(Clone, Default)]
+pub struct EventObservers {
+    observers: Vec<EventObserver>,
+}
+
+impl EventObservers {
+    /// Adds an observer
+    pub fn add(&mut self, observer: EventObserver) {
+        self.observers.push(observer);
+    }
+
+    /// Removes all observers
+    pub fn clear(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Returns the number of observers
+    pub fn len(&self) -> usize {
+        self.observers.len()
+    }
+
+    /// Returns true if there are no observers
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    /// Calls all observers with the event
+    pub fn emit(&self, event: &ApiEvent) {
+        for observer in &self.observers {
+            observer(event);
+        }
+    }
+}
+
+impl fmt::Debug for EventObservers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EventObservers({})", self.observers.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn emit() {
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen2 = seen.clone();
+        let mut observers = EventObservers::default();
+        observers.add(Arc::new(move |e: &ApiEvent| {
+            seen2.lock().unwrap().push(e.clone())
+        }));
+        let event = ApiEvent::RateLimited {
+            action: "edit".to_string(),
+            wait: Duration::from_secs(1),
+            attempts_left: 4,
+        };
+        assert_eq!(event.attempts_left(), Some(4));
+        observers.emit(&event);
+        assert_eq!(*seen.lock().unwrap(), vec![event]);
+        assert_eq!(format!("{:?}", observers), "EventObservers(1)");
+    }
+
+    #[test]
+    fn event_log() {
+        let mut log = EventLog::new(2);
+        for code in ["a", "b", "c"] {
+            log.push(RecordedEvent::new(&ApiEvent::ApiError {
+                action: "edit".to_string(),
+                code: code.to_string(),
+            }));
+        }
+        let events = log.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].error_code(), Some("b"));
+        assert_eq!(events[1].kind(), "api_error");
+        let json = serde_json::to_value(&events).unwrap();
+        assert_eq!(json[1]["error_code"], "c");
+        log.set_capacity(1);
+        assert_eq!(log.events().len(), 1);
+    }
+
+    #[test]
+    fn recorded_wait() {
+        let event = RecordedEvent::new(&ApiEvent::MaxLag {
+            action: "edit".to_string(),
+            wait: Duration::from_secs(5),
+            attempts_left: 1,
+        });
+        assert_eq!(event.wait_ms(), Some(5000));
+        assert_eq!(event.error_code(), Some("maxlag"));
+    }
+}
@@ -0,0 +1,144 @@
+/*!
+The `external_links` module finds pages using external links (`list=exturlusage`), e.g. for spam cleanup.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use futures::Stream;
+use serde_json::Value;
+
+/// A page using an external link, as returned by `list=exturlusage`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExtUrlUsage {
+    title: Title,
+    page_id: u64,
+    url: String,
+}
+
+impl ExtUrlUsage {
+    /// Creates a new entry from a `list=exturlusage` result entry
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            title: Title::new_from_api_result(j),
+            page_id: j["pageid"].as_u64()?,
+            url: j["url"].as_str()?.to_string(),
+        })
+    }
+
+    /// Returns the title of the page using the link
+    pub fn title(&self) -> &Title {
+        &self.title
+    }
+
+    /// Returns the page ID of the page using the link
+    pub fn page_id(&self) -> u64 {
+        self.page_id
+    }
+
+    /// Returns the external URL
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+/// Normalizes a URL pattern for `list=exturlusage`.
+/// Returns the protocol (if any) and the pattern without protocol.
+/// Wildcards are only allowed as leading `*.`, so `*example.org` becomes `*.example.org`,
+/// and trailing wildcards are removed, as the API matches prefixes anyway.
+///
+/// # Examples
+///
+/// ```
+/// use mediawiki::external_links::normalize_ext_url_pattern;
+/// assert_eq!(
+///     normalize_ext_url_pattern("https://*Example.ORG/spam*"),
+///     (Some("https".to_string()), "*.example.org/spam".to_string())
+/// );
+/// ```
+pub fn normalize_ext_url_pattern(pattern: &str) -> (Option<String>, String) {
+    let pattern = pattern.trim();
+    let (protocol, rest) = match pattern.split_once("://") {
+        Some((protocol, rest)) => (Some(protocol.to_lowercase()), rest),
+        None => match pattern.strip_prefix("//") {
+            Some(rest) => (None, rest),
+            None => (None, pattern),
+        },
+    };
+    let rest = rest.trim_end_matches('*');
+    let (host, path) = match rest.find('/') {
+        Some(pos) => rest.split_at(pos),
+        None => (rest, ""),
+    };
+    let host = host.to_lowercase();
+    let host = match host.strip_prefix('*') {
+        Some(h) => format!("*.{}", h.trim_start_matches('.')),
+        None => host,
+    };
+    (protocol, host + path)
+}
+
+impl Api {
+    /// Returns a stream of pages using external links matching `pattern`, via `list=exturlusage`.
+    /// The pattern is normalized with `normalize_ext_url_pattern`; an explicit `protocol` overrides
+    /// the one in the pattern. An empty `namespaces` slice means all namespaces.
+    pub async fn ext_url_usage<'a>(
+        &'a self,
+        pattern: &str,
+        protocol: Option<&str>,
+        namespaces: &[NamespaceID],
+    ) -> impl Stream<Item = Result<ExtUrlUsage, MediaWikiError>> + 'a {
+        let (pattern_protocol, pattern) = normalize_ext_url_pattern(pattern);
+        let mut params = self.params_into(&[
+            ("action", "query"),
+            ("list", "exturlusage"),
+            ("euquery", &pattern),
+            ("euprop", "ids|title|url"),
+            ("eulimit", "max"),
+        ]);
+        if let Some(protocol) = protocol.map(|s| s.to_string()).or(pattern_protocol) {
+            params.insert("euprotocol".to_string(), protocol);
+        }
+        if !namespaces.is_empty() {
+            let namespaces: Vec<String> = namespaces.iter().map(|ns| ns.to_string()).collect();
+            params.insert("eunamespace".to_string(), namespaces.join("|"));
+        }
+        self.get_query_api_list_iter(&params, "exturlusage", ExtUrlUsage::from_json)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_patterns() {
+        assert_eq!(
+            normalize_ext_url_pattern("example.org"),
+            (None, "example.org".to_string())
+        );
+        assert_eq!(
+            normalize_ext_url_pattern("//*.Example.org/path/X"),
+            (None, "*.example.org/path/X".to_string())
+        );
+        assert_eq!(
+            normalize_ext_url_pattern("HTTP://example.org*"),
+            (Some("http".to_string()), "example.org".to_string())
+        );
+    }
+
+    #[test]
+    fn ext_url_usage_from_json() {
+        let usage = ExtUrlUsage::from_json(
+            &json!({"pageid":5,"ns":2,"title":"User:Foo","url":"https://example.org/spam"}),
+        )
+        .unwrap();
+        assert_eq!(usage.title(), &Title::new("Foo", 2));
+        assert_eq!(usage.page_id(), 5);
+        assert_eq!(usage.url(), "https://example.org/spam");
+        assert!(ExtUrlUsage::from_json(&json!({"title":"Foo","ns":0})).is_none());
+    }
+}
@@ -0,0 +1,327 @@
+/*!
+The `generators` module understands the page generator arguments used by pywikibot and AWB,
+such as `-cat:Foo` or `-search:"insource:bar"`, and turns them into `Title` lists.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use futures::future::Either;
+use futures::{Stream, StreamExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// A single pywikibot-style page generator specification
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GeneratorSpec {
+    /// `-cat:Foo`: pages in a category
+    Category(String),
+    /// `-links:Foo`: pages linked from a page
+    Links(String),
+    /// `-ref:Foo`: pages linking to a page
+    Ref(String),
+    /// `-transcludes:Foo`: pages transcluding a template
+    Transcludes(String),
+    /// `-search:"foo"`: full-text search results
+    Search(String),
+    /// `-prefixindex:Foo`: pages whose title starts with a prefix (main namespace)
+    PrefixIndex(String),
+    /// `-page:Foo`: a single page
+    Page(String),
+    /// `-file:list.txt`: titles read from a local file, one per line or as `[[links]]`
+    File(String),
+}
+
+impl GeneratorSpec {
+    /// Parses all generator arguments from a list of command line arguments.
+    /// Arguments that are not generator specifications are ignored.
+    pub fn parse_args<S: AsRef<str>>(args: &[S]) -> Vec<Self> {
+        args.iter()
+            .filter_map(|arg| arg.as_ref().parse().ok())
+            .collect()
+    }
+
+    /// Returns the pywikibot option name, without leading dash
+    fn option_name(&self) -> &str {
+        match self {
+            Self::Category(_) => "cat",
+            Self::Links(_) => "links",
+            Self::Ref(_) => "ref",
+            Self::Transcludes(_) => "transcludes",
+            Self::Search(_) => "search",
+            Self::PrefixIndex(_) => "prefixindex",
+            Self::Page(_) => "page",
+            Self::File(_) => "file",
+        }
+    }
+
+    /// Returns the option value
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Category(s)
+            | Self::Links(s)
+            | Self::Ref(s)
+            | Self::Transcludes(s)
+            | Self::Search(s)
+            | Self::PrefixIndex(s)
+            | Self::Page(s)
+            | Self::File(s) => s,
+        }
+    }
+
+    /// Prefixes `value` with the canonical `namespace` name, unless it already is
+    fn with_namespace(namespace: &str, value: &str) -> String {
+        match value.split_once(':') {
+            Some((ns, _)) if Title::first_letter_uppercase(ns) == namespace => value.to_string(),
+            _ => format!("{}:{}", namespace, value),
+        }
+    }
+
+    /// Returns the API query parameters for this generator.
+    /// Returns `None` for generators that do not query the API (`-page`, `-file`).
+    pub fn params(&self) -> Option<HashMap<String, String>> {
+        let params: Vec<(&str, String)> = match self {
+            Self::Category(s) => vec![
+                ("list", "categorymembers".to_string()),
+                ("cmtitle", Self::with_namespace("Category", s)),
+                ("cmlimit", "max".to_string()),
+            ],
+            Self::Links(s) => vec![
+                ("prop", "links".to_string()),
+                ("titles", s.to_string()),
+                ("pllimit", "max".to_string()),
+            ],
+            Self::Ref(s) => vec![
+                ("list", "backlinks".to_string()),
+                ("bltitle", s.to_string()),
+                ("bllimit", "max".to_string()),
+            ],
+            Self::Transcludes(s) => vec![
+                ("list", "embeddedin".to_string()),
+                ("eititle", Self::with_namespace("Template", s)),
+                ("eilimit", "max".to_string()),
+            ],
+            Self::Search(s) => vec![
+                ("list", "search".to_string()),
+                ("srsearch", s.to_string()),
+                ("srlimit", "max".to_string()),
+                ("srprop", "".to_string()),
+            ],
+            Self::PrefixIndex(s) => vec![
+                ("list", "allpages".to_string()),
+                ("apprefix", s.to_string()),
+                ("aplimit", "max".to_string()),
+            ],
+            Self::Page(_) | Self::File(_) => return None,
+        };
+        let mut ret: HashMap<String, String> = params
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect();
+        ret.insert("action".to_string(), "query".to_string());
+        Some(ret)
+    }
+
+    /// Parses the contents of a `-file` list into titles.
+    /// Uses `[[links]]` if there are any, otherwise one title per non-empty line.
+    pub fn titles_from_list(text: &str, api: &Api) -> Vec<Title> {
+        let links: Vec<&str> = text
+            .split("[[")
+            .skip(1)
+            .filter_map(|part| part.split_once("]]"))
+            .map(|(link, _)| link.split('|').next().unwrap_or(link))
+            .collect();
+        let lines: Vec<&str> = if links.is_empty() {
+            text.lines().collect()
+        } else {
+            links
+        };
+        lines
+            .iter()
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .map(|s| Title::new_from_full(s, api))
+            .collect()
+    }
+
+    /// Returns the titles produced by this generator as a stream, loading more via continuation as needed
+    pub fn titles<'a>(
+        &'a self,
+        api: &'a Api,
+    ) -> impl Stream<Item = Result<Title, MediaWikiError>> + 'a {
+        futures::stream::once(async move {
+            match self.params() {
+                Some(params) => {
+                    Either::Left(api.get_query_api_json_limit_iter(&params, None).await.map(
+                        |result| result.map(|result| Api::result_array_to_titles(&result["query"])),
+                    ))
+                }
+                None => Either::Right(futures::stream::once(self.local_titles(api))),
+            }
+        })
+        .flatten()
+        .flat_map(|batch| {
+            let items: Vec<Result<Title, MediaWikiError>> = match batch {
+                Ok(titles) => titles.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures::stream::iter(items)
+        })
+    }
+
+    /// Returns the titles of the generators that do not query the API (`-page`, `-file`)
+    async fn local_titles(&self, api: &Api) -> Result<Vec<Title>, MediaWikiError> {
+        match self {
+            Self::Page(s) => Ok(vec![Title::new_from_full(s, api)]),
+            Self::File(path) => {
+                let text = tokio::fs::read_to_string(path)
+                    .await
+                    .map_err(|e| MediaWikiError::String(format!("{}: {}", path, e)))?;
+                Ok(Self::titles_from_list(&text, api))
+            }
+            _ => Err(MediaWikiError::String(format!(
+                "Generator {} queries the API",
+                self
+            ))),
+        }
+    }
+}
+
+impl FromStr for GeneratorSpec {
+    type Err = MediaWikiError;
+
+    /// Parses a single argument, such as `-cat:Foo` or `-search:"insource:bar"`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (option, value) = s
+            .strip_prefix('-')
+            .and_then(|s| s.split_once(':'))
+            .ok_or_else(|| MediaWikiError::String(format!("Not a generator argument: {}", s)))?;
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value)
+            .to_string();
+        if value.is_empty() {
+            return Err(MediaWikiError::String(format!(
+                "Generator argument without value: {}",
+                s
+            )));
+        }
+        match option {
+            "cat" => Ok(Self::Category(value)),
+            "links" => Ok(Self::Links(value)),
+            "ref" => Ok(Self::Ref(value)),
+            "transcludes" => Ok(Self::Transcludes(value)),
+            "search" => Ok(Self::Search(value)),
+            "prefixindex" => Ok(Self::PrefixIndex(value)),
+            "page" => Ok(Self::Page(value)),
+            "file" => Ok(Self::File(value)),
+            other => Err(MediaWikiError::String(format!(
+                "Unknown generator: -{}",
+                other
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for GeneratorSpec {
+    /// Emits the argument in pywikibot syntax; values with whitespace are quoted
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = self.value();
+        if value.contains(char::is_whitespace) {
+            write!(f, "-{}:\"{}\"", self.option_name(), value)
+        } else {
+            write!(f, "-{}:{}", self.option_name(), value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_simple() {
+        assert_eq!(
+            "-cat:Foo".parse::<GeneratorSpec>().unwrap(),
+            GeneratorSpec::Category("Foo".to_string())
+        );
+        assert_eq!(
+            "-search:\"insource:baz\"".parse::<GeneratorSpec>().unwrap(),
+            GeneratorSpec::Search("insource:baz".to_string())
+        );
+        assert!("-notagenerator:Foo".parse::<GeneratorSpec>().is_err());
+        assert!("-cat:".parse::<GeneratorSpec>().is_err());
+        assert!("Foo".parse::<GeneratorSpec>().is_err());
+    }
+
+    #[test]
+    fn parse_args() {
+        let args = ["-cat:Foo", "-always", "-links:Bar", "-file:list.txt"];
+        assert_eq!(
+            GeneratorSpec::parse_args(&args),
+            vec![
+                GeneratorSpec::Category("Foo".to_string()),
+                GeneratorSpec::Links("Bar".to_string()),
+                GeneratorSpec::File("list.txt".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for arg in ["-cat:Foo", "-search:\"insource:baz qux\"", "-ref:Main_Page"] {
+            let spec: GeneratorSpec = arg.parse().unwrap();
+            assert_eq!(spec.to_string(), arg);
+        }
+    }
+
+    #[test]
+    fn params() {
+        let params = GeneratorSpec::Category("Foo".to_string()).params().unwrap();
+        assert_eq!(params["cmtitle"], "Category:Foo");
+        let params = GeneratorSpec::Category("category:Foo".to_string())
+            .params()
+            .unwrap();
+        assert_eq!(params["cmtitle"], "category:Foo");
+        assert!(GeneratorSpec::Page("Foo".to_string()).params().is_none());
+    }
+
+    #[tokio::test]
+    async fn titles_stream() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"general":{},"namespaces":{"0":{"id":0,"name":"","canonical":""}},"namespacealiases":[],"libraries":[],"extensions":[],"statistics":{}}}),
+            )
+            .add(
+                &[("cmcontinue", "page|B")],
+                json!({"query":{"categorymembers":[{"ns":0,"title":"B"}]}}),
+            )
+            .add(
+                &[("list", "categorymembers")],
+                json!({"continue":{"cmcontinue":"page|B","continue":"-||"},"query":{"categorymembers":[{"ns":0,"title":"A"}]}}),
+            );
+        let api = Api::new_mocked(responses).unwrap();
+        let spec = GeneratorSpec::Category("Foo".to_string());
+        let titles: Vec<Title> = spec
+            .titles(&api)
+            .map(|title| title.unwrap())
+            .collect()
+            .await;
+        assert_eq!(titles, vec![Title::new("A", 0), Title::new("B", 0)]);
+
+        let spec = GeneratorSpec::Page("C".to_string());
+        let titles: Vec<_> = spec.titles(&api).collect().await;
+        assert_eq!(titles.len(), 1);
+        assert_eq!(titles[0].as_ref().unwrap(), &Title::new("C", 0));
+
+        let spec = GeneratorSpec::File("/nonexistent/list.txt".to_string());
+        let titles: Vec<_> = spec.titles(&api).collect().await;
+        assert!(titles[0].is_err());
+    }
+}
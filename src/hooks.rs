@@ -0,0 +1,113 @@
+/*!
+The `hooks` module lets users intercept API requests and responses, e.g. for logging, metrics,
+adding parameters to every request, or recording responses for tests.
+*/
+
+#![deny(missing_docs)]
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A callback that can inspect and rewrite the parameters of each API request
+pub type RequestHook = Arc<dyn Fn(&mut HashMap<String, String>) + Send + Sync>;
+
+/// A callback that is called with the parameters and the JSON result of each API request
+pub type ResponseHook = Arc<dyn Fn(&HashMap<String, String>, &Value) + Send + Sync>;
+
+/// Request and response hooks, called in the order they were added
+#[derive(Clone, Default)]
+pub struct Hooks {
+    request: Vec<RequestHook>,
+    response: Vec<ResponseHook>,
+}
+
+impl Hooks {
+    /// Adds a request hook
+    pub fn add_request_hook(&mut self, hook: RequestHook) {
+        self.request.push(hook);
+    }
+
+    /// Adds a response hook
+    pub fn add_response_hook(&mut self, hook: ResponseHook) {
+        self.response.push(hook);
+    }
+
+    /// Removes all hooks
+    pub fn clear(&mut self) {
+        self.request.clear();
+        self.response.clear();
+    }
+
+    /// Returns true if there are no hooks
+    pub fn is_empty(&self) -> bool {
+        self.request.is_empty() && self.response.is_empty()
+    }
+
+    /// Returns the parameters after all request hooks were applied, or `None` if there are no request hooks
+    pub fn apply_request(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Option<HashMap<String, String>> {
+        if self.request.is_empty() {
+            return None;
+        }
+        let mut params = params.clone();
+        for hook in &self.request {
+            hook(&mut params);
+        }
+        Some(params)
+    }
+
+    /// Calls all response hooks
+    pub fn apply_response(&self, params: &HashMap<String, String>, result: &Value) {
+        for hook in &self.response {
+            hook(params, result);
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Hooks(request: {}, response: {})",
+            self.request.len(),
+            self.response.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn apply() {
+        let mut hooks = Hooks::default();
+        let params: HashMap<String, String> = [("action".to_string(), "edit".to_string())].into();
+        assert_eq!(hooks.apply_request(&params), None);
+        hooks.add_request_hook(Arc::new(|params: &mut HashMap<String, String>| {
+            params.insert("bot".to_string(), "1".to_string());
+        }));
+        let seen = Arc::new(Mutex::new(vec![]));
+        let seen2 = seen.clone();
+        hooks.add_response_hook(Arc::new(move |params, result: &Value| {
+            seen2
+                .lock()
+                .unwrap()
+                .push((params.get("bot").cloned(), result.clone()))
+        }));
+        let params = hooks.apply_request(&params).unwrap();
+        assert_eq!(params.get("bot"), Some(&"1".to_string()));
+        hooks.apply_response(&params, &json!({"edit":{"result":"Success"}}));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(Some("1".to_string()), json!({"edit":{"result":"Success"}}))]
+        );
+        hooks.clear();
+        assert!(hooks.is_empty());
+    }
+}
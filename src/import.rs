@@ -0,0 +1,203 @@
+/*!
+The `import` module imports pages with their history via `action=import`, from an XML dump or from another wiki.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The source of an import
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportSource {
+    /// An XML dump, as produced by `Special:Export`; requires the `importupload` right
+    Xml(Vec<u8>),
+    /// A page on a wiki configured as import source (e.g. `en`), with the `import` right
+    Interwiki {
+        /// The interwiki prefix of the source wiki
+        source: String,
+        /// The full title of the page on the source wiki
+        page: String,
+    },
+}
+
+/// Options for `Api::import_pages`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportOptions {
+    summary: Option<String>,
+    interwiki_prefix: Option<String>,
+    namespace: Option<NamespaceID>,
+    root_page: Option<String>,
+    full_history: bool,
+    templates: bool,
+}
+
+impl ImportOptions {
+    /// Creates options for importing with the original titles
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the log summary
+    pub fn set_summary(&mut self, summary: Option<&str>) {
+        self.summary = summary.map(|s| s.to_string());
+    }
+
+    /// Sets the interwiki prefix for usernames in XML imports; required by most wikis for XML imports
+    pub fn set_interwiki_prefix(&mut self, interwiki_prefix: Option<&str>) {
+        self.interwiki_prefix = interwiki_prefix.map(|s| s.to_string());
+    }
+
+    /// Imports into this namespace instead of the original one
+    pub fn set_namespace(&mut self, namespace: Option<NamespaceID>) {
+        self.namespace = namespace;
+    }
+
+    /// Imports as subpages of this page
+    pub fn set_root_page(&mut self, root_page: Option<&str>) {
+        self.root_page = root_page.map(|s| s.to_string());
+    }
+
+    /// Imports the full history of interwiki imports, instead of the current revision only
+    pub fn set_full_history(&mut self, full_history: bool) {
+        self.full_history = full_history;
+    }
+
+    /// Also imports the templates used by interwiki imports
+    pub fn set_templates(&mut self, templates: bool) {
+        self.templates = templates;
+    }
+
+    /// Adds the options to `params`
+    fn add_params(&self, params: &mut HashMap<String, String>) {
+        let mut insert = |k: &str, v: String| {
+            params.insert(k.to_string(), v);
+        };
+        if let Some(summary) = &self.summary {
+            insert("summary", summary.to_string());
+        }
+        if let Some(interwiki_prefix) = &self.interwiki_prefix {
+            insert("interwikiprefix", interwiki_prefix.to_string());
+        }
+        if let Some(namespace) = self.namespace {
+            insert("namespace", namespace.to_string());
+        }
+        if let Some(root_page) = &self.root_page {
+            insert("rootpage", root_page.to_string());
+        }
+        if self.full_history {
+            insert("fullhistory", "1".to_string());
+        }
+        if self.templates {
+            insert("templates", "1".to_string());
+        }
+    }
+}
+
+/// A page imported by `Api::import_pages`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImportedPage {
+    title: Title,
+    revisions: u64,
+}
+
+impl ImportedPage {
+    /// Creates an imported page from an entry of the `import` result
+    pub fn from_json(j: &Value) -> Option<Self> {
+        j["title"].as_str()?;
+        Some(Self {
+            title: Title::new_from_api_result(j),
+            revisions: j["revisions"].as_u64().unwrap_or_default(),
+        })
+    }
+
+    /// Returns the title of the imported page
+    pub fn title(&self) -> &Title {
+        &self.title
+    }
+
+    /// Returns the number of imported revisions
+    pub fn revisions(&self) -> u64 {
+        self.revisions
+    }
+}
+
+impl Api {
+    /// Imports pages from an XML dump or another wiki, and returns the imported pages
+    pub async fn import_pages(
+        &self,
+        source: ImportSource,
+        options: &ImportOptions,
+    ) -> Result<Vec<ImportedPage>, MediaWikiError> {
+        let token = self.get_edit_token().await?;
+        let mut params = self.params_into(&[("action", "import"), ("token", &token)]);
+        options.add_params(&mut params);
+        let result = match source {
+            ImportSource::Xml(xml) => {
+                self.post_query_api_multipart(&params, "xml", "import.xml", xml)
+                    .await?
+            }
+            ImportSource::Interwiki { source, page } => {
+                params.insert("interwikisource".to_string(), source);
+                params.insert("interwikipage".to_string(), page);
+                self.post_query_api_json(&params).await?
+            }
+        };
+        match result["import"].as_array() {
+            Some(pages) => Ok(pages.iter().filter_map(ImportedPage::from_json).collect()),
+            None => Err(MediaWikiError::from_api_result(&result)
+                .unwrap_or(MediaWikiError::EditError(result))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn import_pages() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "import"), ("interwikisource", "en")],
+                json!({"import":[{"ns":10,"title":"Template:Foo","revisions":3}]}),
+            )
+            .add(
+                &[("action", "import")],
+                json!({"error":{"code":"nointerwikiprefix","info":"No interwiki prefix was specified."}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut options = ImportOptions::new();
+        options.set_full_history(true);
+        options.set_namespace(Some(2));
+        let pages = api
+            .import_pages(
+                ImportSource::Interwiki {
+                    source: "en".to_string(),
+                    page: "Template:Foo".to_string(),
+                },
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(pages[0].title(), &Title::new("Foo", 10));
+        assert_eq!(pages[0].revisions(), 3);
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["fullhistory"], "1");
+        assert_eq!(request["namespace"], "2");
+        assert!(matches!(
+            api.import_pages(ImportSource::Xml(b"<mediawiki/>".to_vec()), &options)
+                .await,
+            Err(MediaWikiError::Api { code, .. }) if code == "nointerwikiprefix"
+        ));
+    }
+}
@@ -0,0 +1,126 @@
+/*!
+The `LangLink` class represents a link from a page to the same topic on a wiki in another language,
+as returned by `prop=langlinks`.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::Page;
+use serde_json::Value;
+
+/// A language link of a page
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LangLink {
+    lang: String,
+    title: String,
+    url: Option<String>,
+}
+
+impl LangLink {
+    /// Creates a language link from an entry of `prop=langlinks`
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            lang: j["lang"].as_str()?.to_string(),
+            title: j["*"].as_str().or_else(|| j["title"].as_str())?.to_string(),
+            url: j["url"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    /// Returns the language code, which is also the interwiki prefix (e.g. `fr`)
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    /// Returns the full title on the other wiki
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Returns the URL of the page on the other wiki
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+}
+
+impl Page {
+    /// Returns the language links of this page, including their URLs
+    pub async fn langlinks(&self, api: &Api) -> Result<Vec<LangLink>, MediaWikiError> {
+        let result = self
+            .action_query(
+                api,
+                &[("prop", "langlinks"), ("lllimit", "max"), ("llprop", "url")],
+            )
+            .await?;
+        Ok(self
+            .extract_page_properties_from_api_results(result, "langlinks")?
+            .iter()
+            .filter_map(LangLink::from_json)
+            .collect())
+    }
+
+    /// Returns the ID of the Wikidata item connected to this page, via the `wikibase_item` page property.
+    /// Returns `None` if the page is not connected to an item.
+    pub async fn wikidata_item(&self, api: &Api) -> Result<Option<String>, MediaWikiError> {
+        let result = self
+            .action_query(api, &[("prop", "pageprops"), ("ppprop", "wikibase_item")])
+            .await?;
+        self.wikidata_item_from_api_result(&result)
+    }
+
+    fn wikidata_item_from_api_result(
+        &self,
+        result: &Value,
+    ) -> Result<Option<String>, MediaWikiError> {
+        let page = result["query"]["pages"]
+            .as_object()
+            .and_then(|pages| pages.values().next())
+            .filter(|page| page["missing"].is_null())
+            .ok_or_else(|| MediaWikiError::Missing(self.title().clone()))?;
+        Ok(page["pageprops"]["wikibase_item"]
+            .as_str()
+            .map(|s| s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+    use crate::title::Title;
+
+    #[tokio::test]
+    async fn langlinks_and_item() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"namespaces":{"0":{"id":0,"case":"first-letter","*":""}}}}),
+            )
+            .add(
+                &[("prop", "langlinks")],
+                json!({"query":{"pages":{"22989":{"pageid":22989,"ns":0,"title":"Paris","langlinks":[
+                    {"lang":"de","url":"https://de.wikipedia.org/wiki/Paris","*":"Paris"},
+                    {"lang":"fr","url":"https://fr.wikipedia.org/wiki/Paris","title":"Paris"}]}}}}),
+            )
+            .add(
+                &[("prop", "pageprops")],
+                json!({"query":{"pages":{"22989":{"pageid":22989,"ns":0,"title":"Paris","pageprops":{"wikibase_item":"Q90"}}}}}),
+            );
+        let api = Api::new_mocked(responses).unwrap();
+        let page = Page::new(Title::new("Paris", 0));
+        let langlinks = page.langlinks(&api).await.unwrap();
+        assert_eq!(langlinks.len(), 2);
+        assert_eq!(langlinks[1].lang(), "fr");
+        assert_eq!(langlinks[1].title(), "Paris");
+        assert_eq!(
+            langlinks[0].url(),
+            Some("https://de.wikipedia.org/wiki/Paris")
+        );
+        assert_eq!(
+            page.wikidata_item(&api).await.unwrap(),
+            Some("Q90".to_string())
+        );
+    }
+}
@@ -0,0 +1,340 @@
+/*!
+The `language` module provides language metadata (`meta=languageinfo`) and resolves MediaWiki
+language fallback chains, e.g. `nb` → `no` → `en`.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// The language every fallback chain ends with
+const FINAL_FALLBACK: &str = "en";
+
+/// Returns the first value in `map` for a language in `chain`, in chain order.
+///
+/// # Examples
+///
+/// ```
+/// use mediawiki::language::pick_localized;
+/// let labels = mediawiki::hashmap!["no".to_string() => "Norsk", "en".to_string() => "English"];
+/// assert_eq!(pick_localized(&labels, &["nb", "no", "en"]), Some(&"Norsk"));
+/// assert_eq!(pick_localized(&labels, &["de"]), None);
+/// ```
+pub fn pick_localized<'a, V, S: AsRef<str>>(
+    map: &'a HashMap<String, V>,
+    chain: &[S],
+) -> Option<&'a V> {
+    chain.iter().find_map(|lang| map.get(lang.as_ref()))
+}
+
+/// A property to load via `meta=languageinfo`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum LanguageProp {
+    /// The language code
+    Code,
+    /// The BCP-47 language code
+    Bcp47,
+    /// The writing direction, `ltr` or `rtl`
+    Dir,
+    /// The name of the language in itself
+    Autonym,
+    /// The name of the language in the `uselang` language, see `Api::set_default_language`
+    Name,
+    /// The fallback languages
+    Fallbacks,
+    /// The language variants
+    Variants,
+}
+
+impl LanguageProp {
+    /// Returns the value as used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Code => "code",
+            Self::Bcp47 => "bcp47",
+            Self::Dir => "dir",
+            Self::Autonym => "autonym",
+            Self::Name => "name",
+            Self::Fallbacks => "fallbacks",
+            Self::Variants => "variants",
+        }
+    }
+}
+
+impl fmt::Display for LanguageProp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Metadata of a language, as returned by `meta=languageinfo`; properties not requested are empty
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguageInfo {
+    code: String,
+    bcp47: Option<String>,
+    dir: Option<String>,
+    autonym: Option<String>,
+    name: Option<String>,
+    fallbacks: Vec<String>,
+    variants: Vec<String>,
+}
+
+impl LanguageInfo {
+    /// Creates the language info for `code` from its `meta=languageinfo` entry
+    pub fn from_json(code: &str, j: &Value) -> Self {
+        Self {
+            code: code.to_string(),
+            bcp47: j["bcp47"].as_str().map(|s| s.to_string()),
+            dir: j["dir"].as_str().map(|s| s.to_string()),
+            autonym: j["autonym"].as_str().map(|s| s.to_string()),
+            name: j["name"].as_str().map(|s| s.to_string()),
+            fallbacks: string_array(&j["fallbacks"]),
+            variants: string_array(&j["variants"]),
+        }
+    }
+
+    /// Returns the language code
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Returns the BCP-47 language code
+    pub fn bcp47(&self) -> Option<&str> {
+        self.bcp47.as_deref()
+    }
+
+    /// Returns the writing direction, `ltr` or `rtl`
+    pub fn dir(&self) -> Option<&str> {
+        self.dir.as_deref()
+    }
+
+    /// Returns the name of the language in itself
+    pub fn autonym(&self) -> Option<&str> {
+        self.autonym.as_deref()
+    }
+
+    /// Returns the name of the language in the requested language
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the fallback languages, most preferred first
+    pub fn fallbacks(&self) -> &[String] {
+        &self.fallbacks
+    }
+
+    /// Returns the language variants, including the language itself for languages with variants
+    pub fn variants(&self) -> &[String] {
+        &self.variants
+    }
+}
+
+/// Language info by language code
+pub type Languages = HashMap<String, LanguageInfo>;
+
+/// Cache for `Api::languages`, keyed by the requested properties and `uselang`
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LanguageCache {
+    cache: Arc<RwLock<HashMap<String, Arc<Languages>>>>,
+}
+
+impl LanguageCache {
+    fn get(&self, key: &str) -> Option<Arc<Languages>> {
+        self.cache.read().ok()?.get(key).cloned()
+    }
+
+    fn insert(&self, key: String, languages: Arc<Languages>) {
+        if let Ok(mut cache) = self.cache.write() {
+            cache.insert(key, languages);
+        }
+    }
+}
+
+/// Returns the strings in a JSON array
+fn string_array(v: &Value) -> Vec<String> {
+    v.as_array()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Parses a `meta=languageinfo` result
+fn languages_from_json(j: &Value) -> Languages {
+    j["query"]["languageinfo"]
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .map(|(code, info)| (code.to_string(), LanguageInfo::from_json(code, info)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Language fallback chains, as returned by `meta=languageinfo`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LanguageFallbacks {
+    fallbacks: HashMap<String, Vec<String>>,
+}
+
+impl LanguageFallbacks {
+    /// Creates the fallback chains from a `meta=languageinfo` result with `liprop=fallbacks`
+    pub fn from_json(j: &Value) -> Self {
+        Self::from_languages(&languages_from_json(j))
+    }
+
+    /// Creates the fallback chains from language info loaded with `LanguageProp::Fallbacks`
+    pub fn from_languages(languages: &Languages) -> Self {
+        let fallbacks = languages
+            .iter()
+            .map(|(code, info)| (code.to_string(), info.fallbacks.clone()))
+            .collect();
+        Self { fallbacks }
+    }
+
+    /// Returns the full chain for `lang`: the language itself, its fallbacks, and finally `en`
+    pub fn chain(&self, lang: &str) -> Vec<String> {
+        let mut ret = vec![lang.to_string()];
+        if let Some(fallbacks) = self.fallbacks.get(lang) {
+            for fallback in fallbacks {
+                if !ret.contains(fallback) {
+                    ret.push(fallback.to_string());
+                }
+            }
+        }
+        if !ret.iter().any(|l| l == FINAL_FALLBACK) {
+            ret.push(FINAL_FALLBACK.to_string());
+        }
+        ret
+    }
+
+    /// Returns the value in `map` for `lang`, applying the fallback chain
+    pub fn pick_localized<'a, V>(&self, map: &'a HashMap<String, V>, lang: &str) -> Option<&'a V> {
+        pick_localized(map, &self.chain(lang))
+    }
+}
+
+impl Api {
+    /// Returns the `props` of all languages known to the wiki, via `meta=languageinfo`.
+    /// Names are in the language set by `Api::set_default_language`, or the user language.
+    /// Results are cached on this `Api` (and its clones).
+    pub async fn languages(
+        &self,
+        props: &[LanguageProp],
+    ) -> Result<Arc<Languages>, MediaWikiError> {
+        let mut props = props.to_vec();
+        props.sort();
+        props.dedup();
+        let liprop: Vec<&str> = props.iter().map(|p| p.as_str()).collect();
+        let liprop = liprop.join("|");
+        let key = format!("{}#{}", liprop, self.default_language().unwrap_or_default());
+        if let Some(languages) = self.language_cache().get(&key) {
+            return Ok(languages);
+        }
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("meta", "languageinfo"),
+            ("liprop", &liprop),
+            ("licode", "*"),
+        ]);
+        let result = self.get_query_api_json_all(&params).await?;
+        let languages = Arc::new(languages_from_json(&result));
+        self.language_cache().insert(key, languages.clone());
+        Ok(languages)
+    }
+
+    /// Loads the fallback chains for `languages` via `meta=languageinfo`;
+    /// an empty slice loads all languages, using the cache of `Api::languages`
+    pub async fn load_language_fallbacks(
+        &self,
+        languages: &[&str],
+    ) -> Result<LanguageFallbacks, MediaWikiError> {
+        if languages.is_empty() {
+            let languages = self.languages(&[LanguageProp::Fallbacks]).await?;
+            return Ok(LanguageFallbacks::from_languages(&languages));
+        }
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("meta", "languageinfo"),
+            ("liprop", "fallbacks"),
+            ("licode", &languages.join("|")),
+        ]);
+        let result = self.get_query_api_json_all(&params).await?;
+        Ok(LanguageFallbacks::from_json(&result))
+    }
+
+    /// Returns the fallback chain for `lang`, starting with `lang` itself and ending with `en`
+    pub async fn language_fallbacks(&self, lang: &str) -> Result<Vec<String>, MediaWikiError> {
+        Ok(self.load_language_fallbacks(&[lang]).await?.chain(lang))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fallbacks() -> LanguageFallbacks {
+        LanguageFallbacks::from_json(&json!({"query":{"languageinfo":{
+            "nb":{"fallbacks":["no","nn","en"]},
+            "de-ch":{"fallbacks":["de"]},
+            "en":{"fallbacks":[]}
+        }}}))
+    }
+
+    #[test]
+    fn language_info() {
+        let languages = languages_from_json(&json!({"query":{"languageinfo":{
+            "zh":{"autonym":"中文","name":"Chinese","variants":["zh","zh-hans","zh-hant"],"fallbacks":["zh-hans"]},
+            "de":{"autonym":"Deutsch","name":"German","variants":["de"],"fallbacks":[]}
+        }}}));
+        let zh = &languages["zh"];
+        assert_eq!(zh.code(), "zh");
+        assert_eq!(zh.autonym(), Some("中文"));
+        assert_eq!(zh.name(), Some("Chinese"));
+        assert_eq!(zh.variants().len(), 3);
+        assert_eq!(zh.dir(), None);
+        assert_eq!(
+            LanguageFallbacks::from_languages(&languages).chain("zh"),
+            vec!["zh", "zh-hans", "en"]
+        );
+    }
+
+    #[test]
+    fn language_cache() {
+        let cache = LanguageCache::default();
+        assert!(cache.get("fallbacks#").is_none());
+        cache
+            .clone()
+            .insert("fallbacks#".to_string(), Arc::new(Languages::new()));
+        assert!(cache.get("fallbacks#").is_some());
+    }
+
+    #[test]
+    fn chain() {
+        let fallbacks = fallbacks();
+        assert_eq!(fallbacks.chain("nb"), vec!["nb", "no", "nn", "en"]);
+        assert_eq!(fallbacks.chain("de-ch"), vec!["de-ch", "de", "en"]);
+        assert_eq!(fallbacks.chain("en"), vec!["en"]);
+        assert_eq!(fallbacks.chain("xyz"), vec!["xyz", "en"]);
+    }
+
+    #[test]
+    fn pick() {
+        let fallbacks = fallbacks();
+        let map = hashmap!["no".to_string() => 1, "en".to_string() => 2];
+        assert_eq!(fallbacks.pick_localized(&map, "nb"), Some(&1));
+        assert_eq!(fallbacks.pick_localized(&map, "de-ch"), Some(&2));
+        assert_eq!(
+            fallbacks.pick_localized(&HashMap::<String, u8>::new(), "nb"),
+            None
+        );
+    }
+}
@@ -31,17 +31,89 @@ macro_rules! hashmap {
 
 pub use reqwest;
 
+pub mod abuse_filter;
+pub mod admin_options;
 pub mod api;
+pub mod api_builder;
+#[cfg(feature = "sync")]
 pub mod api_sync;
+pub mod backlinks;
+pub mod blocking;
+pub mod cancellation;
+pub mod captcha;
+pub mod category;
+pub mod change_tags;
+pub mod collation;
+pub mod config;
+pub mod conflict;
+pub mod continuation;
+pub mod cookie_jar;
+pub mod deleted_revisions;
+#[cfg(feature = "dump")]
+pub mod dump;
+pub mod duplicate_files;
+pub mod edit_guard;
+pub mod edit_summary;
+#[cfg(feature = "wikibase")]
+pub mod entity_container;
+#[cfg(feature = "wikibase")]
+pub mod entity_store;
+pub mod events;
+pub mod external_links;
+pub mod generators;
+pub mod hooks;
+pub mod import;
+pub mod langlinks;
+pub mod language;
+pub mod login;
 pub mod media_wiki_error;
+pub mod metrics;
+pub mod mock;
+pub mod multi_value;
+pub mod namespace_map;
+pub mod oauth2;
 pub mod page;
+pub mod page_info;
+pub mod page_props;
+pub mod page_texts;
+pub mod parallel_query;
+pub mod profiles;
+pub mod protection;
+pub mod query;
+pub mod query_dump;
+pub mod rate_limit;
+pub mod recent_changes;
+pub mod relogin;
+pub mod report;
+pub mod rest;
+pub mod review;
 pub mod revision;
+pub mod search;
+pub mod section;
+pub mod session;
+pub mod site_health;
+pub mod site_info;
+#[cfg(feature = "wikibase")]
+pub mod sparql;
 pub mod title;
+pub mod typed;
+pub mod upload;
+pub mod usage;
 pub mod user;
+pub mod user_block;
+pub mod user_contribs;
+pub mod watchlist;
+#[cfg(feature = "wikibase")]
+pub mod wikibase;
+#[cfg(feature = "wikibase")]
+pub mod wikibase_edit;
 
 pub use crate::api::Api;
+#[cfg(feature = "sync")]
 pub use crate::api_sync::ApiSync;
+pub use crate::conflict::Conflict;
 pub use crate::media_wiki_error::MediaWikiError;
+pub use crate::media_wiki_error::MediaWikiError as Error;
 pub use crate::page::Page;
 pub use crate::revision::Revision;
 pub use crate::title::Title;
@@ -0,0 +1,159 @@
+/*!
+The `LoginError` class describes why an `action=login` request failed.
+*/
+
+#![deny(missing_docs)]
+
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+/// The `result` of a failed `action=login` request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginResult {
+    /// Wrong password
+    WrongPass,
+    /// Wrong password, as reported by an authentication plugin
+    WrongPluginPass,
+    /// The user does not exist
+    NotExists,
+    /// Login failed; for bot passwords, this includes a wrong name or password
+    Failed,
+    /// Login was aborted, e.g. because the main account password was used instead of a bot password
+    Aborted,
+    /// A login token is required, or the session was lost
+    NeedToken,
+    /// The login token was invalid
+    WrongToken,
+    /// Too many recent login attempts
+    Throttled,
+    /// Any other result
+    Other(String),
+}
+
+impl LoginResult {
+    /// Parses the `result` field of a login response
+    pub fn from_result(result: &str) -> Self {
+        match result {
+            "WrongPass" => Self::WrongPass,
+            "WrongPluginPass" => Self::WrongPluginPass,
+            "NotExists" => Self::NotExists,
+            "Failed" => Self::Failed,
+            "Aborted" => Self::Aborted,
+            "NeedToken" => Self::NeedToken,
+            "WrongToken" => Self::WrongToken,
+            "Throttled" => Self::Throttled,
+            other => Self::Other(other.to_string()),
+        }
+    }
+
+    /// Returns the result as used by the API
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::WrongPass => "WrongPass",
+            Self::WrongPluginPass => "WrongPluginPass",
+            Self::NotExists => "NotExists",
+            Self::Failed => "Failed",
+            Self::Aborted => "Aborted",
+            Self::NeedToken => "NeedToken",
+            Self::WrongToken => "WrongToken",
+            Self::Throttled => "Throttled",
+            Self::Other(s) => s,
+        }
+    }
+}
+
+/// A failed login, with the reason given by the API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginError {
+    result: LoginResult,
+    reason: Option<String>,
+    wait: Option<Duration>,
+}
+
+impl LoginError {
+    /// Creates an error from the `login` object of an `action=login` response
+    pub fn from_json(login: &Value) -> Self {
+        let reason = match &login["reason"] {
+            Value::String(s) => Some(s.to_string()),
+            // errorformat=plaintext etc.
+            Value::Object(o) => o
+                .get("text")
+                .or_else(|| o.get("*"))
+                .or_else(|| o.get("code"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string()),
+            _ => None,
+        };
+        Self {
+            result: LoginResult::from_result(login["result"].as_str().unwrap_or("")),
+            reason,
+            wait: login["wait"].as_u64().map(Duration::from_secs),
+        }
+    }
+
+    /// Returns the login result
+    pub fn result(&self) -> &LoginResult {
+        &self.result
+    }
+
+    /// Returns the reason given by the API, if any
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    /// Returns the time to wait before trying again, for `Throttled`
+    pub fn wait(&self) -> Option<Duration> {
+        self.wait
+    }
+
+    /// Returns true if the login was throttled, either as `Throttled` or with a throttling reason
+    pub fn is_throttled(&self) -> bool {
+        self.result == LoginResult::Throttled
+            || self
+                .reason
+                .as_deref()
+                .is_some_and(|r| r.contains("too many recent login attempts"))
+    }
+}
+
+impl fmt::Display for LoginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "login failed: {}", self.result.as_str())?;
+        if let Some(reason) = &self.reason {
+            write!(f, " ({})", reason)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_json() {
+        let error = LoginError::from_json(
+            &json!({"result":"Failed","reason":"Incorrect username or password entered.\nPlease try again."}),
+        );
+        assert_eq!(error.result(), &LoginResult::Failed);
+        assert_eq!(
+            error.to_string(),
+            "login failed: Failed (Incorrect username or password entered.\nPlease try again.)"
+        );
+        let error = LoginError::from_json(
+            &json!({"result":"Aborted","reason":{"code":"botpasswords-needed"}}),
+        );
+        assert_eq!(error.reason(), Some("botpasswords-needed"));
+        let error = LoginError::from_json(
+            &json!({"result":"Failed","reason":"You have made too many recent login attempts. Please wait 5 minutes before trying again."}),
+        );
+        assert!(error.is_throttled());
+        let error = LoginError::from_json(&json!({"result":"Throttled","wait":300}));
+        assert_eq!(error.wait(), Some(Duration::from_secs(300)));
+        assert_eq!(
+            LoginError::from_json(&json!({"result":"Banana"})).result(),
+            &LoginResult::Other("Banana".to_string())
+        );
+    }
+}
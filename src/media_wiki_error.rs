@@ -1,84 +1,120 @@
-use std::error::Error;
 use std::fmt;
 
 use serde_json::Value;
 
+use crate::captcha::Captcha;
+use crate::conflict::Conflict;
+use crate::login::LoginError;
 use crate::title::Title;
 
-#[derive(Debug)]
+/// The error type for all operations of this crate, also available as `mediawiki::Error`
+#[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 pub enum MediaWikiError {
-    Serde(serde_json::Error),
-    Reqwest(reqwest::Error),
-    ReqwestHeader(reqwest::header::InvalidHeaderValue),
+    #[error(transparent)]
+    Serde(#[from] serde_json::Error),
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+    #[error(transparent)]
+    ReqwestHeader(#[from] reqwest::header::InvalidHeaderValue),
+    #[error("{0}")]
     String(String),
-    Url(url::ParseError),
-    Fmt(std::fmt::Error),
-    Time(std::time::SystemTimeError),
-
-    /// Error while logging in.
-    Login(String),
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+    #[error(transparent)]
+    Fmt(#[from] fmt::Error),
+    #[error(transparent)]
+    Time(#[from] std::time::SystemTimeError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// Login failed, with the result and reason from the API.
+    #[error("{0}")]
+    Login(LoginError),
+
+    /// The API returned an error; only used if `Api::set_raise_api_errors` is enabled.
+    #[error("API error {code}: {info}")]
+    Api {
+        /// The error code, e.g. `badtoken`
+        code: String,
+        /// The human-readable error message
+        info: String,
+        /// The complete `error` object of the response
+        details: Value,
+    },
+
+    /// An `assert=user` or `assert=bot` check failed, usually because the session expired; the error code is provided.
+    #[error("session expired: {0}")]
+    SessionExpired(String),
+
+    /// A mocked `Api` has no response for a request; the request parameters are provided.
+    #[error("no mock response for {0:?}")]
+    NoMockResponse(std::collections::HashMap<String, String>),
+
+    /// Edit attempted after logging out.
+    #[error("not logged in")]
+    NotLoggedIn,
 
     // These are errors for the Page struct
     /// Couldn't obtain the title for this page for use in an API request.
+    #[error("invalid title for this Page: {0:?}")]
     BadTitle(Title),
 
     /// Couldn't understand the API response (provided).
+    #[error("bad API response while fetching revision content: {0:?}")]
     BadResponse(Value),
 
     /// Missing page.
+    #[error("page missing: {0:?}")]
     Missing(Title),
 
+    /// Missing or deleted revision; the revision ID is provided.
+    #[error("revision missing: {0}")]
+    MissingRevision(u64),
+
     /// Edit failed; API response is provided.
+    #[error("edit resulted in error: {0:?}")]
     EditError(Value),
 
-    /// Unexpected data structure (eg array instead of object) in API JSON result
-    UnexpectedResultFormat(String),
-}
+    /// Upload failed; API response is provided.
+    #[error("upload resulted in error: {0:?}")]
+    UploadError(Value),
 
-impl Error for MediaWikiError {}
-
-impl fmt::Display for MediaWikiError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::Serde(e) => f.write_str(&e.to_string()),
-            Self::Reqwest(e) => f.write_str(&e.to_string()),
-            Self::ReqwestHeader(e) => f.write_str(&e.to_string()),
-            Self::String(s) => f.write_str(s),
-            Self::Url(e) => f.write_str(&e.to_string()),
-            Self::Fmt(e) => f.write_str(&e.to_string()),
-            Self::Time(e) => f.write_str(&e.to_string()),
-            Self::Login(s) => f.write_str(s),
-
-            Self::BadTitle(title) => write!(f, "invalid title for this Page: {:?}", title),
-            Self::BadResponse(response) => write!(
-                f,
-                "bad API response while fetching revision content: {:?}",
-                response
-            ),
-            Self::Missing(title) => write!(f, "page missing: {:?}", title),
-            Self::EditError(response) => write!(f, "edit resulted in error: {:?}", response),
-            Self::UnexpectedResultFormat(error) => write!(f, "result format error: {}", error),
-        }
-    }
-}
+    /// Upload failed because a file with this name exists, locally or in a shared repository; the filename is provided.
+    #[error("file exists: {0}")]
+    FileExists(String),
 
-impl From<serde_json::Error> for MediaWikiError {
-    fn from(e: serde_json::Error) -> Self {
-        Self::Serde(e)
-    }
-}
+    /// An identical edit to this target was submitted recently.
+    #[error("duplicate edit refused: {0}")]
+    DuplicateEdit(String),
 
-impl From<reqwest::Error> for MediaWikiError {
-    fn from(e: reqwest::Error) -> Self {
-        Self::Reqwest(e)
-    }
-}
+    /// Edit requires solving a captcha.
+    #[error("captcha required: {}", .0.id())]
+    Captcha(Box<Captcha>),
 
-impl From<reqwest::header::InvalidHeaderValue> for MediaWikiError {
-    fn from(e: reqwest::header::InvalidHeaderValue) -> Self {
-        Self::ReqwestHeader(e)
-    }
+    /// Edit failed because of an edit conflict.
+    #[error("edit conflict: base revision {:?}", .0.base_rev())]
+    EditConflict(Box<Conflict>),
+
+    /// Block failed because the user is already blocked; the user is provided.
+    #[error("user already blocked: {0}")]
+    AlreadyBlocked(String),
+
+    /// The current user lacks the right for an action; the API message is provided.
+    #[error("permission denied: {0}")]
+    PermissionDenied(String),
+
+    /// The change cannot be patrolled, e.g. because it is too old; the API message is provided.
+    #[error("change not patrollable: {0}")]
+    NotPatrollable(String),
+
+    /// The request was cancelled via a `CancellationToken`, or its deadline passed.
+    #[error("request cancelled")]
+    Cancelled,
+
+    /// Unexpected data structure (eg array instead of object) in API JSON result
+    #[error("result format error: {0}")]
+    UnexpectedResultFormat(String),
 }
 
 impl From<reqwest::header::ToStrError> for MediaWikiError {
@@ -99,20 +135,48 @@ impl From<&str> for MediaWikiError {
     }
 }
 
-impl From<url::ParseError> for MediaWikiError {
-    fn from(e: url::ParseError) -> Self {
-        Self::Url(e)
+impl MediaWikiError {
+    /// Creates a `MediaWikiError::Api` from an API result, if it contains an `error` member
+    pub fn from_api_result(result: &Value) -> Option<Self> {
+        let error = result.get("error")?;
+        Some(Self::Api {
+            code: error["code"].as_str().unwrap_or_default().to_string(),
+            info: error["info"]
+                .as_str()
+                .or_else(|| error["text"].as_str()) // errorformat=plaintext
+                .unwrap_or_default()
+                .to_string(),
+            details: error.to_owned(),
+        })
     }
 }
 
-impl From<std::fmt::Error> for MediaWikiError {
-    fn from(e: std::fmt::Error) -> Self {
-        Self::Fmt(e)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<MediaWikiError>();
+    }
+
+    #[test]
+    fn from_api_result() {
+        let error = MediaWikiError::from_api_result(
+            &json!({"error":{"code":"badtoken","info":"Invalid CSRF token."}}),
+        )
+        .unwrap();
+        assert_eq!(error.to_string(), "API error badtoken: Invalid CSRF token.");
+        assert!(MediaWikiError::from_api_result(&json!({"query":{}})).is_none());
     }
-}
 
-impl From<std::time::SystemTimeError> for MediaWikiError {
-    fn from(e: std::time::SystemTimeError) -> Self {
-        Self::Time(e)
+    #[test]
+    fn error_display() {
+        assert_eq!(MediaWikiError::NotLoggedIn.to_string(), "not logged in");
+        assert_eq!(
+            MediaWikiError::from("something failed").to_string(),
+            "something failed"
+        );
     }
 }
@@ -0,0 +1,192 @@
+/*!
+The `metrics` module lets callers collect metrics about API requests, retries and edits,
+e.g. to export them to Prometheus, by adding a `MetricsSink` to an `Api`.
+*/
+
+#![deny(missing_docs)]
+
+use crate::events::ApiEvent;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Receives metrics from an `Api`; all methods do nothing by default
+pub trait MetricsSink: Send + Sync {
+    /// Called after each API request, with the `action` parameter, the HTTP method, and the time until the response
+    fn request(&self, _action: &str, _method: &str, _duration: Duration, _success: bool) {}
+
+    /// Called before a request is retried; `kind` is `maxlag`, `ratelimited` or `too_many_requests`
+    fn retry(&self, _action: &str, _kind: &str, _wait: Duration) {}
+
+    /// Called after an edit, with the time from the first request until the result, including retries
+    fn edit(&self, _action: &str, _duration: Duration, _success: bool) {}
+}
+
+/// The metrics sinks of an `Api`, called in the order they were added
+#[derive(Clone, Default)]
+pub struct Metrics {
+    sinks: Vec<Arc<dyn MetricsSink>>,
+}
+
+impl Metrics {
+    /// Adds a sink
+    pub fn add(&mut self, sink: Arc<dyn MetricsSink>) {
+        self.sinks.push(sink);
+    }
+
+    /// Removes all sinks
+    pub fn clear(&mut self) {
+        self.sinks.clear();
+    }
+
+    /// Returns true if there are no sinks
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    /// Reports a request to all sinks
+    pub fn request(&self, action: &str, method: &str, duration: Duration, success: bool) {
+        for sink in &self.sinks {
+            sink.request(action, method, duration, success);
+        }
+    }
+
+    /// Reports a retry to all sinks, if the event causes one
+    pub fn event(&self, event: &ApiEvent) {
+        let wait = match event {
+            ApiEvent::RateLimited { wait, .. }
+            | ApiEvent::MaxLag { wait, .. }
+            | ApiEvent::TooManyRequests { wait, .. } => *wait,
+            _ => return,
+        };
+        for sink in &self.sinks {
+            sink.retry(event.action(), event.kind(), wait);
+        }
+    }
+
+    /// Reports an edit to all sinks
+    pub fn edit(&self, action: &str, duration: Duration, success: bool) {
+        for sink in &self.sinks {
+            sink.edit(action, duration, success);
+        }
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Metrics({})", self.sinks.len())
+    }
+}
+
+/// Counts of the metrics reported to a `CountingMetrics`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricCounts {
+    /// Requests by `action`
+    pub requests: HashMap<String, u64>,
+    /// Failed requests by `action`
+    pub failed_requests: HashMap<String, u64>,
+    /// Retries by kind, e.g. `maxlag`
+    pub retries: HashMap<String, u64>,
+    /// Total time waited before retries
+    pub retry_wait: Duration,
+    /// Number of edits
+    pub edits: u64,
+    /// Number of failed edits
+    pub failed_edits: u64,
+    /// Total time of all edits
+    pub edit_time: Duration,
+}
+
+/// A simple `MetricsSink` that counts requests, retries and edits in memory
+#[derive(Debug, Default)]
+pub struct CountingMetrics {
+    counts: Mutex<MetricCounts>,
+}
+
+impl CountingMetrics {
+    /// Creates a sink with all counts at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a snapshot of the counts
+    pub fn counts(&self) -> MetricCounts {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MetricCounts> {
+        self.counts
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+impl MetricsSink for CountingMetrics {
+    fn request(&self, action: &str, _method: &str, _duration: Duration, success: bool) {
+        let mut counts = self.lock();
+        *counts.requests.entry(action.to_string()).or_default() += 1;
+        if !success {
+            *counts
+                .failed_requests
+                .entry(action.to_string())
+                .or_default() += 1;
+        }
+    }
+
+    fn retry(&self, _action: &str, kind: &str, wait: Duration) {
+        let mut counts = self.lock();
+        *counts.retries.entry(kind.to_string()).or_default() += 1;
+        counts.retry_wait += wait;
+    }
+
+    fn edit(&self, _action: &str, duration: Duration, success: bool) {
+        let mut counts = self.lock();
+        counts.edits += 1;
+        if !success {
+            counts.failed_edits += 1;
+        }
+        counts.edit_time += duration;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Api;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn counting_metrics() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "purge")],
+                json!({"batchcomplete":"","purge":[{"ns":0,"title":"Foo","purged":""}]}),
+            );
+        let mut api = Api::new_mocked(responses).unwrap();
+        let metrics = Arc::new(CountingMetrics::new());
+        api.add_metrics_sink(metrics.clone());
+        let token = api.get_edit_token().await.unwrap();
+        let params = api.params_into(&[("action", "purge"), ("titles", "Foo"), ("token", &token)]);
+        api.post_query_api_json(&params).await.unwrap();
+        let counts = metrics.counts();
+        assert_eq!(counts.requests["query"], 1);
+        assert_eq!(counts.requests["purge"], 1);
+        assert_eq!(counts.edits, 1);
+        assert_eq!(counts.failed_edits, 0);
+        let mut sinks = Metrics::default();
+        sinks.add(metrics.clone());
+        sinks.event(&ApiEvent::MaxLag {
+            action: "edit".to_string(),
+            wait: Duration::from_secs(5),
+            attempts_left: 1,
+        });
+        assert_eq!(metrics.counts().retries["maxlag"], 1);
+        assert_eq!(metrics.counts().retry_wait, Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,329 @@
+/*!
+The `mock` module serves canned JSON responses instead of querying a live wiki, so bot logic can be unit-tested offline.
+Responses can be written by hand, or recorded from a live `Api` and saved as JSON.
+
+```
+# tokio::runtime::Runtime::new().unwrap().block_on(async {
+use mediawiki::mock::MockResponses;
+let mut responses = MockResponses::new();
+responses.add(&[("action", "query"), ("meta", "userinfo")], serde_json::json!({"query":{"userinfo":{"id":0,"name":"127.0.0.1","anon":""}}}));
+let api = mediawiki::Api::new_mocked(responses).unwrap();
+let params = api.params_into(&[("action", "query"), ("meta", "userinfo")]);
+let result = api.get_query_api_json(&params).await.unwrap();
+assert_eq!(result["query"]["userinfo"]["id"], 0);
+# });
+```
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Parameters that are set by the `Api` itself, and are ignored when recording
+const IGNORED_PARAMS: &[&str] = &[
+    "format",
+    "token",
+    "lgtoken",
+    "lgpassword",
+    "maxlag",
+    "assert",
+];
+
+/// The parameter that holds the method and path or URL of a plain HTTP request, e.g. `GET /page/Foo`
+const ROUTE_PARAM: &str = "route";
+
+/// A canned response, served for requests that have all of the given parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MockResponse {
+    params: HashMap<String, String>,
+    response: Value,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    once: bool,
+}
+
+impl MockResponse {
+    /// Returns true if the request has all parameters of this response, with the same values
+    fn matches(&self, params: &HashMap<String, String>) -> bool {
+        self.params.iter().all(|(k, v)| params.get(k) == Some(v))
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: Vec<MockResponse>,
+    requests: Vec<HashMap<String, String>>,
+}
+
+/// Canned responses, and the requests that were made; clones share both
+#[derive(Debug, Clone, Default)]
+pub struct MockResponses {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockResponses {
+    /// Creates an empty set of responses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Adds a response for requests that have all of the given parameters.
+    /// Responses are matched in the order they were added.
+    pub fn add(&mut self, params: &[(&str, &str)], response: Value) -> &mut Self {
+        self.push(params, response, false)
+    }
+
+    /// Adds a response that is served only once, e.g. an error before a successful retry.
+    /// Responses are matched in the order they were added.
+    pub fn add_once(&mut self, params: &[(&str, &str)], response: Value) -> &mut Self {
+        self.push(params, response, true)
+    }
+
+    fn push(&mut self, params: &[(&str, &str)], response: Value, once: bool) -> &mut Self {
+        let params = params
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        self.state().responses.push(MockResponse {
+            params,
+            response,
+            once,
+        });
+        self
+    }
+
+    /// Adds a REST API response for `method` and `path`, relative to `rest.php/v1`, e.g. `/page/Foo`.
+    /// A string `body` is served as text, anything else as JSON.
+    pub fn add_rest(
+        &mut self,
+        method: &str,
+        path: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: Value,
+    ) -> &mut Self {
+        self.add_http(method, path, status, headers, body)
+    }
+
+    /// Adds a response to a plain HTTP request for `method` and `url`, e.g. to a SPARQL endpoint.
+    /// A string `body` is served as text, anything else as JSON.
+    pub fn add_http(
+        &mut self,
+        method: &str,
+        url: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: Value,
+    ) -> &mut Self {
+        let route = format!("{} {}", method, url);
+        let response = Self::http_response(status, headers, body);
+        self.add(&[(ROUTE_PARAM, &route)], response)
+    }
+
+    /// Same as `add_http`, but the response is only served once
+    pub fn add_http_once(
+        &mut self,
+        method: &str,
+        url: &str,
+        status: u16,
+        headers: &[(&str, &str)],
+        body: Value,
+    ) -> &mut Self {
+        let route = format!("{} {}", method, url);
+        let response = Self::http_response(status, headers, body);
+        self.add_once(&[(ROUTE_PARAM, &route)], response)
+    }
+
+    fn http_response(status: u16, headers: &[(&str, &str)], body: Value) -> Value {
+        let headers: serde_json::Map<String, Value> = headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::from(*v)))
+            .collect();
+        serde_json::json!({"status": status, "headers": headers, "body": body})
+    }
+
+    /// Records a plain HTTP request, and returns the matching response.
+    /// The request is recorded with its method and path or URL as `route`, its `if-match` header,
+    /// and the fields of its JSON body, with dotted keys for nested fields, e.g. `original.revid`.
+    pub(crate) fn respond_http(
+        &self,
+        method: &str,
+        path: &str,
+        if_match: Option<&str>,
+        body: Option<&Value>,
+    ) -> Result<reqwest::Response, MediaWikiError> {
+        let mut params = HashMap::new();
+        params.insert(ROUTE_PARAM.to_string(), format!("{} {}", method, path));
+        if let Some(etag) = if_match {
+            params.insert("if-match".to_string(), etag.to_string());
+        }
+        if let Some(body) = body {
+            Self::flatten_body("", body, &mut params);
+        }
+        let response: Value = serde_json::from_str(&self.respond(&params)?)?;
+        let status = response["status"].as_u64().unwrap_or(200) as u16;
+        let mut builder = http::Response::builder().status(status);
+        for (k, v) in response["headers"].as_object().into_iter().flatten() {
+            builder = builder.header(k.as_str(), v.as_str().unwrap_or_default());
+        }
+        let body = match &response["body"] {
+            Value::String(s) => s.to_owned(),
+            Value::Null => String::new(),
+            other => other.to_string(),
+        };
+        let response = builder
+            .body(body)
+            .map_err(|e| MediaWikiError::String(e.to_string()))?;
+        Ok(reqwest::Response::from(response))
+    }
+
+    fn flatten_body(prefix: &str, body: &Value, params: &mut HashMap<String, String>) {
+        match body {
+            Value::Object(o) => {
+                for (k, v) in o {
+                    let key = match prefix.is_empty() {
+                        true => k.to_owned(),
+                        false => format!("{}.{}", prefix, k),
+                    };
+                    Self::flatten_body(&key, v, params);
+                }
+            }
+            Value::String(s) => {
+                params.insert(prefix.to_string(), s.to_owned());
+            }
+            other => {
+                params.insert(prefix.to_string(), other.to_string());
+            }
+        }
+    }
+
+    /// Returns the first response matching the parameters
+    pub fn response_for(&self, params: &HashMap<String, String>) -> Option<Value> {
+        self.state()
+            .responses
+            .iter()
+            .find(|r| r.matches(params))
+            .map(|r| r.response.to_owned())
+    }
+
+    /// Records the request, and returns the matching response as a string, as the API would
+    pub(crate) fn respond(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Result<String, MediaWikiError> {
+        let mut state = self.state();
+        state.requests.push(params.to_owned());
+        let position = state.responses.iter().position(|r| r.matches(params));
+        match position {
+            Some(position) if state.responses[position].once => {
+                Ok(state.responses.remove(position).response.to_string())
+            }
+            Some(position) => Ok(state.responses[position].response.to_string()),
+            None => Err(MediaWikiError::NoMockResponse(params.to_owned())),
+        }
+    }
+
+    /// Returns the parameters of all requests served so far, oldest first
+    pub fn requests(&self) -> Vec<HashMap<String, String>> {
+        self.state().requests.to_owned()
+    }
+
+    /// Adds a response for exactly these request parameters, ignoring tokens, passwords and other per-session parameters
+    pub fn record(&self, params: &HashMap<String, String>, response: &Value) {
+        let params = params
+            .iter()
+            .filter(|(k, _)| !IGNORED_PARAMS.contains(&k.as_str()))
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        self.state().responses.push(MockResponse {
+            params,
+            response: response.to_owned(),
+            once: false,
+        });
+    }
+
+    /// Returns all responses as JSON, e.g. to save recorded responses to a file
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(&self.state().responses).unwrap_or_default()
+    }
+
+    /// Creates responses from JSON, as returned by `to_json`
+    pub fn from_json(j: &Value) -> Result<Self, MediaWikiError> {
+        let responses: Vec<MockResponse> = serde_json::from_value(j.to_owned())?;
+        Ok(Self {
+            state: Arc::new(Mutex::new(MockState {
+                responses,
+                requests: vec![],
+            })),
+        })
+    }
+}
+
+impl Api {
+    /// Records all API responses into the returned `MockResponses`, which can later be used with `Api::new_mocked`
+    pub fn record_responses(&mut self) -> MockResponses {
+        let recorder = MockResponses::new();
+        let responses = recorder.clone();
+        self.add_response_hook(move |params, result| responses.record(params, result));
+        recorder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn mocked_api() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("action", "query"), ("meta", "siteinfo")],
+                json!({"query":{"general":{"sitename":"Mockpedia","lang":"en"}}}),
+            )
+            .add(
+                &[("action", "query"), ("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        assert_eq!(
+            api.get_site_info_string("general", "sitename").unwrap(),
+            "Mockpedia"
+        );
+        assert_eq!(api.get_edit_token().await.unwrap(), "abc+\\");
+        assert_eq!(responses.requests().last().unwrap()["type"], "csrf");
+
+        let params = api.params_into(&[("action", "parse")]);
+        assert!(matches!(
+            api.get_query_api_json(&params).await,
+            Err(MediaWikiError::NoMockResponse(_))
+        ));
+    }
+
+    #[test]
+    fn record_and_restore() {
+        let recorder = MockResponses::new();
+        let params: HashMap<String, String> = [
+            ("action".to_string(), "query".to_string()),
+            ("format".to_string(), "json".to_string()),
+            ("lgpassword".to_string(), "hunter2".to_string()),
+        ]
+        .into();
+        recorder.record(&params, &json!({"batchcomplete":""}));
+        let j = recorder.to_json();
+        assert!(!j.to_string().contains("hunter2"));
+        let restored = MockResponses::from_json(&j).unwrap();
+        assert_eq!(
+            restored.response_for(&params),
+            Some(json!({"batchcomplete":""}))
+        );
+    }
+}
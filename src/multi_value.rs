@@ -0,0 +1,148 @@
+/*!
+The `multi_value` module handles pipe-separated multi-value parameters such as `titles` or `ids`,
+splitting long value lists into batches within the API limits, and merging the results of all batches.
+
+```no_run
+use mediawiki::multi_value::MultiValueParams;
+# tokio::runtime::Runtime::new().unwrap().block_on(async {
+let api = mediawiki::api::Api::new("https://en.wikipedia.org/w/api.php").await.unwrap();
+let titles: Vec<String> = (1..=120).map(|n| n.to_string()).collect();
+let params = api.params_into(&[("action", "query"), ("prop", "info")]);
+let result = params.multi("titles", &titles).run(&api).await.unwrap();
+# });
+```
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Maximum number of values of a multi-value parameter
+pub const MULTI_VALUE_LIMIT: usize = 50;
+
+/// Maximum number of values of a multi-value parameter for users with the `apihighlimits` right, e.g. bots
+pub const MULTI_VALUE_HIGH_LIMIT: usize = 500;
+
+/// Splits `values` into pipe-separated batches of at most `batch_size` values
+pub fn multi_value_batches<I, S>(values: I, batch_size: usize) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let values: Vec<S> = values.into_iter().collect();
+    values
+        .chunks(batch_size.max(1))
+        .map(|chunk| {
+            chunk
+                .iter()
+                .map(|v| v.as_ref())
+                .collect::<Vec<&str>>()
+                .join("|")
+        })
+        .collect()
+}
+
+/// A query with a multi-value parameter that may need several requests; see `MultiValueParams::multi`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiValueQuery {
+    params: HashMap<String, String>,
+    key: String,
+    values: Vec<String>,
+}
+
+impl MultiValueQuery {
+    /// Returns the parameters of each request, with at most `batch_size` values each
+    pub fn batches(&self, batch_size: usize) -> Vec<HashMap<String, String>> {
+        multi_value_batches(&self.values, batch_size)
+            .into_iter()
+            .map(|batch| {
+                let mut params = self.params.clone();
+                params.insert(self.key.clone(), batch);
+                params
+            })
+            .collect()
+    }
+
+    /// Runs one GET query per batch, with the batch size allowed for the current user,
+    /// following continuation, and returns the merged results
+    pub async fn run(&self, api: &Api) -> Result<Value, MediaWikiError> {
+        let mut ret = Value::Null;
+        for params in self.batches(api.multi_value_limit()) {
+            let result = api.get_query_api_json_all(&params).await?;
+            Api::json_merge(&mut ret, result);
+        }
+        Ok(ret)
+    }
+}
+
+/// Adds `multi` to parameter maps
+pub trait MultiValueParams {
+    /// Returns a query with these parameters, and `values` for the multi-value parameter `key`
+    fn multi<I, S>(&self, key: &str, values: I) -> MultiValueQuery
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>;
+}
+
+impl MultiValueParams for HashMap<String, String> {
+    fn multi<I, S>(&self, key: &str, values: I) -> MultiValueQuery
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        MultiValueQuery {
+            params: self.clone(),
+            key: key.to_string(),
+            values: values.into_iter().map(|v| v.as_ref().to_string()).collect(),
+        }
+    }
+}
+
+impl Api {
+    /// Returns the maximum number of values of a multi-value parameter for the current user:
+    /// `MULTI_VALUE_HIGH_LIMIT` with the `apihighlimits` right, `MULTI_VALUE_LIMIT` otherwise
+    pub fn multi_value_limit(&self) -> usize {
+        match self.user().has_right("apihighlimits") {
+            true => MULTI_VALUE_HIGH_LIMIT,
+            false => MULTI_VALUE_LIMIT,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn multi() {
+        assert_eq!(multi_value_batches(["A", "B", "C"], 2), vec!["A|B", "C"]);
+        assert!(multi_value_batches(Vec::<String>::new(), 50).is_empty());
+
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("titles", "T50")],
+                json!({"batchcomplete":"","query":{"pages":{"-2":{"ns":0,"title":"T50","missing":""}}}}),
+            )
+            .add(
+                &[("prop", "info")],
+                json!({"batchcomplete":"","query":{"pages":{"-1":{"ns":0,"title":"T0","missing":""}}}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        assert_eq!(api.multi_value_limit(), MULTI_VALUE_LIMIT);
+        let titles: Vec<String> = (0..=50).map(|n| format!("T{}", n)).collect();
+        let params = api.params_into(&[("action", "query"), ("prop", "info")]);
+        let query = params.multi("titles", &titles);
+        assert_eq!(query.batches(500).len(), 1);
+        let result = query.run(&api).await.unwrap();
+        assert_eq!(result["query"]["pages"].as_object().unwrap().len(), 2);
+        let requests = responses.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0]["titles"].split('|').count(), 50);
+        assert_eq!(requests[1]["titles"], "T50");
+    }
+}
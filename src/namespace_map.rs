@@ -0,0 +1,280 @@
+/*!
+The `NamespaceMap` class maps namespace names and aliases to IDs, so titles can be parsed without an `Api`,
+e.g. in hot loops or when processing dumps offline. It can be serialized, to be stored alongside such jobs.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::{Namespace, Title, CANONICAL_NAMESPACES};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+
+/// Namespace names, canonical names and aliases, and the namespaces that are case-sensitive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NamespaceMap {
+    ids: HashMap<String, NamespaceID>,
+    names: HashMap<NamespaceID, String>,
+    #[serde(default)]
+    canonical_names: HashMap<NamespaceID, String>,
+    case_sensitive: HashSet<NamespaceID>,
+}
+
+impl Default for NamespaceMap {
+    /// Returns a map of the canonical namespaces that exist on every MediaWiki installation
+    fn default() -> Self {
+        let mut ret = Self::empty();
+        for (_, id, name) in CANONICAL_NAMESPACES {
+            if *id != 0 {
+                ret.insert(*id, name);
+            }
+        }
+        ret
+    }
+}
+
+impl NamespaceMap {
+    /// Creates a map without any namespaces
+    pub fn empty() -> Self {
+        Self {
+            ids: HashMap::new(),
+            names: HashMap::new(),
+            canonical_names: HashMap::new(),
+            case_sensitive: HashSet::new(),
+        }
+    }
+
+    /// Creates a map from site info, with `siprop=namespaces|namespacealiases`
+    pub fn from_site_info(site_info: &Value) -> Self {
+        let mut ret = Self::empty();
+        if let Some(namespaces) = site_info["query"]["namespaces"].as_object() {
+            for ns in namespaces.values() {
+                let id = match ns["id"].as_i64() {
+                    Some(id) => id,
+                    None => continue,
+                };
+                if let Some(name) = ns["*"].as_str().or_else(|| ns["name"].as_str()) {
+                    ret.insert(id, name);
+                }
+                if let Some(canonical) = ns["canonical"].as_str() {
+                    ret.insert_canonical(id, canonical);
+                }
+                if ns["case"].as_str() == Some("case-sensitive") {
+                    ret.case_sensitive.insert(id);
+                }
+            }
+        }
+        if let Some(aliases) = site_info["query"]["namespacealiases"].as_array() {
+            for alias in aliases {
+                if let (Some(id), Some(name)) = (
+                    alias["id"].as_i64(),
+                    alias["*"].as_str().or_else(|| alias["alias"].as_str()),
+                ) {
+                    ret.insert_alias(id, name);
+                }
+            }
+        }
+        ret
+    }
+
+    fn key(name: &str) -> String {
+        Title::underscores_to_spaces(name).to_lowercase()
+    }
+
+    /// Adds a namespace with its local name; the main namespace has no name
+    pub fn insert(&mut self, id: NamespaceID, name: &str) {
+        if name.is_empty() {
+            return;
+        }
+        self.names.insert(id, Title::underscores_to_spaces(name));
+        self.insert_alias(id, name);
+    }
+
+    /// Adds an alternative name of a namespace
+    pub fn insert_alias(&mut self, id: NamespaceID, name: &str) {
+        if !name.is_empty() {
+            self.ids.insert(Self::key(name), id);
+        }
+    }
+
+    /// Adds the canonical (English) name of a namespace, which is also an alias
+    pub fn insert_canonical(&mut self, id: NamespaceID, name: &str) {
+        if !name.is_empty() {
+            self.canonical_names
+                .insert(id, Title::underscores_to_spaces(name));
+            self.insert_alias(id, name);
+        }
+    }
+
+    /// Returns the namespace ID for a local name, canonical name or alias (case-insensitive)
+    pub fn namespace_id(&self, name: &str) -> Option<NamespaceID> {
+        self.ids.get(&Self::key(name)).copied()
+    }
+
+    /// Returns the local name of a namespace
+    pub fn name(&self, id: NamespaceID) -> Option<&str> {
+        self.names.get(&id).map(|s| s.as_str())
+    }
+
+    /// Returns the canonical (English) name of a namespace, from the site info or the built-in canonical namespaces
+    pub fn canonical_name(&self, id: NamespaceID) -> Option<&str> {
+        self.canonical_names
+            .get(&id)
+            .map(|s| s.as_str())
+            .or_else(|| Namespace::from(id).canonical_name())
+    }
+
+    /// Returns true if titles in the namespace are case-sensitive, rather than having an uppercase first letter
+    pub fn is_case_sensitive(&self, id: NamespaceID) -> bool {
+        self.case_sensitive.contains(&id)
+    }
+}
+
+impl Api {
+    /// Returns the namespaces of this wiki as a `NamespaceMap`, from the site info
+    pub fn namespace_map(&self) -> NamespaceMap {
+        self.site_info().namespaces().to_owned()
+    }
+}
+
+impl Title {
+    /// Constructor, where full namespace-prefixed title is known.
+    /// Uses a `NamespaceMap` instead of an `Api` to parse valid namespaces.
+    pub fn new_from_full_with_map(full_title: &str, map: &NamespaceMap) -> Self {
+        if let Some((prefix, rest)) = full_title.split_once(':') {
+            if let Some(namespace_id) = map.namespace_id(prefix) {
+                let title = Title::underscores_to_spaces(rest);
+                let title = if map.is_case_sensitive(namespace_id) {
+                    title
+                } else {
+                    Title::first_letter_uppercase(&title)
+                };
+                return Self::new(&title, namespace_id);
+            }
+        }
+        Self::new(full_title, 0)
+    }
+
+    /// Returns the full title with spaces, using a `NamespaceMap` for the namespace prefix
+    pub fn full_with_map(&self, map: &NamespaceMap) -> Option<String> {
+        match self.namespace_id() {
+            0 => Some(self.pretty().to_string()),
+            id => Some(format!("{}:{}", map.name(id)?, self.pretty())),
+        }
+    }
+
+    /// Returns a `Display` of the prefixed title, including interwiki prefix and `#fragment`.
+    /// Namespaces without a local name in the map use their canonical name.
+    ///
+    /// # Errors
+    /// If the map has neither a local nor a canonical name for the namespace, will return a `MediaWikiError::BadTitle`.
+    pub fn display_with<'a>(
+        &'a self,
+        map: &'a NamespaceMap,
+    ) -> Result<TitleDisplay<'a>, MediaWikiError> {
+        let namespace = match self.namespace_id() {
+            0 => None,
+            id => Some(
+                map.name(id)
+                    .or_else(|| map.canonical_name(id))
+                    .ok_or_else(|| MediaWikiError::BadTitle(self.clone()))?,
+            ),
+        };
+        Ok(TitleDisplay {
+            title: self,
+            namespace,
+        })
+    }
+}
+
+/// Formats a `Title` with its prefixes, as returned by `Title::display_with`
+#[derive(Debug, Clone, Copy)]
+pub struct TitleDisplay<'a> {
+    title: &'a Title,
+    namespace: Option<&'a str>,
+}
+
+impl Display for TitleDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(interwiki) = self.title.interwiki() {
+            write!(f, "{}:", interwiki)?;
+        }
+        if let Some(namespace) = self.namespace {
+            write!(f, "{}:", namespace)?;
+        }
+        write!(f, "{}", self.title.pretty())?;
+        if let Some(fragment) = self.title.fragment() {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_map() {
+        let map = NamespaceMap::default();
+        assert_eq!(
+            Title::new_from_full_with_map("category:some_thing", &map),
+            Title::new("Some thing", 14)
+        );
+        assert_eq!(
+            Title::new_from_full_with_map("Main:Foo", &map),
+            Title::new("Main:Foo", 0)
+        );
+        assert_eq!(
+            Title::new("Foo bar", 3).full_with_map(&map),
+            Some("User talk:Foo bar".to_string())
+        );
+    }
+
+    #[test]
+    fn from_site_info() {
+        let site_info = json!({"query":{"namespaces":{
+            "0":{"id":0,"case":"first-letter","name":"","content":true},
+            "4":{"id":4,"case":"first-letter","name":"Wikipedia","canonical":"Project"},
+            "120":{"id":120,"case":"case-sensitive","name":"Property","canonical":"Property"},
+            "828":{"id":828,"case":"first-letter","canonical":"Module"}},
+            "namespacealiases":[{"id":4,"alias":"WP"}]}});
+        let map = NamespaceMap::from_site_info(&site_info);
+        assert_eq!(map.namespace_id("project"), Some(4));
+        assert_eq!(map.namespace_id("WP"), Some(4));
+        assert_eq!(map.name(4), Some("Wikipedia"));
+        assert_eq!(
+            Title::new_from_full_with_map("Property:p31", &map),
+            Title::new("p31", 120)
+        );
+        assert_eq!(
+            Title::new("Foo", 4)
+                .with_fragment("Bar")
+                .display_with(&map)
+                .unwrap()
+                .to_string(),
+            "Wikipedia:Foo#Bar"
+        );
+        assert_eq!(
+            Title::new("Foo", 3).display_with(&map).unwrap().to_string(),
+            "User talk:Foo"
+        );
+        assert_eq!(map.canonical_name(828), Some("Module"));
+        assert_eq!(
+            Title::new("Foo", 828)
+                .display_with(&map)
+                .unwrap()
+                .to_string(),
+            "Module:Foo"
+        );
+        assert!(matches!(
+            Title::new("Foo", 2600).display_with(&map),
+            Err(MediaWikiError::BadTitle(_))
+        ));
+        let j = serde_json::to_string(&map).unwrap();
+        assert_eq!(serde_json::from_str::<NamespaceMap>(&j).unwrap(), map);
+    }
+}
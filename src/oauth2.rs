@@ -0,0 +1,240 @@
+/*!
+The `oauth2` module supports owner-only OAuth 2 consumers, fetching access tokens via the `client_credentials` grant.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Tokens are re-fetched this long before they expire
+const OAUTH2_EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+/// Prefix of the API error codes for rejected OAuth authorization, e.g. a revoked access token
+const INVALID_AUTHORIZATION_CODE: &str = "mwoauth-invalid-authorization";
+
+#[derive(Debug, Clone)]
+struct AccessToken {
+    token: String,
+    expires_at: Option<Instant>,
+}
+
+impl AccessToken {
+    fn from_json(j: &Value, now: Instant) -> Option<Self> {
+        Some(Self {
+            token: j["access_token"].as_str()?.to_string(),
+            expires_at: j["expires_in"]
+                .as_u64()
+                .map(|secs| now + Duration::from_secs(secs)),
+        })
+    }
+
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| now + OAUTH2_EXPIRY_MARGIN >= expires_at)
+    }
+}
+
+/// Credentials of an owner-only OAuth 2 consumer, and the current access token
+#[derive(Clone)]
+pub struct OAuth2Client {
+    client_id: String,
+    client_secret: String,
+    token: Arc<Mutex<Option<AccessToken>>>,
+}
+
+impl OAuth2Client {
+    /// Creates a client; no token is fetched yet
+    pub fn new(client_id: &str, client_secret: &str) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            token: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Returns the client ID
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// Returns the current access token, if one was fetched and has not expired
+    pub fn access_token(&self) -> Option<String> {
+        let token = self.token.lock().ok()?;
+        token
+            .as_ref()
+            .filter(|t| !t.is_expired(Instant::now()))
+            .map(|t| t.token.to_owned())
+    }
+
+    /// Returns the URL of the token endpoint, next to `api.php`
+    fn token_url(api_url: &str) -> String {
+        let base = api_url.strip_suffix("api.php").unwrap_or(api_url);
+        format!("{}rest.php/oauth2/access_token", base)
+    }
+}
+
+impl fmt::Debug for OAuth2Client {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OAuth2Client")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &"[redacted]")
+            .finish()
+    }
+}
+
+impl Api {
+    /// Uses an owner-only OAuth 2 consumer. An access token is fetched via the `client_credentials` grant,
+    /// and fetched again automatically when it expires, or once when the wiki rejects it.
+    pub async fn set_oauth2_client(
+        &self,
+        client_id: &str,
+        client_secret: &str,
+    ) -> Result<(), MediaWikiError> {
+        let client = OAuth2Client::new(client_id, client_secret);
+        self.fetch_oauth2_token(&client).await?;
+        self.set_oauth2_client_ref(Some(client));
+        Ok(())
+    }
+
+    /// Fetches a new access token if the current one is missing or about to expire
+    pub(crate) async fn refresh_oauth2_token(&self) -> Result<(), MediaWikiError> {
+        if let Some(client) = self.oauth2_client() {
+            if client.access_token().is_none() {
+                self.fetch_oauth2_token(&client).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetches a new access token, even if the current one has not expired, e.g. after the wiki rejected it.
+    /// Returns false if no OAuth 2 client is set, so there is no token to renew.
+    pub(crate) async fn renew_oauth2_token(&self) -> Result<bool, MediaWikiError> {
+        match self.oauth2_client() {
+            Some(client) => {
+                self.fetch_oauth2_token(&client).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Returns true if the API result is an error because the wiki rejected the OAuth authorization
+    pub(crate) fn is_invalid_authorization(result: &Value) -> bool {
+        result["error"]["code"]
+            .as_str()
+            .is_some_and(|code| code.starts_with(INVALID_AUTHORIZATION_CODE))
+    }
+
+    async fn fetch_oauth2_token(&self, client: &OAuth2Client) -> Result<(), MediaWikiError> {
+        let params = self.params_into(&[
+            ("grant_type", "client_credentials"),
+            ("client_id", client.client_id.as_str()),
+            ("client_secret", client.client_secret.as_str()),
+        ]);
+        let now = Instant::now();
+        let result: Value = match self.mock() {
+            Some(mock) => serde_json::from_str(&mock.respond(&params)?)?,
+            None => {
+                self.client()
+                    .post(OAuth2Client::token_url(self.api_url()))
+                    .header(reqwest::header::USER_AGENT, self.user_agent_full())
+                    .form(&params)
+                    .send()
+                    .await?
+                    .json()
+                    .await?
+            }
+        };
+        let token = AccessToken::from_json(&result, now)
+            .ok_or_else(|| MediaWikiError::BadResponse(result.to_owned()))?;
+        if let Ok(mut current) = client.token.lock() {
+            *current = Some(token);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_url() {
+        assert_eq!(
+            OAuth2Client::token_url("https://meta.wikimedia.org/w/api.php"),
+            "https://meta.wikimedia.org/w/rest.php/oauth2/access_token"
+        );
+    }
+
+    #[test]
+    fn access_token_expiry() {
+        let now = Instant::now();
+        let token = AccessToken::from_json(
+            &json!({"token_type":"Bearer","expires_in":14400,"access_token":"abc"}),
+            now,
+        )
+        .unwrap();
+        assert!(!token.is_expired(now));
+        assert!(token.is_expired(now + Duration::from_secs(14400 - 30)));
+        assert!(AccessToken::from_json(&json!({"error":"invalid_client"}), now).is_none());
+
+        let client = OAuth2Client::new("id", "secret");
+        assert_eq!(client.access_token(), None);
+        *client.token.lock().unwrap() = Some(token);
+        assert_eq!(client.access_token(), Some("abc".to_string()));
+        assert!(!format!("{:?}", client).contains("secret\""));
+    }
+
+    #[tokio::test]
+    async fn renew_rejected_token() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add_once(
+                &[("grant_type", "client_credentials")],
+                json!({"token_type":"Bearer","expires_in":14400,"access_token":"revoked"}),
+            )
+            .add(
+                &[("grant_type", "client_credentials")],
+                json!({"token_type":"Bearer","expires_in":14400,"access_token":"fresh"}),
+            )
+            .add_once(
+                &[("meta", "userinfo")],
+                json!({"error":{"code":"mwoauth-invalid-authorization","info":"The authorization headers in your request are not valid"}}),
+            )
+            .add(
+                &[("meta", "userinfo")],
+                json!({"batchcomplete":true,"query":{"userinfo":{"id":1,"name":"Bot"}}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        api.set_oauth2_client("id", "secret").await.unwrap();
+        let params = api.params_into(&[("action", "query"), ("meta", "userinfo")]);
+        let result = api.get_query_api_json(&params).await.unwrap();
+        assert_eq!(result["query"]["userinfo"]["name"], "Bot");
+        assert_eq!(
+            api.oauth2_client().unwrap().access_token(),
+            Some("fresh".to_string())
+        );
+        let token_requests = responses
+            .requests()
+            .iter()
+            .filter(|r| r.contains_key("grant_type"))
+            .count();
+        assert_eq!(token_requests, 2);
+
+        let no_params = std::collections::HashMap::new();
+        let auth = reqwest::header::AUTHORIZATION;
+        let headers = api
+            .request_headers(api.api_url(), &no_params, "GET")
+            .unwrap();
+        assert_eq!(headers[&auth], "Bearer fresh");
+        let headers = api
+            .request_headers("https://query.wikidata.org/sparql", &no_params, "GET")
+            .unwrap();
+        assert!(!headers.contains_key(&auth));
+    }
+}
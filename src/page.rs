@@ -5,12 +5,71 @@ The `Page` class deals with operations done on pages, like editing.
 #![deny(missing_docs)]
 
 use crate::api::Api;
+use crate::captcha::Captcha;
+use crate::conflict::Conflict;
+use crate::duplicate_files::DuplicateFile;
 use crate::media_wiki_error::MediaWikiError;
+use crate::protection::{protect_params, Protection};
+use crate::rest::RestPage;
+use crate::section::Section;
 use crate::title::Title;
 use crate::Revision;
 use serde_json::Value;
 use std::collections::HashMap;
-use std::error::Error;
+
+/// Options for [`Page::extract`], mapping to the TextExtracts `prop=extracts` parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ExtractOptions {
+    intro: bool,
+    plain_text: bool,
+    sentences: Option<u32>,
+    chars: Option<u32>,
+}
+
+impl ExtractOptions {
+    /// Returns the default options (full page, limited HTML)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only return the content before the first section (`exintro`)
+    pub fn set_intro(&mut self, intro: bool) {
+        self.intro = intro;
+    }
+
+    /// Return plain text instead of limited HTML (`explaintext`)
+    pub fn set_plain_text(&mut self, plain_text: bool) {
+        self.plain_text = plain_text;
+    }
+
+    /// Limit the extract to this many sentences (`exsentences`, 1-10)
+    pub fn set_sentences(&mut self, sentences: Option<u32>) {
+        self.sentences = sentences;
+    }
+
+    /// Limit the extract to this many characters (`exchars`, 1-1200)
+    pub fn set_chars(&mut self, chars: Option<u32>) {
+        self.chars = chars;
+    }
+
+    /// Returns the API parameters for these options
+    pub fn params(&self) -> Vec<(String, String)> {
+        let mut ret = vec![("prop".to_string(), "extracts".to_string())];
+        if self.intro {
+            ret.push(("exintro".to_string(), "1".to_string()));
+        }
+        if self.plain_text {
+            ret.push(("explaintext".to_string(), "1".to_string()));
+        }
+        if let Some(sentences) = self.sentences {
+            ret.push(("exsentences".to_string(), sentences.to_string()));
+        }
+        if let Some(chars) = self.chars {
+            ret.push(("exchars".to_string(), chars.to_string()));
+        }
+        ret
+    }
+}
 
 /// Represents a page.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -18,6 +77,7 @@ pub struct Page {
     title: Title,
     page_id: Option<usize>,
     revision: Option<Revision>,
+    start_timestamp: Option<String>,
 }
 
 impl Page {
@@ -27,6 +87,7 @@ impl Page {
             title,
             page_id: None,
             revision: None,
+            start_timestamp: None,
         }
     }
 
@@ -40,7 +101,8 @@ impl Page {
     /// the "main" slot is fetched, or an error is returned if there is
     /// no "main" slot.
     ///
-    /// The `revision` field of this `Page` is set to the fetched revision.
+    /// The `revision` field of this `Page` is set to the fetched revision, and the server time
+    /// of the request is remembered, so that [`Page::edit_text`] can detect edit conflicts.
     ///
     /// # Errors
     /// If the page is missing, will return a `MediaWikiError::Missing`.
@@ -57,79 +119,216 @@ impl Page {
             ("titles", &title),
             ("rvslots", "*"),
             ("rvprop", crate::revision::RVPROP),
+            ("curtimestamp", "1"),
             ("formatversion", "2"),
         ]
         .iter()
         .map(|&(k, v)| (k.to_string(), v.to_string()))
         .collect();
         let result = api.get_query_api_json(&params).await?;
+        self.start_timestamp = result["curtimestamp"].as_str().map(|s| s.to_string());
         let page = &result["query"]["pages"][0];
 
         if !page.is_object() || page["missing"].as_bool() == Some(true) {
             return Err(MediaWikiError::Missing(self.title.clone()));
         }
-        self.page_id = match page["pageid"].as_u64().map(|x| x as usize) {
-            Some(x) => Some(x),
-            None => return Err(MediaWikiError::BadResponse(result)),
-        };
-        self.revision = Some(Revision::from_json(&page["revisions"][0])?);
-        let wikitext = self.revision.as_ref().unwrap().wikitext();
-        let wikitext = match wikitext {
-            Some(x) => x,
-            None => return Err(MediaWikiError::BadResponse(result)),
-        };
-        Ok(wikitext)
+        if page["pageid"].as_u64().is_none() || page["revisions"][0].is_null() {
+            return Err(MediaWikiError::BadResponse(result));
+        }
+        self.fill_from_api_result(page)?;
+        match self.text_cached() {
+            Some(wikitext) => Ok(wikitext),
+            None => Err(MediaWikiError::BadResponse(result)),
+        }
+    }
+
+    /// Creates a `Page` from a page object of an API result, e.g. an entry of `["query"]["pages"]`
+    /// from a batch query or generator. The page ID and the first revision are taken over if present,
+    /// so [`Page::text_cached`] and [`Page::load`] do not need to fetch the text again.
+    ///
+    /// # Errors
+    /// If the page is missing, will return a `MediaWikiError::Missing`.
+    pub fn from_api_result(page: &Value) -> Result<Self, MediaWikiError> {
+        let mut ret = Self::new(Title::new_from_api_result(page));
+        if !page.is_object()
+            || page
+                .get("missing")
+                .is_some_and(|m| m != &Value::Bool(false))
+        {
+            return Err(MediaWikiError::Missing(ret.title));
+        }
+        ret.fill_from_api_result(page)?;
+        Ok(ret)
+    }
+
+    /// Sets the page ID and revision from a page object of an API result, if present
+    fn fill_from_api_result(&mut self, page: &Value) -> Result<(), MediaWikiError> {
+        if let Some(page_id) = page["pageid"].as_u64() {
+            self.page_id = Some(page_id as usize);
+        }
+        if page["revisions"][0].is_object() {
+            self.revision = Some(Revision::from_json(&page["revisions"][0])?);
+        }
+        Ok(())
+    }
+
+    /// Fetches the text of this `Page` with [`Page::text`], unless it is already loaded.
+    pub async fn load(&mut self, api: &Api) -> Result<(), MediaWikiError> {
+        if self.text_cached().is_none() {
+            self.text(api).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the text of the loaded revision, without a network request.
+    /// Use [`Page::load`] or [`Page::text`] to fetch it.
+    pub fn text_cached(&self) -> Option<&str> {
+        self.revision.as_ref().and_then(|r| r.wikitext())
     }
 
     /// Replaces the contents of this `Page` with the given text, using the given
     /// edit summary.
     ///
+    /// If the text was loaded with [`Page::text`], the base revision, its timestamp and the load time
+    /// are sent along, so the edit fails with an edit conflict instead of overwriting newer changes.
+    /// An MD5 checksum of the text is always sent, to detect corruption in transit.
+    ///
     /// # Errors
     /// May return a `MediaWikiError` or any error from [`Api::post_query_api_json`].
+    /// An edit conflict is returned as `MediaWikiError::EditConflict`, a required captcha as
+    /// `MediaWikiError::Captcha`.
     ///
     /// [`Api::post_query_api_json`]: ../api/struct.Api.html#method.post_query_api_json
     pub async fn edit_text(
         &self,
-        api: &mut Api,
+        api: &Api,
+        text: impl Into<String>,
+        summary: impl Into<String>,
+    ) -> Result<(), MediaWikiError> {
+        self.edit_with_params(api, text.into(), summary.into(), &[])
+            .await
+    }
+
+    /// Same as [`Page::edit_text`], but API error messages come back in `language` (e.g. `en`) via `uselang`,
+    /// overriding `Api::set_default_language`
+    pub async fn edit_text_with_language(
+        &self,
+        api: &Api,
+        text: impl Into<String>,
+        summary: impl Into<String>,
+        language: &str,
+    ) -> Result<(), MediaWikiError> {
+        self.edit_with_params(api, text.into(), summary.into(), &[("uselang", language)])
+            .await
+    }
+
+    /// Same as [`Page::edit_text`], but applies the change `tags` to the edit; see `Api::list_change_tags`
+    pub async fn edit_text_with_tags(
+        &self,
+        api: &Api,
+        text: impl Into<String>,
+        summary: impl Into<String>,
+        tags: &[&str],
+    ) -> Result<(), MediaWikiError> {
+        let tags = tags.join("|");
+        let params: &[(&str, &str)] = match tags.is_empty() {
+            true => &[],
+            false => &[("tags", &tags)],
+        };
+        self.edit_with_params(api, text.into(), summary.into(), params)
+            .await
+    }
+
+    /// Replaces the text of section `index` (0 for the lead), as returned by [`Page::sections`].
+    /// `text` includes the section heading. Conflicts are detected as in [`Page::edit_text`].
+    pub async fn edit_section(
+        &self,
+        api: &Api,
+        index: usize,
         text: impl Into<String>,
         summary: impl Into<String>,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), MediaWikiError> {
+        let index = index.to_string();
+        self.edit_with_params(api, text.into(), summary.into(), &[("section", &index)])
+            .await
+    }
+
+    /// Performs an `action=edit`, with `additional_params` such as `section`
+    async fn edit_with_params(
+        &self,
+        api: &Api,
+        text: String,
+        summary: String,
+        additional_params: &[(&str, &str)],
+    ) -> Result<(), MediaWikiError> {
         let title = self
             .title
             .full_pretty(api)
             .ok_or_else(|| MediaWikiError::BadTitle(self.title.clone()))?;
         let bot = if api.user().is_bot() { "true" } else { "false" };
+        let summary = api.edit_summary().apply(&summary);
         let mut params: HashMap<String, String> = [
             ("action", "edit"),
             ("title", &title),
-            ("text", &text.into()),
-            ("summary", &summary.into()),
+            ("text", &text),
+            ("summary", &summary),
             ("bot", bot),
             ("formatversion", "2"),
             ("token", &api.get_edit_token().await?),
         ]
         .iter()
+        .chain(additional_params)
         .map(|&(k, v)| (k.to_string(), v.to_string()))
         .collect();
 
-        // Set the base revision ID if available, to avoid edit conflicts
-        if let Some(baserevid) = self.revision.as_ref().map(|r| r.id()) {
-            params.insert("baserevid".to_string(), baserevid.to_string());
+        // Set the base revision ID and timestamps if available and not given, to avoid edit conflicts
+        if let Some(revision) = self
+            .revision
+            .as_ref()
+            .filter(|_| !params.contains_key("baserevid"))
+        {
+            if let Some(id) = revision.id() {
+                params.insert("baserevid".to_string(), id.to_string());
+            }
+            if let Some(timestamp) = revision.timestamp() {
+                params.insert(
+                    "basetimestamp".to_string(),
+                    timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                );
+            }
+        }
+        if let Some(start_timestamp) = &self.start_timestamp {
+            params.insert("starttimestamp".to_string(), start_timestamp.to_string());
+        }
+        params.insert("md5".to_string(), format!("{:x}", md5::compute(&text)));
+        if !params.contains_key("tags") && !api.edit_tags().is_empty() {
+            params.insert("tags".to_string(), api.edit_tags().join("|"));
         }
 
-        if !api.user().user_name().is_empty() {
+        if api.assert_level().is_none() && !api.user().user_name().is_empty() {
             params.insert("assert".to_string(), "user".to_string());
         }
 
         let result = api.post_query_api_json(&params).await?;
         match result["edit"]["result"].as_str() {
             Some("Success") => Ok(()),
-            _ => Err(Box::new(MediaWikiError::EditError(result))),
+            _ => match Conflict::from_response(
+                &result,
+                self.revision.as_ref().and_then(|r| r.id()),
+                self.revision.as_ref().and_then(|r| r.wikitext()),
+                &text,
+            ) {
+                Some(conflict) => Err(MediaWikiError::EditConflict(Box::new(conflict))),
+                None => match Captcha::from_edit_response(&result) {
+                    Some(captcha) => Err(MediaWikiError::Captcha(Box::new(captcha))),
+                    None => Err(MediaWikiError::EditError(result)),
+                },
+            },
         }
     }
 
     /// Performs an "action=query" API action and returns the result.
-    async fn action_query(
+    pub(crate) async fn action_query(
         &self,
         api: &Api,
         additional_params: &[(&str, &str)],
@@ -146,13 +345,13 @@ impl Page {
     }
 
     // From an API result in the form of query/pages, extract a sub-object for each page (should be only one)
-    fn extract_page_properties_from_api_results(
+    pub(crate) fn extract_page_properties_from_api_results(
         &self,
         result: Value,
         subkey: &str,
-    ) -> Result<Vec<Value>, Box<dyn Error>> {
+    ) -> Result<Vec<Value>, MediaWikiError> {
         match result["query"]["pages"].is_null() {
-            true => Err(Box::new(MediaWikiError::Missing(self.title.clone()))),
+            true => Err(MediaWikiError::Missing(self.title.clone())),
             false => match result["query"]["pages"].as_object() {
                 Some(obj) => Ok(obj
                     .iter()
@@ -161,10 +360,10 @@ impl Page {
                         None => vec![],
                     })
                     .collect()),
-                None => Err(Box::new(MediaWikiError::UnexpectedResultFormat(format!(
+                None => Err(MediaWikiError::UnexpectedResultFormat(format!(
                     "{:?}",
                     &result["query"]["pages"]
-                )))),
+                ))),
             },
         }
     }
@@ -179,8 +378,119 @@ impl Page {
             .collect()
     }
 
+    /// Returns the rendered HTML of the current revision via `action=parse`,
+    /// converted to the language `variant` if given (e.g. `zh-hant`).
+    pub async fn html(&self, api: &Api, variant: Option<&str>) -> Result<String, MediaWikiError> {
+        self.html_with_language(api, variant, None).await
+    }
+
+    /// Same as [`Page::html`], but interface messages in the HTML (e.g. "edit" links) are in `language`
+    /// via `uselang`, if given. Both override `Api::set_default_variant` and `Api::set_default_language`.
+    pub async fn html_with_language(
+        &self,
+        api: &Api,
+        variant: Option<&str>,
+        language: Option<&str>,
+    ) -> Result<String, MediaWikiError> {
+        let title = self
+            .title
+            .full_pretty(api)
+            .ok_or_else(|| MediaWikiError::BadTitle(self.title.clone()))?;
+        let mut params = api.params_into(&[
+            ("action", "parse"),
+            ("page", &title),
+            ("prop", "text"),
+            ("formatversion", "2"),
+        ]);
+        if let Some(variant) = variant {
+            params.insert("variant".to_string(), variant.to_string());
+        }
+        if let Some(language) = language {
+            params.insert("uselang".to_string(), language.to_string());
+        }
+        let result = api.get_query_api_json(&params).await?;
+        match result["parse"]["text"].as_str() {
+            Some(html) => Ok(html.to_string()),
+            None => match result["error"]["code"].as_str() {
+                Some("missingtitle") => Err(MediaWikiError::Missing(self.title.clone())),
+                _ => Err(MediaWikiError::BadResponse(result)),
+            },
+        }
+    }
+
+    /// Returns the current revision via the REST API, with its Parsoid HTML (see `RestPage::html`),
+    /// including `data-mw` annotations that allow the HTML to be converted back to wikitext.
+    pub async fn parsoid_html(&self, api: &Api) -> Result<RestPage, MediaWikiError> {
+        api.rest().get_page_with_html(&self.title).await
+    }
+
+    /// Converts `html`, modified from the HTML of `original` as returned by [`Page::parsoid_html`],
+    /// to wikitext via the REST API, changing only the modified parts, and saves it.
+    /// The edit is based on the revision of `original`, so a newer change results in `MediaWikiError::EditConflict`.
+    pub async fn edit_html(
+        &self,
+        api: &Api,
+        original: &RestPage,
+        html: &str,
+        summary: impl Into<String>,
+    ) -> Result<(), MediaWikiError> {
+        let text = api
+            .rest()
+            .html_to_wikitext(&self.title, html, Some(original))
+            .await?;
+        let baserevid = original.latest_revision().map(|id| id.to_string());
+        let mut params = vec![];
+        if let Some(baserevid) = &baserevid {
+            params.push(("baserevid", baserevid.as_str()));
+        }
+        if let Some(timestamp) = original.latest_timestamp() {
+            params.push(("basetimestamp", timestamp));
+        }
+        self.edit_with_params(api, text, summary.into(), &params)
+            .await
+    }
+
+    /// Returns the sections of the current revision via `action=parse&prop=sections`,
+    /// including sections of transcluded pages
+    pub async fn sections(&self, api: &Api) -> Result<Vec<Section>, MediaWikiError> {
+        let title = self
+            .title
+            .full_pretty(api)
+            .ok_or_else(|| MediaWikiError::BadTitle(self.title.clone()))?;
+        let params = api.params_into(&[
+            ("action", "parse"),
+            ("page", &title),
+            ("prop", "sections"),
+            ("formatversion", "2"),
+        ]);
+        let result = api.get_query_api_json(&params).await?;
+        self.sections_from_api_result(&result)
+    }
+
+    fn sections_from_api_result(&self, result: &Value) -> Result<Vec<Section>, MediaWikiError> {
+        match result["parse"]["sections"].as_array() {
+            Some(sections) => Ok(sections.iter().filter_map(Section::from_json).collect()),
+            None => match result["error"]["code"].as_str() {
+                Some("missingtitle") => Err(MediaWikiError::Missing(self.title.clone())),
+                _ => Err(MediaWikiError::BadResponse(result.to_owned())),
+            },
+        }
+    }
+
+    /// Returns the files with the same content as this file page, both local and on shared repositories
+    pub async fn duplicate_files(&self, api: &Api) -> Result<Vec<DuplicateFile>, MediaWikiError> {
+        let result = self
+            .action_query(api, &[("prop", "duplicatefiles"), ("dflimit", "max")])
+            .await?;
+        Ok(self
+            .extract_page_properties_from_api_results(result, "duplicatefiles")?
+            .iter()
+            .filter_map(DuplicateFile::from_json)
+            .collect())
+    }
+
     /// Returns the categories of a page, as a JSON Value Vec
-    pub async fn categories(&self, api: &Api) -> Result<Vec<Value>, Box<dyn Error>> {
+    pub async fn categories(&self, api: &Api) -> Result<Vec<Value>, MediaWikiError> {
         let result = self
             .action_query(
                 api,
@@ -195,7 +505,7 @@ impl Page {
     }
 
     /// Returns the categories of a page, as a JSON Value Vec
-    pub async fn interwiki_links(&self, api: &Api) -> Result<Vec<Value>, Box<dyn Error>> {
+    pub async fn interwiki_links(&self, api: &Api) -> Result<Vec<Value>, MediaWikiError> {
         let result = self
             .action_query(api, &[("prop", "iwlinks"), ("iwlimit", "max")])
             .await?;
@@ -203,7 +513,7 @@ impl Page {
     }
 
     /// Returns the templates of a page, as a Title Vec
-    pub async fn templates(&self, api: &Api) -> Result<Vec<Title>, Box<dyn Error>> {
+    pub async fn templates(&self, api: &Api) -> Result<Vec<Title>, MediaWikiError> {
         let result = self
             .action_query(
                 api,
@@ -219,7 +529,7 @@ impl Page {
     }
 
     /// Returns the wiki-internal links on a page, as a Title Vec
-    pub async fn links(&self, api: &Api) -> Result<Vec<Title>, Box<dyn Error>> {
+    pub async fn links(&self, api: &Api) -> Result<Vec<Title>, MediaWikiError> {
         let result = self
             .action_query(
                 api,
@@ -236,7 +546,7 @@ impl Page {
         api: &Api,
         direct_links: bool,
         redirects: bool,
-    ) -> Result<Vec<Title>, Box<dyn Error>> {
+    ) -> Result<Vec<Title>, MediaWikiError> {
         let lhshow = match (direct_links, redirects) {
             (true, true) => "!redirect|redirect",
             (true, false) => "!redirect",
@@ -259,7 +569,7 @@ impl Page {
     }
 
     /// Returns the images used on a page, as a Title Vec
-    pub async fn images(&self, api: &Api) -> Result<Vec<Title>, Box<dyn Error>> {
+    pub async fn images(&self, api: &Api) -> Result<Vec<Title>, MediaWikiError> {
         let result = self
             .action_query(api, &[("prop", "images"), ("imlimit", "max")])
             .await?;
@@ -268,7 +578,7 @@ impl Page {
     }
 
     /// Returns the coordinates of a page, as a JSON Value Vec
-    pub async fn coordinates(&self, api: &Api) -> Result<Vec<Value>, Box<dyn Error>> {
+    pub async fn coordinates(&self, api: &Api) -> Result<Vec<Value>, MediaWikiError> {
         self.extract_page_properties_from_api_results(
             self.action_query(
                 api,
@@ -290,7 +600,7 @@ impl Page {
         api: &Api,
         lat: f64,
         lon: f64,
-    ) -> Result<Vec<Value>, Box<dyn Error>> {
+    ) -> Result<Vec<Value>, MediaWikiError> {
         self.extract_page_properties_from_api_results(
             self.action_query(
                 api,
@@ -308,7 +618,7 @@ impl Page {
     }
 
     /// Returns the external links of a page, as a String Vec
-    pub async fn external_links(&self, api: &Api) -> Result<Vec<String>, Box<dyn Error>> {
+    pub async fn external_links(&self, api: &Api) -> Result<Vec<String>, MediaWikiError> {
         let result = self
             .action_query(api, &[("prop", "extlinks"), ("ellimit", "max")])
             .await?;
@@ -320,6 +630,132 @@ impl Page {
             .collect())
     }
 
+    /// Turns this `Page` into a redirect to `target`, using the redirect magic word
+    /// of the wiki's content language (e.g. `#WEITERLEITUNG` on German wikis).
+    ///
+    /// # Errors
+    /// May return any error from [`Page::edit_text`].
+    pub async fn create_redirect(
+        &self,
+        api: &Api,
+        target: &Title,
+        reason: impl Into<String>,
+    ) -> Result<(), MediaWikiError> {
+        let target = target
+            .full_pretty(api)
+            .ok_or_else(|| MediaWikiError::BadTitle(target.clone()))?;
+        let magic_word = api
+            .get_magic_word_aliases("redirect")
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_else(|| "#REDIRECT".to_string());
+        let text = format!("{} [[{}]]", magic_word, target);
+        self.edit_text(api, text, reason).await
+    }
+
+    /// Checks if this page is a disambiguation page, based on the `disambiguation` page property.
+    pub async fn is_disambiguation(&self, api: &Api) -> Result<bool, MediaWikiError> {
+        let result = self
+            .action_query(api, &[("prop", "pageprops"), ("ppprop", "disambiguation")])
+            .await?;
+        match result["query"]["pages"].as_object() {
+            Some(pages) => Ok(pages
+                .values()
+                .any(|page| !page["pageprops"]["disambiguation"].is_null())),
+            None => Err(MediaWikiError::Missing(self.title.clone())),
+        }
+    }
+
+    /// Returns a summary of the page via TextExtracts (`prop=extracts`), as plain text or limited HTML.
+    pub async fn extract(
+        &self,
+        api: &Api,
+        options: &ExtractOptions,
+    ) -> Result<String, MediaWikiError> {
+        let params = options.params();
+        let params: Vec<(&str, &str)> = params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let result = self.action_query(api, &params).await?;
+        self.extract_from_api_result(&result)
+    }
+
+    fn extract_from_api_result(&self, result: &Value) -> Result<String, MediaWikiError> {
+        result["query"]["pages"]
+            .as_object()
+            .and_then(|pages| pages.values().next())
+            .filter(|page| page["missing"].is_null())
+            .and_then(|page| page["extract"].as_str())
+            .map(|s| s.trim().to_string())
+            .ok_or_else(|| MediaWikiError::Missing(self.title.clone()))
+    }
+
+    /// Returns the current protections of this page, via `prop=info&inprop=protection`.
+    pub async fn protection_status(&self, api: &Api) -> Result<Vec<Protection>, MediaWikiError> {
+        let result = self
+            .action_query(api, &[("prop", "info"), ("inprop", "protection")])
+            .await?;
+        self.protection_from_api_result(&result)
+    }
+
+    fn protection_from_api_result(
+        &self,
+        result: &Value,
+    ) -> Result<Vec<Protection>, MediaWikiError> {
+        let page = result["query"]["pages"]
+            .as_object()
+            .and_then(|pages| pages.values().next())
+            .ok_or_else(|| MediaWikiError::Missing(self.title.clone()))?;
+        Ok(page["protection"]
+            .as_array()
+            .map(|arr| arr.iter().filter_map(Protection::from_json).collect())
+            .unwrap_or_default())
+    }
+
+    /// Sets the protections of this page via `action=protect`. Protections without their own expiry
+    /// use `expiry` (a timestamp or relative time like `1 week`), or never expire.
+    /// Protection types not listed are removed.
+    pub async fn protect(
+        &self,
+        api: &Api,
+        protections: &[Protection],
+        expiry: Option<&str>,
+        reason: impl Into<String>,
+    ) -> Result<(), MediaWikiError> {
+        let title = self
+            .title
+            .full_pretty(api)
+            .ok_or_else(|| MediaWikiError::BadTitle(self.title.clone()))?;
+        let (protections, expiry) = protect_params(protections, expiry);
+        let token = api.get_edit_token().await?;
+        let params = api.params_into(&[
+            ("action", "protect"),
+            ("title", &title),
+            ("protections", &protections),
+            ("expiry", &expiry),
+            ("reason", &reason.into()),
+            ("token", &token),
+        ]);
+        let result = api.post_query_api_json(&params).await?;
+        match result["protect"].is_object() {
+            true => Ok(()),
+            false => Err(MediaWikiError::EditError(result)),
+        }
+    }
+
+    /// Adds this page to the watchlist of the current user.
+    pub async fn watch(&self, api: &Api) -> Result<(), MediaWikiError> {
+        api.watch_batch(std::slice::from_ref(&self.title), None)
+            .await
+    }
+
+    /// Removes this page from the watchlist of the current user.
+    pub async fn unwatch(&self, api: &Api) -> Result<(), MediaWikiError> {
+        api.unwatch_batch(std::slice::from_ref(&self.title)).await
+    }
+
     /// Returns the page ID (usually set after some API operation).
     pub fn page_id(&self) -> Option<usize> {
         self.page_id
@@ -330,23 +766,20 @@ impl Page {
         self.revision.as_ref()
     }
 
+    /// Returns the server time at which the text was loaded by [`Page::text`].
+    pub fn start_timestamp(&self) -> Option<&str> {
+        self.start_timestamp.as_deref()
+    }
+
     /*
     TODO for action=query:
-    extracts
-    fileusage
-    globalusage
     imageinfo
     images
-    info
-    langlinks
     linkshere
     pageimages
-    pageprops
     pageterms
-    pageviews
     redirects
     revisions
-    transcludedin
     wbentityusage
     */
 }
@@ -362,6 +795,81 @@ mod tests {
             .unwrap()
     }
 
+    #[test]
+    fn from_api_result() {
+        let page = Page::from_api_result(&json!({"pageid":1,"ns":0,"title":"Main Page","revisions":[{"revid":5,"slots":{"main":{"content":"Hello"}}}]})).unwrap();
+        assert_eq!(page.title(), &Title::new("Main Page", 0));
+        assert_eq!(page.page_id(), Some(1));
+        assert_eq!(page.text_cached(), Some("Hello"));
+
+        let page =
+            Page::from_api_result(&json!({"pageid":2,"ns":4,"title":"Project:Sandbox"})).unwrap();
+        assert_eq!(page.title(), &Title::new("Sandbox", 4));
+        assert_eq!(page.text_cached(), None);
+
+        assert!(Page::from_api_result(&json!({"ns":0,"title":"Nope","missing":true})).is_err());
+        assert!(Page::from_api_result(&json!({"ns":0,"title":"Nope","missing":""})).is_err());
+    }
+
+    #[test]
+    fn sections_from_api_result() {
+        let page = Page::new(Title::new("Talk:Foo", 1));
+        let result = json!({"parse":{"title":"Talk:Foo","pageid":1,"sections":[
+            {"toclevel":1,"level":"2","line":"Question","number":"1","index":"1","fromtitle":"Talk:Foo","byteoffset":0,"anchor":"Question"},
+            {"toclevel":1,"level":"2","line":"Reply","number":"2","index":"2","fromtitle":"Talk:Foo","byteoffset":120,"anchor":"Reply"}]}});
+        let sections = page.sections_from_api_result(&result).unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[1].byte_offset(), Some(120));
+        assert!(matches!(
+            page.sections_from_api_result(&json!({"error":{"code":"missingtitle"}})),
+            Err(MediaWikiError::Missing(_))
+        ));
+    }
+
+    #[test]
+    fn protection_from_api_result() {
+        let page = Page::new(Title::new("Main Page", 0));
+        let result = json!({"query":{"pages":{"1":{"title":"Main Page","protection":[{"type":"edit","level":"sysop","expiry":"infinity"}]}}}});
+        let protections = page.protection_from_api_result(&result).unwrap();
+        assert_eq!(
+            protections,
+            vec![Protection::from_json(
+                &json!({"type":"edit","level":"sysop","expiry":"infinity"})
+            )
+            .unwrap()]
+        );
+        assert!(page.protection_from_api_result(&json!({})).is_err());
+    }
+
+    #[test]
+    fn extract_options_params() {
+        assert_eq!(
+            ExtractOptions::new().params(),
+            vec![("prop".to_string(), "extracts".to_string())]
+        );
+        let mut options = ExtractOptions::new();
+        options.set_intro(true);
+        options.set_plain_text(true);
+        options.set_sentences(Some(2));
+        let params: HashMap<String, String> = options.params().into_iter().collect();
+        assert_eq!(params["exintro"], "1");
+        assert_eq!(params["explaintext"], "1");
+        assert_eq!(params["exsentences"], "2");
+        assert!(!params.contains_key("exchars"));
+    }
+
+    #[test]
+    fn extract_from_api_result() {
+        let page = Page::new(Title::new("Berlin", 0));
+        let result = json!({"query":{"pages":{"3354":{"pageid":3354,"ns":0,"title":"Berlin","extract":"Berlin is the capital of Germany.\n"}}}});
+        assert_eq!(
+            page.extract_from_api_result(&result).unwrap(),
+            "Berlin is the capital of Germany."
+        );
+        let result = json!({"query":{"pages":{"-1":{"ns":0,"title":"Berlin","missing":""}}}});
+        assert!(page.extract_from_api_result(&result).is_err());
+    }
+
     #[tokio::test]
     async fn page_text_main_page_nonempty() {
         let mut page = Page::new(Title::new("Main Page", 4));
@@ -449,4 +957,236 @@ mod tests {
         // println!("{:?}", &result);
         assert!(result.contains(&json!({"prefix":"mw","*":"Wikidata_query_service/User_Manual"})));
     }
+
+    fn mocked_rest_page(responses: &mut crate::mock::MockResponses) {
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"general":{},"namespaces":{"0":{"id":0,"name":"","canonical":""}},"namespacealiases":[],"libraries":[],"extensions":[],"statistics":{}}}),
+            )
+            .add_rest(
+                "GET",
+                "/page/Foo/with_html?stash=true",
+                200,
+                &[("etag", "W/\"5/abc\"")],
+                json!({"id":1,"key":"Foo","title":"Foo","latest":{"id":5,"timestamp":"2024-01-31T12:00:00Z"},
+                    "content_model":"wikitext","html":"<p>Hello</p>"}),
+            );
+    }
+
+    #[tokio::test]
+    async fn parsoid_html() {
+        let mut responses = crate::mock::MockResponses::new();
+        mocked_rest_page(&mut responses);
+        let api = Api::new_mocked(responses).unwrap();
+        let page = Page::new(Title::new("Foo", 0));
+        let original = page.parsoid_html(&api).await.unwrap();
+        assert_eq!(original.html(), Some("<p>Hello</p>"));
+        assert_eq!(original.latest_revision(), Some(5));
+        assert_eq!(original.etag(), Some("W/\"5/abc\""));
+    }
+
+    #[tokio::test]
+    async fn edit_html() {
+        let mut responses = crate::mock::MockResponses::new();
+        mocked_rest_page(&mut responses);
+        responses
+            .add_rest(
+                "POST",
+                "/transform/html/to/wikitext/Foo/5",
+                200,
+                &[],
+                json!("Hello [[world]]"),
+            )
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "edit")],
+                json!({"edit":{"result":"Success","newrevid":6}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let page = Page::new(Title::new("Foo", 0));
+        let original = page.parsoid_html(&api).await.unwrap();
+        page.edit_html(&api, &original, "<p>Hello <a>world</a></p>", "Link")
+            .await
+            .unwrap();
+
+        let requests = responses.requests();
+        let transform = requests
+            .iter()
+            .find(|r| r.get("route").is_some_and(|r| r.starts_with("POST")))
+            .unwrap();
+        assert_eq!(transform["if-match"], "W/\"5/abc\"");
+        assert_eq!(transform["original.revid"], "5");
+        assert_eq!(transform["original.html.body"], "<p>Hello</p>");
+        assert_eq!(transform["html"], "<p>Hello <a>world</a></p>");
+        let edit = requests.last().unwrap();
+        assert_eq!(edit["text"], "Hello [[world]]");
+        assert_eq!(edit["baserevid"], "5");
+        assert_eq!(edit["basetimestamp"], "2024-01-31T12:00:00Z");
+    }
+
+    #[tokio::test]
+    async fn language_and_variant() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"general":{},"namespaces":{"0":{"id":0,"name":"","canonical":""}},"namespacealiases":[],"libraries":[],"extensions":[],"statistics":{}}}),
+            )
+            .add(
+                &[("action", "parse")],
+                json!({"parse":{"title":"Foo","pageid":1,"text":"<p>Hallo</p>"}}),
+            )
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "edit")],
+                json!({"edit":{"result":"Success","newrevid":6}}),
+            );
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        api.set_default_language(Some("de"));
+        api.set_default_variant(Some("zh-hans"));
+        assert_eq!(api.default_language(), Some("de"));
+        assert_eq!(api.default_variant(), Some("zh-hans"));
+        let page = Page::new(Title::new("Foo", 0));
+
+        assert_eq!(page.html(&api, None).await.unwrap(), "<p>Hallo</p>");
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["uselang"], "de");
+        assert_eq!(request["variant"], "zh-hans");
+
+        page.html_with_language(&api, Some("zh-hant"), Some("en"))
+            .await
+            .unwrap();
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["uselang"], "en");
+        assert_eq!(request["variant"], "zh-hant");
+
+        page.edit_text(&api, "Text", "Summary").await.unwrap();
+        assert_eq!(responses.requests().pop().unwrap()["uselang"], "de");
+        page.edit_text_with_language(&api, "Text", "Summary", "en")
+            .await
+            .unwrap();
+        assert_eq!(responses.requests().pop().unwrap()["uselang"], "en");
+
+        api.set_default_language(None);
+        api.set_default_variant(None);
+        page.html(&api, None).await.unwrap();
+        let request = responses.requests().pop().unwrap();
+        assert!(!request.contains_key("uselang"));
+        assert!(!request.contains_key("variant"));
+    }
+
+    #[tokio::test]
+    async fn edit_text_sends_base_revision() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"general":{},"namespaces":{"0":{"id":0,"name":"","canonical":""}},"namespacealiases":[],"libraries":[],"extensions":[],"statistics":{}}}),
+            )
+            .add(
+                &[("prop", "revisions")],
+                json!({"curtimestamp":"2024-02-01T00:00:00Z","query":{"pages":[{"pageid":1,"ns":0,"title":"Foo",
+                    "revisions":[{"revid":5,"timestamp":"2024-01-31T12:00:00Z","slots":{"main":{"content":"Old"}}}]}]}}),
+            )
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "edit")],
+                json!({"edit":{"result":"Success","newrevid":6}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut page = Page::new(Title::new("Foo", 0));
+        assert_eq!(page.text(&api).await.unwrap(), "Old");
+        page.edit_text(&api, "New", "Summary").await.unwrap();
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["baserevid"], "5");
+        assert_eq!(request["basetimestamp"], "2024-01-31T12:00:00Z");
+        assert_eq!(request["starttimestamp"], "2024-02-01T00:00:00Z");
+        assert_eq!(request["md5"], format!("{:x}", md5::compute("New")));
+    }
+
+    fn redirect_responses(magic_words: Value) -> crate::mock::MockResponses {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(&[("siprop", "magicwords")], magic_words)
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"general":{},"namespaces":{"0":{"id":0,"name":"","canonical":""}},"namespacealiases":[],"libraries":[],"extensions":[],"statistics":{}}}),
+            )
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "edit")],
+                json!({"edit":{"result":"Success","newrevid":6}}),
+            );
+        responses
+    }
+
+    #[tokio::test]
+    async fn create_redirect() {
+        let responses = redirect_responses(
+            json!({"query":{"magicwords":[{"name":"redirect","aliases":["#WEITERLEITUNG","#REDIRECT"]}]}}),
+        );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let page = Page::new(Title::new("Foo", 0));
+        page.create_redirect(&api, &Title::new("Bar baz", 0), "Redirect")
+            .await
+            .unwrap();
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["action"], "edit");
+        assert_eq!(request["title"], "Foo");
+        assert_eq!(request["text"], "#WEITERLEITUNG [[Bar baz]]");
+
+        // Falls back to the English magic word if the site does not list any
+        let responses = redirect_responses(json!({"query":{"magicwords":[]}}));
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        page.create_redirect(&api, &Title::new("Bar", 0), "Redirect")
+            .await
+            .unwrap();
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["text"], "#REDIRECT [[Bar]]");
+    }
+
+    #[tokio::test]
+    async fn is_disambiguation() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"general":{},"namespaces":{"0":{"id":0,"name":"","canonical":""}},"namespacealiases":[],"libraries":[],"extensions":[],"statistics":{}}}),
+            )
+            .add(
+                &[("ppprop", "disambiguation"), ("titles", "Mercury")],
+                json!({"query":{"pages":{"19":{"pageid":19,"ns":0,"title":"Mercury","pageprops":{"disambiguation":""}}}}}),
+            )
+            .add(
+                &[("ppprop", "disambiguation"), ("titles", "Venus")],
+                json!({"query":{"pages":{"32":{"pageid":32,"ns":0,"title":"Venus"}}}}),
+            )
+            .add(
+                &[("ppprop", "disambiguation")],
+                json!({"batchcomplete":""}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let page = |title: &str| Page::new(Title::new(title, 0));
+        assert!(page("Mercury").is_disambiguation(&api).await.unwrap());
+        assert!(!page("Venus").is_disambiguation(&api).await.unwrap());
+        assert!(matches!(
+            page("Nowhere").is_disambiguation(&api).await,
+            Err(MediaWikiError::Missing(_))
+        ));
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["prop"], "pageprops");
+    }
 }
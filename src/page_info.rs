@@ -0,0 +1,172 @@
+/*!
+The `PageInfo` class holds basic information about a page, as returned by `prop=info`.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::Page;
+use crate::protection::Protection;
+use serde_json::Value;
+
+/// Returns true for a flag that is set, either `""` (format version 1) or `true` (format version 2)
+fn is_flag_set(v: &Value) -> bool {
+    match v {
+        Value::Bool(b) => *b,
+        Value::Null => false,
+        _ => true,
+    }
+}
+
+/// Basic information about a page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageInfo {
+    title: String,
+    namespace_id: NamespaceID,
+    page_id: Option<u64>,
+    missing: bool,
+    length: Option<u64>,
+    touched: Option<String>,
+    last_rev_id: Option<u64>,
+    redirect: bool,
+    protection: Vec<Protection>,
+    content_model: Option<String>,
+}
+
+impl PageInfo {
+    /// Creates the info from an entry of `query.pages`, with `prop=info&inprop=protection`
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            title: j["title"].as_str()?.to_string(),
+            namespace_id: j["ns"].as_i64().unwrap_or_default(),
+            page_id: j["pageid"].as_u64(),
+            missing: is_flag_set(&j["missing"]),
+            length: j["length"].as_u64(),
+            touched: j["touched"].as_str().map(|s| s.to_string()),
+            last_rev_id: j["lastrevid"].as_u64(),
+            redirect: is_flag_set(&j["redirect"]),
+            protection: j["protection"]
+                .as_array()
+                .map(|arr| arr.iter().filter_map(Protection::from_json).collect())
+                .unwrap_or_default(),
+            content_model: j["contentmodel"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    /// Returns the full title, as normalized by the API
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Returns the namespace ID
+    pub fn namespace_id(&self) -> NamespaceID {
+        self.namespace_id
+    }
+
+    /// Returns the page ID, or `None` if the page does not exist
+    pub fn page_id(&self) -> Option<u64> {
+        self.page_id
+    }
+
+    /// Returns true if the page exists
+    pub fn exists(&self) -> bool {
+        !self.missing
+    }
+
+    /// Returns the length of the current revision, in bytes
+    pub fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    /// Returns the timestamp the page was last touched, e.g. by an edit or a re-render
+    pub fn touched(&self) -> Option<&str> {
+        self.touched.as_deref()
+    }
+
+    /// Returns the ID of the current revision
+    pub fn last_rev_id(&self) -> Option<u64> {
+        self.last_rev_id
+    }
+
+    /// Returns true if the page is a redirect
+    pub fn is_redirect(&self) -> bool {
+        self.redirect
+    }
+
+    /// Returns the current protections; also set for protected titles that do not exist
+    pub fn protection(&self) -> &[Protection] {
+        &self.protection
+    }
+
+    /// Returns the content model, e.g. `wikitext`
+    pub fn content_model(&self) -> Option<&str> {
+        self.content_model.as_deref()
+    }
+}
+
+impl Page {
+    /// Returns information about this page, via `prop=info&inprop=protection`.
+    /// Also succeeds for pages that do not exist; see `PageInfo::exists`.
+    pub async fn info(&self, api: &Api) -> Result<PageInfo, MediaWikiError> {
+        let result = self
+            .action_query(api, &[("prop", "info"), ("inprop", "protection")])
+            .await?;
+        self.info_from_api_result(&result)
+    }
+
+    /// Checks if this page exists
+    pub async fn exists(&self, api: &Api) -> Result<bool, MediaWikiError> {
+        Ok(self.info(api).await?.exists())
+    }
+
+    fn info_from_api_result(&self, result: &Value) -> Result<PageInfo, MediaWikiError> {
+        let page = result["query"]["pages"]
+            .as_object()
+            .and_then(|pages| pages.values().next())
+            .ok_or_else(|| MediaWikiError::Missing(self.title().clone()))?;
+        if is_flag_set(&page["invalid"]) {
+            return Err(MediaWikiError::BadTitle(self.title().clone()));
+        }
+        PageInfo::from_json(page).ok_or_else(|| MediaWikiError::BadResponse(result.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+    use crate::title::Title;
+
+    #[tokio::test]
+    async fn info() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"namespaces":{"0":{"id":0,"case":"first-letter","*":""}}}}),
+            )
+            .add(
+                &[("titles", "Foo")],
+                json!({"batchcomplete":"","query":{"pages":{"123":{"pageid":123,"ns":0,"title":"Foo",
+                    "contentmodel":"wikitext","pagelanguage":"en","touched":"2024-01-02T03:04:05Z",
+                    "lastrevid":456,"length":789,"redirect":"",
+                    "protection":[{"type":"edit","level":"sysop","expiry":"infinity"}]}}}}),
+            )
+            .add(
+                &[("titles", "Bar")],
+                json!({"query":{"pages":{"-1":{"ns":0,"title":"Bar","missing":"","contentmodel":"wikitext","protection":[]}}}}),
+            );
+        let api = Api::new_mocked(responses).unwrap();
+        let info = Page::new(Title::new("Foo", 0)).info(&api).await.unwrap();
+        assert!(info.exists());
+        assert!(info.is_redirect());
+        assert_eq!(info.page_id(), Some(123));
+        assert_eq!(info.length(), Some(789));
+        assert_eq!(info.last_rev_id(), Some(456));
+        assert_eq!(info.touched(), Some("2024-01-02T03:04:05Z"));
+        assert_eq!(info.content_model(), Some("wikitext"));
+        assert_eq!(info.protection()[0].level(), "sysop");
+        assert!(!Page::new(Title::new("Bar", 0)).exists(&api).await.unwrap());
+    }
+}
@@ -0,0 +1,200 @@
+/*!
+The `page_props` module lists page property names, the pages that have a given page property,
+and the page properties and page views of a page.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::Page;
+use crate::title::Title;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// A page with a page property, as returned by `list=pageswithprop`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageWithProp {
+    title: Title,
+    page_id: u64,
+    value: Option<String>,
+}
+
+impl PageWithProp {
+    /// Creates a new entry from a `list=pageswithprop` result entry
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            title: Title::new_from_api_result(j),
+            page_id: j["pageid"].as_u64()?,
+            value: j["value"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    /// Returns the title of the page
+    pub fn title(&self) -> &Title {
+        &self.title
+    }
+
+    /// Returns the page ID
+    pub fn page_id(&self) -> u64 {
+        self.page_id
+    }
+
+    /// Returns the value of the page property; empty for flags such as `noindex`
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+}
+
+impl Api {
+    /// Returns the names of all page properties in use on the wiki, via `list=pagepropnames`
+    pub async fn page_prop_names(&self) -> Result<Vec<String>, MediaWikiError> {
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("list", "pagepropnames"),
+            ("ppnlimit", "max"),
+        ]);
+        let result = self.get_query_api_json_all(&params).await?;
+        Ok(result["query"]["pagepropnames"]
+            .as_array()
+            .map(|arr| {
+                arr.iter()
+                    .filter_map(|v| v["propname"].as_str())
+                    .map(|s| s.to_string())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Returns a stream of pages that have the page property `propname` (e.g. `noindex`),
+    /// via `list=pageswithprop`. As the API does not filter by namespace, `namespaces` is applied
+    /// to the results; an empty slice means all namespaces.
+    pub async fn pages_with_prop<'a>(
+        &'a self,
+        propname: &str,
+        namespaces: &'a [NamespaceID],
+    ) -> impl Stream<Item = Result<PageWithProp, MediaWikiError>> + 'a {
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("list", "pageswithprop"),
+            ("pwppropname", propname),
+            ("pwpprop", "ids|title|value"),
+            ("pwplimit", "max"),
+        ]);
+        self.get_query_api_list_iter(&params, "pageswithprop", PageWithProp::from_json)
+            .await
+            .filter(move |result| {
+                let keep = match result {
+                    Ok(page) => {
+                        namespaces.is_empty() || namespaces.contains(&page.title().namespace_id())
+                    }
+                    Err(_) => true,
+                };
+                futures::future::ready(keep)
+            })
+    }
+}
+
+impl Page {
+    /// Returns the page properties of this page (e.g. `wikibase_item`, `defaultsort`), via `prop=pageprops`.
+    /// Flags such as `noindex` have an empty value.
+    pub async fn pageprops(&self, api: &Api) -> Result<HashMap<String, String>, MediaWikiError> {
+        let result = self.action_query(api, &[("prop", "pageprops")]).await?;
+        let page = self.first_page_from_api_result(&result)?;
+        Ok(page["pageprops"]
+            .as_object()
+            .map(|props| {
+                props
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.as_str().unwrap_or_default().to_string()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Returns the daily page views of the last `days` days (at most 60), via `prop=pageviews`.
+    /// Keys are dates like `2024-01-31`; days without data have `None`.
+    pub async fn pageviews(
+        &self,
+        api: &Api,
+        days: u32,
+    ) -> Result<BTreeMap<String, Option<u64>>, MediaWikiError> {
+        let days = days.to_string();
+        let result = self
+            .action_query(api, &[("prop", "pageviews"), ("pvipdays", &days)])
+            .await?;
+        let page = self.first_page_from_api_result(&result)?;
+        Ok(page["pageviews"]
+            .as_object()
+            .map(|views| {
+                views
+                    .iter()
+                    .map(|(date, count)| (date.to_string(), count.as_u64()))
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Returns the only page of a `query.pages` result, or `Missing` if the page does not exist
+    fn first_page_from_api_result<'a>(
+        &self,
+        result: &'a Value,
+    ) -> Result<&'a Value, MediaWikiError> {
+        result["query"]["pages"]
+            .as_object()
+            .and_then(|pages| pages.values().next())
+            .filter(|page| page["missing"].is_null())
+            .ok_or_else(|| MediaWikiError::Missing(self.title().clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_with_prop_from_json() {
+        let page = PageWithProp::from_json(
+            &json!({"pageid":12,"ns":10,"title":"Template:Foo","value":"Q42"}),
+        )
+        .unwrap();
+        assert_eq!(page.title(), &Title::new("Foo", 10));
+        assert_eq!(page.page_id(), 12);
+        assert_eq!(page.value(), Some("Q42"));
+        assert!(PageWithProp::from_json(&json!({"ns":0,"title":"Foo"})).is_none());
+    }
+
+    #[tokio::test]
+    async fn pageprops_and_pageviews() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"namespaces":{"0":{"id":0,"case":"first-letter","*":""}}}}),
+            )
+            .add(
+                &[("prop", "pageprops")],
+                json!({"query":{"pages":{"1":{"pageid":1,"ns":0,"title":"Foo",
+                    "pageprops":{"wikibase_item":"Q42","noindex":""}}}}}),
+            )
+            .add(
+                &[("prop", "pageviews"), ("pvipdays", "2")],
+                json!({"query":{"pages":{"1":{"pageid":1,"ns":0,"title":"Foo",
+                    "pageviews":{"2024-01-30":17,"2024-01-31":null}}}}}),
+            );
+        let api = Api::new_mocked(responses).unwrap();
+        let page = Page::new(Title::new("Foo", 0));
+        let props = page.pageprops(&api).await.unwrap();
+        assert_eq!(props["wikibase_item"], "Q42");
+        assert_eq!(props["noindex"], "");
+        let views = page.pageviews(&api, 2).await.unwrap();
+        assert_eq!(
+            views.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("2024-01-30".to_string(), Some(17)),
+                ("2024-01-31".to_string(), None)
+            ]
+        );
+    }
+}
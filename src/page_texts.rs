@@ -0,0 +1,149 @@
+/*!
+The `page_texts` module fetches the wikitext of many pages in batches.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::Page;
+use crate::title::Title;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Number of titles per request; the API limit for `rvprop=content` on multiple pages
+const PAGE_TEXTS_BATCH_SIZE: usize = 50;
+
+impl Api {
+    /// Fetches the current wikitext of many pages, 50 titles per request.
+    /// Each title maps to its text, or to an error, e.g. `MediaWikiError::Missing` for a missing page.
+    pub async fn get_pages_text(
+        &self,
+        titles: &[Title],
+    ) -> Result<HashMap<Title, Result<String, MediaWikiError>>, MediaWikiError> {
+        let mut ret = HashMap::new();
+        let mut sent: HashMap<String, Title> = HashMap::new();
+        for title in titles {
+            match title.full_with_underscores(self) {
+                Some(full) => {
+                    sent.insert(full, title.to_owned());
+                }
+                None => {
+                    ret.insert(
+                        title.to_owned(),
+                        Err(MediaWikiError::BadTitle(title.to_owned())),
+                    );
+                }
+            }
+        }
+        let sent: Vec<(String, Title)> = sent.into_iter().collect();
+        for chunk in sent.chunks(PAGE_TEXTS_BATCH_SIZE) {
+            let titles_param = chunk
+                .iter()
+                .map(|(full, _)| full.as_str())
+                .collect::<Vec<&str>>()
+                .join("|");
+            let params = self.params_into(&[
+                ("action", "query"),
+                ("prop", "revisions"),
+                ("titles", &titles_param),
+                ("rvslots", "main"),
+                ("rvprop", crate::revision::RVPROP),
+                ("formatversion", "2"),
+            ]);
+            let result = self.get_query_api_json(&params).await?;
+            if !result["error"].is_null() {
+                return Err(MediaWikiError::BadResponse(result));
+            }
+            ret.extend(Self::pages_text_from_api_result(chunk, &result));
+        }
+        Ok(ret)
+    }
+
+    /// Maps each requested title to its text in the API result
+    fn pages_text_from_api_result(
+        sent: &[(String, Title)],
+        result: &Value,
+    ) -> HashMap<Title, Result<String, MediaWikiError>> {
+        // The API reports pages under their normalized titles
+        let normalized: HashMap<String, String> = result["query"]["normalized"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|n| {
+                let from = Title::spaces_to_underscores(n["from"].as_str()?);
+                let to = Title::spaces_to_underscores(n["to"].as_str()?);
+                Some((to, from))
+            })
+            .collect();
+        let sent: HashMap<&str, &Title> = sent.iter().map(|(k, v)| (k.as_str(), v)).collect();
+        let mut ret = HashMap::new();
+        for page in result["query"]["pages"].as_array().into_iter().flatten() {
+            let key = Title::spaces_to_underscores(page["title"].as_str().unwrap_or_default());
+            let key = normalized.get(&key).unwrap_or(&key);
+            let title = match sent.get(key.as_str()) {
+                Some(title) => (*title).to_owned(),
+                None => continue,
+            };
+            let text = if page["invalid"].as_bool() == Some(true) {
+                Err(MediaWikiError::BadTitle(title.clone()))
+            } else {
+                Page::from_api_result(page)
+                    .map_err(|_| MediaWikiError::Missing(title.clone()))
+                    .and_then(|p| {
+                        p.text_cached()
+                            .map(|s| s.to_string())
+                            .ok_or_else(|| MediaWikiError::BadResponse(page.to_owned()))
+                    })
+            };
+            ret.insert(title, text);
+        }
+        for title in sent.values() {
+            if !ret.contains_key(*title) {
+                ret.insert(
+                    (*title).to_owned(),
+                    Err(MediaWikiError::BadResponse(result.to_owned())),
+                );
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_text_from_api_result() {
+        let sent = vec![
+            ("Main_Page".to_string(), Title::new("Main Page", 0)),
+            ("foo".to_string(), Title::new("foo", 0)),
+            ("Nope".to_string(), Title::new("Nope", 0)),
+            ("Lost".to_string(), Title::new("Lost", 0)),
+        ];
+        let result = json!({"query":{
+            "normalized":[{"fromencoded":false,"from":"foo","to":"Foo"}],
+            "pages":[
+                {"pageid":1,"ns":0,"title":"Main Page","revisions":[{"revid":5,"slots":{"main":{"content":"Hello"}}}]},
+                {"pageid":2,"ns":0,"title":"Foo","revisions":[{"revid":6,"slots":{"main":{"content":"Bar"}}}]},
+                {"ns":0,"title":"Nope","missing":true}
+            ]
+        }});
+        let texts = Api::pages_text_from_api_result(&sent, &result);
+        assert_eq!(texts.len(), 4);
+        assert_eq!(
+            texts[&Title::new("Main Page", 0)].as_deref().unwrap(),
+            "Hello"
+        );
+        assert_eq!(texts[&Title::new("foo", 0)].as_deref().unwrap(), "Bar");
+        assert!(matches!(
+            texts[&Title::new("Nope", 0)],
+            Err(MediaWikiError::Missing(_))
+        ));
+        assert!(matches!(
+            texts[&Title::new("Lost", 0)],
+            Err(MediaWikiError::BadResponse(_))
+        ));
+    }
+}
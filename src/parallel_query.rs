@@ -0,0 +1,136 @@
+/*!
+The `parallel_query` module runs a query as several partitions concurrently, each with its own continuation,
+and merges the results; e.g. `list=allpages` split into title ranges, or `prop` queries split into batches of titles.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::multi_value::multi_value_batches;
+use futures::{StreamExt, TryStreamExt};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Returns partitions for the title ranges between `boundaries`, e.g. with `apfrom` and `apto` for `list=allpages`.
+/// The first partition starts at the beginning, and the last one runs to the end.
+/// Boundaries must be sorted in the order the API lists titles.
+pub fn range_partitions(
+    from_key: &str,
+    to_key: &str,
+    boundaries: &[&str],
+) -> Vec<HashMap<String, String>> {
+    (0..=boundaries.len())
+        .map(|i| {
+            let mut partition = HashMap::new();
+            if i > 0 {
+                partition.insert(from_key.to_string(), boundaries[i - 1].to_string());
+            }
+            if let Some(to) = boundaries.get(i) {
+                partition.insert(to_key.to_string(), to.to_string());
+            }
+            partition
+        })
+        .collect()
+}
+
+/// Returns partitions of the `titles` parameter, with up to `batch_size` titles each
+pub fn title_partitions(titles: &[String], batch_size: usize) -> Vec<HashMap<String, String>> {
+    multi_value_batches(titles, batch_size)
+        .into_iter()
+        .map(|batch| HashMap::from([("titles".to_string(), batch)]))
+        .collect()
+}
+
+/// Removes consecutive duplicate entries from the lists in `["query"]`.
+/// MediaWiki includes both ends of a range, so a title at a boundary is returned by two adjacent partitions.
+fn dedup_query_lists(result: &mut Value) {
+    if let Some(query) = result["query"].as_object_mut() {
+        for list in query.values_mut().filter_map(|v| v.as_array_mut()) {
+            list.dedup();
+        }
+    }
+}
+
+impl Api {
+    /// Runs the query once per partition, with the partition parameters added to `params`,
+    /// at most `concurrency` at a time. Each partition follows its own continuation;
+    /// the results are merged in partition order.
+    pub async fn get_query_parallel(
+        &self,
+        params: &HashMap<String, String>,
+        partitions: &[HashMap<String, String>],
+        concurrency: usize,
+    ) -> Result<Value, MediaWikiError> {
+        if partitions.is_empty() {
+            return self.get_query_api_json_all(params).await;
+        }
+        let results: Vec<Value> = futures::stream::iter(partitions)
+            .map(|partition| {
+                let mut params = params.clone();
+                params.extend(partition.clone());
+                async move { self.get_query_api_json_all(&params).await }
+            })
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await?;
+        let mut merged = Value::Null;
+        for result in results {
+            Self::json_merge(&mut merged, result);
+        }
+        dedup_query_lists(&mut merged);
+        Ok(merged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    #[test]
+    fn partitions() {
+        let ranges = range_partitions("apfrom", "apto", &["M"]);
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[0].get("apto"), Some(&"M".to_string()));
+        assert!(!ranges[0].contains_key("apfrom"));
+        assert_eq!(ranges[1].get("apfrom"), Some(&"M".to_string()));
+        assert!(!ranges[1].contains_key("apto"));
+        let titles: Vec<String> = ["A", "B", "C"].iter().map(|s| s.to_string()).collect();
+        let batches = title_partitions(&titles, 2);
+        assert_eq!(batches[0]["titles"], "A|B");
+        assert_eq!(batches[1]["titles"], "C");
+    }
+
+    #[tokio::test]
+    async fn get_query_parallel() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("apfrom", "M"), ("apcontinue", "Z")],
+                json!({"batchcomplete":"","query":{"allpages":[{"ns":0,"title":"Z"}]}}),
+            )
+            .add(
+                &[("apfrom", "M")],
+                json!({"continue":{"apcontinue":"Z","continue":"-||"},"query":{"allpages":[{"ns":0,"title":"M"}]}}),
+            )
+            .add(
+                &[("apto", "M")],
+                json!({"batchcomplete":"","query":{"allpages":[{"ns":0,"title":"A"},{"ns":0,"title":"M"}]}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let params = api.params_into(&[("action", "query"), ("list", "allpages")]);
+        let result = api
+            .get_query_parallel(&params, &range_partitions("apfrom", "apto", &["M"]), 2)
+            .await
+            .unwrap();
+        let titles: Vec<&str> = result["query"]["allpages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|p| p["title"].as_str())
+            .collect();
+        assert_eq!(titles, ["A", "M", "Z"]);
+        assert_eq!(responses.requests().len(), 3);
+    }
+}
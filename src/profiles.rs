@@ -0,0 +1,129 @@
+/*!
+The `Profiles` class loads named wiki configurations from a TOML file, and constructs `Api` instances from them.
+Each profile is an `ApiConfig`, with the same keys as a single configuration file.
+
+Example file:
+```toml
+[prod]
+api_url = "https://www.wikidata.org/w/api.php"
+user_agent = "MyBot/1.0"
+maxlag = 5
+edit_delay_ms = 1000
+tags = ["my-bot"]
+
+[prod.user]
+user = "MyBot@mybot"
+pass_env = "MYBOT_PASSWORD"
+```
+
+Every value can be overridden by an environment variable `MEDIAWIKI_CONFIG__<PROFILE>__<KEY>`,
+e.g. `MEDIAWIKI_CONFIG__PROD__MAXLAG=10`.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::config::{load_config, ApiConfig};
+use crate::media_wiki_error::MediaWikiError;
+use ::config::{File, FileFormat, ValueKind};
+use std::collections::HashMap;
+
+/// Named wiki configurations
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profiles {
+    profiles: HashMap<String, ApiConfig>,
+}
+
+impl Profiles {
+    /// Loads profiles from a TOML file, with environment variable overrides
+    pub fn from_file(path: &str) -> Result<Self, MediaWikiError> {
+        Self::from_source(File::new(path, FileFormat::Toml))
+    }
+
+    /// Loads profiles from a TOML string, with environment variable overrides
+    pub fn from_toml_str(toml: &str) -> Result<Self, MediaWikiError> {
+        Self::from_source(File::from_str(toml, FileFormat::Toml))
+    }
+
+    fn from_source<S>(source: S) -> Result<Self, MediaWikiError>
+    where
+        S: ::config::Source + Send + Sync + 'static,
+    {
+        let error = |e| MediaWikiError::String(format!("Could not load profiles: {}", e));
+        let values: HashMap<String, ::config::Value> = load_config(Some(source))?
+            .try_deserialize()
+            .map_err(error)?;
+        let mut profiles = HashMap::new();
+        // Top-level values are overrides of a single configuration, not profiles
+        for (name, value) in values {
+            if matches!(value.kind, ValueKind::Table(_)) {
+                profiles.insert(name, value.try_deserialize().map_err(error)?);
+            }
+        }
+        Ok(Self { profiles })
+    }
+
+    /// Returns the names of all profiles
+    pub fn names(&self) -> Vec<&str> {
+        let mut ret: Vec<&str> = self.profiles.keys().map(|s| s.as_str()).collect();
+        ret.sort();
+        ret
+    }
+
+    /// Returns a profile by name
+    pub fn get(&self, name: &str) -> Option<&ApiConfig> {
+        self.profiles.get(name)
+    }
+
+    /// Constructs an `Api` from the profile `name`, and logs in if credentials are given; see `ApiConfig::api`
+    pub async fn api(&self, name: &str) -> Result<Api, MediaWikiError> {
+        self.get(name)
+            .ok_or_else(|| MediaWikiError::String(format!("No such profile: {}", name)))?
+            .api()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML: &str = r#"
+        [prod]
+        api_url = "https://www.wikidata.org/w/api.php"
+        maxlag = 5
+        tags = ["my-bot"]
+
+        [prod.user]
+        user = "MyBot@mybot"
+        pass_env = "MEDIAWIKI_TEST_PROFILE_PASSWORD"
+
+        [test]
+        api_url = "https://test.wikidata.org/w/api.php"
+    "#;
+
+    #[tokio::test]
+    async fn load_profiles() {
+        let profiles = Profiles::from_toml_str(TOML).unwrap();
+        assert_eq!(profiles.names(), vec!["prod", "test"]);
+        let prod = profiles.get("prod").unwrap();
+        assert_eq!(prod.api_url(), "https://www.wikidata.org/w/api.php");
+        assert_eq!(prod.maxlag(), Some(5));
+        assert_eq!(prod.tags(), &["my-bot".to_string()]);
+        assert_eq!(prod.user().unwrap().user(), "MyBot@mybot");
+        assert!(prod.user().unwrap().password().is_err());
+        let site_info = json!({"query":{"general":{"sitename":"Test"}}}).to_string();
+        let api = prod.builder().site_info(&site_info).build().await.unwrap();
+        assert_eq!(api.edit_tags(), &["my-bot".to_string()]);
+        let test = profiles.get("test").unwrap();
+        assert_eq!(test.maxlag(), None);
+        assert!(test.tags().is_empty());
+        assert!(test.user().is_none());
+        assert!(profiles.get("dev").is_none());
+    }
+
+    #[test]
+    fn invalid_profiles() {
+        assert!(Profiles::from_toml_str("[prod]\nmaxlag = 5").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+/*!
+The `Protection` class represents a page protection, as returned by `prop=info&inprop=protection`
+and set by `action=protect`.
+*/
+
+#![deny(missing_docs)]
+
+use serde_json::Value;
+
+/// Expiry value for protections that do not expire
+pub const INFINITE: &str = "infinite";
+
+/// A protection of a page for one action
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Protection {
+    kind: String,
+    level: String,
+    expiry: Option<String>,
+    cascade: bool,
+}
+
+impl Protection {
+    /// Creates a new protection for action `kind` (e.g. `edit`, `move`, `create`, `upload`)
+    /// requiring `level` (e.g. `autoconfirmed`, `sysop`); an empty level removes the protection.
+    pub fn new(kind: &str, level: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            level: level.to_string(),
+            expiry: None,
+            cascade: false,
+        }
+    }
+
+    /// Creates a protection from an `inprop=protection` entry
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            kind: j["type"].as_str()?.to_string(),
+            level: j["level"].as_str()?.to_string(),
+            expiry: j["expiry"].as_str().map(|s| s.to_string()),
+            cascade: match &j["cascade"] {
+                Value::Bool(b) => *b,
+                Value::Null => false,
+                _ => true,
+            },
+        })
+    }
+
+    /// Returns the protected action (`type` in the API)
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    /// Returns the required user group or right
+    pub fn level(&self) -> &str {
+        &self.level
+    }
+
+    /// Returns the expiry; `infinity` or `None` for protections that do not expire
+    pub fn expiry(&self) -> Option<&str> {
+        self.expiry.as_deref()
+    }
+
+    /// Sets the expiry, as a timestamp or relative time like `1 week`
+    pub fn set_expiry(&mut self, expiry: Option<&str>) {
+        self.expiry = expiry.map(|s| s.to_string());
+    }
+
+    /// Returns true if the protection cascades to transcluded pages
+    pub fn cascade(&self) -> bool {
+        self.cascade
+    }
+
+    /// Returns true if the protection never expires
+    pub fn is_infinite(&self) -> bool {
+        matches!(
+            self.expiry.as_deref(),
+            None | Some("infinity") | Some("infinite") | Some("indefinite") | Some("never")
+        )
+    }
+}
+
+/// Returns the `protections` and `expiry` parameters for `action=protect`.
+/// Protections without expiry use `default_expiry`, or never expire.
+pub fn protect_params(
+    protections: &[Protection],
+    default_expiry: Option<&str>,
+) -> (String, String) {
+    let default_expiry = default_expiry.unwrap_or(INFINITE);
+    let kinds: Vec<String> = protections
+        .iter()
+        .map(|p| format!("{}={}", p.kind, p.level))
+        .collect();
+    let expiries: Vec<&str> = protections
+        .iter()
+        .map(|p| p.expiry.as_deref().unwrap_or(default_expiry))
+        .collect();
+    (kinds.join("|"), expiries.join("|"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_json() {
+        let p = Protection::from_json(
+            &json!({"type":"edit","level":"sysop","expiry":"infinity","cascade":""}),
+        )
+        .unwrap();
+        assert_eq!(p.kind(), "edit");
+        assert_eq!(p.level(), "sysop");
+        assert!(p.is_infinite());
+        assert!(p.cascade());
+        assert!(Protection::from_json(&json!({"level":"sysop"})).is_none());
+    }
+
+    #[test]
+    fn params() {
+        let mut edit = Protection::new("edit", "autoconfirmed");
+        edit.set_expiry(Some("1 week"));
+        let moves = Protection::new("move", "sysop");
+        assert_eq!(
+            protect_params(&[edit, moves], None),
+            (
+                "edit=autoconfirmed|move=sysop".to_string(),
+                "1 week|infinite".to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,515 @@
+/*!
+The `Query` class builds the parameters of `action=query` from typed lists, props and meta modules,
+instead of hand-written parameter maps, so misspelled modules and parameters fail to compile.
+
+```no_run
+use mediawiki::query::{Limit, List, Query};
+# tokio::runtime::Runtime::new().unwrap().block_on(async {
+let api = mediawiki::api::Api::new("https://en.wikipedia.org/w/api.php").await.unwrap();
+let query = Query::list(List::Search {
+    srsearch: "insource:foo".to_string(),
+    srnamespace: vec![0],
+    srlimit: Some(Limit::Max),
+});
+let result = api.get_query(&query).await.unwrap();
+# });
+```
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID, TokenType};
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A limit for the number of results per request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    /// The maximum the user is allowed
+    Max,
+    /// A fixed number of results
+    Count(u64),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Max => f.write_str("max"),
+            Self::Count(count) => write!(f, "{}", count),
+        }
+    }
+}
+
+/// Collects the parameters of a module, skipping unset ones
+#[derive(Debug, Default)]
+struct ModuleParams {
+    params: Vec<(String, String)>,
+}
+
+impl ModuleParams {
+    fn add(&mut self, key: &str, value: impl ToString) {
+        self.params.push((key.to_string(), value.to_string()));
+    }
+
+    fn add_opt(&mut self, key: &str, value: &Option<impl ToString>) {
+        if let Some(value) = value {
+            self.add(key, value.to_string());
+        }
+    }
+
+    fn add_list(&mut self, key: &str, values: &[impl ToString]) {
+        if !values.is_empty() {
+            let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            self.add(key, values.join("|"));
+        }
+    }
+}
+
+/// A list module (`list=...`), which can also be used as a generator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum List {
+    /// `list=allpages`: all pages, optionally with a title prefix
+    AllPages {
+        /// Title prefix, without namespace
+        apprefix: Option<String>,
+        /// Namespace to list, default 0
+        apnamespace: Option<NamespaceID>,
+        /// Results per request
+        aplimit: Option<Limit>,
+    },
+    /// `list=backlinks`: pages linking to a page
+    Backlinks {
+        /// The linked page
+        bltitle: String,
+        /// Namespaces of the linking pages; all if empty
+        blnamespace: Vec<NamespaceID>,
+        /// Results per request
+        bllimit: Option<Limit>,
+    },
+    /// `list=categorymembers`: pages in a category
+    CategoryMembers {
+        /// The category, with namespace prefix
+        cmtitle: String,
+        /// Namespaces of the members; all if empty
+        cmnamespace: Vec<NamespaceID>,
+        /// Results per request
+        cmlimit: Option<Limit>,
+    },
+    /// `list=embeddedin`: pages transcluding a page
+    EmbeddedIn {
+        /// The transcluded page, with namespace prefix
+        eititle: String,
+        /// Namespaces of the transcluding pages; all if empty
+        einamespace: Vec<NamespaceID>,
+        /// Results per request
+        eilimit: Option<Limit>,
+    },
+    /// `list=search`: full-text search
+    Search {
+        /// The search query
+        srsearch: String,
+        /// Namespaces to search; the wiki default if empty
+        srnamespace: Vec<NamespaceID>,
+        /// Results per request
+        srlimit: Option<Limit>,
+    },
+    /// `list=usercontribs`: contributions of a user
+    UserContribs {
+        /// The user name
+        ucuser: String,
+        /// Namespaces of the edited pages; all if empty
+        ucnamespace: Vec<NamespaceID>,
+        /// Results per request
+        uclimit: Option<Limit>,
+    },
+}
+
+impl List {
+    /// Returns the module name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AllPages { .. } => "allpages",
+            Self::Backlinks { .. } => "backlinks",
+            Self::CategoryMembers { .. } => "categorymembers",
+            Self::EmbeddedIn { .. } => "embeddedin",
+            Self::Search { .. } => "search",
+            Self::UserContribs { .. } => "usercontribs",
+        }
+    }
+
+    fn module_params(&self) -> ModuleParams {
+        let mut p = ModuleParams::default();
+        match self {
+            Self::AllPages {
+                apprefix,
+                apnamespace,
+                aplimit,
+            } => {
+                p.add_opt("apprefix", apprefix);
+                p.add_opt("apnamespace", apnamespace);
+                p.add_opt("aplimit", aplimit);
+            }
+            Self::Backlinks {
+                bltitle,
+                blnamespace,
+                bllimit,
+            } => {
+                p.add("bltitle", bltitle);
+                p.add_list("blnamespace", blnamespace);
+                p.add_opt("bllimit", bllimit);
+            }
+            Self::CategoryMembers {
+                cmtitle,
+                cmnamespace,
+                cmlimit,
+            } => {
+                p.add("cmtitle", cmtitle);
+                p.add_list("cmnamespace", cmnamespace);
+                p.add_opt("cmlimit", cmlimit);
+            }
+            Self::EmbeddedIn {
+                eititle,
+                einamespace,
+                eilimit,
+            } => {
+                p.add("eititle", eititle);
+                p.add_list("einamespace", einamespace);
+                p.add_opt("eilimit", eilimit);
+            }
+            Self::Search {
+                srsearch,
+                srnamespace,
+                srlimit,
+            } => {
+                p.add("srsearch", srsearch);
+                p.add_list("srnamespace", srnamespace);
+                p.add_opt("srlimit", srlimit);
+            }
+            Self::UserContribs {
+                ucuser,
+                ucnamespace,
+                uclimit,
+            } => {
+                p.add("ucuser", ucuser);
+                p.add_list("ucnamespace", ucnamespace);
+                p.add_opt("uclimit", uclimit);
+            }
+        }
+        p
+    }
+}
+
+/// A prop module (`prop=...`), returning information about the queried pages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Prop {
+    /// `prop=categories`: categories of the pages
+    Categories {
+        /// Results per request
+        cllimit: Option<Limit>,
+    },
+    /// `prop=info`: basic page information
+    Info {
+        /// Additional properties, e.g. `url` or `protection`
+        inprop: Vec<String>,
+    },
+    /// `prop=links`: links on the pages
+    Links {
+        /// Namespaces of the linked pages; all if empty
+        plnamespace: Vec<NamespaceID>,
+        /// Results per request
+        pllimit: Option<Limit>,
+    },
+    /// `prop=pageprops`: page properties, e.g. `wikibase_item`
+    PageProps {
+        /// Properties to return; all if empty
+        ppprop: Vec<String>,
+    },
+    /// `prop=revisions`: revisions of the pages
+    Revisions {
+        /// Revision properties, e.g. `ids`, `timestamp` or `content`
+        rvprop: Vec<String>,
+        /// Slots to return content for, e.g. `main`
+        rvslots: Option<String>,
+        /// Number of revisions; only allowed with a single page
+        rvlimit: Option<Limit>,
+    },
+    /// `prop=templates`: templates transcluded on the pages
+    Templates {
+        /// Namespaces of the templates; all if empty
+        tlnamespace: Vec<NamespaceID>,
+        /// Results per request
+        tllimit: Option<Limit>,
+    },
+}
+
+impl Prop {
+    /// Returns the module name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Categories { .. } => "categories",
+            Self::Info { .. } => "info",
+            Self::Links { .. } => "links",
+            Self::PageProps { .. } => "pageprops",
+            Self::Revisions { .. } => "revisions",
+            Self::Templates { .. } => "templates",
+        }
+    }
+
+    fn module_params(&self) -> ModuleParams {
+        let mut p = ModuleParams::default();
+        match self {
+            Self::Categories { cllimit } => p.add_opt("cllimit", cllimit),
+            Self::Info { inprop } => p.add_list("inprop", inprop),
+            Self::Links {
+                plnamespace,
+                pllimit,
+            } => {
+                p.add_list("plnamespace", plnamespace);
+                p.add_opt("pllimit", pllimit);
+            }
+            Self::PageProps { ppprop } => p.add_list("ppprop", ppprop),
+            Self::Revisions {
+                rvprop,
+                rvslots,
+                rvlimit,
+            } => {
+                p.add_list("rvprop", rvprop);
+                p.add_opt("rvslots", rvslots);
+                p.add_opt("rvlimit", rvlimit);
+            }
+            Self::Templates {
+                tlnamespace,
+                tllimit,
+            } => {
+                p.add_list("tlnamespace", tlnamespace);
+                p.add_opt("tllimit", tllimit);
+            }
+        }
+        p
+    }
+}
+
+/// A meta module (`meta=...`), returning information about the wiki or user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Meta {
+    /// `meta=siteinfo`: site information
+    SiteInfo {
+        /// Information to return, e.g. `general` or `namespaces`
+        siprop: Vec<String>,
+    },
+    /// `meta=tokens`: tokens for data-modifying actions
+    Tokens {
+        /// Token types; `csrf` if empty
+        types: Vec<TokenType>,
+    },
+    /// `meta=userinfo`: information about the current user
+    UserInfo {
+        /// Information to return, e.g. `rights` or `groups`
+        uiprop: Vec<String>,
+    },
+}
+
+impl Meta {
+    /// Returns the module name
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::SiteInfo { .. } => "siteinfo",
+            Self::Tokens { .. } => "tokens",
+            Self::UserInfo { .. } => "userinfo",
+        }
+    }
+
+    fn module_params(&self) -> ModuleParams {
+        let mut p = ModuleParams::default();
+        match self {
+            Self::SiteInfo { siprop } => p.add_list("siprop", siprop),
+            Self::Tokens { types } => p.add_list("type", types),
+            Self::UserInfo { uiprop } => p.add_list("uiprop", uiprop),
+        }
+        p
+    }
+}
+
+/// The pages to run prop modules on; only one kind can be given per query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pages {
+    /// Page titles, with namespace prefix
+    Titles(Vec<String>),
+    /// Page IDs
+    PageIds(Vec<u64>),
+    /// Revision IDs
+    RevIds(Vec<u64>),
+    /// The pages returned by a list module
+    Generator(List),
+}
+
+/// A typed `action=query`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    lists: Vec<List>,
+    props: Vec<Prop>,
+    metas: Vec<Meta>,
+    pages: Option<Pages>,
+    extra: Vec<(String, String)>,
+}
+
+impl Query {
+    /// Creates an empty query
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a query with a list module
+    pub fn list(list: List) -> Self {
+        Self::new().and_list(list)
+    }
+
+    /// Creates a query with a prop module
+    pub fn prop(prop: Prop) -> Self {
+        Self::new().and_prop(prop)
+    }
+
+    /// Creates a query with a meta module
+    pub fn meta(meta: Meta) -> Self {
+        Self::new().and_meta(meta)
+    }
+
+    /// Adds a list module
+    pub fn and_list(mut self, list: List) -> Self {
+        self.lists.push(list);
+        self
+    }
+
+    /// Adds a prop module
+    pub fn and_prop(mut self, prop: Prop) -> Self {
+        self.props.push(prop);
+        self
+    }
+
+    /// Adds a meta module
+    pub fn and_meta(mut self, meta: Meta) -> Self {
+        self.metas.push(meta);
+        self
+    }
+
+    /// Sets the pages for the prop modules, replacing any pages set before
+    pub fn pages(mut self, pages: Pages) -> Self {
+        self.pages = Some(pages);
+        self
+    }
+
+    /// Adds a parameter that has no typed equivalent, e.g. `redirects`
+    pub fn param(mut self, key: &str, value: &str) -> Self {
+        self.extra.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Returns the API parameters of this query
+    pub fn params(&self) -> HashMap<String, String> {
+        let mut params: HashMap<String, String> = HashMap::new();
+        params.insert("action".to_string(), "query".to_string());
+        let mut add_modules = |key: &str, names: Vec<&str>| {
+            if !names.is_empty() {
+                params.insert(key.to_string(), names.join("|"));
+            }
+        };
+        add_modules("list", self.lists.iter().map(|m| m.name()).collect());
+        add_modules("prop", self.props.iter().map(|m| m.name()).collect());
+        add_modules("meta", self.metas.iter().map(|m| m.name()).collect());
+        let module_params = self
+            .lists
+            .iter()
+            .map(List::module_params)
+            .chain(self.props.iter().map(Prop::module_params))
+            .chain(self.metas.iter().map(Meta::module_params));
+        for p in module_params {
+            params.extend(p.params);
+        }
+        match &self.pages {
+            Some(Pages::Titles(titles)) => {
+                params.insert("titles".to_string(), titles.join("|"));
+            }
+            Some(Pages::PageIds(ids)) => {
+                params.extend(Self::id_list("pageids", ids));
+            }
+            Some(Pages::RevIds(ids)) => {
+                params.extend(Self::id_list("revids", ids));
+            }
+            Some(Pages::Generator(list)) => {
+                params.insert("generator".to_string(), list.name().to_string());
+                for (k, v) in list.module_params().params {
+                    params.insert(format!("g{}", k), v);
+                }
+            }
+            None => {}
+        }
+        params.extend(self.extra.iter().cloned());
+        params
+    }
+
+    fn id_list(key: &str, ids: &[u64]) -> Option<(String, String)> {
+        let mut p = ModuleParams::default();
+        p.add_list(key, ids);
+        p.params.pop()
+    }
+}
+
+impl Api {
+    /// Runs a typed query, following continuation, and returns the merged result
+    pub async fn get_query(&self, query: &Query) -> Result<Value, MediaWikiError> {
+        self.get_query_api_json_all(&query.params()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params() {
+        let query = Query::list(List::Search {
+            srsearch: "insource:foo".to_string(),
+            srnamespace: vec![0, 14],
+            srlimit: Some(Limit::Max),
+        })
+        .and_meta(Meta::Tokens {
+            types: vec![TokenType::Csrf, TokenType::Watch],
+        });
+        let params = query.params();
+        assert_eq!(params["action"], "query");
+        assert_eq!(params["list"], "search");
+        assert_eq!(params["srnamespace"], "0|14");
+        assert_eq!(params["srlimit"], "max");
+        assert_eq!(params["meta"], "tokens");
+        assert_eq!(params["type"], "csrf|watch");
+
+        let query = Query::prop(Prop::Revisions {
+            rvprop: vec!["ids".to_string(), "content".to_string()],
+            rvslots: Some("main".to_string()),
+            rvlimit: None,
+        })
+        .and_prop(Prop::Info { inprop: vec![] })
+        .pages(Pages::Generator(List::CategoryMembers {
+            cmtitle: "Category:Foo".to_string(),
+            cmnamespace: vec![],
+            cmlimit: Some(Limit::Count(50)),
+        }))
+        .param("redirects", "1");
+        let params = query.params();
+        assert_eq!(params["prop"], "revisions|info");
+        assert_eq!(params["rvprop"], "ids|content");
+        assert_eq!(params["generator"], "categorymembers");
+        assert_eq!(params["gcmtitle"], "Category:Foo");
+        assert_eq!(params["gcmlimit"], "50");
+        assert_eq!(params["redirects"], "1");
+        assert!(!params.contains_key("list"));
+        assert!(!params.contains_key("inprop"));
+        assert!(!params.contains_key("cmnamespace"));
+        let params = Query::prop(Prop::PageProps { ppprop: vec![] })
+            .pages(Pages::PageIds(vec![1, 2]))
+            .params();
+        assert_eq!(params["pageids"], "1|2");
+    }
+}
@@ -0,0 +1,252 @@
+/*!
+The `query_dump` module streams the items of a continued query to an ndjson file, optionally gzip-compressed.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::continuation::Continuation;
+use crate::media_wiki_error::MediaWikiError;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+
+/// Suffix of the file that stores the continuation, next to the dump file
+const CONTINUE_SUFFIX: &str = ".continue";
+
+/// Suffix of the temporary file that replaces the continuation file
+const CONTINUE_TMP_SUFFIX: &str = ".continue.tmp";
+
+/// Key of the dump file length in the continuation file
+const OFFSET_KEY: &str = "offset";
+
+impl Api {
+    /// Runs a query with continuation, and appends every entry of `["query"][list_key]` to `path`
+    /// as one JSON object per line. If `path` ends in `.gz`, each batch is written as a gzip member
+    /// (readable with `zcat` or `flate2::read::MultiGzDecoder`).
+    ///
+    /// After each batch, the continuation and the length of `path` are stored in `<path>.continue`.
+    /// If that file exists when this is called, `path` is truncated to that length, dropping a batch
+    /// written after the last checkpoint, and the query resumes from there; it is removed once the query is complete.
+    /// Returns the number of entries written by this call.
+    pub async fn query_to_file(
+        &self,
+        params: &HashMap<String, String>,
+        list_key: &str,
+        path: impl AsRef<Path>,
+    ) -> Result<usize, MediaWikiError> {
+        let path = path.as_ref();
+        let continue_path = Self::query_dump_path(path, CONTINUE_SUFFIX);
+        let mut continuation = match fs::read_to_string(&continue_path).await {
+            Ok(s) => {
+                let checkpoint: Value = serde_json::from_str(&s)?;
+                if let Some(offset) = checkpoint[OFFSET_KEY].as_u64() {
+                    let file = OpenOptions::new().write(true).open(path).await?;
+                    file.set_len(offset).await?;
+                }
+                Continuation::from_result(&checkpoint)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                // Fresh start; don't append to an old dump
+                File::create(path).await?;
+                None
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut written = 0;
+        loop {
+            let mut current_params = params.clone();
+            if let Some(continuation) = &continuation {
+                current_params.extend(continuation.params(params));
+            }
+            let result = self.get_query_api_json(&current_params).await?;
+            if !result["error"].is_null() {
+                return Err(MediaWikiError::BadResponse(result));
+            }
+            let (entries, offset) =
+                Self::query_dump_write_batch(path, &result["query"][list_key]).await?;
+            written += entries;
+            continuation = Continuation::from_result(&result);
+            match &continuation {
+                Some(continuation) => {
+                    Self::query_dump_write_checkpoint(path, continuation, offset).await?
+                }
+                None => break,
+            }
+        }
+        if let Err(e) = fs::remove_file(&continue_path).await {
+            if e.kind() != ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+        Ok(written)
+    }
+
+    /// Returns the path of a file next to a dump file, e.g. the continuation file
+    fn query_dump_path(path: &Path, suffix: &str) -> PathBuf {
+        let mut ret = path.as_os_str().to_owned();
+        ret.push(suffix);
+        PathBuf::from(ret)
+    }
+
+    /// Stores the continuation and the dump file length; the continuation file is replaced atomically
+    async fn query_dump_write_checkpoint(
+        path: &Path,
+        continuation: &Continuation,
+        offset: u64,
+    ) -> Result<(), MediaWikiError> {
+        let mut checkpoint = continuation.to_json();
+        checkpoint[OFFSET_KEY] = offset.into();
+        let tmp_path = Self::query_dump_path(path, CONTINUE_TMP_SUFFIX);
+        fs::write(&tmp_path, checkpoint.to_string()).await?;
+        fs::rename(&tmp_path, Self::query_dump_path(path, CONTINUE_SUFFIX)).await?;
+        Ok(())
+    }
+
+    /// Appends the entries of an array or object to the dump file, and syncs it to disk.
+    /// Returns the number of entries, and the length of the dump file afterwards.
+    async fn query_dump_write_batch(
+        path: &Path,
+        entries: &Value,
+    ) -> Result<(usize, u64), MediaWikiError> {
+        let entries: Vec<&Value> = match entries {
+            Value::Array(arr) => arr.iter().collect(),
+            Value::Object(obj) => obj.values().collect(),
+            _ => vec![],
+        };
+        let mut lines = String::new();
+        for entry in &entries {
+            lines += &entry.to_string();
+            lines.push('\n');
+        }
+        let bytes = if path.extension().is_some_and(|ext| ext == "gz") {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(lines.as_bytes())?;
+            encoder.finish()?
+        } else {
+            lines.into_bytes()
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        file.write_all(&bytes).await?;
+        file.sync_data().await?;
+        Ok((entries.len(), file.metadata().await?.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::MultiGzDecoder;
+    use std::io::Read;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "mediawiki_query_dump_{}_{}",
+            std::process::id(),
+            name
+        ))
+    }
+
+    #[tokio::test]
+    async fn write_batches_plain() {
+        let path = temp_path("plain.ndjson");
+        Api::query_dump_write_batch(&path, &json!([{"title":"A"},{"title":"B"}]))
+            .await
+            .unwrap();
+        let (entries, offset) = Api::query_dump_write_batch(&path, &json!({"1":{"title":"C"}}))
+            .await
+            .unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            text,
+            "{\"title\":\"A\"}\n{\"title\":\"B\"}\n{\"title\":\"C\"}\n"
+        );
+        assert_eq!((entries, offset), (1, text.len() as u64));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn write_batches_gzip() {
+        let path = temp_path("dump.ndjson.gz");
+        Api::query_dump_write_batch(&path, &json!([{"title":"A"}]))
+            .await
+            .unwrap();
+        Api::query_dump_write_batch(&path, &json!([{"title":"B"}]))
+            .await
+            .unwrap();
+        let mut text = String::new();
+        MultiGzDecoder::new(std::fs::File::open(&path).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "{\"title\":\"A\"}\n{\"title\":\"B\"}\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn continue_path() {
+        assert_eq!(
+            Api::query_dump_path(Path::new("/tmp/x.ndjson.gz"), CONTINUE_SUFFIX),
+            PathBuf::from("/tmp/x.ndjson.gz.continue")
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_after_interrupted_batch() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("apcontinue", "B")],
+                json!({"query":{"allpages":[{"title":"B"}]}}),
+            )
+            .add(
+                &[("list", "allpages")],
+                json!({"continue":{"apcontinue":"B","continue":"-||"},"query":{"allpages":[{"title":"A"}]}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let params = api.params_into(&[("action", "query"), ("list", "allpages")]);
+        let path = temp_path("resume.ndjson");
+        let continue_path = Api::query_dump_path(&path, CONTINUE_SUFFIX);
+
+        // A batch was written after the last checkpoint, but the run stopped before storing the next one
+        let first = "{\"title\":\"A\"}\n";
+        std::fs::write(&path, format!("{}{{\"title\":\"B\"}}\n", first)).unwrap();
+        let continuation =
+            Continuation::from_result(&json!({"continue":{"apcontinue":"B","continue":"-||"}}))
+                .unwrap();
+        Api::query_dump_write_checkpoint(&path, &continuation, first.len() as u64)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            api.query_to_file(&params, "allpages", &path).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"title\":\"A\"}\n{\"title\":\"B\"}\n"
+        );
+        assert!(!continue_path.exists());
+        assert_eq!(responses.requests()[0]["apcontinue"], "B");
+
+        // A fresh run writes a checkpoint after the first batch, and removes it at the end
+        assert_eq!(
+            api.query_to_file(&params, "allpages", &path).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"title\":\"A\"}\n{\"title\":\"B\"}\n"
+        );
+        assert!(!continue_path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
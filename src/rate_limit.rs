@@ -0,0 +1,274 @@
+/*!
+The `rate_limit` module paces requests with a token bucket per kind of request (edit, upload, read).
+A `RateLimiter` is shared between clones of an `Api`, so concurrent tasks share one budget.
+*/
+
+#![deny(missing_docs)]
+
+use crate::user::User;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// The kind of request a rate limit applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitKind {
+    /// Requests that change the wiki (POST with a token), except uploads
+    Edit,
+    /// `action=upload`
+    Upload,
+    /// All other requests
+    Read,
+}
+
+/// A number of requests allowed per period, with an optional burst
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    requests: u32,
+    period: Duration,
+    burst: u32,
+}
+
+impl RateLimit {
+    /// Allows `requests` per `period`; the burst is the same as `requests`
+    pub fn new(requests: u32, period: Duration) -> Self {
+        let requests = requests.max(1);
+        Self {
+            requests,
+            period,
+            burst: requests,
+        }
+    }
+
+    /// Allows `requests` per second
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// Allows `requests` per minute
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// Returns the number of requests per period
+    pub fn requests(&self) -> u32 {
+        self.requests
+    }
+
+    /// Returns the period
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the maximum number of requests that can be made at once
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// Sets the maximum number of requests that can be made at once (at least 1)
+    pub fn set_burst(&mut self, burst: u32) {
+        self.burst = burst.max(1);
+    }
+
+    /// Returns the number of tokens refilled per second
+    fn rate(&self) -> f64 {
+        self.requests as f64 / self.period.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    limit: RateLimit,
+    tokens: f64,
+    last: Instant,
+    from_server: bool,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant, from_server: bool) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            last: now,
+            from_server,
+        }
+    }
+
+    /// Takes a token if available, otherwise returns the time until one is
+    fn take(&mut self, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.rate()).min(self.limit.burst as f64);
+        self.last = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            (1.0 - self.tokens) / self.limit.rate(),
+        ))
+    }
+}
+
+/// Returns the rate limits advertised by the server in the user info, unless the user has `noratelimit`
+pub fn server_rate_limits(user: &User) -> Vec<(RateLimitKind, RateLimit)> {
+    if user.has_right("noratelimit") {
+        return vec![];
+    }
+    [
+        (RateLimitKind::Edit, "edit"),
+        (RateLimitKind::Upload, "upload"),
+    ]
+    .iter()
+    .filter_map(|(kind, action)| {
+        let (hits, seconds) = user.ratelimit(action)?;
+        let hits = u32::try_from(hits).unwrap_or(u32::MAX);
+        Some((*kind, RateLimit::new(hits, Duration::from_secs(seconds))))
+    })
+    .collect()
+}
+
+/// Token buckets per `RateLimitKind`; clones share the same buckets
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<RateLimitKind, Bucket>>>,
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter without limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the limit for a kind of request, if set
+    pub fn limit(&self, kind: RateLimitKind) -> Option<RateLimit> {
+        self.buckets.lock().ok()?.get(&kind).map(|b| b.limit)
+    }
+
+    /// Sets or removes the limit for a kind of request; an explicit limit takes precedence over server limits
+    pub fn set_limit(&self, kind: RateLimitKind, limit: Option<RateLimit>) {
+        if let Ok(mut buckets) = self.buckets.lock() {
+            match limit {
+                Some(limit) => {
+                    buckets.insert(kind, Bucket::new(limit, Instant::now(), false));
+                }
+                None => {
+                    buckets.remove(&kind);
+                }
+            }
+        }
+    }
+
+    /// Returns true if the limit for a kind of request came from the server, not from `set_limit`
+    pub fn is_server_limit(&self, kind: RateLimitKind) -> bool {
+        self.buckets
+            .lock()
+            .is_ok_and(|buckets| buckets.get(&kind).is_some_and(|b| b.from_server))
+    }
+
+    /// Replaces all server limits with `limits`, for kinds without an explicit limit
+    pub fn set_server_limits(&self, limits: &[(RateLimitKind, RateLimit)]) {
+        if let Ok(mut buckets) = self.buckets.lock() {
+            buckets.retain(|_, b| !b.from_server);
+            let now = Instant::now();
+            for (kind, limit) in limits {
+                buckets
+                    .entry(*kind)
+                    .or_insert_with(|| Bucket::new(*limit, now, true));
+            }
+        }
+    }
+
+    /// Removes all server limits, keeping explicit ones
+    pub fn clear_server_limits(&self) {
+        self.set_server_limits(&[]);
+    }
+
+    /// Waits until a request of this kind is allowed
+    pub async fn acquire(&self, kind: RateLimitKind) {
+        while let Some(wait) = self.try_acquire(kind, Instant::now()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Takes a token if available, otherwise returns the time to wait
+    fn try_acquire(&self, kind: RateLimitKind, now: Instant) -> Option<Duration> {
+        self.buckets.lock().ok()?.get_mut(&kind)?.take(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_limits() {
+        let mut user = User::new();
+        user.set_user_info(Some(
+            json!({"query":{"userinfo":{"rights":["edit"],"ratelimits":{
+            "edit":{"user":{"hits":90,"seconds":60}},"upload":{"user":{"hits":8,"seconds":60}}}}}}),
+        ));
+        let limits = server_rate_limits(&user);
+        assert_eq!(
+            limits,
+            vec![
+                (RateLimitKind::Edit, RateLimit::per_minute(90)),
+                (RateLimitKind::Upload, RateLimit::per_minute(8))
+            ]
+        );
+        user.set_user_info(Some(
+            json!({"query":{"userinfo":{"rights":["noratelimit"],"ratelimits":{
+            "edit":{"user":{"hits":90,"seconds":60}}}}}}),
+        ));
+        assert!(server_rate_limits(&user).is_empty());
+    }
+
+    #[test]
+    fn token_bucket() {
+        let limiter = RateLimiter::new();
+        let now = Instant::now();
+        assert_eq!(limiter.try_acquire(RateLimitKind::Edit, now), None);
+        let mut limit = RateLimit::per_minute(60);
+        limit.set_burst(2);
+        limiter.set_limit(RateLimitKind::Edit, Some(limit));
+        let clone = limiter.clone();
+        assert_eq!(clone.limit(RateLimitKind::Edit), Some(limit));
+        assert_eq!(limiter.try_acquire(RateLimitKind::Edit, now), None);
+        assert_eq!(clone.try_acquire(RateLimitKind::Edit, now), None);
+        let wait = limiter.try_acquire(RateLimitKind::Edit, now).unwrap();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+        assert_eq!(
+            limiter.try_acquire(RateLimitKind::Edit, now + Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(limiter.try_acquire(RateLimitKind::Read, now), None);
+        limiter.set_limit(RateLimitKind::Edit, None);
+        assert_eq!(limiter.limit(RateLimitKind::Edit), None);
+    }
+
+    #[test]
+    fn server_and_explicit_limits() {
+        let limiter = RateLimiter::new();
+        limiter.set_limit(RateLimitKind::Edit, Some(RateLimit::per_minute(10)));
+        limiter.set_server_limits(&[
+            (RateLimitKind::Edit, RateLimit::per_minute(90)),
+            (RateLimitKind::Upload, RateLimit::per_minute(8)),
+        ]);
+        assert_eq!(
+            limiter.limit(RateLimitKind::Edit),
+            Some(RateLimit::per_minute(10))
+        );
+        assert!(!limiter.is_server_limit(RateLimitKind::Edit));
+        assert!(limiter.is_server_limit(RateLimitKind::Upload));
+        limiter.set_server_limits(&[(RateLimitKind::Upload, RateLimit::per_minute(4))]);
+        assert_eq!(
+            limiter.limit(RateLimitKind::Upload),
+            Some(RateLimit::per_minute(4))
+        );
+        limiter.clear_server_limits();
+        assert_eq!(limiter.limit(RateLimitKind::Upload), None);
+        assert_eq!(
+            limiter.limit(RateLimitKind::Edit),
+            Some(RateLimit::per_minute(10))
+        );
+    }
+}
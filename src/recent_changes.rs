@@ -0,0 +1,413 @@
+/*!
+The `recent_changes` module follows `list=recentchanges` as an endless stream, polling for new changes.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::continuation::Continuation;
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use futures::Stream;
+use serde_json::Value;
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+/// Default time between polls, once all changes were returned
+pub const DEFAULT_RECENT_CHANGES_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A recent change, as returned by `list=recentchanges`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentChange {
+    rcid: u64,
+    title: Title,
+    kind: Option<String>,
+    page_id: Option<u64>,
+    revid: Option<u64>,
+    old_revid: Option<u64>,
+    user: Option<String>,
+    timestamp: String,
+    comment: Option<String>,
+    old_len: Option<u64>,
+    new_len: Option<u64>,
+    log_type: Option<String>,
+    log_action: Option<String>,
+    bot: bool,
+    minor: bool,
+    new: bool,
+}
+
+impl RecentChange {
+    /// Creates a new change from a `list=recentchanges` result entry (`formatversion=2`)
+    pub fn from_json(j: &Value) -> Option<Self> {
+        j["title"].as_str()?;
+        Some(Self {
+            rcid: j["rcid"].as_u64()?,
+            title: Title::new_from_api_result(j),
+            kind: j["type"].as_str().map(|s| s.to_string()),
+            page_id: j["pageid"].as_u64(),
+            revid: j["revid"].as_u64(),
+            old_revid: j["old_revid"].as_u64(),
+            user: j["user"].as_str().map(|s| s.to_string()),
+            timestamp: j["timestamp"].as_str()?.to_string(),
+            comment: j["comment"].as_str().map(|s| s.to_string()),
+            old_len: j["oldlen"].as_u64(),
+            new_len: j["newlen"].as_u64(),
+            log_type: j["logtype"].as_str().map(|s| s.to_string()),
+            log_action: j["logaction"].as_str().map(|s| s.to_string()),
+            bot: j["bot"].as_bool().unwrap_or(false),
+            minor: j["minor"].as_bool().unwrap_or(false),
+            new: j["new"].as_bool().unwrap_or(false),
+        })
+    }
+
+    /// Returns the ID of the recent changes entry
+    pub fn rcid(&self) -> u64 {
+        self.rcid
+    }
+
+    /// Returns the title of the changed page
+    pub fn title(&self) -> &Title {
+        &self.title
+    }
+
+    /// Returns the type of change: `edit`, `new`, `log`, `categorize` or `external`
+    pub fn kind(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    /// Returns the page ID
+    pub fn page_id(&self) -> Option<u64> {
+        self.page_id
+    }
+
+    /// Returns the revision ID of the change
+    pub fn revid(&self) -> Option<u64> {
+        self.revid
+    }
+
+    /// Returns the revision ID before the change
+    pub fn old_revid(&self) -> Option<u64> {
+        self.old_revid
+    }
+
+    /// Returns the user who made the change
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Returns the timestamp of the change
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    /// Returns the edit summary
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Returns the page size before the change
+    pub fn old_len(&self) -> Option<u64> {
+        self.old_len
+    }
+
+    /// Returns the page size after the change
+    pub fn new_len(&self) -> Option<u64> {
+        self.new_len
+    }
+
+    /// Returns the log type, e.g. `delete`, for log entries
+    pub fn log_type(&self) -> Option<&str> {
+        self.log_type.as_deref()
+    }
+
+    /// Returns the log action, e.g. `delete`, for log entries
+    pub fn log_action(&self) -> Option<&str> {
+        self.log_action.as_deref()
+    }
+
+    /// Returns true for bot edits
+    pub fn is_bot(&self) -> bool {
+        self.bot
+    }
+
+    /// Returns true for minor edits
+    pub fn is_minor(&self) -> bool {
+        self.minor
+    }
+
+    /// Returns true if the change created the page
+    pub fn is_new(&self) -> bool {
+        self.new
+    }
+}
+
+/// Options for `Api::recent_changes`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentChangesOptions {
+    poll_interval: Duration,
+    start: Option<String>,
+    namespaces: Vec<NamespaceID>,
+    types: Vec<String>,
+    bot: Option<bool>,
+}
+
+impl Default for RecentChangesOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: DEFAULT_RECENT_CHANGES_POLL_INTERVAL,
+            start: None,
+            namespaces: vec![],
+            types: vec![],
+            bot: None,
+        }
+    }
+}
+
+impl RecentChangesOptions {
+    /// Creates options for all changes from now on
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the time between polls
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Sets the time between polls, once all changes were returned
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    /// Returns the timestamp of the first change to return, if set
+    pub fn start(&self) -> Option<&str> {
+        self.start.as_deref()
+    }
+
+    /// Sets the timestamp of the first change to return, e.g. `2024-01-01T00:00:00Z`; `None` for now
+    pub fn set_start(&mut self, start: Option<&str>) {
+        self.start = start.map(|s| s.to_string());
+    }
+
+    /// Returns the namespaces to return changes for; empty for all namespaces
+    pub fn namespaces(&self) -> &[NamespaceID] {
+        &self.namespaces
+    }
+
+    /// Only returns changes to pages in these namespaces; empty for all namespaces
+    pub fn set_namespaces(&mut self, namespaces: &[NamespaceID]) {
+        self.namespaces = namespaces.to_vec();
+    }
+
+    /// Returns the types of changes to return; empty for all types
+    pub fn types(&self) -> &[String] {
+        &self.types
+    }
+
+    /// Only returns changes of these types, e.g. `edit`, `new`, `log`; empty for all types
+    pub fn set_types(&mut self, types: &[&str]) {
+        self.types = types.iter().map(|s| s.to_string()).collect();
+    }
+
+    /// Returns the bot filter
+    pub fn bot(&self) -> Option<bool> {
+        self.bot
+    }
+
+    /// Only returns bot changes (`Some(true)`) or non-bot changes (`Some(false)`); `None` for both
+    pub fn set_bot(&mut self, bot: Option<bool>) {
+        self.bot = bot;
+    }
+
+    /// Returns the API parameters, without `rcstart`
+    fn params(&self, api: &Api) -> std::collections::HashMap<String, String> {
+        let mut params = api.params_into(&[
+            ("action", "query"),
+            ("list", "recentchanges"),
+            (
+                "rcprop",
+                "title|ids|sizes|flags|user|timestamp|comment|loginfo",
+            ),
+            ("rcdir", "newer"),
+            ("rclimit", "max"),
+            ("formatversion", "2"),
+        ]);
+        if !self.namespaces.is_empty() {
+            let namespaces: Vec<String> = self.namespaces.iter().map(|ns| ns.to_string()).collect();
+            params.insert("rcnamespace".to_string(), namespaces.join("|"));
+        }
+        if !self.types.is_empty() {
+            params.insert("rctype".to_string(), self.types.join("|"));
+        }
+        match self.bot {
+            Some(true) => {
+                params.insert("rcshow".to_string(), "bot".to_string());
+            }
+            Some(false) => {
+                params.insert("rcshow".to_string(), "!bot".to_string());
+            }
+            None => {}
+        }
+        params
+    }
+}
+
+/// Tracks the position in the recent changes, to avoid returning a change twice.
+/// Polls start at the timestamp of the last change, which may have been returned already.
+#[derive(Debug, Clone, Default)]
+struct RecentChangesCursor {
+    timestamp: Option<String>,
+    rcids: HashSet<u64>,
+}
+
+impl RecentChangesCursor {
+    /// Returns true if the change was not returned before, and moves the cursor
+    fn accept(&mut self, rc: &RecentChange) -> bool {
+        match self.timestamp.as_deref() {
+            Some(ts) if rc.timestamp() < ts => return false,
+            Some(ts) if rc.timestamp() == ts => return self.rcids.insert(rc.rcid()),
+            _ => {}
+        }
+        self.timestamp = Some(rc.timestamp().to_string());
+        self.rcids.clear();
+        self.rcids.insert(rc.rcid());
+        true
+    }
+}
+
+struct RecentChangesPoll<'a> {
+    api: &'a Api,
+    options: RecentChangesOptions,
+    cursor: RecentChangesCursor,
+    start: String,
+    continuation: Option<Continuation>,
+    pending: VecDeque<RecentChange>,
+    wait: bool,
+}
+
+impl RecentChangesPoll<'_> {
+    /// Returns the next change, polling as often as required
+    async fn next_change(&mut self) -> Result<RecentChange, MediaWikiError> {
+        loop {
+            if let Some(rc) = self.pending.pop_front() {
+                return Ok(rc);
+            }
+            if self.wait {
+                tokio::time::sleep(self.options.poll_interval).await;
+            }
+            let mut params = self.options.params(self.api);
+            match &self.continuation {
+                Some(continuation) => params.extend(continuation.params(&params)),
+                None => {
+                    let start = self.cursor.timestamp.as_deref().unwrap_or(&self.start);
+                    params.insert("rcstart".to_string(), start.to_string());
+                }
+            }
+            let result = self.api.get_query_api_json(&params).await?;
+            self.continuation = Continuation::from_result(&result);
+            self.wait = self.continuation.is_none();
+            let changes = result["query"]["recentchanges"]
+                .as_array()
+                .map(|arr| arr.iter().filter_map(RecentChange::from_json).collect())
+                .unwrap_or_else(Vec::new);
+            for rc in changes {
+                if self.cursor.accept(&rc) {
+                    self.pending.push_back(rc);
+                }
+            }
+        }
+    }
+}
+
+impl Api {
+    /// Returns an endless stream of recent changes, oldest first, via `list=recentchanges`.
+    /// When all changes were returned, the API is polled again after the poll interval.
+    /// The stream ends after the first error.
+    pub fn recent_changes(
+        &self,
+        options: &RecentChangesOptions,
+    ) -> impl Stream<Item = Result<RecentChange, MediaWikiError>> + '_ {
+        let start = options
+            .start()
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string());
+        let poll = RecentChangesPoll {
+            api: self,
+            options: options.to_owned(),
+            cursor: RecentChangesCursor::default(),
+            start,
+            continuation: None,
+            pending: VecDeque::new(),
+            wait: false,
+        };
+        futures::stream::unfold(Some(poll), |poll| async move {
+            let mut poll = poll?;
+            match poll.next_change().await {
+                Ok(rc) => Some((Ok(rc), Some(poll))),
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    fn rc(rcid: u64, timestamp: &str) -> RecentChange {
+        RecentChange::from_json(
+            &json!({"type":"edit","ns":0,"title":"Foo","rcid":rcid,"timestamp":timestamp}),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn cursor() {
+        let mut cursor = RecentChangesCursor::default();
+        assert!(cursor.accept(&rc(1, "2024-01-01T00:00:00Z")));
+        assert!(cursor.accept(&rc(2, "2024-01-01T00:00:00Z")));
+        assert!(!cursor.accept(&rc(1, "2024-01-01T00:00:00Z")));
+        assert!(cursor.accept(&rc(3, "2024-01-01T00:00:01Z")));
+        assert!(!cursor.accept(&rc(2, "2024-01-01T00:00:00Z")));
+    }
+
+    #[test]
+    fn options_params() {
+        let api = Api::new_mocked(crate::mock::MockResponses::new()).unwrap();
+        let mut options = RecentChangesOptions::new();
+        options.set_namespaces(&[0, 4]);
+        options.set_types(&["edit", "new"]);
+        options.set_bot(Some(false));
+        let params = options.params(&api);
+        assert_eq!(params["rcnamespace"], "0|4");
+        assert_eq!(params["rctype"], "edit|new");
+        assert_eq!(params["rcshow"], "!bot");
+    }
+
+    #[tokio::test]
+    async fn stream() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("list", "recentchanges")],
+            json!({"query":{"recentchanges":[
+                {"type":"new","ns":0,"title":"Foo","rcid":10,"timestamp":"2024-01-01T00:00:00Z","new":true},
+                {"type":"edit","ns":0,"title":"Bar","rcid":11,"timestamp":"2024-01-01T00:00:05Z"}]}}),
+        );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut options = RecentChangesOptions::new();
+        options.set_start(Some("2024-01-01T00:00:00Z"));
+        options.set_poll_interval(Duration::from_millis(1));
+        let changes: Vec<RecentChange> = api
+            .recent_changes(&options)
+            .take(2)
+            .map(|rc| rc.unwrap())
+            .collect()
+            .await;
+        assert_eq!(changes[0].rcid(), 10);
+        assert!(changes[0].is_new());
+        assert_eq!(changes[1].title(), &Title::new("Bar", 0));
+        assert_eq!(responses.requests()[0]["rcstart"], "2024-01-01T00:00:00Z");
+    }
+}
@@ -0,0 +1,248 @@
+/*!
+The `relogin` module stores login credentials, so an expired session can be refreshed transparently.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, TokenType};
+use crate::login::LoginError;
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Login credentials, used to log in again when the session expires
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    lgname: String,
+    lgpassword: String,
+    lgdomain: Option<String>,
+}
+
+impl Credentials {
+    /// Creates credentials, e.g. for a bot password (`User@botname`)
+    pub fn new<S: Into<String>>(lgname: S, lgpassword: S) -> Self {
+        Self {
+            lgname: lgname.into(),
+            lgpassword: lgpassword.into(),
+            lgdomain: None,
+        }
+    }
+
+    /// Returns the user name
+    pub fn lgname(&self) -> &str {
+        &self.lgname
+    }
+
+    /// Returns the LDAP domain, if set
+    pub fn lgdomain(&self) -> Option<&str> {
+        self.lgdomain.as_deref()
+    }
+
+    /// Sets the LDAP domain
+    pub fn set_lgdomain(&mut self, lgdomain: Option<&str>) {
+        self.lgdomain = lgdomain.map(|s| s.to_string());
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("lgname", &self.lgname)
+            .field("lgpassword", &"[redacted]")
+            .field("lgdomain", &self.lgdomain)
+            .finish()
+    }
+}
+
+impl Api {
+    /// Returns true if the result indicates a lost session, and the request should be retried after logging in again
+    pub(crate) fn needs_relogin(&self, params: &HashMap<String, String>, result: &Value) -> bool {
+        if self.credentials().is_none() || params.get("action").is_some_and(|a| a == "login") {
+            return false;
+        }
+        matches!(
+            result["error"]["code"].as_str(),
+            Some("assertuserfailed" | "assertbotfailed" | "badtoken")
+        )
+    }
+
+    /// Logs in again with the stored credentials, and replaces the `token` in `params` with a fresh one
+    pub(crate) async fn relogin(
+        &self,
+        params: &mut HashMap<String, String>,
+    ) -> Result<(), MediaWikiError> {
+        let credentials = self
+            .credentials()
+            .ok_or_else(|| MediaWikiError::String("No credentials for re-login".to_string()))?
+            .to_owned();
+        let lgtoken = self.fetch_token_raw(TokenType::Login.as_str()).await?;
+        let mut login_params = self.params_into(&[
+            ("action", "login"),
+            ("lgname", &credentials.lgname),
+            ("lgpassword", &credentials.lgpassword),
+            ("lgtoken", &lgtoken),
+            ("format", "json"),
+        ]);
+        if let Some(lgdomain) = &credentials.lgdomain {
+            login_params.insert("lgdomain".to_string(), lgdomain.to_string());
+        }
+        let result: Value =
+            serde_json::from_str(&self.query_api_raw(&login_params, "POST").await?)?;
+        if result["login"]["result"] != "Success" {
+            return Err(MediaWikiError::Login(LoginError::from_json(
+                &result["login"],
+            )));
+        }
+        self.clear_tokens();
+        if params.contains_key("token") {
+            let token_type = Self::token_type_for_action(params.get("action").map(|s| s.as_str()));
+            let token = self.fetch_token_raw(token_type.as_str()).await?;
+            params.insert("token".to_string(), token);
+        }
+        Ok(())
+    }
+
+    /// Fetches a token without going through the retry logic
+    async fn fetch_token_raw(&self, token_type: &str) -> Result<String, MediaWikiError> {
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("meta", "tokens"),
+            ("type", token_type),
+            ("format", "json"),
+        ]);
+        let result: Value = serde_json::from_str(&self.query_api_raw(&params, "GET").await?)?;
+        result["query"]["tokens"][format!("{}token", token_type)]
+            .as_str()
+            .map(|s| s.to_string())
+            .ok_or(MediaWikiError::BadResponse(result))
+    }
+
+    /// Returns the token type required by an action
+    fn token_type_for_action(action: Option<&str>) -> TokenType {
+        match action {
+            Some("watch") => TokenType::Watch,
+            Some("patrol") => TokenType::Patrol,
+            Some("rollback") => TokenType::Rollback,
+            Some("userrights") => TokenType::UserRights,
+            Some("createaccount") => TokenType::CreateAccount,
+            _ => TokenType::Csrf,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn credentials_debug() {
+        let mut credentials = Credentials::new("MyBot@mybot", "hunter2");
+        credentials.set_lgdomain(Some("LDAP"));
+        let debug = format!("{:?}", credentials);
+        assert!(debug.contains("MyBot@mybot"));
+        assert!(!debug.contains("hunter2"));
+    }
+
+    #[test]
+    fn token_type_for_action() {
+        assert_eq!(Api::token_type_for_action(Some("edit")), TokenType::Csrf);
+        assert_eq!(Api::token_type_for_action(Some("watch")), TokenType::Watch);
+        assert_eq!(
+            Api::token_type_for_action(Some("userrights")),
+            TokenType::UserRights
+        );
+        assert_eq!(
+            Api::token_type_for_action(Some("createaccount")),
+            TokenType::CreateAccount
+        );
+        assert_eq!(Api::token_type_for_action(None), TokenType::Csrf);
+    }
+
+    #[tokio::test]
+    async fn relogin_and_retry() {
+        use crate::events::ApiEvent;
+        use crate::mock::MockResponses;
+        use std::sync::{Arc, Mutex};
+
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens"), ("type", "login")],
+                json!({"query":{"tokens":{"logintoken":"lg+\\"}}}),
+            )
+            .add(
+                &[("meta", "tokens"), ("type", "userrights")],
+                json!({"query":{"tokens":{"userrightstoken":"ur+\\"}}}),
+            )
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"fresh+\\"}}}),
+            )
+            .add(
+                &[("action", "login")],
+                json!({"login":{"result":"Success","lguserid":1,"lgusername":"MyBot"}}),
+            )
+            .add_once(
+                &[("action", "edit")],
+                json!({"error":{"code":"assertuserfailed","info":"You are no longer logged in"}}),
+            )
+            .add(&[("action", "edit")], json!({"edit":{"result":"Success"}}))
+            .add_once(
+                &[("action", "userrights")],
+                json!({"error":{"code":"badtoken","info":"Invalid CSRF token"}}),
+            )
+            .add(&[("action", "userrights")], json!({"userrights":{}}))
+            .add(
+                &[("action", "delete")],
+                json!({"error":{"code":"assertuserfailed","info":"You are no longer logged in"}}),
+            );
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        api.set_credentials(Some(Credentials::new("MyBot@mybot", "hunter2")));
+        api.set_max_relogin_attempts(2);
+        let observed = Arc::new(Mutex::new(vec![]));
+        {
+            let observed = observed.clone();
+            api.add_event_observer(Arc::new(move |event: &ApiEvent| {
+                observed.lock().unwrap().push(event.kind().to_string())
+            }));
+        }
+        let logins = || {
+            responses
+                .requests()
+                .iter()
+                .filter(|r| r.get("action").is_some_and(|a| a == "login"))
+                .count()
+        };
+
+        let params = api.params_into(&[("action", "edit"), ("title", "A"), ("token", "old")]);
+        let v = api.post_query_api_json(&params).await.unwrap();
+        assert_eq!(v["edit"]["result"], "Success");
+        assert_eq!(logins(), 1);
+        let requests = responses.requests();
+        let login = requests.iter().find(|r| r["action"] == "login").unwrap();
+        assert_eq!(login["lgname"], "MyBot@mybot");
+        assert_eq!(login["lgtoken"], "lg+\\");
+        assert_eq!(requests.last().unwrap()["token"], "fresh+\\");
+
+        let params = api.params_into(&[("action", "userrights"), ("user", "A"), ("token", "old")]);
+        let v = api.post_query_api_json(&params).await.unwrap();
+        assert!(v["userrights"].is_object());
+        assert_eq!(logins(), 2);
+        assert_eq!(responses.requests().last().unwrap()["token"], "ur+\\");
+
+        let params = api.params_into(&[("action", "delete"), ("title", "A"), ("token", "old")]);
+        assert!(matches!(
+            api.post_query_api_json(&params).await,
+            Err(MediaWikiError::SessionExpired(code)) if code == "assertuserfailed"
+        ));
+        assert_eq!(logins(), 4); // Stopped after max_relogin_attempts
+        let relogins = observed
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|kind| *kind == "relogin")
+            .count();
+        assert_eq!(relogins, 4);
+    }
+}
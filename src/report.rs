@@ -0,0 +1,399 @@
+/*!
+The `ReportPublisher` class renders the results of a batch run as a wikitable, and publishes it to a report page.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::Page;
+use crate::title::Title;
+use std::fmt;
+
+/// Default maximum page size in bytes, if the wiki does not report `maxarticlesize`
+const DEFAULT_MAX_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Bytes reserved per page for the table header and footer
+const PAGE_OVERHEAD: usize = 1024;
+
+/// The outcome of a single edit in a batch run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportOutcome {
+    /// The page was edited, with the new revision ID if known
+    Edited(Option<u64>),
+    /// The page did not need changes
+    Unchanged,
+    /// The page was skipped
+    Skipped,
+    /// The edit failed
+    Failed,
+}
+
+impl fmt::Display for ReportOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Edited(Some(revid)) => write!(f, "[[Special:Diff/{}|edited]]", revid),
+            Self::Edited(None) => write!(f, "edited"),
+            Self::Unchanged => write!(f, "unchanged"),
+            Self::Skipped => write!(f, "skipped"),
+            Self::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+/// Returns a link target for a title that does not depend on the local namespace names
+fn link_target(title: &Title) -> String {
+    match title.namespace_id() {
+        0 => title.pretty().to_string(),
+        ns => format!("{{{{ns:{}}}}}:{}", ns, title.pretty()),
+    }
+}
+
+/// One row of a report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportEntry {
+    title: Title,
+    outcome: ReportOutcome,
+    message: String,
+}
+
+impl ReportEntry {
+    /// Creates a new report row
+    pub fn new(title: Title, outcome: ReportOutcome, message: impl Into<String>) -> Self {
+        Self {
+            title,
+            outcome,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the title of the page
+    pub fn title(&self) -> &Title {
+        &self.title
+    }
+
+    /// Returns the outcome
+    pub fn outcome(&self) -> &ReportOutcome {
+        &self.outcome
+    }
+
+    /// Returns the message, e.g. an error or a change description
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn to_wikitext(&self) -> String {
+        let link = format!("[[:{}]]", link_target(&self.title));
+        let message = self.message.replace('\n', " ").replace('|', "{{!}}");
+        format!("|-\n| {} || {} || {}\n", link, self.outcome, message)
+    }
+}
+
+/// Renders batch run results as wikitable report pages, and publishes them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReportPublisher {
+    page: Title,
+    run_id: String,
+    run_link: Option<String>,
+    max_page_size: Option<usize>,
+    summary: Option<String>,
+    require_bot: bool,
+}
+
+impl ReportPublisher {
+    /// Creates a publisher for the report page `page`; further pages are created as subpages `/2`, `/3`, ...
+    pub fn new(page: Title, run_id: impl Into<String>) -> Self {
+        Self {
+            page,
+            run_id: run_id.into(),
+            run_link: None,
+            max_page_size: None,
+            summary: None,
+            require_bot: false,
+        }
+    }
+
+    /// Sets the maximum size of a report page in bytes; defaults to the wiki's `maxarticlesize`
+    pub fn set_max_page_size(&mut self, max_page_size: Option<usize>) {
+        self.max_page_size = max_page_size;
+    }
+
+    /// Sets the edit summary; the run ID is appended to it
+    pub fn set_summary(&mut self, summary: Option<String>) {
+        self.summary = summary;
+    }
+
+    /// Sets the page the run ID links to in edit summaries, e.g. an EditGroups page; defaults to the first report page
+    pub fn set_run_link(&mut self, run_link: Option<String>) {
+        self.run_link = run_link;
+    }
+
+    /// Sets whether `publish` requires the `bot` right, so the report edits are flagged as bot edits
+    pub fn set_require_bot(&mut self, require_bot: bool) {
+        self.require_bot = require_bot;
+    }
+
+    /// Returns the run ID
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Returns the title of report page `number`, starting at 1
+    pub fn page_title(&self, number: usize) -> Title {
+        match number {
+            0 | 1 => self.page.clone(),
+            n => Title::new(
+                &format!("{}/{}", self.page.pretty(), n),
+                self.page.namespace_id(),
+            ),
+        }
+    }
+
+    /// Returns the edit summary for report page `number` of `total`, with the run ID linked
+    pub fn edit_summary(&self, api: &Api, number: usize, total: usize) -> String {
+        let summary = self.summary.as_deref().unwrap_or("Bot run report");
+        let run = match self
+            .run_link
+            .to_owned()
+            .or_else(|| self.page.full_pretty(api))
+        {
+            Some(target) => format!("[[{}|{}]]", target, self.run_id),
+            None => self.run_id.to_owned(),
+        };
+        match total {
+            0 | 1 => format!("{} (run {})", summary, run),
+            _ => format!("{} (run {}, page {}/{})", summary, run, number, total),
+        }
+    }
+
+    /// Renders the entries as wikitext pages, each at most `max_page_size` bytes
+    pub fn render(&self, entries: &[ReportEntry], max_page_size: usize) -> Vec<String> {
+        let row_budget = max_page_size.saturating_sub(PAGE_OVERHEAD).max(1);
+        let mut chunks: Vec<String> = vec![];
+        let mut current = String::new();
+        for row in entries.iter().map(|e| e.to_wikitext()) {
+            if !current.is_empty() && current.len() + row.len() > row_budget {
+                chunks.push(std::mem::take(&mut current));
+            }
+            current += &row;
+        }
+        if !current.is_empty() || chunks.is_empty() {
+            chunks.push(current);
+        }
+        let total = chunks.len();
+        chunks
+            .into_iter()
+            .enumerate()
+            .map(|(num, rows)| self.render_page(&rows, num + 1, total))
+            .collect()
+    }
+
+    fn render_page(&self, rows: &str, number: usize, total: usize) -> String {
+        let mut ret = format!("Report for run <code>{}</code>", self.run_id);
+        if total > 1 {
+            ret += &format!(", page {} of {}", number, total);
+            let nav: Vec<String> = (1..=total)
+                .map(|n| match n == number {
+                    true => n.to_string(),
+                    false => format!("[[{}|{}]]", link_target(&self.page_title(n)), n),
+                })
+                .collect();
+            ret += &format!(" ({})", nav.join(" · "));
+        }
+        ret += ".\n\n{| class=\"wikitable sortable\"\n! Page !! Result !! Message\n";
+        ret += rows;
+        ret += "|}\n";
+        ret
+    }
+
+    /// Renders the entries and creates or updates the report pages. Subpages left over from
+    /// an earlier, longer report are blanked. Returns the titles of the pages written.
+    ///
+    /// # Errors
+    /// If the bot right is required (see `set_require_bot`) but missing, will return a `MediaWikiError::PermissionDenied`.
+    pub async fn publish(
+        &self,
+        api: &Api,
+        entries: &[ReportEntry],
+    ) -> Result<Vec<Title>, MediaWikiError> {
+        if self.require_bot && !api.user().is_bot() {
+            return Err(MediaWikiError::PermissionDenied(
+                "publishing the report requires the bot right".to_string(),
+            ));
+        }
+        let max_page_size = self.max_page_size.unwrap_or_else(|| {
+            api.get_site_info_value("general", "maxarticlesize")
+                .as_u64()
+                .map(|size| size as usize)
+                .unwrap_or(DEFAULT_MAX_PAGE_SIZE)
+        });
+        let pages = self.render(entries, max_page_size);
+        let total = pages.len();
+        let mut ret = vec![];
+        for (num, text) in pages.into_iter().enumerate() {
+            let title = self.page_title(num + 1);
+            Page::new(title.clone())
+                .edit_text(api, text, self.edit_summary(api, num + 1, total))
+                .await?;
+            ret.push(title);
+        }
+        self.blank_stale_pages(api, total).await?;
+        Ok(ret)
+    }
+
+    /// Blanks the subpages after page `total` that still have content, up to the first missing one
+    async fn blank_stale_pages(&self, api: &Api, total: usize) -> Result<(), MediaWikiError> {
+        for number in total + 1.. {
+            let title = self.page_title(number);
+            let full_title = title
+                .full_pretty(api)
+                .ok_or_else(|| MediaWikiError::BadTitle(title.clone()))?;
+            let params = api.params_into(&[
+                ("action", "query"),
+                ("prop", "info"),
+                ("titles", &full_title),
+                ("formatversion", "2"),
+            ]);
+            let result = api.get_query_api_json(&params).await?;
+            let page = &result["query"]["pages"][0];
+            if !page.is_object() || page["missing"].as_bool() == Some(true) {
+                return Ok(());
+            }
+            if page["length"].as_u64() != Some(0) {
+                let summary = format!(
+                    "{}; page {} is no longer used",
+                    self.edit_summary(api, 1, 1),
+                    number
+                );
+                Page::new(title)
+                    .edit_text(api, String::new(), summary)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    fn entries() -> Vec<ReportEntry> {
+        vec![
+            ReportEntry::new(Title::new("Foo", 0), ReportOutcome::Edited(Some(123)), ""),
+            ReportEntry::new(Title::new("Bar.jpg", 6), ReportOutcome::Failed, "a|b"),
+        ]
+    }
+
+    fn mocked_responses() -> MockResponses {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"general":{},"namespaces":{"0":{"id":0,"name":"","canonical":""},
+                    "4":{"id":4,"name":"Project","canonical":"Project"}},
+                    "namespacealiases":[],"libraries":[],"extensions":[],"statistics":{}}}),
+            )
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "edit")],
+                json!({"edit":{"result":"Success","newrevid":6}}),
+            );
+        responses
+    }
+
+    #[test]
+    fn render_single_page() {
+        let api = Api::new_mocked(mocked_responses()).unwrap();
+        let mut publisher = ReportPublisher::new(Title::new("Bot/Report", 4), "run-1");
+        let pages = publisher.render(&entries(), 100_000);
+        assert_eq!(pages.len(), 1);
+        assert!(pages[0].contains("| [[:Foo]] || [[Special:Diff/123|edited]] || \n"));
+        assert!(pages[0].contains("| [[:{{ns:6}}:Bar.jpg]] || failed || a{{!}}b\n"));
+        assert!(pages[0].ends_with("|}\n"));
+        assert_eq!(
+            publisher.edit_summary(&api, 1, 1),
+            "Bot run report (run [[Project:Bot/Report|run-1]])"
+        );
+        publisher.set_run_link(Some(":toollabs:editgroups/b/CB/run-1".to_string()));
+        assert_eq!(
+            publisher.edit_summary(&api, 1, 1),
+            "Bot run report (run [[:toollabs:editgroups/b/CB/run-1|run-1]])"
+        );
+    }
+
+    #[test]
+    fn render_paginated() {
+        let api = Api::new_mocked(mocked_responses()).unwrap();
+        let mut publisher = ReportPublisher::new(Title::new("Bot/Report", 4), "run-1");
+        publisher.set_summary(Some("Report".to_string()));
+        let pages = publisher.render(&entries(), PAGE_OVERHEAD + 60);
+        assert_eq!(pages.len(), 2);
+        assert!(pages[0].contains("page 1 of 2 (1 · [[{{ns:4}}:Bot/Report/2|2]])"));
+        assert!(pages[1].contains("Bar.jpg"));
+        assert_eq!(publisher.page_title(2), Title::new("Bot/Report/2", 4));
+        assert_eq!(
+            publisher.edit_summary(&api, 2, 2),
+            "Report (run [[Project:Bot/Report|run-1]], page 2/2)"
+        );
+    }
+
+    #[tokio::test]
+    async fn publish_blanks_stale_pages() {
+        let mut responses = mocked_responses();
+        responses
+            .add(
+                &[("titles", "Project:Bot/Report/3")],
+                json!({"query":{"pages":[{"pageid":3,"ns":4,"title":"Project:Bot/Report/3","length":120}]}}),
+            )
+            .add(
+                &[("titles", "Project:Bot/Report/4")],
+                json!({"query":{"pages":[{"pageid":4,"ns":4,"title":"Project:Bot/Report/4","length":0}]}}),
+            )
+            .add(
+                &[("prop", "info")],
+                json!({"query":{"pages":[{"ns":4,"title":"Project:Bot/Report/5","missing":true}]}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut publisher = ReportPublisher::new(Title::new("Bot/Report", 4), "run-1");
+        publisher.set_max_page_size(Some(PAGE_OVERHEAD + 60));
+        let written = publisher.publish(&api, &entries()).await.unwrap();
+        assert_eq!(
+            written,
+            vec![Title::new("Bot/Report", 4), Title::new("Bot/Report/2", 4)]
+        );
+        let edits: Vec<_> = responses
+            .requests()
+            .into_iter()
+            .filter(|r| r.get("action").map(|a| a.as_str()) == Some("edit"))
+            .collect();
+        let titles: Vec<&str> = edits.iter().map(|r| r["title"].as_str()).collect();
+        assert_eq!(
+            titles,
+            [
+                "Project:Bot/Report",
+                "Project:Bot/Report/2",
+                "Project:Bot/Report/3"
+            ]
+        );
+        assert_eq!(edits[2]["text"], "");
+        assert!(edits[0]["summary"].contains("[[Project:Bot/Report|run-1]]"));
+    }
+
+    #[tokio::test]
+    async fn publish_requires_bot_right() {
+        let responses = mocked_responses();
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut publisher = ReportPublisher::new(Title::new("Bot/Report", 4), "run-1");
+        publisher.set_require_bot(true);
+        assert!(matches!(
+            publisher.publish(&api, &entries()).await,
+            Err(MediaWikiError::PermissionDenied(_))
+        ));
+        assert!(responses.requests().is_empty());
+    }
+}
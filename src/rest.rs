@@ -0,0 +1,537 @@
+/*!
+The `rest` module offers a client for the MediaWiki REST API (`rest.php/v1`), sharing the session of an `Api`.
+
+```no_run
+# tokio::runtime::Runtime::new().unwrap().block_on(async {
+let api = mediawiki::Api::new("https://en.wikipedia.org/w/api.php").await.unwrap();
+let rest = api.rest();
+let page = rest.get_page(&mediawiki::Title::new("Rust (programming language)", 0)).await.unwrap();
+println!("{}", page.source().unwrap_or_default());
+# });
+```
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::conflict::Conflict;
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A page, as returned by the REST API `page` endpoints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestPage {
+    id: u64,
+    key: String,
+    title: String,
+    latest_revision: Option<u64>,
+    latest_timestamp: Option<String>,
+    content_model: Option<String>,
+    source: Option<String>,
+    html: Option<String>,
+    etag: Option<String>,
+}
+
+impl RestPage {
+    /// Creates a page from a REST API response, and the `ETag` header of that response
+    pub fn from_json(j: &Value, etag: Option<&str>) -> Option<Self> {
+        Some(Self {
+            id: j["id"].as_u64()?,
+            key: j["key"].as_str()?.to_string(),
+            title: j["title"].as_str()?.to_string(),
+            latest_revision: j["latest"]["id"].as_u64(),
+            latest_timestamp: j["latest"]["timestamp"].as_str().map(|s| s.to_string()),
+            content_model: j["content_model"].as_str().map(|s| s.to_string()),
+            source: j["source"].as_str().map(|s| s.to_string()),
+            html: j["html"].as_str().map(|s| s.to_string()),
+            etag: etag.map(|s| s.to_string()),
+        })
+    }
+
+    /// Returns the page ID
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the page title with underscores, as used in URLs
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the page title with spaces
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Returns the ID of the latest revision
+    pub fn latest_revision(&self) -> Option<u64> {
+        self.latest_revision
+    }
+
+    /// Returns the timestamp of the latest revision
+    pub fn latest_timestamp(&self) -> Option<&str> {
+        self.latest_timestamp.as_deref()
+    }
+
+    /// Returns the content model, e.g. `wikitext`
+    pub fn content_model(&self) -> Option<&str> {
+        self.content_model.as_deref()
+    }
+
+    /// Returns the page source, e.g. the wikitext
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Returns the Parsoid HTML, if loaded via `RestApi::get_page_with_html`
+    pub fn html(&self) -> Option<&str> {
+        self.html.as_deref()
+    }
+
+    /// Returns the `ETag` of the response, used to detect edit conflicts
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+}
+
+/// A result of the REST API `search/page` endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestSearchResult {
+    id: u64,
+    key: String,
+    title: String,
+    excerpt: Option<String>,
+    description: Option<String>,
+}
+
+impl RestSearchResult {
+    /// Creates a search result from an entry of the `pages` array
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            id: j["id"].as_u64()?,
+            key: j["key"].as_str()?.to_string(),
+            title: j["title"].as_str()?.to_string(),
+            excerpt: j["excerpt"].as_str().map(|s| s.to_string()),
+            description: j["description"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    /// Returns the page ID
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the page title with underscores
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Returns the page title with spaces
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Returns the matching excerpt, with search terms highlighted as HTML
+    pub fn excerpt(&self) -> Option<&str> {
+        self.excerpt.as_deref()
+    }
+
+    /// Returns the short description of the page, if any
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+/// A client for the MediaWiki REST API; clones of the `Api` share the session
+#[derive(Debug, Clone)]
+pub struct RestApi {
+    api: Api,
+    rest_url: String,
+}
+
+impl RestApi {
+    /// Creates a REST client next to the `api.php` of `api`, e.g. `https://en.wikipedia.org/w/rest.php/v1`
+    pub fn new(api: &Api) -> Self {
+        Self {
+            rest_url: Self::rest_url_for(api.api_url()),
+            api: api.clone(),
+        }
+    }
+
+    /// Returns the REST API base URL
+    pub fn rest_url(&self) -> &str {
+        &self.rest_url
+    }
+
+    /// Sets the REST API base URL, for wikis where it is not next to `api.php`
+    pub fn set_rest_url<S: Into<String>>(&mut self, rest_url: S) {
+        self.rest_url = rest_url.into().trim_end_matches('/').to_string();
+    }
+
+    fn rest_url_for(api_url: &str) -> String {
+        let base = api_url.strip_suffix("api.php").unwrap_or(api_url);
+        format!("{}rest.php/v1", base)
+    }
+
+    fn page_url(&self, title: &Title, suffix: &str) -> Result<String, MediaWikiError> {
+        let key = title
+            .full_with_underscores(&self.api)
+            .ok_or_else(|| MediaWikiError::BadTitle(title.clone()))?;
+        Ok(format!(
+            "{}/page/{}{}",
+            self.rest_url,
+            urlencoding::encode(&key),
+            suffix
+        ))
+    }
+
+    /// Sends a request, and returns the response if its status is a success
+    async fn send(
+        &self,
+        url: &str,
+        method: &str,
+        title: Option<&Title>,
+        body: Option<&Value>,
+        if_match: Option<&str>,
+    ) -> Result<reqwest::Response, MediaWikiError> {
+        if let Some(response) = self.simulate_edit(url, method, body)? {
+            return Ok(response);
+        }
+        if let Some(mock) = self.api.mock() {
+            let path = url.strip_prefix(&self.rest_url).unwrap_or(url);
+            let response = mock.respond_http(method, path, if_match, body)?;
+            return Self::check_status(response, title).await;
+        }
+        let headers = self.api.request_headers(url, &HashMap::new(), method)?;
+        let mut request = match method {
+            "GET" => self.api.client().get(url),
+            "PUT" => self.api.client().put(url),
+            "POST" => self.api.client().post(url),
+            other => return Err(From::from(format!("Unsupported method '{}'", other))),
+        }
+        .headers(headers);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        if let Some(etag) = if_match {
+            request = request.header(reqwest::header::IF_MATCH, etag);
+        }
+        let response = request.send().await?;
+        Self::check_status(response, title).await
+    }
+
+    /// In dry-run mode, records a write request (a `PUT`, or a `POST` with a token) and returns a
+    /// simulated response with the body `{"dryrun":true}`, without sending it
+    fn simulate_edit(
+        &self,
+        url: &str,
+        method: &str,
+        body: Option<&Value>,
+    ) -> Result<Option<reqwest::Response>, MediaWikiError> {
+        let is_write = match method {
+            "PUT" => true,
+            "POST" => body.is_some_and(|b| b.get("token").is_some()),
+            _ => false,
+        };
+        if !self.api.dry_run() || !is_write {
+            return Ok(None);
+        }
+        let mut body = body.cloned().unwrap_or_default();
+        if let Some(o) = body.as_object_mut() {
+            o.remove("token");
+        }
+        let request = [
+            ("method".to_string(), method.to_string()),
+            ("url".to_string(), url.to_string()),
+            ("body".to_string(), body.to_string()),
+        ];
+        self.api
+            .record_dry_run("rest", request.into_iter().collect());
+        let response = http::Response::builder()
+            .status(StatusCode::OK)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::json!({"dryrun": true}).to_string())
+            .map_err(|e| MediaWikiError::String(e.to_string()))?;
+        Ok(Some(reqwest::Response::from(response)))
+    }
+
+    /// Returns the response if its status is a success, and the error otherwise
+    async fn check_status(
+        response: reqwest::Response,
+        title: Option<&Title>,
+    ) -> Result<reqwest::Response, MediaWikiError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let mut details: Value = response
+            .json()
+            .await
+            .unwrap_or_else(|_| serde_json::json!({}));
+        if let Some(o) = details.as_object_mut() {
+            o.entry("httpCode").or_insert(status.as_u16().into());
+        }
+        Err(Self::status_error(status, title, details))
+    }
+
+    /// Returns the error for an unsuccessful response; edit conflicts are handled by the caller
+    fn status_error(status: StatusCode, title: Option<&Title>, details: Value) -> MediaWikiError {
+        match (status, title) {
+            (StatusCode::NOT_FOUND, Some(title)) => MediaWikiError::Missing(title.clone()),
+            _ => MediaWikiError::BadResponse(details),
+        }
+    }
+
+    /// Returns true if the status indicates that the page was changed since it was loaded
+    fn is_conflict(status: StatusCode) -> bool {
+        status == StatusCode::CONFLICT || status == StatusCode::PRECONDITION_FAILED
+    }
+
+    /// Returns the page, including its source and `ETag`
+    pub async fn get_page(&self, title: &Title) -> Result<RestPage, MediaWikiError> {
+        self.get_page_json(title, "").await
+    }
+
+    /// Returns the page, including its Parsoid HTML and `ETag`. The rendering is stashed on the wiki,
+    /// so modified HTML can be converted back with minimal changes via `html_to_wikitext`.
+    pub async fn get_page_with_html(&self, title: &Title) -> Result<RestPage, MediaWikiError> {
+        self.get_page_json(title, "/with_html?stash=true").await
+    }
+
+    async fn get_page_json(&self, title: &Title, suffix: &str) -> Result<RestPage, MediaWikiError> {
+        let url = self.page_url(title, suffix)?;
+        let response = self.send(&url, "GET", Some(title), None, None).await?;
+        let etag = response
+            .headers()
+            .get(reqwest::header::ETAG)
+            .map(|v| v.to_str())
+            .transpose()?
+            .map(|s| s.to_string());
+        let j: Value = response.json().await?;
+        RestPage::from_json(&j, etag.as_deref()).ok_or(MediaWikiError::BadResponse(j))
+    }
+
+    /// Returns the rendered HTML of the page
+    pub async fn get_html(&self, title: &Title) -> Result<String, MediaWikiError> {
+        let url = self.page_url(title, "/html")?;
+        let response = self.send(&url, "GET", Some(title), None, None).await?;
+        Ok(response.text().await?)
+    }
+
+    /// Converts Parsoid HTML of a page to wikitext, via the `transform/html/to/wikitext` endpoint.
+    /// With the `original` page the HTML was loaded from via `get_page_with_html`, only the modified
+    /// parts are serialized again (selective serialization), so unchanged wikitext stays as it was.
+    pub async fn html_to_wikitext(
+        &self,
+        title: &Title,
+        html: &str,
+        original: Option<&RestPage>,
+    ) -> Result<String, MediaWikiError> {
+        let key = title
+            .full_with_underscores(&self.api)
+            .ok_or_else(|| MediaWikiError::BadTitle(title.clone()))?;
+        let mut url = format!(
+            "{}/transform/html/to/wikitext/{}",
+            self.rest_url,
+            urlencoding::encode(&key)
+        );
+        let mut body = serde_json::json!({ "html": html });
+        if let Some(original) = original {
+            if let Some(revid) = original.latest_revision() {
+                url += &format!("/{}", revid);
+                body["original"] = serde_json::json!({ "revid": revid });
+                if let Some(original_html) = original.html() {
+                    body["original"]["html"] = serde_json::json!({ "body": original_html });
+                }
+            }
+        }
+        let etag = original.and_then(|o| o.etag());
+        let response = self.send(&url, "POST", None, Some(&body), etag).await?;
+        Ok(response.text().await?)
+    }
+
+    /// Replaces the source of a page that was loaded via `get_page`, and returns the updated page.
+    /// If the page was changed in the meantime, returns `MediaWikiError::EditConflict`.
+    /// In dry-run mode, the update is not sent, and `base` with the new source is returned.
+    pub async fn update_page(
+        &self,
+        base: &RestPage,
+        source: &str,
+        comment: &str,
+    ) -> Result<RestPage, MediaWikiError> {
+        let title = Title::new_from_full(base.title(), &self.api);
+        let url = self.page_url(&title, "")?;
+        let mut body = serde_json::json!({"source": source, "comment": comment});
+        if let Some(id) = base.latest_revision() {
+            body["latest"] = serde_json::json!({ "id": id });
+        }
+        if !self.api.uses_oauth() {
+            body["token"] = Value::String(self.api.get_edit_token().await?);
+        }
+        match self
+            .send(&url, "PUT", Some(&title), Some(&body), base.etag())
+            .await
+        {
+            Ok(response) => {
+                let j: Value = response.json().await?;
+                if Api::is_dry_run_result(&j) {
+                    return Ok(RestPage {
+                        source: Some(source.to_string()),
+                        etag: None,
+                        ..base.clone()
+                    });
+                }
+                RestPage::from_json(&j, None).ok_or(MediaWikiError::BadResponse(j))
+            }
+            Err(MediaWikiError::BadResponse(details)) if Self::is_conflict_details(&details) => {
+                Err(MediaWikiError::EditConflict(Box::new(Conflict::new(
+                    base.latest_revision(),
+                    base.source(),
+                    source,
+                ))))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns true if the error details of a failed update describe an edit conflict
+    fn is_conflict_details(details: &Value) -> bool {
+        details["httpCode"]
+            .as_u64()
+            .and_then(|code| StatusCode::from_u16(u16::try_from(code).ok()?).ok())
+            .is_some_and(Self::is_conflict)
+            || details["errorKey"].as_str() == Some("rest-update-mismatch")
+    }
+
+    /// Returns up to `limit` pages matching a full-text search
+    pub async fn search(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<RestSearchResult>, MediaWikiError> {
+        let mut url = format!(
+            "{}/search/page?q={}",
+            self.rest_url,
+            urlencoding::encode(query)
+        );
+        if let Some(limit) = limit {
+            url += &format!("&limit={}", limit);
+        }
+        let j: Value = self
+            .send(&url, "GET", None, None, None)
+            .await?
+            .json()
+            .await?;
+        Ok(Self::search_results_from_json(&j))
+    }
+
+    fn search_results_from_json(j: &Value) -> Vec<RestSearchResult> {
+        j["pages"]
+            .as_array()
+            .map(|pages| {
+                pages
+                    .iter()
+                    .filter_map(RestSearchResult::from_json)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl Api {
+    /// Returns a client for the REST API of this wiki, sharing the session
+    pub fn rest(&self) -> RestApi {
+        RestApi::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rest_url() {
+        assert_eq!(
+            RestApi::rest_url_for("https://en.wikipedia.org/w/api.php"),
+            "https://en.wikipedia.org/w/rest.php/v1"
+        );
+    }
+
+    #[test]
+    fn page_from_json() {
+        let j = json!({"id":9228,"key":"Earth","title":"Earth","latest":{"id":1234,"timestamp":"2024-01-01T00:00:00Z"},
+            "content_model":"wikitext","license":{},"source":"'''Earth''' is"});
+        let page = RestPage::from_json(&j, Some("\"1234\"")).unwrap();
+        assert_eq!(page.latest_revision(), Some(1234));
+        assert_eq!(page.source(), Some("'''Earth''' is"));
+        assert_eq!(page.etag(), Some("\"1234\""));
+        assert!(RestPage::from_json(&json!({"httpCode":404}), None).is_none());
+    }
+
+    #[test]
+    fn search_results() {
+        let j = json!({"pages":[{"id":1,"key":"Jupiter","title":"Jupiter","excerpt":"<span class=\"searchmatch\">Jupiter</span>","description":"Fifth planet"},{"bad":true}]});
+        let results = RestApi::search_results_from_json(&j);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].description(), Some("Fifth planet"));
+    }
+
+    #[test]
+    fn conflict_details() {
+        assert!(RestApi::is_conflict_details(
+            &json!({"httpCode":409,"errorKey":"rest-update-mismatch"})
+        ));
+        assert!(RestApi::is_conflict_details(&json!({"httpCode":412})));
+        assert!(!RestApi::is_conflict_details(&json!({"httpCode":403})));
+        assert!(matches!(
+            RestApi::status_error(
+                StatusCode::NOT_FOUND,
+                Some(&Title::new("Nope", 0)),
+                Value::Null
+            ),
+            MediaWikiError::Missing(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn update_page_dry_run() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"general":{},"namespaces":{"0":{"id":0,"name":"","canonical":""}},"namespacealiases":[],"libraries":[],"extensions":[],"statistics":{}}}),
+            )
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add_rest(
+                "GET",
+                "/page/Foo",
+                200,
+                &[("etag", "W/\"5/abc\"")],
+                json!({"id":1,"key":"Foo","title":"Foo","latest":{"id":5,"timestamp":"2024-01-31T12:00:00Z"},
+                    "content_model":"wikitext","source":"Old"}),
+            );
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        api.set_dry_run(true);
+        let rest = api.rest();
+        let base = rest.get_page(&Title::new("Foo", 0)).await.unwrap();
+        let updated = rest.update_page(&base, "New", "Summary").await.unwrap();
+        assert_eq!(updated.source(), Some("New"));
+        assert_eq!(updated.latest_revision(), Some(5));
+        assert!(responses
+            .requests()
+            .iter()
+            .all(|r| !r.get("route").is_some_and(|route| route.starts_with("PUT"))));
+        let recorded = api.dry_run_requests();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0]["method"], "PUT");
+        let body: Value = serde_json::from_str(&recorded[0]["body"]).unwrap();
+        assert_eq!(body["source"], "New");
+        assert!(body.get("token").is_none());
+    }
+}
@@ -0,0 +1,116 @@
+/*!
+The `review` module thanks users for edits via `action=thank` (Thanks extension),
+and marks changes as patrolled via `action=patrol`.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, TokenType};
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+
+/// The change to mark as patrolled with `Api::patrol`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PatrolTarget {
+    /// A recent changes ID
+    Rcid(u64),
+    /// A revision ID
+    Revid(u64),
+}
+
+impl PatrolTarget {
+    /// Returns the API parameter name and value
+    fn param(&self) -> (&'static str, String) {
+        match self {
+            Self::Rcid(rcid) => ("rcid", rcid.to_string()),
+            Self::Revid(revid) => ("revid", revid.to_string()),
+        }
+    }
+}
+
+impl Api {
+    /// Thanks the author of a revision, and returns the name of the thanked user
+    pub async fn thank(&self, revid: u64) -> Result<String, MediaWikiError> {
+        let token = self.get_token_of_type(TokenType::Csrf).await?;
+        let params = self.params_into(&[
+            ("action", "thank"),
+            ("rev", &revid.to_string()),
+            ("source", env!("CARGO_PKG_NAME")),
+            ("token", &token),
+        ]);
+        let result = self.post_query_api_json(&params).await?;
+        match result["result"]["success"].as_u64() {
+            Some(1) => Ok(result["result"]["recipient"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string()),
+            _ => Err(MediaWikiError::from_api_result(&result)
+                .unwrap_or(MediaWikiError::EditError(result))),
+        }
+    }
+
+    /// Marks a change as patrolled, using the patrol token.
+    /// Fails with `MediaWikiError::NotPatrollable` if the change cannot be patrolled, e.g. because it is too old.
+    pub async fn patrol(&self, target: PatrolTarget) -> Result<Value, MediaWikiError> {
+        let token = self.get_token_of_type(TokenType::Patrol).await?;
+        let (key, value) = target.param();
+        let params = self.params_into(&[("action", "patrol"), (key, &value), ("token", &token)]);
+        let error = match self.post_query_api_json(&params).await {
+            Ok(result) if result["patrol"].is_object() => return Ok(result),
+            Ok(result) => MediaWikiError::from_api_result(&result)
+                .unwrap_or(MediaWikiError::EditError(result)),
+            Err(e) => e,
+        };
+        Err(match error {
+            MediaWikiError::Api { code, info, .. } if code == "notpatrollable" => {
+                MediaWikiError::NotPatrollable(info)
+            }
+            MediaWikiError::Api { code, info, .. } if code == "permissiondenied" => {
+                MediaWikiError::PermissionDenied(info)
+            }
+            e => e,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn thank_and_patrol() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens"), ("type", "csrf")],
+                json!({"query":{"tokens":{"csrftoken":"csrf+\\"}}}),
+            )
+            .add(
+                &[("meta", "tokens"), ("type", "patrol")],
+                json!({"query":{"tokens":{"patroltoken":"patrol+\\"}}}),
+            )
+            .add(
+                &[("action", "thank")],
+                json!({"result":{"success":1,"recipient":"Foo"}}),
+            )
+            .add(
+                &[("action", "patrol"), ("rcid", "12")],
+                json!({"patrol":{"rcid":12,"ns":0,"title":"Bar"}}),
+            )
+            .add(
+                &[("action", "patrol")],
+                json!({"error":{"code":"notpatrollable","info":"The revision r13 can't be patrolled as it's too old."}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        assert_eq!(api.thank(5).await.unwrap(), "Foo");
+        assert_eq!(responses.requests().pop().unwrap()["token"], "csrf+\\");
+        let result = api.patrol(PatrolTarget::Rcid(12)).await.unwrap();
+        assert_eq!(result["patrol"]["title"], "Bar");
+        assert_eq!(responses.requests().pop().unwrap()["token"], "patrol+\\");
+        assert!(matches!(
+            api.patrol(PatrolTarget::Revid(13)).await,
+            Err(MediaWikiError::NotPatrollable(_))
+        ));
+    }
+}
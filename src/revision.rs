@@ -5,46 +5,195 @@ The `Revision` class deals with page revisions.
 #![deny(missing_docs)]
 
 use chrono::NaiveDateTime;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
 
+use crate::api::Api;
+use crate::title::Title;
 use crate::MediaWikiError;
 
 /// The revision properties to fetch.
-pub(crate) const RVPROP: &str = "ids|content|timestamp|size|sha1|comment|tags|user|userid";
+pub(crate) const RVPROP: &str = "ids|flags|content|timestamp|size|sha1|comment|tags|user|userid";
+
+/// A revision field that can be hidden by revision deletion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum RevisionField<T> {
+    /// The field was returned by the API.
+    Present(T),
+    /// The field was not requested, or not returned for another reason.
+    NotRequested,
+    /// The field was hidden (deleted or suppressed).
+    Hidden,
+}
+
+impl<T> RevisionField<T> {
+    fn from_option(value: Option<T>, hidden: bool) -> Self {
+        match (value, hidden) {
+            (_, true) => Self::Hidden,
+            (Some(value), false) => Self::Present(value),
+            (None, false) => Self::NotRequested,
+        }
+    }
+
+    /// Returns the value, if present.
+    pub fn present(self) -> Option<T> {
+        match self {
+            Self::Present(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the field was hidden.
+    pub fn is_hidden(&self) -> bool {
+        matches!(self, Self::Hidden)
+    }
+
+    /// Converts from `&RevisionField<T>` to `RevisionField<&T>`.
+    pub fn as_ref(&self) -> RevisionField<&T> {
+        match self {
+            Self::Present(value) => RevisionField::Present(value),
+            Self::NotRequested => RevisionField::NotRequested,
+            Self::Hidden => RevisionField::Hidden,
+        }
+    }
+
+    /// Converts from `&RevisionField<T>` to `RevisionField<&T::Target>`, e.g. `&str` for `String`.
+    pub fn as_deref(&self) -> RevisionField<&T::Target>
+    where
+        T: std::ops::Deref,
+    {
+        match self {
+            Self::Present(value) => RevisionField::Present(value),
+            Self::NotRequested => RevisionField::NotRequested,
+            Self::Hidden => RevisionField::Hidden,
+        }
+    }
+}
+
+/// Which parts of a revision are hidden by revision deletion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct RevisionVisibility {
+    user_hidden: bool,
+    comment_hidden: bool,
+    text_hidden: bool,
+    sha1_hidden: bool,
+    suppressed: bool,
+}
+
+impl RevisionVisibility {
+    /// Reads the visibility flags from API-returned revision JSON (`formatversion` 1 or 2).
+    pub fn from_json(j: &Value) -> Self {
+        Self {
+            user_hidden: Self::flag(&j["userhidden"]),
+            comment_hidden: Self::flag(&j["commenthidden"]),
+            text_hidden: Self::flag(&j["texthidden"])
+                || Self::flag(&j["slots"]["main"]["texthidden"]),
+            sha1_hidden: Self::flag(&j["sha1hidden"]),
+            suppressed: Self::flag(&j["suppressed"]),
+        }
+    }
+
+    // formatversion=1 uses an empty string, formatversion=2 uses a boolean
+    pub(crate) fn flag(v: &Value) -> bool {
+        match v {
+            Value::Bool(b) => *b,
+            Value::Null => false,
+            _ => true,
+        }
+    }
+
+    /// Returns true if the user is hidden.
+    pub fn user_hidden(&self) -> bool {
+        self.user_hidden
+    }
+
+    /// Returns true if the edit summary is hidden.
+    pub fn comment_hidden(&self) -> bool {
+        self.comment_hidden
+    }
+
+    /// Returns true if the text is hidden.
+    pub fn text_hidden(&self) -> bool {
+        self.text_hidden
+    }
+
+    /// Returns true if the SHA1 is hidden.
+    pub fn sha1_hidden(&self) -> bool {
+        self.sha1_hidden
+    }
+
+    /// Returns true if the hidden fields are also hidden from administrators.
+    pub fn suppressed(&self) -> bool {
+        self.suppressed
+    }
+
+    /// Returns true if any field is hidden.
+    pub fn is_deleted(&self) -> bool {
+        self.user_hidden || self.comment_hidden || self.text_hidden || self.sha1_hidden
+    }
+}
 
 /// Repesents a revision of a page.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Revision {
-    id: u64,
+    id: Option<u64>,
     parent_id: Option<u64>,
-    wikitext: Option<String>,
+    wikitext: RevisionField<String>,
     timestamp: Option<NaiveDateTime>,
     size: Option<usize>,
-    sha1: Option<String>,
+    sha1: RevisionField<String>,
     tags: Vec<String>,
-    user: Option<String>,
-    userid: Option<u64>,
+    user: RevisionField<String>,
+    userid: RevisionField<u64>,
+    comment: RevisionField<String>,
+    minor: bool,
+    bot: bool,
+    #[serde(default)]
+    deleted: bool,
+    visibility: RevisionVisibility,
 }
 
 impl Revision {
     /// Creates a new revision from API-returned JSON.
+    /// Fields hidden by revision deletion are represented as [`RevisionField::Hidden`].
     pub fn from_json(j: &Value) -> Result<Self, MediaWikiError> {
-        let id = j["revid"]
-            .as_u64()
-            .ok_or_else(|| MediaWikiError::UnexpectedResultFormat("No revision ID".to_string()))?;
+        let visibility = RevisionVisibility::from_json(j);
+        let main = &j["slots"]["main"];
+        let wikitext = main["content"]
+            .as_str()
+            .or_else(|| main["*"].as_str()) // formatversion=1
+            .or_else(|| j["*"].as_str()); // formatversion=1 without slots
         Ok(Self {
-            id,
+            id: j["revid"].as_u64(),
             parent_id: j["parentid"].as_u64(),
-            wikitext: j["slots"]["main"]["content"]
-                .as_str()
-                .map(|s| s.to_string()),
+            wikitext: RevisionField::from_option(
+                wikitext.map(|s| s.to_string()),
+                visibility.text_hidden,
+            ),
             timestamp: j["timestamp"]
                 .as_str()
                 .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%SZ").ok()),
             size: j["size"].as_u64().map(|s| s as usize),
-            sha1: j["sha1"].as_str().map(|s| s.to_string()),
-            user: j["user"].as_str().map(|s| s.to_string()),
-            userid: j["userid"].as_u64(),
+            sha1: RevisionField::from_option(
+                j["sha1"].as_str().map(|s| s.to_string()),
+                visibility.sha1_hidden,
+            ),
+            user: RevisionField::from_option(
+                j["user"].as_str().map(|s| s.to_string()),
+                visibility.user_hidden,
+            ),
+            userid: RevisionField::from_option(j["userid"].as_u64(), visibility.user_hidden),
+            comment: RevisionField::from_option(
+                j["comment"].as_str().map(|s| s.to_string()),
+                visibility.comment_hidden,
+            ),
+            minor: RevisionVisibility::flag(&j["minor"]),
+            bot: RevisionVisibility::flag(&j["bot"]),
+            deleted: false,
+            visibility,
             tags: j["tags"]
                 .as_array()
                 .map(|a| {
@@ -56,8 +205,8 @@ impl Revision {
         })
     }
 
-    /// Returns the revision ID.
-    pub fn id(&self) -> u64 {
+    /// Returns the revision ID, if it was returned (`rvprop=ids`).
+    pub fn id(&self) -> Option<u64> {
         self.id
     }
 
@@ -73,6 +222,326 @@ impl Revision {
 
     /// Returns the wikitext of the revision.
     pub fn wikitext(&self) -> Option<&str> {
+        self.wikitext.as_ref().present().map(|s| s.as_str())
+    }
+
+    /// Returns the wikitext of the revision, or whether it was hidden.
+    pub fn wikitext_field(&self) -> RevisionField<&str> {
         self.wikitext.as_deref()
     }
+
+    /// Returns the user name of the revision author, or whether it was hidden.
+    pub fn user(&self) -> RevisionField<&str> {
+        self.user.as_deref()
+    }
+
+    /// Returns the user ID of the revision author, or whether it was hidden.
+    pub fn userid(&self) -> RevisionField<u64> {
+        self.userid
+    }
+
+    /// Returns the edit summary of the revision, or whether it was hidden.
+    pub fn comment(&self) -> RevisionField<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Returns the SHA1 of the revision content, or whether it was hidden.
+    pub fn sha1(&self) -> RevisionField<&str> {
+        self.sha1.as_deref()
+    }
+
+    /// Returns the revision size in bytes.
+    pub fn size(&self) -> Option<usize> {
+        self.size
+    }
+
+    /// Returns the change tags of the revision.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns which parts of the revision are hidden.
+    pub fn visibility(&self) -> &RevisionVisibility {
+        &self.visibility
+    }
+
+    /// Returns true if the revision is marked as a minor edit.
+    pub fn is_minor(&self) -> bool {
+        self.minor
+    }
+
+    /// Returns true if the revision was made by a bot, where the source reports it (e.g. recent changes).
+    pub fn is_bot(&self) -> bool {
+        self.bot
+    }
+
+    /// Returns true if the revision was deleted along with its page, as returned by `Page::deleted_revisions`.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    /// Marks the revision as deleted along with its page.
+    pub(crate) fn set_deleted(&mut self, deleted: bool) {
+        self.deleted = deleted;
+    }
+
+    /// Checks the SHA1 of the wikitext against the SHA1 of the revision, which can be hexadecimal (API)
+    /// or base 36 (XML dumps). Returns `None` if the wikitext or SHA1 is not available.
+    pub fn verify_sha1(&self) -> Option<bool> {
+        let expected = self.sha1().present()?.to_lowercase();
+        let digest = Sha1::digest(self.wikitext()?.as_bytes());
+        let actual = match expected.len() {
+            40 => digest.iter().map(|b| format!("{:02x}", b)).collect(),
+            _ => sha1_base36(&digest),
+        };
+        Some(actual == expected)
+    }
+}
+
+/// Returns a SHA1 digest in base 36, zero-padded to 31 digits, as used in MediaWiki dumps.
+fn sha1_base36(digest: &[u8]) -> String {
+    let mut number = digest.to_vec();
+    let mut digits = vec![];
+    while number.iter().any(|&b| b != 0) {
+        let mut remainder = 0u32;
+        for byte in number.iter_mut() {
+            let value = (remainder << 8) | u32::from(*byte);
+            *byte = (value / 36) as u8;
+            remainder = value % 36;
+        }
+        digits.push(std::char::from_digit(remainder, 36).unwrap_or('0'));
+    }
+    while digits.len() < 31 {
+        digits.push('0');
+    }
+    digits.iter().rev().collect()
+}
+
+/// The difference between two revisions, as returned by `action=compare`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    from_revid: u64,
+    to_revid: u64,
+    from_title: Option<Title>,
+    to_title: Option<Title>,
+    body: String,
+}
+
+impl Comparison {
+    /// Creates a comparison from an `action=compare` result (`formatversion=2`).
+    pub fn from_json(j: &Value) -> Option<Self> {
+        let c = &j["compare"];
+        let title = |prefix: &str| {
+            let title = c[format!("{}title", prefix)].as_str()?;
+            let ns = c[format!("{}ns", prefix)].as_i64().unwrap_or(0);
+            Some(Title::new(title, ns))
+        };
+        Some(Self {
+            from_revid: c["fromrevid"].as_u64()?,
+            to_revid: c["torevid"].as_u64()?,
+            from_title: title("from"),
+            to_title: title("to"),
+            body: c["body"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    /// Returns the ID of the old revision.
+    pub fn from_revid(&self) -> u64 {
+        self.from_revid
+    }
+
+    /// Returns the ID of the new revision.
+    pub fn to_revid(&self) -> u64 {
+        self.to_revid
+    }
+
+    /// Returns the title of the page of the old revision.
+    pub fn from_title(&self) -> Option<&Title> {
+        self.from_title.as_ref()
+    }
+
+    /// Returns the title of the page of the new revision.
+    pub fn to_title(&self) -> Option<&Title> {
+        self.to_title.as_ref()
+    }
+
+    /// Returns the diff as HTML table rows, as rendered by MediaWiki (or wikidiff2); empty if the revisions are identical.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+}
+
+impl Api {
+    /// Fetches a single revision by ID, including its wikitext.
+    ///
+    /// # Errors
+    /// If the revision does not exist or was deleted, will return a `MediaWikiError::MissingRevision`.
+    pub async fn revision(&self, revid: u64) -> Result<Revision, MediaWikiError> {
+        self.revisions(&[revid])
+            .await?
+            .pop()
+            .ok_or(MediaWikiError::MissingRevision(revid))
+    }
+
+    /// Fetches revisions by ID, including their wikitext, in batches via `revids`.
+    /// Revisions are returned in the order of `revids`; missing revisions are skipped.
+    pub async fn revisions(&self, revids: &[u64]) -> Result<Vec<Revision>, MediaWikiError> {
+        let mut found: HashMap<u64, Revision> = HashMap::new();
+        for chunk in revids.chunks(self.multi_value_limit()) {
+            let ids: Vec<String> = chunk.iter().map(|id| id.to_string()).collect();
+            let params = self.params_into(&[
+                ("action", "query"),
+                ("prop", "revisions"),
+                ("revids", &ids.join("|")),
+                ("rvslots", "main"),
+                ("rvprop", RVPROP),
+                ("formatversion", "2"),
+            ]);
+            // Large contents make the API return the revisions over several continued requests
+            let results: Vec<_> = self
+                .get_query_api_json_limit_iter(&params, None)
+                .await
+                .collect()
+                .await;
+            for result in results {
+                let result = result?;
+                let revisions = result["query"]["pages"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|page| page["revisions"].as_array())
+                    .flatten();
+                for revision in revisions {
+                    let revision = Revision::from_json(revision)?;
+                    if let Some(id) = revision.id() {
+                        found.insert(id, revision);
+                    }
+                }
+            }
+        }
+        Ok(revids.iter().filter_map(|id| found.remove(id)).collect())
+    }
+
+    /// Compares two revisions via `action=compare`, and returns the diff.
+    ///
+    /// # Errors
+    /// If either revision does not exist, will return a `MediaWikiError::MissingRevision`.
+    pub async fn compare(
+        &self,
+        old_revid: u64,
+        new_revid: u64,
+    ) -> Result<Comparison, MediaWikiError> {
+        let (old, new) = (old_revid.to_string(), new_revid.to_string());
+        let params = self.params_into(&[
+            ("action", "compare"),
+            ("fromrev", &old),
+            ("torev", &new),
+            ("prop", "diff|ids|title"),
+            ("formatversion", "2"),
+        ]);
+        let result = self.get_query_api_json(&params).await?;
+        if result["error"]["code"].as_str() == Some("nosuchrevid") {
+            let missing = match result["error"]["info"].as_str() {
+                Some(info) if info.contains(&new) && !info.contains(&old) => new_revid,
+                _ => old_revid,
+            };
+            return Err(MediaWikiError::MissingRevision(missing));
+        }
+        Comparison::from_json(&result).ok_or(MediaWikiError::BadResponse(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revision_visible() {
+        let j = json!({"revid":123,"parentid":122,"user":"Foo","userid":5,"comment":"Bar","sha1":"abc","size":3,"slots":{"main":{"content":"Baz"}}});
+        let rev = Revision::from_json(&j).unwrap();
+        assert_eq!(rev.user(), RevisionField::Present("Foo"));
+        assert_eq!(rev.userid(), RevisionField::Present(5));
+        assert_eq!(rev.comment(), RevisionField::Present("Bar"));
+        assert_eq!(rev.wikitext(), Some("Baz"));
+        assert!(!rev.visibility().is_deleted());
+    }
+
+    #[tokio::test]
+    async fn revisions_and_compare() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("action", "compare")],
+                json!({"compare":{"fromid":1,"fromrevid":122,"fromns":0,"fromtitle":"Foo",
+                    "toid":1,"torevid":123,"tons":0,"totitle":"Foo","body":"<tr><td>diff</td></tr>"}}),
+            )
+            .add(
+                &[("revids", "123|99|122")],
+                json!({"batchcomplete":true,"query":{"badrevids":{"99":{"revid":99,"missing":true}},
+                    "pages":[{"pageid":1,"ns":0,"title":"Foo","revisions":[
+                        {"revid":122,"parentid":0,"slots":{"main":{"content":"Old"}}},
+                        {"revid":123,"parentid":122,"slots":{"main":{"content":"New"}}}]}]}}),
+            )
+            .add(
+                &[("revids", "99")],
+                json!({"batchcomplete":true,"query":{"badrevids":{"99":{"revid":99,"missing":true}}}}),
+            );
+        let api = Api::new_mocked(responses).unwrap();
+        let revisions = api.revisions(&[123, 99, 122]).await.unwrap();
+        let ids: Vec<u64> = revisions.iter().filter_map(|r| r.id()).collect();
+        assert_eq!(ids, [123, 122]);
+        assert_eq!(revisions[0].wikitext(), Some("New"));
+        assert!(matches!(
+            api.revision(99).await,
+            Err(MediaWikiError::MissingRevision(99))
+        ));
+        let comparison = api.compare(122, 123).await.unwrap();
+        assert_eq!(comparison.to_revid(), 123);
+        assert_eq!(comparison.from_title().unwrap().pretty(), "Foo");
+        assert!(comparison.body().contains("diff"));
+    }
+
+    #[test]
+    fn revision_serde_and_sha1() {
+        // SHA1 of "Baz": hexadecimal as returned by the API, and base 36 as in dumps
+        let j = json!({"revid":123,"minor":"","timestamp":"2024-01-02T03:04:05Z","comment":"Bar",
+            "sha1":"e4f0f7897e4c87c201d1e272276610c53115d287","slots":{"main":{"content":"Baz"}}});
+        let rev = Revision::from_json(&j).unwrap();
+        assert!(rev.is_minor());
+        assert!(!rev.is_bot());
+        assert_eq!(rev.verify_sha1(), Some(true));
+        let base36 = Revision::from_json(
+            &json!({"revid":1,"sha1":"qqqsibcd8ylxks6i3opxdyddqxd53lz","*":"Baz"}),
+        )
+        .unwrap();
+        assert_eq!(base36.verify_sha1(), Some(true));
+        let wrong = Revision::from_json(&json!({"revid":1,"sha1":"0000","*":"Baz"})).unwrap();
+        assert_eq!(wrong.verify_sha1(), Some(false));
+        let serialized = serde_json::to_string(&rev).unwrap();
+        assert_eq!(serde_json::from_str::<Revision>(&serialized).unwrap(), rev);
+    }
+
+    #[test]
+    fn revision_hidden() {
+        let j = json!({"revid":123,"userhidden":true,"commenthidden":"","suppressed":true,"slots":{"main":{"texthidden":true}}});
+        let rev = Revision::from_json(&j).unwrap();
+        assert_eq!(rev.user(), RevisionField::Hidden);
+        assert_eq!(rev.userid(), RevisionField::Hidden);
+        assert_eq!(rev.comment(), RevisionField::Hidden);
+        assert_eq!(rev.wikitext_field(), RevisionField::Hidden);
+        assert_eq!(rev.sha1(), RevisionField::NotRequested);
+        assert!(rev.visibility().suppressed());
+        assert!(rev.visibility().is_deleted());
+    }
+
+    #[test]
+    fn revision_without_id() {
+        // e.g. `rvprop=content|timestamp`, without `ids`
+        let j = json!({"timestamp":"2024-01-02T03:04:05Z","slots":{"main":{"content":"Baz"}}});
+        let rev = Revision::from_json(&j).unwrap();
+        assert_eq!(rev.id(), None);
+        assert_eq!(rev.parent_id(), None);
+        assert_eq!(rev.wikitext_field(), RevisionField::Present("Baz"));
+        assert!(rev.timestamp().is_some());
+    }
 }
@@ -0,0 +1,229 @@
+/*!
+The `search` module finds similar pages with CirrusSearch `morelike:` queries, and related pages
+via the Wikimedia REST API (feature `related-pages`).
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use serde_json::Value;
+
+/// A page similar to the query pages, in order of relevance
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarPage {
+    title: Title,
+    page_id: Option<u64>,
+    rank: usize,
+    score: f64,
+    description: Option<String>,
+}
+
+impl SimilarPage {
+    /// Creates a new result from a `list=search` result entry, at position `rank` (0-based) of `total`
+    pub fn from_search_json(j: &Value, rank: usize, total: usize) -> Self {
+        Self {
+            title: Title::new_from_api_result(j),
+            page_id: j["pageid"].as_u64(),
+            rank,
+            score: Self::score_from_rank(rank, total),
+            description: None,
+        }
+    }
+
+    /// Creates a new result from a REST `page/related` entry, at position `rank` (0-based) of `total`
+    #[cfg(feature = "related-pages")]
+    pub fn from_rest_json(j: &Value, rank: usize, total: usize) -> Option<Self> {
+        Some(Self {
+            title: Title::new(
+                &j["titles"]["canonical"]
+                    .as_str()
+                    .or_else(|| j["title"].as_str())?
+                    .replace('_', " "),
+                j["namespace"]["id"]
+                    .as_i64()
+                    .or_else(|| j["ns"].as_i64())
+                    .unwrap_or(0),
+            ),
+            page_id: j["pageid"].as_u64(),
+            rank,
+            score: Self::score_from_rank(rank, total),
+            description: j["description"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    // CirrusSearch does not return scores, so results are scored by rank, from 1.0 down towards 0.0
+    fn score_from_rank(rank: usize, total: usize) -> f64 {
+        match total {
+            0 => 0.0,
+            total => (total - rank.min(total)) as f64 / total as f64,
+        }
+    }
+
+    /// Returns the title
+    pub fn title(&self) -> &Title {
+        &self.title
+    }
+
+    /// Returns the page ID
+    pub fn page_id(&self) -> Option<u64> {
+        self.page_id
+    }
+
+    /// Returns the 0-based position in the results
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+
+    /// Returns a relevance score between 0.0 and 1.0, derived from the rank
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// Returns the short description, if available
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+/// Options for `Api::more_like`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MoreLikeOptions {
+    limit: usize,
+    namespaces: Vec<NamespaceID>,
+}
+
+impl Default for MoreLikeOptions {
+    fn default() -> Self {
+        Self {
+            limit: 10,
+            namespaces: vec![0],
+        }
+    }
+}
+
+impl MoreLikeOptions {
+    /// Returns the default options: 10 results from the main namespace
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of results
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
+    /// Sets the namespaces to search; an empty slice searches all namespaces
+    pub fn set_namespaces(&mut self, namespaces: &[NamespaceID]) {
+        self.namespaces = namespaces.to_vec();
+    }
+}
+
+impl Api {
+    /// Returns pages similar to `titles`, via a CirrusSearch `morelike:` query
+    pub async fn more_like(
+        &self,
+        titles: &[Title],
+        options: &MoreLikeOptions,
+    ) -> Result<Vec<SimilarPage>, MediaWikiError> {
+        let titles = titles
+            .iter()
+            .map(|t| {
+                t.full_pretty(self)
+                    .ok_or_else(|| MediaWikiError::BadTitle(t.clone()))
+            })
+            .collect::<Result<Vec<String>, MediaWikiError>>()?;
+        let namespaces = match options.namespaces.is_empty() {
+            true => "*".to_string(),
+            false => options
+                .namespaces
+                .iter()
+                .map(|ns| ns.to_string())
+                .collect::<Vec<String>>()
+                .join("|"),
+        };
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("list", "search"),
+            ("srsearch", &format!("morelike:{}", titles.join("|"))),
+            ("srnamespace", &namespaces),
+            ("srlimit", &options.limit.to_string()),
+            ("srprop", ""),
+        ]);
+        let result = self.get_query_api_json(&params).await?;
+        if !result["error"].is_null() {
+            return Err(MediaWikiError::BadResponse(result));
+        }
+        Ok(Self::similar_pages_from_search_result(&result))
+    }
+
+    fn similar_pages_from_search_result(result: &Value) -> Vec<SimilarPage> {
+        let entries = result["query"]["search"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        entries
+            .iter()
+            .enumerate()
+            .map(|(rank, j)| SimilarPage::from_search_json(j, rank, entries.len()))
+            .collect()
+    }
+
+    /// Returns pages related to `title`, via the Wikimedia REST API `page/related` endpoint.
+    /// The REST URL is derived from the API URL, e.g. `https://en.wikipedia.org/api/rest_v1/`.
+    #[cfg(feature = "related-pages")]
+    pub async fn related_pages(&self, title: &Title) -> Result<Vec<SimilarPage>, MediaWikiError> {
+        let title = title
+            .full_with_underscores(self)
+            .ok_or_else(|| MediaWikiError::BadTitle(title.clone()))?;
+        let mut url = url::Url::parse(self.api_url())?;
+        url.set_query(None);
+        let url = url.join(&format!(
+            "/api/rest_v1/page/related/{}",
+            urlencoding::encode(&title)
+        ))?;
+        let headers =
+            self.request_headers(url.as_str(), &std::collections::HashMap::new(), "GET")?;
+        let result: Value = self
+            .client()
+            .get(url)
+            .headers(headers)
+            .send()
+            .await?
+            .json()
+            .await?;
+        let pages = result["pages"].as_array().cloned().unwrap_or_default();
+        Ok(pages
+            .iter()
+            .enumerate()
+            .filter_map(|(rank, j)| SimilarPage::from_rest_json(j, rank, pages.len()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similar_pages() {
+        let result = json!({"query":{"search":[{"ns":0,"title":"Cologne","pageid":1},{"ns":0,"title":"Hamburg","pageid":2}]}});
+        let pages = Api::similar_pages_from_search_result(&result);
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].title(), &Title::new("Cologne", 0));
+        assert_eq!(pages[0].score(), 1.0);
+        assert_eq!(pages[1].rank(), 1);
+        assert_eq!(pages[1].score(), 0.5);
+        assert!(Api::similar_pages_from_search_result(&json!({})).is_empty());
+    }
+
+    #[cfg(feature = "related-pages")]
+    #[test]
+    fn related_from_rest() {
+        let j = json!({"title":"Hamburg","titles":{"canonical":"Hamburg"},"namespace":{"id":0},"pageid":2,"description":"city in Germany"});
+        let page = SimilarPage::from_rest_json(&j, 0, 1).unwrap();
+        assert_eq!(page.title(), &Title::new("Hamburg", 0));
+        assert_eq!(page.description(), Some("city in Germany"));
+    }
+}
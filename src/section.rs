@@ -0,0 +1,109 @@
+/*!
+The `Section` class describes a section of a page, as returned by `action=parse&prop=sections`.
+*/
+
+#![deny(missing_docs)]
+
+use serde_json::Value;
+
+/// A section heading of a page
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Section {
+    index: String,
+    number: String,
+    level: u8,
+    line: String,
+    anchor: String,
+    from_title: Option<String>,
+    byte_offset: Option<usize>,
+}
+
+impl Section {
+    /// Creates a section from an entry of `parse.sections`
+    pub fn from_json(j: &Value) -> Option<Self> {
+        let as_string = |v: &Value| match v {
+            Value::String(s) => Some(s.to_string()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        };
+        Some(Self {
+            index: as_string(&j["index"])?,
+            number: as_string(&j["number"]).unwrap_or_default(),
+            level: as_string(&j["level"])
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            line: j["line"].as_str()?.to_string(),
+            anchor: j["anchor"].as_str().unwrap_or_default().to_string(),
+            from_title: j["fromtitle"].as_str().map(|s| s.to_string()),
+            byte_offset: j["byteoffset"].as_u64().map(|o| o as usize),
+        })
+    }
+
+    /// Returns the section index as used by `section=`; `T-1` etc. for sections of transcluded pages
+    pub fn index(&self) -> &str {
+        &self.index
+    }
+
+    /// Returns the section index on this page, or `None` for sections of transcluded pages
+    pub fn local_index(&self) -> Option<usize> {
+        self.index.parse().ok()
+    }
+
+    /// Returns the section number in the table of contents, e.g. `2.1`
+    pub fn number(&self) -> &str {
+        &self.number
+    }
+
+    /// Returns the heading level, e.g. 2 for `== Heading ==`
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    /// Returns the heading, as rendered HTML
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// Returns the HTML anchor of the heading
+    pub fn anchor(&self) -> &str {
+        &self.anchor
+    }
+
+    /// Returns the title of the page the section is defined on
+    pub fn from_title(&self) -> Option<&str> {
+        self.from_title.as_deref()
+    }
+
+    /// Returns the byte offset of the heading in the wikitext, for sections on this page
+    pub fn byte_offset(&self) -> Option<usize> {
+        self.byte_offset
+    }
+
+    /// Returns true if the section comes from a transcluded page
+    pub fn is_transcluded(&self) -> bool {
+        self.local_index().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_json() {
+        let section = Section::from_json(&json!({"toclevel":2,"level":"3","line":"Early <i>life</i>","number":"1.1",
+            "index":"2","fromtitle":"Albert_Einstein","byteoffset":4321,"anchor":"Early_life","linkAnchor":"Early_life"}))
+        .unwrap();
+        assert_eq!(section.local_index(), Some(2));
+        assert_eq!(section.level(), 3);
+        assert_eq!(section.number(), "1.1");
+        assert_eq!(section.byte_offset(), Some(4321));
+        let section = Section::from_json(
+            &json!({"toclevel":1,"level":"2","line":"Notes","number":"2",
+            "index":"T-1","fromtitle":"Template:Notes","byteoffset":null,"anchor":"Notes"}),
+        )
+        .unwrap();
+        assert!(section.is_transcluded());
+        assert_eq!(section.index(), "T-1");
+    }
+}
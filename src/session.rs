@@ -0,0 +1,85 @@
+/*!
+The `Session` class holds the cookies, tokens and user of a logged-in `Api`,
+so tools can save a session with `Api::save_session`, and restore it later without logging in again.
+*/
+
+#![deny(missing_docs)]
+
+use crate::media_wiki_error::MediaWikiError;
+use crate::user::User;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A saved session of an `Api`; the serialized form contains credentials and should be stored like a password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub(crate) api_url: String,
+    pub(crate) cookies: String,
+    pub(crate) tokens: HashMap<String, String>,
+    pub(crate) user: User,
+}
+
+impl Session {
+    /// Parses a session saved by `to_json`
+    pub fn from_json(json: &str) -> Result<Self, MediaWikiError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serializes the session to JSON
+    pub fn to_json(&self) -> Result<String, MediaWikiError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Returns the API URL of the wiki the session belongs to
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    /// Returns the user of the session
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Api;
+    use crate::mock::MockResponses;
+    use reqwest::cookie::CookieStore;
+    use reqwest::header::HeaderValue;
+    use url::Url;
+
+    #[test]
+    fn save_and_restore() {
+        let api = Api::new_mocked(MockResponses::new()).unwrap();
+        let url = Url::parse(api.api_url()).unwrap();
+        let cookie = HeaderValue::from_static("testwikiSession=abc; Path=/; HttpOnly");
+        api.cookie_jar()
+            .set_cookies(&mut std::iter::once(&cookie), &url);
+        api.user_mut()
+            .set_from_login(&json!({"result":"Success","lguserid":3,"lgusername":"Foo"}))
+            .unwrap();
+        let saved = api.save_session().unwrap();
+        assert_eq!(
+            Session::from_json(&saved).unwrap().user().user_name(),
+            "Foo"
+        );
+
+        let restored = Api::new_mocked(MockResponses::new()).unwrap();
+        restored.restore_session(&saved).unwrap();
+        assert!(restored.user().logged_in());
+        assert_eq!(restored.user().user_id(), 3);
+        assert_eq!(
+            restored.cookie_jar().cookies(&url).unwrap(),
+            "testwikiSession=abc"
+        );
+        let session = Session {
+            api_url: "https://other.example/w/api.php".to_string(),
+            ..Session::from_json(&saved).unwrap()
+        };
+        assert!(restored
+            .restore_session(&session.to_json().unwrap())
+            .is_err());
+    }
+}
@@ -0,0 +1,294 @@
+/*!
+The `site_health` module tracks whether a wiki is read-only or lagged, and can pause edits until it recovers.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::events::ApiEvent;
+use crate::media_wiki_error::MediaWikiError;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const DEFAULT_HEALTH_MAX_LAG: Duration = Duration::from_secs(5);
+const DEFAULT_HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// A snapshot of the read-only state and replication lag of a wiki
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SiteHealth {
+    read_only: bool,
+    read_only_reason: Option<String>,
+    lag: Option<Duration>,
+    updated: Option<DateTime<Utc>>,
+}
+
+impl SiteHealth {
+    /// Returns true if the wiki is read-only
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns the reason the wiki is read-only, if given
+    pub fn read_only_reason(&self) -> Option<&str> {
+        self.read_only_reason.as_deref()
+    }
+
+    /// Returns the last observed replication lag, if any
+    pub fn lag(&self) -> Option<Duration> {
+        self.lag
+    }
+
+    /// Returns the time of the last observation, if any
+    pub fn updated(&self) -> Option<DateTime<Utc>> {
+        self.updated
+    }
+
+    /// Updates the read-only state from a siteinfo `general` object
+    pub(crate) fn update_from_general(&mut self, general: &Value) {
+        if !general.is_object() {
+            return;
+        }
+        // `readonly` is an empty string (format version 1) or `true` if set, and absent otherwise
+        self.read_only = !matches!(general["readonly"], Value::Null | Value::Bool(false));
+        self.read_only_reason = match self.read_only {
+            true => general["readonlyreason"].as_str().map(|s| s.to_string()),
+            false => None,
+        };
+        self.updated = Some(Utc::now());
+    }
+
+    /// Updates the lag from a siteinfo `dbrepllag` array
+    pub(crate) fn update_from_dbrepllag(&mut self, dbrepllag: &Value) {
+        let lag = dbrepllag
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|db| db["lag"].as_f64())
+            .fold(None, |max: Option<f64>, lag| {
+                Some(max.map_or(lag, |m| m.max(lag)))
+            });
+        if let Some(lag) = lag {
+            self.set_lag(Duration::from_secs_f64(lag.max(0.0)));
+        }
+    }
+
+    /// Updates the state from an API result, if it is a `maxlag` or `readonly` error
+    pub(crate) fn update_from_result(&mut self, result: &Value) {
+        match result["error"]["code"].as_str() {
+            Some("maxlag") => {
+                if let Some(lag) = result["error"]["lag"].as_f64() {
+                    self.set_lag(Duration::from_secs_f64(lag.max(0.0)));
+                }
+            }
+            Some("readonly") => {
+                self.read_only = true;
+                self.read_only_reason = result["error"]["readonlyreason"]
+                    .as_str()
+                    .map(|s| s.to_string());
+                self.updated = Some(Utc::now());
+            }
+            _ => {}
+        }
+    }
+
+    /// Sets the observed lag
+    pub(crate) fn set_lag(&mut self, lag: Duration) {
+        self.lag = Some(lag);
+        self.updated = Some(Utc::now());
+    }
+}
+
+/// A policy that pauses edits while the wiki is read-only or lagged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthPolicy {
+    max_lag: Duration,
+    pause_when_read_only: bool,
+    poll_interval: Duration,
+}
+
+impl Default for HealthPolicy {
+    fn default() -> Self {
+        Self {
+            max_lag: DEFAULT_HEALTH_MAX_LAG,
+            pause_when_read_only: true,
+            poll_interval: DEFAULT_HEALTH_POLL_INTERVAL,
+        }
+    }
+}
+
+impl HealthPolicy {
+    /// Returns the maximum lag at which edits are allowed (default 5s)
+    pub fn max_lag(&self) -> Duration {
+        self.max_lag
+    }
+
+    /// Sets the maximum lag at which edits are allowed
+    pub fn set_max_lag(&mut self, max_lag: Duration) {
+        self.max_lag = max_lag;
+    }
+
+    /// Returns true if edits are paused while the wiki is read-only (default true)
+    pub fn pause_when_read_only(&self) -> bool {
+        self.pause_when_read_only
+    }
+
+    /// Sets whether edits are paused while the wiki is read-only
+    pub fn set_pause_when_read_only(&mut self, pause_when_read_only: bool) {
+        self.pause_when_read_only = pause_when_read_only;
+    }
+
+    /// Returns the time between health checks while paused (default 30s)
+    pub fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    /// Sets the time between health checks while paused
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.poll_interval = poll_interval;
+    }
+
+    /// Returns true if edits are allowed with this site health
+    pub fn allows(&self, health: &SiteHealth) -> bool {
+        if self.pause_when_read_only && health.read_only() {
+            return false;
+        }
+        health.lag().is_none_or(|lag| lag <= self.max_lag)
+    }
+}
+
+impl Api {
+    /// Returns a snapshot of the site health, as last observed
+    pub fn site_health(&self) -> SiteHealth {
+        self.site_health_state()
+            .lock()
+            .map(|health| health.clone())
+            .unwrap_or_default()
+    }
+
+    /// Queries the current read-only state and replication lag, and returns the updated site health
+    pub async fn refresh_site_health(&self) -> Result<SiteHealth, MediaWikiError> {
+        let params: HashMap<String, String> = [
+            ("action", "query"),
+            ("meta", "siteinfo"),
+            ("siprop", "general|dbrepllag"),
+            ("format", "json"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        // Not via `query_raw_response`, which waits for the site to be healthy before edits
+        let text = match self.mock() {
+            Some(mock) => mock.respond(&params)?,
+            None => {
+                self.request_builder(self.api_url(), &params, "GET")?
+                    .send()
+                    .await?
+                    .text()
+                    .await?
+            }
+        };
+        let result: Value = serde_json::from_str(&text)?;
+        if let Ok(mut health) = self.site_health_state().lock() {
+            health.update_from_general(&result["query"]["general"]);
+            health.update_from_dbrepllag(&result["query"]["dbrepllag"]);
+        }
+        Ok(self.site_health())
+    }
+
+    /// Waits until the health policy allows an edit, if one is set
+    pub(crate) async fn wait_for_site_health(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Result<(), MediaWikiError> {
+        let policy = match self.health_policy() {
+            Some(policy) => *policy,
+            None => return Ok(()),
+        };
+        while !policy.allows(&self.site_health()) {
+            self.emit_event(&ApiEvent::SiteUnhealthy {
+                action: params.get("action").cloned().unwrap_or_default(),
+                wait: policy.poll_interval(),
+            });
+            self.sleep_cancellable(policy.poll_interval()).await?;
+            self.refresh_site_health().await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_only_from_general() {
+        let mut health = SiteHealth::default();
+        health.update_from_general(&json!({"readonly":"","readonlyreason":"Maintenance"}));
+        assert!(health.read_only());
+        assert_eq!(health.read_only_reason(), Some("Maintenance"));
+        health.update_from_general(&json!({"sitename":"Wikipedia"}));
+        assert!(!health.read_only());
+        assert_eq!(health.read_only_reason(), None);
+    }
+
+    #[test]
+    fn lag_from_results() {
+        let mut health = SiteHealth::default();
+        health.update_from_dbrepllag(&json!([{"host":"a","lag":0.5},{"host":"b","lag":2}]));
+        assert_eq!(health.lag(), Some(Duration::from_secs(2)));
+        health.update_from_result(&json!({"error":{"code":"maxlag","lag":12}}));
+        assert_eq!(health.lag(), Some(Duration::from_secs(12)));
+        health.update_from_result(&json!({"error":{"code":"readonly"}}));
+        assert!(health.read_only());
+    }
+
+    #[tokio::test]
+    async fn refresh_and_wait() {
+        let health_params = [("meta", "siteinfo"), ("siprop", "general|dbrepllag")];
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add_once(
+                &health_params,
+                json!({"query":{"general":{"readonly":"","readonlyreason":"Maintenance"},
+                    "dbrepllag":[{"host":"a","lag":0}]}}),
+            )
+            .add(
+                &health_params,
+                json!({"query":{"general":{},"dbrepllag":[{"host":"a","lag":1}]}}),
+            );
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        let mut policy = HealthPolicy::default();
+        policy.set_poll_interval(Duration::from_millis(10));
+        api.set_health_policy(Some(policy));
+
+        let health = api.refresh_site_health().await.unwrap();
+        assert!(health.read_only());
+        assert_eq!(health.read_only_reason(), Some("Maintenance"));
+
+        // Edits wait until the site is writable again
+        let params = api.params_into(&[("action", "edit"), ("title", "A")]);
+        api.wait_for_site_health(&params).await.unwrap();
+        assert!(!api.site_health().read_only());
+        assert_eq!(api.site_health().lag(), Some(Duration::from_secs(1)));
+        let requests = responses.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r["meta"] == "siteinfo"));
+    }
+
+    #[test]
+    fn policy_allows() {
+        let mut policy = HealthPolicy::default();
+        let mut health = SiteHealth::default();
+        assert!(policy.allows(&health));
+        health.set_lag(Duration::from_secs(10));
+        assert!(!policy.allows(&health));
+        policy.set_max_lag(Duration::from_secs(10));
+        assert!(policy.allows(&health));
+        health.update_from_result(&json!({"error":{"code":"readonly"}}));
+        assert!(!policy.allows(&health));
+        policy.set_pause_when_read_only(false);
+        assert!(policy.allows(&health));
+    }
+}
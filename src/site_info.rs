@@ -0,0 +1,258 @@
+/*!
+The `SiteInfo` class gives typed access to the site info of a wiki, as loaded by `Api` via `meta=siteinfo`.
+*/
+
+#![deny(missing_docs)]
+
+use crate::namespace_map::NamespaceMap;
+use serde_json::Value;
+
+/// How the first letter of titles is treated, from the `case` of the general site info
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaseSensitivity {
+    /// The first letter is always uppercase
+    FirstLetter,
+    /// Titles are case-sensitive
+    CaseSensitive,
+}
+
+/// An installed extension, from siteinfo `extensions`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Extension {
+    name: String,
+    kind: Option<String>,
+    version: Option<String>,
+}
+
+impl Extension {
+    /// Creates an extension from an entry of siteinfo `extensions`
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            name: j["name"].as_str()?.to_string(),
+            kind: j["type"].as_str().map(|s| s.to_string()),
+            version: j["version"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    /// Returns the name, e.g. `ParserFunctions`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type, e.g. `parserhook` or `specialpage`
+    pub fn kind(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    /// Returns the version, if the extension declares one
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+}
+
+/// Site statistics, from siteinfo `statistics`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Statistics {
+    pages: u64,
+    articles: u64,
+    edits: u64,
+    images: u64,
+    users: u64,
+    active_users: u64,
+    admins: u64,
+}
+
+impl Statistics {
+    /// Creates the statistics from siteinfo `statistics`
+    pub fn from_json(j: &Value) -> Self {
+        let get = |key: &str| j[key].as_u64().unwrap_or_default();
+        Self {
+            pages: get("pages"),
+            articles: get("articles"),
+            edits: get("edits"),
+            images: get("images"),
+            users: get("users"),
+            active_users: get("activeusers"),
+            admins: get("admins"),
+        }
+    }
+
+    /// Returns the number of pages in all namespaces
+    pub fn pages(&self) -> u64 {
+        self.pages
+    }
+
+    /// Returns the number of content pages
+    pub fn articles(&self) -> u64 {
+        self.articles
+    }
+
+    /// Returns the number of edits
+    pub fn edits(&self) -> u64 {
+        self.edits
+    }
+
+    /// Returns the number of uploaded files
+    pub fn images(&self) -> u64 {
+        self.images
+    }
+
+    /// Returns the number of registered users
+    pub fn users(&self) -> u64 {
+        self.users
+    }
+
+    /// Returns the number of users with an action in the last 30 days
+    pub fn active_users(&self) -> u64 {
+        self.active_users
+    }
+
+    /// Returns the number of administrators
+    pub fn admins(&self) -> u64 {
+        self.admins
+    }
+}
+
+/// The site info of a wiki
+#[derive(Debug, Clone, PartialEq)]
+pub struct SiteInfo {
+    raw: Value,
+    namespaces: NamespaceMap,
+}
+
+impl Default for SiteInfo {
+    fn default() -> Self {
+        Self::from_json(Value::Object(Default::default()))
+    }
+}
+
+impl SiteInfo {
+    /// Creates the site info from a `meta=siteinfo` result
+    pub fn from_json(raw: Value) -> Self {
+        Self {
+            namespaces: NamespaceMap::from_site_info(&raw),
+            raw,
+        }
+    }
+
+    /// Returns the `meta=siteinfo` result
+    pub fn raw(&self) -> &Value {
+        &self.raw
+    }
+
+    /// Returns a string from the general site info
+    fn general(&self, key: &str) -> Option<&str> {
+        self.raw["query"]["general"][key].as_str()
+    }
+
+    /// Returns the name of the site, e.g. `Wikipedia`
+    pub fn site_name(&self) -> Option<&str> {
+        self.general("sitename")
+    }
+
+    /// Returns the content language code, e.g. `en`
+    pub fn lang(&self) -> Option<&str> {
+        self.general("lang")
+    }
+
+    /// Returns the server URL, e.g. `//en.wikipedia.org`; may be protocol-relative
+    pub fn server(&self) -> Option<&str> {
+        self.general("server")
+    }
+
+    /// Returns the path of `index.php` and `api.php` relative to the server, e.g. `/w`
+    pub fn script_path(&self) -> Option<&str> {
+        self.general("scriptpath")
+    }
+
+    /// Returns the path of pages relative to the server, e.g. `/wiki/$1`
+    pub fn article_path(&self) -> Option<&str> {
+        self.general("articlepath")
+    }
+
+    /// Returns the title of the main page
+    pub fn main_page(&self) -> Option<&str> {
+        self.general("mainpage")
+    }
+
+    /// Returns the database name, e.g. `enwiki`
+    pub fn db_name(&self) -> Option<&str> {
+        self.general("wikiid")
+    }
+
+    /// Returns the MediaWiki version, e.g. `MediaWiki 1.42.0-wmf.5`
+    pub fn generator(&self) -> Option<&str> {
+        self.general("generator")
+    }
+
+    /// Returns how the first letter of titles is treated; MediaWiki defaults to `FirstLetter`
+    pub fn case_sensitivity(&self) -> CaseSensitivity {
+        match self.general("case") {
+            Some("case-sensitive") => CaseSensitivity::CaseSensitive,
+            _ => CaseSensitivity::FirstLetter,
+        }
+    }
+
+    /// Returns the namespaces, including their aliases
+    pub fn namespaces(&self) -> &NamespaceMap {
+        &self.namespaces
+    }
+
+    /// Returns the installed extensions, with `siprop=extensions`
+    pub fn extensions(&self) -> Vec<Extension> {
+        self.raw["query"]["extensions"]
+            .as_array()
+            .map(|arr| arr.iter().filter_map(Extension::from_json).collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns true if an extension with this name is installed
+    pub fn has_extension(&self, name: &str) -> bool {
+        self.extensions().iter().any(|e| e.name() == name)
+    }
+
+    /// Returns the site statistics, with `siprop=statistics`
+    pub fn statistics(&self) -> Statistics {
+        Statistics::from_json(&self.raw["query"]["statistics"])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::Api;
+
+    #[test]
+    fn from_json() {
+        let site_info = SiteInfo::from_json(json!({"query":{
+            "general":{"sitename":"Wikipedia","lang":"de","server":"//de.wikipedia.org","scriptpath":"/w",
+                "articlepath":"/wiki/$1","case":"first-letter","wikiid":"dewiki"},
+            "namespaces":{"4":{"id":4,"case":"first-letter","*":"Wikipedia","canonical":"Project"}},
+            "namespacealiases":[{"id":4,"*":"WP"}],
+            "extensions":[{"type":"parserhook","name":"ParserFunctions","version":"1.6.1"}],
+            "statistics":{"pages":100,"articles":40,"edits":1000,"activeusers":7}}}));
+        assert_eq!(site_info.lang(), Some("de"));
+        assert_eq!(site_info.server(), Some("//de.wikipedia.org"));
+        assert_eq!(site_info.script_path(), Some("/w"));
+        assert_eq!(site_info.db_name(), Some("dewiki"));
+        assert_eq!(site_info.case_sensitivity(), CaseSensitivity::FirstLetter);
+        assert_eq!(site_info.namespaces().namespace_id("WP"), Some(4));
+        assert!(site_info.has_extension("ParserFunctions"));
+        assert_eq!(site_info.extensions()[0].version(), Some("1.6.1"));
+        assert_eq!(site_info.statistics().articles(), 40);
+        assert_eq!(site_info.statistics().active_users(), 7);
+        assert_eq!(SiteInfo::default().lang(), None);
+    }
+
+    #[tokio::test]
+    async fn refresh() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("meta", "siteinfo")],
+            json!({"query":{"general":{"lang":"fr"}}}),
+        );
+        let mut api = Api::new_mocked(responses).unwrap();
+        assert_eq!(api.site_info().lang(), Some("fr"));
+        assert_eq!(api.refresh_site_info().await.unwrap().lang(), Some("fr"));
+    }
+}
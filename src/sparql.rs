@@ -0,0 +1,491 @@
+/*!
+The `sparql` module runs SPARQL queries with a timeout and retries, and streams query results row by row,
+instead of buffering the whole result.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use futures::Stream;
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+const TSV_CONTENT_TYPE: &str = "text/tab-separated-values";
+/// Part of the HTTP 500 response body of the Wikidata Query Service when a query times out
+const WDQS_TIMEOUT_EXCEPTION: &str = "java.util.concurrent.TimeoutException";
+const DEFAULT_SPARQL_RETRIES: u32 = 3;
+const DEFAULT_SPARQL_BACKOFF: Duration = Duration::from_secs(2);
+const MAX_SPARQL_BACKOFF: Duration = Duration::from_secs(120);
+
+/// Options for [`Api::sparql_query_with`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparqlOptions {
+    endpoint: Option<String>,
+    timeout: Option<Duration>,
+    retries: u32,
+    backoff: Duration,
+}
+
+impl Default for SparqlOptions {
+    fn default() -> Self {
+        Self {
+            endpoint: None,
+            timeout: None,
+            retries: DEFAULT_SPARQL_RETRIES,
+            backoff: DEFAULT_SPARQL_BACKOFF,
+        }
+    }
+}
+
+impl SparqlOptions {
+    /// Creates options with the defaults: the site's SPARQL endpoint, the client timeout, 3 retries
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the SPARQL endpoint, if set
+    pub fn endpoint(&self) -> Option<&str> {
+        self.endpoint.as_deref()
+    }
+
+    /// Sets the SPARQL endpoint; `None` uses the one from the site info
+    pub fn set_endpoint(&mut self, endpoint: Option<&str>) {
+        self.endpoint = endpoint.map(|s| s.to_string());
+    }
+
+    /// Returns the timeout for a single attempt, if set
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Sets the timeout for a single attempt; `None` uses the timeout of the `reqwest` client
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the number of retries
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Sets the number of retries after a timeout (of the request, or of the query on the endpoint),
+    /// or an HTTP 429, 502, 503 or 504 response
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
+
+    /// Returns the wait before the first retry
+    pub fn backoff(&self) -> Duration {
+        self.backoff
+    }
+
+    /// Sets the wait before the first retry; it doubles with every further retry, up to two minutes
+    pub fn set_backoff(&mut self, backoff: Duration) {
+        self.backoff = backoff;
+    }
+
+    /// Returns the wait before retry number `attempt` (starting at 0)
+    fn backoff_for(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_SPARQL_BACKOFF)
+    }
+
+    /// Returns true if a response with this status should be retried
+    fn is_retry_status(status: StatusCode) -> bool {
+        matches!(
+            status,
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        )
+    }
+
+    /// Returns true if a failed response should be retried; the Wikidata Query Service
+    /// reports a query timeout as HTTP 500 with a Java `TimeoutException`
+    fn is_retry_response(status: StatusCode, body: &str) -> bool {
+        Self::is_retry_status(status)
+            || (status == StatusCode::INTERNAL_SERVER_ERROR
+                && body.contains(WDQS_TIMEOUT_EXCEPTION))
+    }
+}
+
+/// A single result row of a SPARQL query; unbound variables are absent
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SparqlRow {
+    values: HashMap<String, String>,
+}
+
+impl SparqlRow {
+    /// Returns the value of a variable (without `?`): the IRI of a resource, or the text of a literal
+    pub fn get(&self, variable: &str) -> Option<&str> {
+        self.values.get(variable).map(|s| s.as_str())
+    }
+
+    /// Returns the variables bound in this row
+    pub fn variables(&self) -> Vec<&str> {
+        self.values.keys().map(|s| s.as_str()).collect()
+    }
+
+    /// Parses a TSV term: `<iri>`, `"literal"@lang`, `"literal"^^<type>`, or a bare number or blank node
+    fn parse_term(term: &str) -> String {
+        if let Some(iri) = term.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+            return iri.to_string();
+        }
+        let literal = match term
+            .strip_prefix('"')
+            .and_then(|t| t.rfind('"').map(|pos| &t[..pos]))
+        {
+            Some(literal) => literal,
+            None => return term.to_string(),
+        };
+        let mut ret = String::with_capacity(literal.len());
+        let mut chars = literal.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                ret.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('t') => ret.push('\t'),
+                Some('n') => ret.push('\n'),
+                Some('r') => ret.push('\r'),
+                Some(c) => ret.push(c),
+                None => ret.push('\\'),
+            }
+        }
+        ret
+    }
+}
+
+/// Splits a TSV result into rows, as the bytes arrive
+#[derive(Debug, Default)]
+struct SparqlTsvParser {
+    header: Option<Vec<String>>,
+    buffer: Vec<u8>,
+}
+
+impl SparqlTsvParser {
+    /// Adds bytes, and returns the rows that are complete
+    fn push(&mut self, bytes: &[u8]) -> Vec<SparqlRow> {
+        self.buffer.extend_from_slice(bytes);
+        let mut ret = vec![];
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            if let Some(row) = self.parse_line(&line[..pos]) {
+                ret.push(row);
+            }
+        }
+        ret
+    }
+
+    /// Returns the last row, if the result does not end with a newline
+    fn finish(&mut self) -> Option<SparqlRow> {
+        let line = std::mem::take(&mut self.buffer);
+        self.parse_line(&line)
+    }
+
+    fn parse_line(&mut self, line: &[u8]) -> Option<SparqlRow> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches('\r');
+        let header = match &self.header {
+            Some(header) => header,
+            None => {
+                self.header = Some(
+                    line.split('\t')
+                        .map(|v| v.trim_start_matches(['?', '$']).to_string())
+                        .collect(),
+                );
+                return None;
+            }
+        };
+        if line.is_empty() {
+            return None;
+        }
+        let values = header
+            .iter()
+            .zip(line.split('\t'))
+            .filter(|(_, term)| !term.is_empty())
+            .map(|(variable, term)| (variable.to_owned(), SparqlRow::parse_term(term)))
+            .collect();
+        Some(SparqlRow { values })
+    }
+}
+
+impl Api {
+    /// Runs a SPARQL query with a timeout, and retries with exponential backoff if the endpoint
+    /// times out or is overloaded. A `Retry-After` header is honored, if longer than the backoff.
+    pub async fn sparql_query_with(
+        &self,
+        query: &str,
+        options: &SparqlOptions,
+    ) -> Result<Value, MediaWikiError> {
+        let params = self.params_into(&[("query", query), ("format", "json")]);
+        let bytes = self
+            .sparql_send(&params, None, options)
+            .await?
+            .bytes()
+            .await?;
+        serde_json::from_slice(&bytes).map_err(|e| {
+            let bytes_start = String::from_utf8_lossy(&bytes[..bytes.len().min(100)]);
+            MediaWikiError::String(format!("{e}: {bytes_start}"))
+        })
+    }
+
+    /// Sends a SPARQL query, retrying as described for `sparql_query_with`, and returns the successful response
+    async fn sparql_send(
+        &self,
+        params: &HashMap<String, String>,
+        accept: Option<&str>,
+        options: &SparqlOptions,
+    ) -> Result<reqwest::Response, MediaWikiError> {
+        let endpoint = match options.endpoint() {
+            Some(endpoint) => endpoint,
+            None => self.get_site_info_string("general", "wikibase-sparql")?,
+        };
+        let mut attempt = 0;
+        loop {
+            let response = match self.mock() {
+                Some(mock) => Ok(mock.respond_http(
+                    "POST",
+                    endpoint,
+                    None,
+                    Some(&serde_json::json!(params)),
+                )?),
+                None => {
+                    let mut request = self.request_builder(endpoint, params, "POST")?;
+                    if let Some(accept) = accept {
+                        request = request.header(reqwest::header::ACCEPT, accept);
+                    }
+                    if let Some(timeout) = options.timeout() {
+                        request = request.timeout(timeout);
+                    }
+                    request.send().await
+                }
+            };
+            let wait = match response {
+                Ok(response) if response.status().is_success() => return Ok(response),
+                Ok(response) => {
+                    let status = response.status();
+                    let retry_after = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|s| s.parse().ok())
+                        .map(Duration::from_secs)
+                        .unwrap_or_default();
+                    let body = response.text().await.unwrap_or_default();
+                    if !SparqlOptions::is_retry_response(status, &body) {
+                        let body_start: String = body.chars().take(100).collect();
+                        return Err(MediaWikiError::String(format!(
+                            "SPARQL query failed with HTTP {}: {}",
+                            status, body_start
+                        )));
+                    }
+                    if attempt >= options.retries() {
+                        return Err(MediaWikiError::String(format!(
+                            "SPARQL query failed with HTTP {} after {} retries",
+                            status, attempt
+                        )));
+                    }
+                    options.backoff_for(attempt).max(retry_after)
+                }
+                Err(e) if e.is_timeout() && attempt < options.retries() => {
+                    options.backoff_for(attempt)
+                }
+                Err(e) => return Err(e.into()),
+            };
+            attempt += 1;
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Runs a SPARQL query against the site's SPARQL endpoint, and returns the result rows as a stream.
+    /// The result is requested as tab-separated values and parsed as it arrives, so large results
+    /// do not need to fit into memory.
+    pub async fn sparql_query_stream(
+        &self,
+        query: &str,
+    ) -> Result<impl Stream<Item = Result<SparqlRow, MediaWikiError>>, MediaWikiError> {
+        self.sparql_query_stream_with(query, &SparqlOptions::new())
+            .await
+    }
+
+    /// Runs a SPARQL query against a specific endpoint, and returns the result rows as a stream.
+    pub async fn sparql_query_stream_endpoint(
+        &self,
+        query: &str,
+        endpoint: &str,
+    ) -> Result<impl Stream<Item = Result<SparqlRow, MediaWikiError>>, MediaWikiError> {
+        let mut options = SparqlOptions::new();
+        options.set_endpoint(Some(endpoint));
+        self.sparql_query_stream_with(query, &options).await
+    }
+
+    /// Same as `sparql_query_stream`, with the timeout and retries of `options`.
+    /// Only the request is retried; an error while reading the rows ends the stream.
+    pub async fn sparql_query_stream_with(
+        &self,
+        query: &str,
+        options: &SparqlOptions,
+    ) -> Result<impl Stream<Item = Result<SparqlRow, MediaWikiError>>, MediaWikiError> {
+        let params = self.params_into(&[("query", query)]);
+        let response = self
+            .sparql_send(&params, Some(TSV_CONTENT_TYPE), options)
+            .await?;
+        let state = (
+            Some(response),
+            SparqlTsvParser::default(),
+            VecDeque::<SparqlRow>::new(),
+        );
+        Ok(futures::stream::unfold(
+            state,
+            |(mut response, mut parser, mut pending)| async move {
+                loop {
+                    if let Some(row) = pending.pop_front() {
+                        return Some((Ok(row), (response, parser, pending)));
+                    }
+                    let chunk = match response.as_mut() {
+                        Some(r) => r.chunk().await,
+                        None => return None,
+                    };
+                    match chunk {
+                        Ok(Some(bytes)) => pending.extend(parser.push(&bytes)),
+                        Ok(None) => {
+                            response = None;
+                            pending.extend(parser.finish());
+                        }
+                        Err(e) => return Some((Err(e.into()), (None, parser, pending))),
+                    }
+                }
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn sparql_options() {
+        let mut options = SparqlOptions::new();
+        assert_eq!(options.retries(), 3);
+        options.set_backoff(Duration::from_secs(1));
+        assert_eq!(options.backoff_for(0), Duration::from_secs(1));
+        assert_eq!(options.backoff_for(3), Duration::from_secs(8));
+        assert_eq!(options.backoff_for(40), MAX_SPARQL_BACKOFF);
+        assert!(SparqlOptions::is_retry_status(
+            StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert!(!SparqlOptions::is_retry_status(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn parse_term() {
+        assert_eq!(
+            SparqlRow::parse_term("<http://www.wikidata.org/entity/Q42>"),
+            "http://www.wikidata.org/entity/Q42"
+        );
+        assert_eq!(
+            SparqlRow::parse_term("\"Douglas \\\"DNA\\\" Adams\"@en"),
+            "Douglas \"DNA\" Adams"
+        );
+        assert_eq!(
+            SparqlRow::parse_term(
+                "\"1952-03-11T00:00:00Z\"^^<http://www.w3.org/2001/XMLSchema#dateTime>"
+            ),
+            "1952-03-11T00:00:00Z"
+        );
+        assert_eq!(SparqlRow::parse_term("42"), "42");
+    }
+
+    #[test]
+    fn tsv_parser() {
+        let mut parser = SparqlTsvParser::default();
+        let mut rows = parser.push(b"?q\t?qLabel\n<http://www.wikidata.org/entity/Q42>\t\"Douglas");
+        assert!(rows.is_empty());
+        rows.extend(parser.push(b" Adams\"@en\n<http://www.wikidata.org/entity/Q1>\t"));
+        rows.extend(parser.finish());
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get("qLabel"), Some("Douglas Adams"));
+        assert_eq!(rows[1].get("q"), Some("http://www.wikidata.org/entity/Q1"));
+        assert_eq!(rows[1].get("qLabel"), None);
+    }
+
+    #[tokio::test]
+    async fn sparql_retries() {
+        let endpoint = "https://query.example.org/sparql";
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add_http_once(
+                "POST",
+                endpoint,
+                500,
+                &[],
+                json!("java.util.concurrent.ExecutionException: java.util.concurrent.TimeoutException"),
+            )
+            .add_http(
+                "POST",
+                endpoint,
+                200,
+                &[],
+                json!({"head":{"vars":["q"]},"results":{"bindings":[]}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut options = SparqlOptions::new();
+        options.set_endpoint(Some(endpoint));
+        options.set_backoff(Duration::ZERO);
+        let result = api
+            .sparql_query_with("SELECT ?q {}", &options)
+            .await
+            .unwrap();
+        assert_eq!(result["head"]["vars"][0], "q");
+        assert_eq!(responses.requests().len(), 2);
+        assert_eq!(responses.requests()[0]["query"], "SELECT ?q {}");
+
+        // Other server errors are not retried
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add_http("POST", endpoint, 500, &[], json!("Internal error"));
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        assert!(api
+            .sparql_query_with("SELECT ?q {}", &options)
+            .await
+            .is_err());
+        assert_eq!(responses.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn sparql_stream_retries() {
+        let endpoint = "https://query.example.org/sparql";
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add_http_once("POST", endpoint, 503, &[], json!("Service Unavailable"))
+            .add_http(
+                "POST",
+                endpoint,
+                200,
+                &[("content-type", TSV_CONTENT_TYPE)],
+                json!("?q\n<http://www.wikidata.org/entity/Q42>\n"),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut options = SparqlOptions::new();
+        options.set_endpoint(Some(endpoint));
+        options.set_backoff(Duration::ZERO);
+        let rows: Vec<SparqlRow> = api
+            .sparql_query_stream_with("SELECT ?q {}", &options)
+            .await
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect()
+            .await;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get("q"), Some("http://www.wikidata.org/entity/Q42"));
+        assert_eq!(responses.requests().len(), 2);
+    }
+}
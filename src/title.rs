@@ -4,8 +4,11 @@ The `Title` class deals with page titles and namespaces
 
 #![deny(missing_docs)]
 
+use crate::media_wiki_error::MediaWikiError;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt::{self, Display};
+use std::str::FromStr;
 
 /// Shortcut for crate::api::NamespaceID
 type NamespaceID = crate::api::NamespaceID;
@@ -32,11 +35,148 @@ pub fn toggle_namespace_id(id: NamespaceID) -> Option<NamespaceID> {
     }
 }
 
+/// The namespaces that exist on every MediaWiki installation, plus any other namespace by ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Namespace {
+    /// Media (-2)
+    Media,
+    /// Special (-1)
+    Special,
+    /// Main (article) namespace (0)
+    Main,
+    /// Talk (1)
+    Talk,
+    /// User (2)
+    User,
+    /// User talk (3)
+    UserTalk,
+    /// Project (4)
+    Project,
+    /// Project talk (5)
+    ProjectTalk,
+    /// File (6)
+    File,
+    /// File talk (7)
+    FileTalk,
+    /// MediaWiki (8)
+    MediaWiki,
+    /// MediaWiki talk (9)
+    MediaWikiTalk,
+    /// Template (10)
+    Template,
+    /// Template talk (11)
+    TemplateTalk,
+    /// Help (12)
+    Help,
+    /// Help talk (13)
+    HelpTalk,
+    /// Category (14)
+    Category,
+    /// Category talk (15)
+    CategoryTalk,
+    /// Any other namespace, by ID
+    Other(NamespaceID),
+}
+
+/// The canonical namespaces with their IDs and canonical names
+pub(crate) const CANONICAL_NAMESPACES: &[(Namespace, NamespaceID, &str)] = &[
+    (Namespace::Media, -2, "Media"),
+    (Namespace::Special, -1, "Special"),
+    (Namespace::Main, 0, "Main"),
+    (Namespace::Talk, 1, "Talk"),
+    (Namespace::User, 2, "User"),
+    (Namespace::UserTalk, 3, "User talk"),
+    (Namespace::Project, 4, "Project"),
+    (Namespace::ProjectTalk, 5, "Project talk"),
+    (Namespace::File, 6, "File"),
+    (Namespace::FileTalk, 7, "File talk"),
+    (Namespace::MediaWiki, 8, "MediaWiki"),
+    (Namespace::MediaWikiTalk, 9, "MediaWiki talk"),
+    (Namespace::Template, 10, "Template"),
+    (Namespace::TemplateTalk, 11, "Template talk"),
+    (Namespace::Help, 12, "Help"),
+    (Namespace::HelpTalk, 13, "Help talk"),
+    (Namespace::Category, 14, "Category"),
+    (Namespace::CategoryTalk, 15, "Category talk"),
+];
+
+impl Namespace {
+    /// Returns the namespace ID
+    pub fn id(&self) -> NamespaceID {
+        match self {
+            Self::Other(id) => *id,
+            ns => CANONICAL_NAMESPACES
+                .iter()
+                .find(|(n, _, _)| n == ns)
+                .map(|(_, id, _)| *id)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the canonical (English) name, if this is a canonical namespace
+    pub fn canonical_name(&self) -> Option<&'static str> {
+        CANONICAL_NAMESPACES
+            .iter()
+            .find(|(n, _, _)| n == self)
+            .map(|(_, _, name)| *name)
+    }
+}
+
+impl From<NamespaceID> for Namespace {
+    fn from(id: NamespaceID) -> Self {
+        CANONICAL_NAMESPACES
+            .iter()
+            .find(|(_, i, _)| *i == id)
+            .map(|(n, _, _)| *n)
+            .unwrap_or(Self::Other(id))
+    }
+}
+
+impl From<Namespace> for NamespaceID {
+    fn from(ns: Namespace) -> Self {
+        ns.id()
+    }
+}
+
+impl Display for Namespace {
+    /// Writes the canonical name, or the ID for other namespaces
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.canonical_name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{}", self.id()),
+        }
+    }
+}
+
+impl FromStr for Namespace {
+    type Err = MediaWikiError;
+
+    /// Parses a namespace ID, or a canonical name (case-insensitive, spaces or underscores)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = Title::underscores_to_spaces(s).to_lowercase();
+        if let Ok(id) = s.parse::<NamespaceID>() {
+            return Ok(id.into());
+        }
+        if s.is_empty() || s == "(main)" {
+            return Ok(Self::Main);
+        }
+        CANONICAL_NAMESPACES
+            .iter()
+            .find(|(_, _, name)| name.to_lowercase() == s)
+            .map(|(n, _, _)| *n)
+            .ok_or_else(|| MediaWikiError::String(format!("Unknown namespace: {}", s)))
+    }
+}
+
 /// Title struct
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Title {
     title: String, // Always stored without underscores
     namespace_id: NamespaceID,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    interwiki: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fragment: Option<String>,
 }
 
 impl Title {
@@ -46,12 +186,24 @@ impl Title {
         Title {
             title: Title::underscores_to_spaces(title),
             namespace_id,
+            interwiki: None,
+            fragment: None,
         }
     }
 
     /// Constructor, where full namespace-prefixed title is known.
-    /// Uses Api to parse valid namespaces
+    /// Uses Api to parse valid namespaces. A `#fragment` is split off and kept as `fragment()`
     pub fn new_from_full(full_title: &str, api: &crate::api::Api) -> Self {
+        match full_title.split_once('#') {
+            Some((full_title, fragment)) => {
+                Self::new_from_full_without_fragment(full_title, api).with_fragment(fragment)
+            }
+            None => Self::new_from_full_without_fragment(full_title, api),
+        }
+    }
+
+    /// Constructor, used internally by `new_from_full`
+    fn new_from_full_without_fragment(full_title: &str, api: &crate::api::Api) -> Self {
         let mut v: Vec<&str> = full_title.split(':').collect();
         if v.len() == 1 {
             return Self::new(full_title, 0);
@@ -92,6 +244,19 @@ impl Title {
             }
         }
 
+        // Interwiki and language prefixes; the rest of the title is not parsed, as it belongs to another wiki
+        if let Some(info) = api.get_interwiki_info(full_title.split(':').next().unwrap_or_default())
+        {
+            if let Some(prefix) = info["prefix"].as_str() {
+                return Self {
+                    title: title.trim().to_string(),
+                    namespace_id: 0,
+                    interwiki: Some(prefix.to_string()),
+                    fragment: None,
+                };
+            }
+        }
+
         // Fallback
         Self::new(full_title, 0)
     }
@@ -123,6 +288,8 @@ impl Title {
         Title {
             title: Title::underscores_to_spaces(&title),
             namespace_id,
+            interwiki: None,
+            fragment: None,
         }
     }
 
@@ -131,6 +298,31 @@ impl Title {
         self.namespace_id
     }
 
+    /// Returns the interwiki or language prefix (e.g. `fr`), if the title is on another wiki.
+    /// The title and namespace ID are then not parsed, so `pretty()` returns the full title on that wiki
+    pub fn interwiki(&self) -> Option<&str> {
+        self.interwiki.as_deref()
+    }
+
+    /// Returns true if the title is on another wiki
+    pub fn is_interwiki(&self) -> bool {
+        self.interwiki.is_some()
+    }
+
+    /// Returns the URL of an interwiki title on the other wiki, based on the Api interwiki map
+    pub fn interwiki_url(&self, api: &crate::api::Api) -> Option<String> {
+        let url = api.get_interwiki_info(self.interwiki()?)?["url"].as_str()?;
+        Some(url.replace(
+            "$1",
+            &urlencoding::encode(&self.with_underscores()).replace("%2F", "/"),
+        ))
+    }
+
+    /// Returns the API URL of the wiki an interwiki title is on, based on the Api interwiki map
+    pub fn interwiki_api_url(&self, api: &crate::api::Api) -> Option<String> {
+        api.get_interwiki_api_url(self.interwiki()?)
+    }
+
     /// Returns the canonical namespace text, based on the Api
     pub fn namespace_name<'a>(&self, api: &'a crate::api::Api) -> Option<&'a str> {
         api.get_canonical_namespace_name(self.namespace_id)
@@ -249,9 +441,227 @@ impl Title {
     }
 }
 
+impl Title {
+    /// Returns the `#section` fragment, without the `#`
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    /// Sets the `#section` fragment; an empty fragment removes it
+    pub fn set_fragment(&mut self, fragment: Option<&str>) {
+        self.fragment = fragment
+            .map(Title::underscores_to_spaces)
+            .filter(|f| !f.is_empty());
+    }
+
+    /// Returns this title with a `#section` fragment
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mediawiki::title::Title;
+    /// let title = Title::new("Foo", 0).with_fragment("Early_life");
+    /// assert_eq!(title.fragment(), Some("Early life"));
+    /// assert_eq!(title.pretty(), "Foo");
+    /// ```
+    pub fn with_fragment(mut self, fragment: &str) -> Self {
+        self.set_fragment(Some(fragment));
+        self
+    }
+
+    /// Returns true if the title has a `/` with text on both sides.
+    /// Does not check if subpages are enabled for the namespace, which they are not by default for the main namespace
+    pub fn is_subpage(&self) -> bool {
+        self.title
+            .rsplit_once('/')
+            .is_some_and(|(base, name)| !base.is_empty() && !name.is_empty())
+    }
+
+    /// Returns the top-level page of a subpage, or this title for other pages
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mediawiki::title::Title;
+    /// let title = Title::new("Foo/Bar/Baz", 2);
+    /// assert_eq!(title.root(), Title::new("Foo", 2));
+    /// assert_eq!(title.base(), Title::new("Foo/Bar", 2));
+    /// assert_eq!(title.subpage_name(), "Baz");
+    /// ```
+    pub fn root(&self) -> Self {
+        match self.is_subpage() {
+            true => Self::new(
+                self.title.split('/').next().unwrap_or_default(),
+                self.namespace_id,
+            ),
+            false => Self::new(&self.title, self.namespace_id),
+        }
+    }
+
+    /// Returns the parent page of a subpage, or this title for other pages
+    pub fn base(&self) -> Self {
+        match self.title.rsplit_once('/') {
+            Some((base, _)) if self.is_subpage() => Self::new(base, self.namespace_id),
+            _ => Self::new(&self.title, self.namespace_id),
+        }
+    }
+
+    /// Returns the last part of a subpage title, or the whole title for other pages
+    pub fn subpage_name(&self) -> &str {
+        match self.title.rsplit_once('/') {
+            Some((_, name)) if self.is_subpage() => name,
+            _ => &self.title,
+        }
+    }
+
+    /// Returns the talk page of this title, or `None` for special pages
+    pub fn talk_page(&self) -> Option<Self> {
+        match self.namespace_id {
+            n if n < 0 => None,
+            n => Some(Self::new(&self.title, n | 1)),
+        }
+    }
+
+    /// Returns the subject page of a talk page, or this title for other pages
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mediawiki::title::Title;
+    /// assert_eq!(Title::new("Foo", 3).subject_page(), Title::new("Foo", 2));
+    /// assert_eq!(Title::new("Foo", 2).talk_page(), Some(Title::new("Foo", 3)));
+    /// assert_eq!(Title::new("Foo", -1).talk_page(), None);
+    /// ```
+    pub fn subject_page(&self) -> Self {
+        match self.namespace_id {
+            n if n < 0 => Self::new(&self.title, n),
+            n => Self::new(&self.title, n & !1),
+        }
+    }
+}
+
+impl Title {
+    /// Returns a site-independent canonical form, `namespace_id:title`, which `FromStr` parses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mediawiki::title::Title;
+    /// let title = Title::new("Foo.jpg", 6);
+    /// assert_eq!(title.to_canonical_string(), "6:Foo.jpg");
+    /// assert_eq!(title.to_canonical_string().parse::<Title>().unwrap(), title);
+    /// ```
+    pub fn to_canonical_string(&self) -> String {
+        format!("{}:{}", self.namespace_id, self.title)
+    }
+}
+
+impl Title {
+    /// Returns the hex-encoded binary sort key of this page in `category`, as stored by the wiki
+    /// for its configured collation. Sort keys of members of the same category compare in on-wiki order.
+    /// Returns `None` if the page is not in the category.
+    pub async fn sortkey(
+        &self,
+        api: &crate::api::Api,
+        category: &Title,
+    ) -> Result<Option<String>, MediaWikiError> {
+        let title = self
+            .full_pretty(api)
+            .ok_or_else(|| MediaWikiError::BadTitle(self.clone()))?;
+        let category = category
+            .full_pretty(api)
+            .ok_or_else(|| MediaWikiError::BadTitle(category.clone()))?;
+        let params = api.params_into(&[
+            ("action", "query"),
+            ("prop", "categories"),
+            ("titles", &title),
+            ("clcategories", &category),
+            ("clprop", "sortkey"),
+        ]);
+        let result = api.get_query_api_json_all(&params).await?;
+        Ok(Self::sortkey_from_api_result(&result))
+    }
+
+    fn sortkey_from_api_result(result: &serde_json::Value) -> Option<String> {
+        result["query"]["pages"]
+            .as_object()?
+            .values()
+            .filter_map(|page| page["categories"].as_array())
+            .flatten()
+            .find_map(|c| c["sortkey"].as_str())
+            .map(|s| s.to_string())
+    }
+}
+
+/// Orders by namespace ID, then by title code points, interwiki prefix and fragment. See `Collation` for wiki-style ordering.
+impl Ord for Title {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.namespace_id
+            .cmp(&other.namespace_id)
+            .then_with(|| self.title.cmp(&other.title))
+            .then_with(|| self.interwiki.cmp(&other.interwiki))
+            .then_with(|| self.fragment.cmp(&other.fragment))
+    }
+}
+
+impl PartialOrd for Title {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Title {
+    /// Splits a leading namespace ID or canonical (English) non-main namespace name off `s`.
+    /// `Main` is not accepted as a prefix, as `Main:Foo` is a valid title in the main namespace.
+    fn split_canonical_prefix(s: &str) -> Option<(NamespaceID, &str)> {
+        let (prefix, title) = s.split_once(':')?;
+        if let Ok(id) = prefix.trim().parse::<NamespaceID>() {
+            return Some((id, title));
+        }
+        match prefix.parse::<Namespace>() {
+            Ok(Namespace::Main) | Err(_) => None,
+            Ok(namespace) => Some((namespace.id(), title)),
+        }
+    }
+}
+
+impl FromStr for Title {
+    type Err = MediaWikiError;
+
+    /// Parses `namespace:title`, where namespace is an ID or a canonical (English) namespace name.
+    /// Titles without a recognized namespace prefix, or with a leading `:`, are in the main namespace.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = Title::underscores_to_spaces(s);
+        if let Some(title) = s.strip_prefix(':') {
+            return match title.is_empty() {
+                true => Err(MediaWikiError::String("Empty title".to_string())),
+                false => Ok(Title::new(title, 0)),
+            };
+        }
+        if s.is_empty() {
+            return Err(MediaWikiError::String("Empty title".to_string()));
+        }
+        match Title::split_canonical_prefix(&s) {
+            Some((namespace_id, title)) => Ok(Title::new(title, namespace_id)),
+            None => Ok(Title::new(&s, 0)),
+        }
+    }
+}
+
+/// Writes the title with its canonical (English) namespace name, or the namespace ID for other namespaces,
+/// which `FromStr` parses back. Main namespace titles that look prefixed get a leading `:`.
+/// Interwiki prefix and fragment are left out; see `Title::display_with` for the local namespace names.
 impl Display for Title {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.pretty())
+        match self.namespace_id {
+            0 if self.title.starts_with(':')
+                || Title::split_canonical_prefix(&self.title).is_some() =>
+            {
+                write!(f, ":{}", self.title)
+            }
+            0 => write!(f, "{}", self.title),
+            id => write!(f, "{}:{}", Namespace::from(id), self.title),
+        }
     }
 }
 
@@ -266,10 +676,33 @@ mod tests {
             .unwrap()
     }
 
+    #[test]
+    fn title_ordering() {
+        let mut titles = vec![Title::new("B", 1), Title::new("C", 0), Title::new("A", 0)];
+        titles.sort();
+        assert_eq!(
+            titles,
+            vec![Title::new("A", 0), Title::new("C", 0), Title::new("B", 1)]
+        );
+    }
+
+    #[test]
+    fn sortkey_from_api_result() {
+        let result = json!({"query":{"pages":{"1":{"title":"Foo","categories":[{"ns":14,"title":"Category:Bar","sortkey":"464f4f","sortkeyprefix":""}]}}}});
+        assert_eq!(
+            Title::sortkey_from_api_result(&result),
+            Some("464f4f".to_string())
+        );
+        assert_eq!(
+            Title::sortkey_from_api_result(&json!({"query":{"pages":{"1":{"title":"Foo"}}}})),
+            None
+        );
+    }
+
     #[tokio::test]
     async fn new_from_full_main_namespace() {
         assert_eq!(
-            Title::new_from_full(&"Main namespace", &wd_api().await),
+            Title::new_from_full("Main namespace", &wd_api().await),
             Title::new("Main namespace", 0)
         );
     }
@@ -277,7 +710,7 @@ mod tests {
     #[tokio::test]
     async fn new_from_full_canonical_namespace() {
         assert_eq!(
-            Title::new_from_full(&"File:Some file.jpg", &wd_api().await),
+            Title::new_from_full("File:Some file.jpg", &wd_api().await),
             Title::new("Some file.jpg", 6)
         );
     }
@@ -285,7 +718,7 @@ mod tests {
     #[tokio::test]
     async fn new_from_full_canonical_namespace_with_colon() {
         assert_eq!(
-            Title::new_from_full(&"Project talk:A project:yes, really", &wd_api().await),
+            Title::new_from_full("Project talk:A project:yes, really", &wd_api().await),
             Title::new("A project:yes, really", 5)
         );
     }
@@ -293,7 +726,7 @@ mod tests {
     #[tokio::test]
     async fn new_from_full_namespace_alias() {
         assert_eq!(
-            Title::new_from_full(&"Item:Q12345", &wd_api().await),
+            Title::new_from_full("Item:Q12345", &wd_api().await),
             Title::new("Q12345", 0)
         );
     }
@@ -301,7 +734,7 @@ mod tests {
     #[tokio::test]
     async fn new_from_full_special_namespace() {
         assert_eq!(
-            Title::new_from_full(&"Special:A title", &wd_api().await),
+            Title::new_from_full("Special:A title", &wd_api().await),
             Title::new("A title", -1)
         );
     }
@@ -309,15 +742,79 @@ mod tests {
     #[tokio::test]
     async fn new_from_full_invalid_namespace() {
         assert_eq!(
-            Title::new_from_full(&"This is not a namespace:A title", &wd_api().await),
+            Title::new_from_full("This is not a namespace:A title", &wd_api().await),
             Title::new("This is not a namespace:A title", 0)
         );
     }
 
+    #[test]
+    fn namespace_round_trip() {
+        assert_eq!(Namespace::from(14), Namespace::Category);
+        assert_eq!(Namespace::from(120), Namespace::Other(120));
+        assert_eq!(Namespace::UserTalk.to_string(), "User talk");
+        assert_eq!(
+            "user_talk".parse::<Namespace>().unwrap(),
+            Namespace::UserTalk
+        );
+        assert_eq!("120".parse::<Namespace>().unwrap(), Namespace::Other(120));
+        assert_eq!(
+            Namespace::Other(120)
+                .to_string()
+                .parse::<Namespace>()
+                .unwrap(),
+            Namespace::Other(120)
+        );
+        assert!("Notanamespace".parse::<Namespace>().is_err());
+    }
+
+    #[test]
+    fn title_from_str() {
+        assert_eq!(
+            "File:Foo.jpg".parse::<Title>().unwrap(),
+            Title::new("Foo.jpg", 6)
+        );
+        assert_eq!("120:P31".parse::<Title>().unwrap(), Title::new("P31", 120));
+        assert_eq!(
+            "Foo: Bar".parse::<Title>().unwrap(),
+            Title::new("Foo: Bar", 0)
+        );
+        assert_eq!(":Foo".parse::<Title>().unwrap(), Title::new("Foo", 0));
+        assert_eq!(
+            "Main:Foo".parse::<Title>().unwrap(),
+            Title::new("Main:Foo", 0)
+        );
+        assert!("".parse::<Title>().is_err());
+        assert!(":".parse::<Title>().is_err());
+    }
+
+    #[test]
+    fn title_display_round_trip() {
+        let titles = [
+            Title::new("Foo bar", 0),
+            Title::new("Foo.jpg", 6),
+            Title::new("Foo", 3),
+            Title::new("P31", 120),
+            Title::new("Main:Foo", 0),
+            Title::new("2001: A Space Odyssey", 0),
+            Title::new("Category:Foo", 0),
+            Title::new("Foo: Bar", 0),
+        ];
+        for title in titles {
+            assert_eq!(title.to_string().parse::<Title>().unwrap(), title);
+        }
+        assert_eq!(Title::new("Foo.jpg", 6).to_string(), "File:Foo.jpg");
+        assert_eq!(Title::new("P31", 120).to_string(), "120:P31");
+        assert_eq!(Title::new("Main:Foo", 0).to_string(), "Main:Foo");
+        assert_eq!(
+            Title::new("2001: A Space Odyssey", 0).to_string(),
+            ":2001: A Space Odyssey"
+        );
+    }
+
     #[tokio::test]
     async fn spaces_to_underscores() {
         assert_eq!(
-            Title::spaces_to_underscores(&" A little  test "),
+            Title::spaces_to_underscores(" A little  test "),
             "A_little__test"
         );
     }
@@ -325,24 +822,24 @@ mod tests {
     #[tokio::test]
     async fn underscores_to_spaces() {
         assert_eq!(
-            Title::underscores_to_spaces(&"_A_little__test_"),
+            Title::underscores_to_spaces("_A_little__test_"),
             "A little  test"
         );
     }
 
     #[tokio::test]
     async fn first_letter_uppercase() {
-        assert_eq!(Title::first_letter_uppercase(&""), "");
-        assert_eq!(Title::first_letter_uppercase(&"FooBar"), "FooBar");
-        assert_eq!(Title::first_letter_uppercase(&"fooBar"), "FooBar");
-        assert_eq!(Title::first_letter_uppercase(&"über"), "Über");
-        assert_eq!(Title::first_letter_uppercase(&"ვიკიპედია"), "ვიკიპედია");
+        assert_eq!(Title::first_letter_uppercase(""), "");
+        assert_eq!(Title::first_letter_uppercase("FooBar"), "FooBar");
+        assert_eq!(Title::first_letter_uppercase("fooBar"), "FooBar");
+        assert_eq!(Title::first_letter_uppercase("über"), "Über");
+        assert_eq!(Title::first_letter_uppercase("ვიკიპედია"), "ვიკიპედია");
     }
 
     #[tokio::test]
     async fn full() {
         let api = &wd_api().await;
-        let title = Title::new_from_full(&"User talk:Magnus_Manske", api);
+        let title = Title::new_from_full("User talk:Magnus_Manske", api);
         assert_eq!(
             title.full_pretty(api),
             Some("User talk:Magnus Manske".to_string())
@@ -352,4 +849,97 @@ mod tests {
             Some("User_talk:Magnus_Manske".to_string())
         );
     }
+
+    #[tokio::test]
+    async fn interwiki() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("meta", "siteinfo")],
+            json!({"query":{"namespaces":{"0":{"id":0,"case":"first-letter","*":""},
+                "4":{"id":4,"case":"first-letter","*":"Wikipedia","canonical":"Project"}},
+                "interwikimap":[
+                    {"prefix":"fr","local":true,"language":"français","url":"https://fr.wikipedia.org/wiki/$1"},
+                    {"prefix":"commons","local":true,"url":"https://commons.wikimedia.org/wiki/$1","api":"https://commons.wikimedia.org/w/api.php"},
+                    {"prefix":"foo","url":"https://foo.example.org/index.php?title=$1"}]}}),
+        );
+        let api = Api::new_mocked(responses).unwrap();
+        let title = Title::new_from_full("fr:Paris_(ville)", &api);
+        assert_eq!(title.interwiki(), Some("fr"));
+        assert_eq!(title.pretty(), "Paris (ville)");
+        assert_eq!(title.namespace_id(), 0);
+        assert_eq!(
+            title.interwiki_url(&api),
+            Some("https://fr.wikipedia.org/wiki/Paris_%28ville%29".to_string())
+        );
+        assert_eq!(
+            title.interwiki_api_url(&api),
+            Some("https://fr.wikipedia.org/w/api.php".to_string())
+        );
+        let title = Title::new_from_full("Commons:Category:Paris", &api);
+        assert_eq!(title.interwiki(), Some("commons"));
+        assert_eq!(title.pretty(), "Category:Paris");
+        assert_eq!(
+            api.get_interwiki_api_url("commons"),
+            Some("https://commons.wikimedia.org/w/api.php".to_string())
+        );
+        assert_eq!(
+            api.get_interwiki_api_url("foo"),
+            Some("https://foo.example.org/api.php".to_string())
+        );
+        let title = Title::new_from_full("Wikipedia:Foo", &api);
+        assert_eq!(title, Title::new("Foo", 4));
+        assert!(!title.is_interwiki());
+        assert_eq!(
+            Title::new_from_full("xx:Foo", &api),
+            Title::new("xx:Foo", 0)
+        );
+    }
+
+    #[test]
+    fn subpages() {
+        let title = Title::new("Magnus_Manske/Bots/List", 3);
+        assert!(title.is_subpage());
+        assert_eq!(title.root(), Title::new("Magnus Manske", 3));
+        assert_eq!(title.base().base(), title.root());
+        assert_eq!(title.subpage_name(), "List");
+        assert_eq!(
+            title.subject_page(),
+            Title::new("Magnus Manske/Bots/List", 2)
+        );
+        let title = Title::new("AC/", 0);
+        assert!(!title.is_subpage());
+        assert_eq!(title.base(), title);
+        assert_eq!(title.subpage_name(), "AC/");
+    }
+
+    #[tokio::test]
+    async fn new_from_full_fragment() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("meta", "siteinfo")],
+            json!({"query":{"namespaces":{"2":{"id":2,"case":"first-letter","*":"User","canonical":"User"}}}}),
+        );
+        let api = Api::new_mocked(responses).unwrap();
+        let title = Title::new_from_full("User:Foo#Some_section", &api);
+        assert_eq!(title.fragment(), Some("Some section"));
+        assert_eq!(title, Title::new("Foo", 2).with_fragment("Some section"));
+        assert_ne!(title, Title::new("Foo", 2));
+        let mut title = title;
+        title.set_fragment(Some(""));
+        assert_eq!(title, Title::new("Foo", 2));
+    }
+
+    #[test]
+    fn title_serde() {
+        let title = Title::new("Foo", 2);
+        let j = serde_json::to_value(&title).unwrap();
+        assert_eq!(j, json!({"title":"Foo","namespace_id":2}));
+        assert_eq!(serde_json::from_value::<Title>(j).unwrap(), title);
+        let title = title.with_fragment("Bar");
+        let j = serde_json::to_string(&title).unwrap();
+        assert_eq!(serde_json::from_str::<Title>(&j).unwrap(), title);
+        let map: std::collections::BTreeMap<Title, u32> =
+            [(Title::new("B", 0), 1), (Title::new("A", 0), 2)].into();
+        assert_eq!(map.keys().next(), Some(&Title::new("A", 0)));
+    }
 }
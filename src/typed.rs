@@ -0,0 +1,177 @@
+/*!
+The `typed` module deserializes API results into the caller's own `serde` structs,
+instead of navigating `Value` trees, and flattens `query.pages` across `formatversion` 1 and 2.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::Page;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Returns the entries of `query.pages`, which is an object keyed by page ID with `formatversion=1`,
+/// and an array with `formatversion=2`
+pub fn query_pages(result: &Value) -> Vec<&Value> {
+    match &result["query"]["pages"] {
+        Value::Object(pages) => pages.values().collect(),
+        Value::Array(pages) => pages.iter().collect(),
+        _ => vec![],
+    }
+}
+
+/// Deserializes the entries of `query.pages`; see `query_pages`
+pub fn deserialize_query_pages<T: DeserializeOwned>(
+    result: &Value,
+) -> Result<Vec<T>, MediaWikiError> {
+    query_pages(result)
+        .into_iter()
+        .map(|page| Ok(T::deserialize(page)?))
+        .collect()
+}
+
+impl Api {
+    /// Runs a GET query, and deserializes the result into `T`
+    pub async fn get_query_typed<T: DeserializeOwned>(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Result<T, MediaWikiError> {
+        let result = self.get_query_api_json(params).await?;
+        Ok(T::deserialize(&result)?)
+    }
+
+    /// Runs a GET query with continuation, and deserializes the entries of `query.pages` into `T`
+    pub async fn get_query_pages_typed<T: DeserializeOwned>(
+        &self,
+        params: &HashMap<String, String>,
+    ) -> Result<Vec<T>, MediaWikiError> {
+        let result = self.get_query_api_json_all(params).await?;
+        deserialize_query_pages(&result)
+    }
+}
+
+impl Page {
+    /// Runs a query for this page, and deserializes the entries of `subkey` (e.g. `categories`) into `T`
+    pub async fn query_typed<T: DeserializeOwned>(
+        &self,
+        api: &Api,
+        additional_params: &[(&str, &str)],
+        subkey: &str,
+    ) -> Result<Vec<T>, MediaWikiError> {
+        let result = self.action_query(api, additional_params).await?;
+        self.extract_page_properties_from_api_results(result, subkey)?
+            .into_iter()
+            .map(|v| Ok(T::deserialize(v)?))
+            .collect()
+    }
+
+    /// Same as `categories`, but deserializes each category into `T`
+    pub async fn categories_typed<T: DeserializeOwned>(
+        &self,
+        api: &Api,
+    ) -> Result<Vec<T>, MediaWikiError> {
+        self.categories(api)
+            .await?
+            .into_iter()
+            .map(|v| Ok(T::deserialize(v)?))
+            .collect()
+    }
+
+    /// Same as `interwiki_links`, but deserializes each link into `T`
+    pub async fn interwiki_links_typed<T: DeserializeOwned>(
+        &self,
+        api: &Api,
+    ) -> Result<Vec<T>, MediaWikiError> {
+        self.interwiki_links(api)
+            .await?
+            .into_iter()
+            .map(|v| Ok(T::deserialize(v)?))
+            .collect()
+    }
+
+    /// Same as `coordinates`, but deserializes each coordinate into `T`
+    pub async fn coordinates_typed<T: DeserializeOwned>(
+        &self,
+        api: &Api,
+    ) -> Result<Vec<T>, MediaWikiError> {
+        self.coordinates(api)
+            .await?
+            .into_iter()
+            .map(|v| Ok(T::deserialize(v)?))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+    use crate::title::Title;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct PageEntry {
+        pageid: u64,
+        title: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Category {
+        title: String,
+        #[serde(default)]
+        hidden: Option<String>,
+    }
+
+    #[test]
+    fn flatten_pages() {
+        let v1 = json!({"query":{"pages":{"12":{"pageid":12,"ns":0,"title":"Foo"}}}});
+        let v2 = json!({"query":{"pages":[{"pageid":12,"ns":0,"title":"Foo"}]}});
+        let expected = vec![PageEntry {
+            pageid: 12,
+            title: "Foo".to_string(),
+        }];
+        assert_eq!(deserialize_query_pages::<PageEntry>(&v1).unwrap(), expected);
+        assert_eq!(deserialize_query_pages::<PageEntry>(&v2).unwrap(), expected);
+        assert!(query_pages(&json!({})).is_empty());
+        assert!(deserialize_query_pages::<PageEntry>(
+            &json!({"query":{"pages":[{"title":"No ID"}]}})
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn typed_queries() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"namespaces":{"0":{"id":0,"case":"first-letter","*":""}}}}),
+            )
+            .add(
+                &[("prop", "categories")],
+                json!({"batchcomplete":"","query":{"pages":{"12":{"pageid":12,"ns":0,"title":"Foo",
+                    "categories":[{"ns":14,"title":"Category:Bar","hidden":""}]}}}}),
+            )
+            .add(
+                &[("prop", "info")],
+                json!({"batchcomplete":"","query":{"pages":[{"pageid":12,"ns":0,"title":"Foo"}]}}),
+            );
+        let api = Api::new_mocked(responses).unwrap();
+        let params = api.params_into(&[("action", "query"), ("prop", "info"), ("titles", "Foo")]);
+        let pages: Vec<PageEntry> = api.get_query_pages_typed(&params).await.unwrap();
+        assert_eq!(pages[0].pageid, 12);
+        let batch: Value = api.get_query_typed(&params).await.unwrap();
+        assert_eq!(batch["batchcomplete"], "");
+        let page = Page::new(Title::new("Foo", 0));
+        let categories: Vec<Category> = page.categories_typed(&api).await.unwrap();
+        assert_eq!(
+            categories,
+            vec![Category {
+                title: "Category:Bar".to_string(),
+                hidden: Some(String::new())
+            }]
+        );
+    }
+}
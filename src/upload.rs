@@ -0,0 +1,700 @@
+/*!
+The `upload` module deals with uploading files, directly or in chunks for large files.
+Chunked uploads read one chunk at a time, so large files are never held in memory as a whole.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{Read, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+const DEFAULT_CHUNK_SIZE: usize = 5 * 1024 * 1024;
+
+/// Files larger than this are uploaded in chunks by default
+const DEFAULT_CHUNK_THRESHOLD: usize = 20 * 1024 * 1024;
+
+/// Placeholder stash file key of chunks that were simulated in dry-run mode
+const DRY_RUN_FILEKEY: &str = "dry-run";
+
+/// A callback for upload progress
+pub type ProgressCallback = Box<dyn FnMut(&UploadProgress) + Send>;
+
+/// Progress of a chunked upload, passed to the progress callback after each acknowledged chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// Bytes acknowledged by the server so far
+    pub bytes_sent: usize,
+    /// Total bytes of the file
+    pub total: usize,
+    /// Index of the chunk that was just acknowledged, starting with 0
+    pub chunk_index: usize,
+}
+
+/// The data to upload
+pub enum UploadSource {
+    /// A file on disk, read one chunk at a time
+    Path(PathBuf),
+    /// A blocking reader, read one chunk at a time on a blocking thread
+    Reader {
+        /// The reader
+        reader: Box<dyn Read + Send>,
+        /// The number of bytes to upload from the reader
+        size: usize,
+    },
+    /// Data in memory
+    Bytes(Vec<u8>),
+}
+
+impl UploadSource {
+    /// Creates a source that uploads `size` bytes from `reader`
+    pub fn reader(reader: impl Read + Send + 'static, size: usize) -> Self {
+        Self::Reader {
+            reader: Box::new(reader),
+            size,
+        }
+    }
+
+    /// Opens the source, and returns its size in bytes and a reader for its chunks
+    async fn open(self) -> Result<(usize, ChunkReader<'static>), MediaWikiError> {
+        match self {
+            Self::Path(path) => {
+                let file = tokio::fs::File::open(path).await?;
+                let size = file.metadata().await?.len() as usize;
+                Ok((size, ChunkReader::File(file)))
+            }
+            Self::Reader { reader, size } => Ok((
+                size,
+                ChunkReader::Reader {
+                    reader: Some(reader),
+                    position: 0,
+                },
+            )),
+            Self::Bytes(data) => Ok((data.len(), ChunkReader::Bytes(Cow::Owned(data)))),
+        }
+    }
+}
+
+impl fmt::Debug for UploadSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "UploadSource::Path({:?})", path),
+            Self::Reader { size, .. } => write!(f, "UploadSource::Reader({} bytes)", size),
+            Self::Bytes(data) => write!(f, "UploadSource::Bytes({} bytes)", data.len()),
+        }
+    }
+}
+
+impl From<&Path> for UploadSource {
+    fn from(path: &Path) -> Self {
+        Self::Path(path.to_path_buf())
+    }
+}
+
+impl From<PathBuf> for UploadSource {
+    fn from(path: PathBuf) -> Self {
+        Self::Path(path)
+    }
+}
+
+impl From<Vec<u8>> for UploadSource {
+    fn from(data: Vec<u8>) -> Self {
+        Self::Bytes(data)
+    }
+}
+
+/// Reads the data to upload one chunk at a time
+enum ChunkReader<'a> {
+    File(tokio::fs::File),
+    Reader {
+        reader: Option<Box<dyn Read + Send>>,
+        position: usize,
+    },
+    Bytes(Cow<'a, [u8]>),
+}
+
+impl ChunkReader<'_> {
+    /// Reads `len` bytes starting at `offset`. Readers can only skip forward.
+    async fn read_chunk(&mut self, offset: usize, len: usize) -> Result<Vec<u8>, MediaWikiError> {
+        match self {
+            Self::File(file) => {
+                file.seek(SeekFrom::Start(offset as u64)).await?;
+                let mut chunk = vec![0; len];
+                file.read_exact(&mut chunk).await?;
+                Ok(chunk)
+            }
+            Self::Reader { reader, position } => {
+                if offset < *position {
+                    return Err(MediaWikiError::String(format!(
+                        "Cannot rewind upload reader from {} to {}",
+                        position, offset
+                    )));
+                }
+                let mut current = reader
+                    .take()
+                    .ok_or_else(|| MediaWikiError::String("Upload reader failed".to_string()))?;
+                let skip = (offset - *position) as u64;
+                let (current, chunk) = tokio::task::spawn_blocking(move || {
+                    let chunk = Self::read_blocking(&mut current, skip, len);
+                    (current, chunk)
+                })
+                .await
+                .map_err(|e| MediaWikiError::String(e.to_string()))?;
+                *reader = Some(current);
+                let chunk = chunk?;
+                *position = offset + len;
+                Ok(chunk)
+            }
+            Self::Bytes(data) => data
+                .get(offset..offset + len)
+                .map(|chunk| chunk.to_vec())
+                .ok_or_else(|| {
+                    MediaWikiError::String(format!("Chunk at {} is beyond the data", offset))
+                }),
+        }
+    }
+
+    /// Skips `skip` bytes of `reader`, and reads the next `len` bytes
+    fn read_blocking(
+        reader: &mut Box<dyn Read + Send>,
+        skip: u64,
+        len: usize,
+    ) -> std::io::Result<Vec<u8>> {
+        std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())?;
+        let mut chunk = vec![0; len];
+        reader.read_exact(&mut chunk)?;
+        Ok(chunk)
+    }
+}
+
+/// Options for `Api::upload_file`
+pub struct UploadOptions {
+    chunk_threshold: usize,
+    chunk_size: usize,
+    max_bytes_per_second: Option<u64>,
+    ignore_warnings: bool,
+    progress: Option<ProgressCallback>,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            chunk_threshold: DEFAULT_CHUNK_THRESHOLD,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_bytes_per_second: None,
+            ignore_warnings: false,
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for UploadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UploadOptions")
+            .field("chunk_threshold", &self.chunk_threshold)
+            .field("chunk_size", &self.chunk_size)
+            .field("max_bytes_per_second", &self.max_bytes_per_second)
+            .field("ignore_warnings", &self.ignore_warnings)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl UploadOptions {
+    /// Returns the default options: chunked upload above 20MB, in 5MB chunks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the file size in bytes above which the file is uploaded in chunks
+    pub fn set_chunk_threshold(&mut self, chunk_threshold: usize) {
+        self.chunk_threshold = chunk_threshold;
+    }
+
+    /// Sets the chunk size in bytes for chunked uploads
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size.max(1);
+    }
+
+    /// Sets a bandwidth cap in bytes per second for chunked uploads
+    pub fn set_max_bytes_per_second(&mut self, max_bytes_per_second: Option<u64>) {
+        self.max_bytes_per_second = max_bytes_per_second;
+    }
+
+    /// Sets whether warnings (e.g. duplicate files) should be ignored
+    pub fn set_ignore_warnings(&mut self, ignore_warnings: bool) {
+        self.ignore_warnings = ignore_warnings;
+    }
+
+    /// Sets a callback that is called after each chunk, or once after a direct upload
+    pub fn set_progress(&mut self, progress: Option<ProgressCallback>) {
+        self.progress = progress;
+    }
+
+    /// Returns true if a file of `size` bytes is uploaded in chunks
+    pub fn is_chunked(&self, size: usize) -> bool {
+        size > self.chunk_threshold
+    }
+}
+
+/// `ChunkedUpload` uploads a file in chunks, via the upload stash.
+/// If a chunk fails, the upload can be resumed from the last acknowledged offset
+/// by calling `upload` again.
+#[derive(Debug, Clone)]
+pub struct ChunkedUpload {
+    filename: String,
+    chunk_size: usize,
+    max_bytes_per_second: Option<u64>,
+    filekey: Option<String>,
+    offset: usize,
+    chunk_index: usize,
+    comment: String,
+    text: Option<String>,
+    ignore_warnings: bool,
+}
+
+impl ChunkedUpload {
+    /// Creates a new chunked upload for the given target file name (without namespace prefix)
+    pub fn new<S: Into<String>>(filename: S) -> Self {
+        Self {
+            filename: filename.into(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            max_bytes_per_second: None,
+            filekey: None,
+            offset: 0,
+            chunk_index: 0,
+            comment: String::new(),
+            text: None,
+            ignore_warnings: false,
+        }
+    }
+
+    /// Returns the target file name
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Sets the chunk size in bytes (default 5MB)
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size.max(1);
+    }
+
+    /// Sets a bandwidth cap in bytes per second (or `None` for no cap)
+    pub fn set_max_bytes_per_second(&mut self, max_bytes_per_second: Option<u64>) {
+        self.max_bytes_per_second = max_bytes_per_second;
+    }
+
+    /// Sets the upload comment
+    pub fn set_comment<S: Into<String>>(&mut self, comment: S) {
+        self.comment = comment.into();
+    }
+
+    /// Sets the initial page text for a new file
+    pub fn set_text<S: Into<String>>(&mut self, text: Option<S>) {
+        self.text = text.map(|s| s.into());
+    }
+
+    /// Sets whether warnings (e.g. duplicate files) should be ignored
+    pub fn set_ignore_warnings(&mut self, ignore_warnings: bool) {
+        self.ignore_warnings = ignore_warnings;
+    }
+
+    /// Returns the stash file key, once the first chunk has been acknowledged
+    pub fn filekey(&self) -> Option<&str> {
+        self.filekey.as_deref()
+    }
+
+    /// Returns the offset of the last acknowledged chunk
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Resumes a previous upload from a stash file key and an acknowledged offset
+    pub fn resume_from<S: Into<String>>(&mut self, filekey: S, offset: usize) {
+        self.filekey = Some(filekey.into());
+        self.offset = offset;
+        self.chunk_index = offset.div_ceil(self.chunk_size);
+    }
+
+    /// Returns the time to wait so that `bytes` sent in `elapsed` stay under the bandwidth cap
+    fn throttle_delay(&self, bytes: usize, elapsed: Duration) -> Option<Duration> {
+        let max = self.max_bytes_per_second.filter(|max| *max > 0)?;
+        let required = Duration::from_secs_f64(bytes as f64 / max as f64);
+        required.checked_sub(elapsed).filter(|d| !d.is_zero())
+    }
+
+    /// Uploads `data` in chunks, starting from the last acknowledged offset, and
+    /// commits the file from the stash. `progress` is called after each acknowledged chunk.
+    /// Returns the final `action=upload` result.
+    pub async fn upload(
+        &mut self,
+        api: &Api,
+        data: &[u8],
+        progress: Option<&mut (dyn FnMut(&UploadProgress) + Send)>,
+    ) -> Result<Value, MediaWikiError> {
+        let mut reader = ChunkReader::Bytes(Cow::Borrowed(data));
+        self.upload_chunks(api, data.len(), &mut reader, progress)
+            .await
+    }
+
+    /// Same as `upload`, but reads each chunk from `source` just before it is sent
+    pub async fn upload_source(
+        &mut self,
+        api: &Api,
+        source: UploadSource,
+        progress: Option<&mut (dyn FnMut(&UploadProgress) + Send)>,
+    ) -> Result<Value, MediaWikiError> {
+        let (total, mut reader) = source.open().await?;
+        self.upload_chunks(api, total, &mut reader, progress).await
+    }
+
+    async fn upload_chunks(
+        &mut self,
+        api: &Api,
+        total: usize,
+        reader: &mut ChunkReader<'_>,
+        mut progress: Option<&mut (dyn FnMut(&UploadProgress) + Send)>,
+    ) -> Result<Value, MediaWikiError> {
+        let token = api.get_edit_token().await?;
+        while self.offset < total {
+            let start = Instant::now();
+            let chunk_start = self.offset;
+            let end = (self.offset + self.chunk_size).min(total);
+            let mut params = api.params_into(&[
+                ("action", "upload"),
+                ("filename", &self.filename),
+                ("filesize", &total.to_string()),
+                ("offset", &self.offset.to_string()),
+                ("stash", "1"),
+                ("token", &token),
+            ]);
+            if self.ignore_warnings {
+                params.insert("ignorewarnings".to_string(), "1".to_string());
+            }
+            if let Some(filekey) = &self.filekey {
+                params.insert("filekey".to_string(), filekey.to_string());
+            }
+            let chunk = reader.read_chunk(self.offset, end - self.offset).await?;
+            let result = api
+                .post_query_api_multipart(&params, "chunk", &self.filename, chunk)
+                .await?;
+            let upload = &result["upload"];
+            match upload["result"].as_str() {
+                Some("Continue") | Some("Success") => {}
+                _ => return Err(MediaWikiError::UploadError(result)),
+            }
+            if let Some(filekey) = upload["filekey"].as_str() {
+                self.filekey = Some(filekey.to_string());
+            } else if Api::is_dry_run_result(&result) {
+                self.filekey = Some(DRY_RUN_FILEKEY.to_string());
+            }
+            self.offset = upload["offset"].as_u64().map(|o| o as usize).unwrap_or(end);
+            if let Some(progress) = progress.as_mut() {
+                progress(&UploadProgress {
+                    bytes_sent: self.offset,
+                    total,
+                    chunk_index: self.chunk_index,
+                });
+            }
+            self.chunk_index += 1;
+            if let Some(delay) = self.throttle_delay(end - chunk_start, start.elapsed()) {
+                tokio::time::sleep(delay).await;
+            }
+        }
+        self.commit(api, &token).await
+    }
+
+    /// Commits the stashed file
+    async fn commit(&self, api: &Api, token: &str) -> Result<Value, MediaWikiError> {
+        let filekey = self
+            .filekey
+            .as_ref()
+            .ok_or_else(|| MediaWikiError::String("No file key for upload".to_string()))?;
+        let mut params = api.params_into(&[
+            ("action", "upload"),
+            ("filename", &self.filename),
+            ("filekey", filekey),
+            ("comment", &self.comment),
+            ("token", token),
+        ]);
+        if let Some(text) = &self.text {
+            params.insert("text".to_string(), text.to_string());
+        }
+        if self.ignore_warnings {
+            params.insert("ignorewarnings".to_string(), "1".to_string());
+        }
+        let result = api.post_query_api_json(&params).await?;
+        match result["upload"]["result"].as_str() {
+            Some("Success") => Ok(result),
+            _ => Err(MediaWikiError::UploadError(result)),
+        }
+    }
+}
+
+impl Api {
+    /// Uploads a file as `filename` (without namespace prefix), with the initial page `text` and upload `comment`.
+    /// Files above the chunk threshold of `options` are uploaded in chunks via the upload stash,
+    /// reading one chunk at a time. Returns the final `action=upload` result.
+    pub async fn upload_file(
+        &self,
+        source: impl Into<UploadSource>,
+        filename: &str,
+        text: &str,
+        comment: &str,
+        mut options: UploadOptions,
+    ) -> Result<Value, MediaWikiError> {
+        let (total, mut reader) = source.into().open().await?;
+        if options.is_chunked(total) {
+            let mut upload = ChunkedUpload::new(filename);
+            upload.set_chunk_size(options.chunk_size);
+            upload.set_max_bytes_per_second(options.max_bytes_per_second);
+            upload.set_ignore_warnings(options.ignore_warnings);
+            upload.set_comment(comment);
+            upload.set_text(Some(text));
+            let progress: Option<&mut (dyn FnMut(&UploadProgress) + Send)> =
+                match &mut options.progress {
+                    Some(progress) => Some(progress.as_mut()),
+                    None => None,
+                };
+            return upload
+                .upload_chunks(self, total, &mut reader, progress)
+                .await;
+        }
+        let data = reader.read_chunk(0, total).await?;
+        let token = self.get_edit_token().await?;
+        let mut params = self.params_into(&[
+            ("action", "upload"),
+            ("filename", filename),
+            ("text", text),
+            ("comment", comment),
+            ("token", &token),
+        ]);
+        if options.ignore_warnings {
+            params.insert("ignorewarnings".to_string(), "1".to_string());
+        }
+        let result = self
+            .post_query_api_multipart(&params, "file", filename, data)
+            .await?;
+        if result["upload"]["result"].as_str() != Some("Success") {
+            return Err(MediaWikiError::UploadError(result));
+        }
+        if let Some(progress) = options.progress.as_mut() {
+            progress(&UploadProgress {
+                bytes_sent: total,
+                total,
+                chunk_index: 0,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Uploads a file from a URL as `filename` (without namespace prefix), with the initial page `text`
+    /// and upload `comment`. Requires the `upload_by_url` right, and a whitelisted domain on most wikis.
+    /// Fails with `MediaWikiError::FileExists` if the file exists here or in a shared repository such as Commons.
+    pub async fn upload_by_url(
+        &self,
+        url: &str,
+        filename: &str,
+        text: &str,
+        comment: &str,
+    ) -> Result<Value, MediaWikiError> {
+        let token = self.get_edit_token().await?;
+        let params = self.params_into(&[
+            ("action", "upload"),
+            ("url", url),
+            ("filename", filename),
+            ("text", text),
+            ("comment", comment),
+            ("token", &token),
+        ]);
+        Self::upload_result(filename, self.post_query_api_json(&params).await)
+    }
+
+    /// Checks the result of an upload, and converts warnings and errors about existing files
+    fn upload_result(
+        filename: &str,
+        result: Result<Value, MediaWikiError>,
+    ) -> Result<Value, MediaWikiError> {
+        let is_exists_code = |code: &str| code.starts_with("fileexists");
+        match result {
+            Ok(result) if result["upload"]["result"].as_str() == Some("Success") => Ok(result),
+            Ok(result)
+                if !result["upload"]["warnings"]["exists"].is_null()
+                    || result["error"]["code"].as_str().is_some_and(is_exists_code) =>
+            {
+                Err(MediaWikiError::FileExists(filename.to_string()))
+            }
+            Ok(result) => Err(MediaWikiError::UploadError(result)),
+            Err(MediaWikiError::Api { code, .. }) if is_exists_code(&code) => {
+                Err(MediaWikiError::FileExists(filename.to_string()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_delay() {
+        let mut upload = ChunkedUpload::new("Test.webm");
+        assert_eq!(upload.throttle_delay(1000, Duration::ZERO), None);
+        upload.set_max_bytes_per_second(Some(1000));
+        assert_eq!(
+            upload.throttle_delay(2000, Duration::from_millis(500)),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(upload.throttle_delay(2000, Duration::from_secs(3)), None);
+    }
+
+    #[test]
+    fn upload_options() {
+        let mut options = UploadOptions::new();
+        assert!(!options.is_chunked(1024));
+        options.set_chunk_threshold(100);
+        assert!(options.is_chunked(101));
+        assert!(!options.is_chunked(100));
+    }
+
+    #[tokio::test]
+    async fn upload_source() {
+        let source = UploadSource::reader(&b"abcdef"[..], 6);
+        assert_eq!(format!("{:?}", source), "UploadSource::Reader(6 bytes)");
+        let (size, mut reader) = source.open().await.unwrap();
+        assert_eq!(size, 6);
+        assert_eq!(reader.read_chunk(2, 2).await.unwrap(), b"cd".to_vec());
+        assert_eq!(reader.read_chunk(4, 2).await.unwrap(), b"ef".to_vec());
+        assert!(reader.read_chunk(0, 2).await.is_err());
+        let source: UploadSource = vec![1, 2].into();
+        assert_eq!(format!("{:?}", source), "UploadSource::Bytes(2 bytes)");
+        let source: UploadSource = Path::new("/nonexistent/file.jpg").into();
+        assert!(source.open().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn chunked_file_upload() {
+        let path =
+            std::env::temp_dir().join(format!("mediawiki-upload-{}.bin", std::process::id()));
+        tokio::fs::write(&path, b"0123456789").await.unwrap();
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("offset", "0")],
+                json!({"upload":{"result":"Continue","offset":4,"filekey":"k.bin"}}),
+            )
+            .add(
+                &[("offset", "4")],
+                json!({"upload":{"result":"Continue","offset":8,"filekey":"k.bin"}}),
+            )
+            .add(
+                &[("offset", "8")],
+                json!({"upload":{"result":"Success","offset":10,"filekey":"k.bin"}}),
+            )
+            .add(
+                &[("action", "upload"), ("filekey", "k.bin")],
+                json!({"upload":{"result":"Success","filename":"Test.bin"}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut options = UploadOptions::new();
+        options.set_chunk_threshold(5);
+        options.set_chunk_size(4);
+        let sent = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let sent_clone = sent.clone();
+        options.set_progress(Some(Box::new(move |p: &UploadProgress| {
+            sent_clone.lock().unwrap().push(p.bytes_sent)
+        })));
+        api.upload_file(path.as_path(), "Test.bin", "text", "comment", options)
+            .await
+            .unwrap();
+        assert_eq!(*sent.lock().unwrap(), vec![4, 8, 10]);
+        let requests = responses.requests();
+        let chunks: Vec<&str> = requests
+            .iter()
+            .filter(|r| r.contains_key("offset"))
+            .map(|r| r["offset"].as_str())
+            .collect();
+        assert_eq!(chunks, vec!["0", "4", "8"]);
+        assert_eq!(requests.last().unwrap()["comment"], "comment");
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn chunked_upload_dry_run() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses.add(
+            &[("meta", "tokens")],
+            json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+        );
+        let mut api = Api::new_mocked(responses.clone()).unwrap();
+        api.set_dry_run(true);
+        let mut upload = ChunkedUpload::new("Test.bin");
+        upload.set_chunk_size(4);
+        upload.set_comment("comment");
+        let result = upload.upload(&api, b"0123456789", None).await.unwrap();
+        assert!(Api::is_dry_run_result(&result));
+        assert_eq!(upload.offset(), 10);
+        let requests = api.dry_run_requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[3]["filekey"], DRY_RUN_FILEKEY);
+        assert!(responses
+            .requests()
+            .iter()
+            .all(|r| r.get("action").is_none_or(|a| a != "upload")));
+    }
+
+    #[test]
+    fn resume_from() {
+        let mut upload = ChunkedUpload::new("Test.webm");
+        upload.set_chunk_size(100);
+        upload.resume_from("abc123.webm", 250);
+        assert_eq!(upload.filekey(), Some("abc123.webm"));
+        assert_eq!(upload.offset(), 250);
+        assert_eq!(upload.chunk_index, 3);
+    }
+
+    #[tokio::test]
+    async fn upload_by_url() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "upload"), ("filename", "New.jpg")],
+                json!({"upload":{"result":"Success","filename":"New.jpg"}}),
+            )
+            .add(
+                &[("action", "upload"), ("filename", "Local.jpg")],
+                json!({"upload":{"result":"Warning","warnings":{"exists":"Local.jpg"},"filekey":"x.jpg"}}),
+            )
+            .add(
+                &[("action", "upload")],
+                json!({"error":{"code":"fileexists-shared-forbidden","info":"A file with this name exists in the shared file repository."}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let url = "https://upload.wikimedia.org/wikipedia/commons/a/a9/Example.jpg";
+        api.upload_by_url(url, "New.jpg", "text", "comment")
+            .await
+            .unwrap();
+        assert_eq!(responses.requests().pop().unwrap()["url"], url);
+        for filename in ["Local.jpg", "Shared.jpg"] {
+            assert!(matches!(
+                api.upload_by_url(url, filename, "text", "comment").await,
+                Err(MediaWikiError::FileExists(f)) if f == filename
+            ));
+        }
+    }
+}
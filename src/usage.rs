@@ -0,0 +1,187 @@
+/*!
+The `usage` module lists where a template or file is used, via `prop=transcludedin`, `prop=fileusage`
+and `prop=globalusage`, as streams that handle continuation.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::page::Page;
+use crate::title::Title;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+
+/// A use of a file on another wiki, as returned by `prop=globalusage`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GlobalUsage {
+    wiki: String,
+    title: String,
+    url: Option<String>,
+}
+
+impl GlobalUsage {
+    /// Creates a global usage from an entry of `prop=globalusage`
+    pub fn from_json(j: &Value) -> Option<Self> {
+        Some(Self {
+            wiki: j["wiki"].as_str()?.to_string(),
+            title: j["title"].as_str()?.to_string(),
+            url: j["url"].as_str().map(|s| s.to_string()),
+        })
+    }
+
+    /// Returns the domain of the wiki using the file, e.g. `en.wikipedia.org`
+    pub fn wiki(&self) -> &str {
+        &self.wiki
+    }
+
+    /// Returns the full title of the page using the file, on that wiki
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Returns the URL of the page using the file
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+}
+
+impl Page {
+    /// Returns the pages that transclude this page (e.g. a template), as a stream
+    pub async fn transcluded_in<'a>(
+        &self,
+        api: &'a Api,
+    ) -> Result<impl Stream<Item = Result<Title, MediaWikiError>> + 'a, MediaWikiError> {
+        self.prop_stream(
+            api,
+            &[("prop", "transcludedin"), ("tilimit", "max")],
+            "transcludedin",
+            move |v| Some(Title::new_from_full(v["title"].as_str()?, api)),
+        )
+        .await
+    }
+
+    /// Returns the pages that use this file on this wiki, as a stream
+    pub async fn file_usage<'a>(
+        &self,
+        api: &'a Api,
+    ) -> Result<impl Stream<Item = Result<Title, MediaWikiError>> + 'a, MediaWikiError> {
+        self.prop_stream(
+            api,
+            &[("prop", "fileusage"), ("fulimit", "max")],
+            "fileusage",
+            move |v| Some(Title::new_from_full(v["title"].as_str()?, api)),
+        )
+        .await
+    }
+
+    /// Returns the pages that use this file on all wikis sharing the file repository
+    /// (requires the GlobalUsage extension, e.g. on Wikimedia Commons), as a stream
+    pub async fn global_usage<'a>(
+        &self,
+        api: &'a Api,
+    ) -> Result<impl Stream<Item = Result<GlobalUsage, MediaWikiError>> + 'a, MediaWikiError> {
+        self.prop_stream(
+            api,
+            &[
+                ("prop", "globalusage"),
+                ("gulimit", "max"),
+                ("guprop", "url"),
+            ],
+            "globalusage",
+            GlobalUsage::from_json,
+        )
+        .await
+    }
+
+    /// Returns a stream of the entries in `query.pages.*.<key>` of a query on this page, converted by `parse`
+    async fn prop_stream<'a, T, F>(
+        &self,
+        api: &'a Api,
+        additional_params: &[(&str, &str)],
+        key: &'a str,
+        parse: F,
+    ) -> Result<impl Stream<Item = Result<T, MediaWikiError>> + 'a, MediaWikiError>
+    where
+        T: 'a,
+        F: Fn(&Value) -> Option<T> + 'a,
+    {
+        let title = self
+            .title()
+            .full_pretty(api)
+            .ok_or_else(|| MediaWikiError::BadTitle(self.title().clone()))?;
+        let mut params = api.params_into(&[("action", "query"), ("titles", &title)]);
+        for (k, v) in additional_params {
+            params.insert(k.to_string(), v.to_string());
+        }
+        Ok(api
+            .get_query_api_json_limit_iter(&params, None)
+            .await
+            .flat_map(move |result| {
+                let items: Vec<Result<T, MediaWikiError>> = match result {
+                    Ok(result) => result["query"]["pages"]
+                        .as_object()
+                        .map(|pages| {
+                            pages
+                                .values()
+                                .filter_map(|page| page[key].as_array())
+                                .flatten()
+                                .filter_map(&parse)
+                                .map(Ok)
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    Err(e) => vec![Err(e)],
+                };
+                futures::stream::iter(items)
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn transcluded_in() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "siteinfo")],
+                json!({"query":{"namespaces":{
+                    "0":{"id":0,"case":"first-letter","*":""},
+                    "2":{"id":2,"case":"first-letter","*":"User","canonical":"User"},
+                    "10":{"id":10,"case":"first-letter","*":"Template","canonical":"Template"}}}}),
+            )
+            .add(
+                &[("prop", "transcludedin"), ("ticontinue", "5")],
+                json!({"batchcomplete":"","query":{"pages":{"1":{"pageid":1,"ns":10,"title":"Template:Foo",
+                    "transcludedin":[{"pageid":5,"ns":2,"title":"User:Bar"}]}}}}),
+            )
+            .add(
+                &[("prop", "transcludedin")],
+                json!({"continue":{"ticontinue":"5","continue":"||"},"query":{"pages":{"1":{"pageid":1,"ns":10,
+                    "title":"Template:Foo","transcludedin":[{"pageid":4,"ns":0,"title":"Baz"}]}}}}),
+            );
+        let api = Api::new_mocked(responses).unwrap();
+        let titles: Vec<Title> = Page::new(Title::new("Foo", 10))
+            .transcluded_in(&api)
+            .await
+            .unwrap()
+            .map(|t| t.unwrap())
+            .collect()
+            .await;
+        assert_eq!(titles, vec![Title::new("Baz", 0), Title::new("Bar", 2)]);
+    }
+
+    #[test]
+    fn global_usage_from_json() {
+        let usage = GlobalUsage::from_json(&json!({"title":"Paris","wiki":"de.wikipedia.org",
+            "url":"https://de.wikipedia.org/wiki/Paris","ns":"0"}))
+        .unwrap();
+        assert_eq!(usage.wiki(), "de.wikipedia.org");
+        assert_eq!(usage.title(), "Paris");
+        assert_eq!(usage.url(), Some("https://de.wikipedia.org/wiki/Paris"));
+    }
+}
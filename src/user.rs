@@ -4,12 +4,13 @@ The `User` class deals with the (current) ApiSync user.
 
 #![deny(missing_docs)]
 
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::media_wiki_error::MediaWikiError;
 
 /// `User` contains the login data for the `ApiSync`
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct User {
     lgusername: String,
     lguserid: u64,
@@ -89,6 +90,20 @@ impl User {
         self.has_right("patrol")
     }
 
+    /// Returns the most restrictive rate limit for an action (e.g. "edit") as `(hits, seconds)`,
+    /// if user info with rate limits has been loaded
+    pub fn ratelimit(&self, action: &str) -> Option<(u64, u64)> {
+        self.user_info.as_ref()?["query"]["userinfo"]["ratelimits"][action]
+            .as_object()?
+            .values()
+            .filter_map(|limit| Some((limit["hits"].as_u64()?, limit["seconds"].as_u64()?)))
+            .max_by(|(h1, s1), (h2, s2)| {
+                let r1 = *s1 as f64 / (*h1).max(1) as f64;
+                let r2 = *s2 as f64 / (*h2).max(1) as f64;
+                r1.total_cmp(&r2)
+            })
+    }
+
     /// Sets the user_info
     pub fn set_user_info(&mut self, user_info: Option<Value>) {
         self.user_info = user_info;
@@ -110,7 +125,7 @@ impl User {
             match login["lgusername"].as_str() {
                 Some(s) => self.lgusername = s.to_string(),
                 None => {
-                    return Err(MediaWikiError::Login(
+                    return Err(MediaWikiError::UnexpectedResultFormat(
                         "No lgusername in login result".to_string(),
                     ))
                 }
@@ -118,7 +133,7 @@ impl User {
             match login["lguserid"].as_u64() {
                 Some(u) => self.lguserid = u,
                 None => {
-                    return Err(MediaWikiError::Login(
+                    return Err(MediaWikiError::UnexpectedResultFormat(
                         "No lguserid in login result".to_string(),
                     ))
                 }
@@ -135,8 +150,10 @@ impl User {
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "sync")]
     use crate::api_sync::*;
 
+    #[cfg(feature = "sync")]
     fn wd_api() -> ApiSync {
         ApiSync::new("https://www.wikidata.org/w/api.php").unwrap()
     }
@@ -160,6 +177,19 @@ mod tests {
     }
 
     #[test]
+    fn user_ratelimit() {
+        let mut user = User::new();
+        assert_eq!(user.ratelimit("edit"), None);
+        user.set_user_info(Some(json!({"query":{"userinfo":{"ratelimits":{"edit":{
+            "user":{"hits":90,"seconds":60},
+            "newbie":{"hits":8,"seconds":60}
+        }}}}})));
+        assert_eq!(user.ratelimit("edit"), Some((8, 60)));
+        assert_eq!(user.ratelimit("move"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "sync")]
     fn user_rights() {
         let mut user = User::new();
         wd_api().load_user_info(&mut user).unwrap();
@@ -171,6 +201,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "sync")]
     fn user_has_info() {
         let mut user = User::new();
         assert!(!user.has_user_info());
@@ -0,0 +1,212 @@
+/*!
+The `user_block` module blocks and unblocks users and IP addresses, via `action=block` and `action=unblock`.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use serde_json::Value;
+
+/// Expiry value for blocks that do not expire
+pub const INFINITE: &str = "infinite";
+
+/// Options for `Api::block_user`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockOptions {
+    expiry: Option<String>,
+    reason: Option<String>,
+    nocreate: bool,
+    autoblock: bool,
+    noemail: bool,
+    reblock: bool,
+}
+
+impl BlockOptions {
+    /// Creates options for an indefinite block without reason, that only prevents editing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the expiry; `None` for an indefinite block
+    pub fn expiry(&self) -> Option<&str> {
+        self.expiry.as_deref()
+    }
+
+    /// Sets the expiry, as a timestamp or relative time like `1 week`; `None` for an indefinite block
+    pub fn set_expiry(&mut self, expiry: Option<&str>) {
+        self.expiry = expiry.map(|s| s.to_string());
+    }
+
+    /// Returns the reason
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.as_deref()
+    }
+
+    /// Sets the reason, shown in the block log and to the blocked user
+    pub fn set_reason(&mut self, reason: Option<&str>) {
+        self.reason = reason.map(|s| s.to_string());
+    }
+
+    /// Returns true if account creation is prevented
+    pub fn nocreate(&self) -> bool {
+        self.nocreate
+    }
+
+    /// Prevents account creation
+    pub fn set_nocreate(&mut self, nocreate: bool) {
+        self.nocreate = nocreate;
+    }
+
+    /// Returns true if the IP addresses used by the user are blocked as well
+    pub fn autoblock(&self) -> bool {
+        self.autoblock
+    }
+
+    /// Blocks the last IP address used by the user, and any IP addresses they try to edit from
+    pub fn set_autoblock(&mut self, autoblock: bool) {
+        self.autoblock = autoblock;
+    }
+
+    /// Returns true if sending email via the wiki is prevented
+    pub fn noemail(&self) -> bool {
+        self.noemail
+    }
+
+    /// Prevents sending email via the wiki
+    pub fn set_noemail(&mut self, noemail: bool) {
+        self.noemail = noemail;
+    }
+
+    /// Returns true if an existing block is overwritten
+    pub fn reblock(&self) -> bool {
+        self.reblock
+    }
+
+    /// Overwrites an existing block, instead of failing with `MediaWikiError::AlreadyBlocked`
+    pub fn set_reblock(&mut self, reblock: bool) {
+        self.reblock = reblock;
+    }
+
+    /// Returns the `action=block` parameters for these options, except user and token
+    fn params(&self) -> Vec<(&str, &str)> {
+        let mut params = vec![
+            ("action", "block"),
+            ("expiry", self.expiry().unwrap_or(INFINITE)),
+        ];
+        if let Some(reason) = self.reason() {
+            params.push(("reason", reason));
+        }
+        for (key, set) in [
+            ("nocreate", self.nocreate),
+            ("autoblock", self.autoblock),
+            ("noemail", self.noemail),
+            ("reblock", self.reblock),
+        ] {
+            if set {
+                params.push((key, "1"));
+            }
+        }
+        params
+    }
+}
+
+impl Api {
+    /// Blocks a user or IP address (range). Fails with `MediaWikiError::AlreadyBlocked` if the user is
+    /// blocked already (unless `reblock` is set), and `MediaWikiError::PermissionDenied` without the `block` right.
+    pub async fn block_user(
+        &self,
+        user: &str,
+        options: &BlockOptions,
+    ) -> Result<Value, MediaWikiError> {
+        let token = self.get_edit_token().await?;
+        let mut params = options.params();
+        params.push(("user", user));
+        params.push(("token", &token));
+        let params = self.params_into(&params);
+        self.block_result(user, "block", self.post_query_api_json(&params).await)
+    }
+
+    /// Removes the block of a user or IP address (range)
+    pub async fn unblock_user(&self, user: &str, reason: &str) -> Result<Value, MediaWikiError> {
+        let token = self.get_edit_token().await?;
+        let params = self.params_into(&[
+            ("action", "unblock"),
+            ("user", user),
+            ("reason", reason),
+            ("token", &token),
+        ]);
+        self.block_result(user, "unblock", self.post_query_api_json(&params).await)
+    }
+
+    /// Checks the result of a block or unblock, and converts known API errors
+    fn block_result(
+        &self,
+        user: &str,
+        key: &str,
+        result: Result<Value, MediaWikiError>,
+    ) -> Result<Value, MediaWikiError> {
+        let error = match result {
+            Ok(result) if result[key].is_object() => return Ok(result),
+            Ok(result) => MediaWikiError::from_api_result(&result)
+                .unwrap_or(MediaWikiError::EditError(result)),
+            Err(e) => e,
+        };
+        Err(match error {
+            MediaWikiError::Api { code, .. } if code == "alreadyblocked" => {
+                MediaWikiError::AlreadyBlocked(user.to_string())
+            }
+            MediaWikiError::Api { code, info, .. } if code == "permissiondenied" => {
+                MediaWikiError::PermissionDenied(info)
+            }
+            e => e,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+
+    #[tokio::test]
+    async fn block_and_unblock() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "block"), ("user", "Vandal")],
+                json!({"block":{"user":"Vandal","userID":5,"expiry":"2024-02-01T00:00:00Z","id":7}}),
+            )
+            .add(
+                &[("action", "block"), ("user", "Blocked")],
+                json!({"error":{"code":"alreadyblocked","info":"\"Blocked\" is already blocked."}}),
+            )
+            .add(
+                &[("action", "unblock")],
+                json!({"error":{"code":"permissiondenied","info":"You don't have permission to unblock users."}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut options = BlockOptions::new();
+        options.set_expiry(Some("1 week"));
+        options.set_nocreate(true);
+        options.set_reason(Some("Vandalism"));
+        let result = api.block_user("Vandal", &options).await.unwrap();
+        assert_eq!(result["block"]["id"], 7);
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["expiry"], "1 week");
+        assert_eq!(request["nocreate"], "1");
+        assert!(!request.contains_key("autoblock"));
+        assert!(matches!(
+            api.block_user("Blocked", &BlockOptions::new()).await,
+            Err(MediaWikiError::AlreadyBlocked(user)) if user == "Blocked"
+        ));
+        assert!(matches!(
+            api.unblock_user("Vandal", "Appeal").await,
+            Err(MediaWikiError::PermissionDenied(_))
+        ));
+    }
+}
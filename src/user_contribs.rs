@@ -0,0 +1,261 @@
+/*!
+The `user_contribs` module lists the contributions of a user via `list=usercontribs`, as a stream.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, NamespaceID};
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use futures::Stream;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A contribution of a user, as returned by `list=usercontribs`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserContrib {
+    title: Title,
+    page_id: Option<u64>,
+    revid: u64,
+    parent_id: Option<u64>,
+    timestamp: String,
+    comment: Option<String>,
+    size: Option<u64>,
+    size_diff: Option<i64>,
+    tags: Vec<String>,
+    minor: bool,
+    new: bool,
+    top: bool,
+}
+
+impl UserContrib {
+    /// Creates a new contribution from a `list=usercontribs` result entry (`formatversion=2`)
+    pub fn from_json(j: &Value) -> Option<Self> {
+        j["title"].as_str()?;
+        Some(Self {
+            title: Title::new_from_api_result(j),
+            page_id: j["pageid"].as_u64(),
+            revid: j["revid"].as_u64()?,
+            parent_id: j["parentid"].as_u64(),
+            timestamp: j["timestamp"].as_str()?.to_string(),
+            comment: j["comment"].as_str().map(|s| s.to_string()),
+            size: j["size"].as_u64(),
+            size_diff: j["sizediff"].as_i64(),
+            tags: j["tags"]
+                .as_array()
+                .map(|tags| {
+                    tags.iter()
+                        .filter_map(|t| t.as_str())
+                        .map(|s| s.to_string())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            minor: j["minor"].as_bool().unwrap_or(false),
+            new: j["new"].as_bool().unwrap_or(false),
+            top: j["top"].as_bool().unwrap_or(false),
+        })
+    }
+
+    /// Returns the title of the edited page
+    pub fn title(&self) -> &Title {
+        &self.title
+    }
+
+    /// Returns the page ID
+    pub fn page_id(&self) -> Option<u64> {
+        self.page_id
+    }
+
+    /// Returns the revision ID of the contribution
+    pub fn revid(&self) -> u64 {
+        self.revid
+    }
+
+    /// Returns the ID of the previous revision; 0 for page creations
+    pub fn parent_id(&self) -> Option<u64> {
+        self.parent_id
+    }
+
+    /// Returns the timestamp of the contribution
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    /// Returns the edit summary
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Returns the page size after the contribution
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// Returns the change of the page size, in bytes
+    pub fn size_diff(&self) -> Option<i64> {
+        self.size_diff
+    }
+
+    /// Returns the change tags of the contribution
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Returns true for minor edits
+    pub fn is_minor(&self) -> bool {
+        self.minor
+    }
+
+    /// Returns true if the contribution created the page
+    pub fn is_new(&self) -> bool {
+        self.new
+    }
+
+    /// Returns true if the contribution is the current revision of the page
+    pub fn is_top(&self) -> bool {
+        self.top
+    }
+}
+
+/// Options for `Api::user_contribs`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserContribsOptions {
+    namespaces: Vec<NamespaceID>,
+    start: Option<String>,
+    end: Option<String>,
+    oldest_first: bool,
+}
+
+impl UserContribsOptions {
+    /// Creates options for all contributions, newest first
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the namespaces to return contributions for; empty for all namespaces
+    pub fn namespaces(&self) -> &[NamespaceID] {
+        &self.namespaces
+    }
+
+    /// Only returns contributions to pages in these namespaces; empty for all namespaces
+    pub fn set_namespaces(&mut self, namespaces: &[NamespaceID]) {
+        self.namespaces = namespaces.to_vec();
+    }
+
+    /// Returns the timestamp to start listing from, if set
+    pub fn start(&self) -> Option<&str> {
+        self.start.as_deref()
+    }
+
+    /// Sets the timestamp to start listing from, e.g. `2024-01-01T00:00:00Z`
+    pub fn set_start(&mut self, start: Option<&str>) {
+        self.start = start.map(|s| s.to_string());
+    }
+
+    /// Returns the timestamp to stop listing at, if set
+    pub fn end(&self) -> Option<&str> {
+        self.end.as_deref()
+    }
+
+    /// Sets the timestamp to stop listing at
+    pub fn set_end(&mut self, end: Option<&str>) {
+        self.end = end.map(|s| s.to_string());
+    }
+
+    /// Returns true if the oldest contributions are returned first
+    pub fn oldest_first(&self) -> bool {
+        self.oldest_first
+    }
+
+    /// Returns the oldest contributions first; `start` must then be before `end`
+    pub fn set_oldest_first(&mut self, oldest_first: bool) {
+        self.oldest_first = oldest_first;
+    }
+
+    /// Returns the API parameters for the contributions of `username`
+    fn params(&self, api: &Api, username: &str) -> HashMap<String, String> {
+        let mut params = api.params_into(&[
+            ("action", "query"),
+            ("list", "usercontribs"),
+            ("ucuser", username),
+            (
+                "ucprop",
+                "ids|title|timestamp|comment|size|sizediff|flags|tags",
+            ),
+            ("uclimit", "max"),
+            ("formatversion", "2"),
+        ]);
+        if !self.namespaces.is_empty() {
+            let namespaces: Vec<String> = self.namespaces.iter().map(|ns| ns.to_string()).collect();
+            params.insert("ucnamespace".to_string(), namespaces.join("|"));
+        }
+        if let Some(start) = &self.start {
+            params.insert("ucstart".to_string(), start.to_string());
+        }
+        if let Some(end) = &self.end {
+            params.insert("ucend".to_string(), end.to_string());
+        }
+        if self.oldest_first {
+            params.insert("ucdir".to_string(), "newer".to_string());
+        }
+        params
+    }
+}
+
+impl Api {
+    /// Returns a stream of the contributions of a user or IP address, handling continuation
+    pub async fn user_contribs<'a>(
+        &'a self,
+        username: &str,
+        options: &UserContribsOptions,
+    ) -> impl Stream<Item = Result<UserContrib, MediaWikiError>> + 'a {
+        let params = options.params(self, username);
+        self.get_query_api_list_iter(&params, "usercontribs", UserContrib::from_json)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::MockResponses;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn user_contribs() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(&[("meta", "siteinfo")], json!({"query":{}}))
+            .add(
+                &[("list", "usercontribs"), ("uccontinue", "20240101|5")],
+                json!({"batchcomplete":true,"query":{"usercontribs":[
+                    {"userid":1,"user":"Foo","pageid":3,"revid":5,"parentid":0,"ns":0,"title":"Baz",
+                    "timestamp":"2024-01-01T00:00:00Z","new":true,"minor":false,"top":true,
+                    "comment":"new","size":50,"sizediff":50,"tags":[]}]}}),
+            )
+            .add(
+                &[("list", "usercontribs")],
+                json!({"continue":{"uccontinue":"20240101|5","continue":"-||"},"query":{"usercontribs":[
+                    {"userid":1,"user":"Foo","pageid":2,"revid":7,"parentid":6,"ns":2,"title":"User:Foo/Bar",
+                    "timestamp":"2024-01-02T00:00:00Z","new":false,"minor":true,"top":false,
+                    "comment":"fix","size":100,"sizediff":-3,"tags":["mobile edit"]}]}}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut options = UserContribsOptions::new();
+        options.set_namespaces(&[0, 2]);
+        let contribs: Vec<UserContrib> = api
+            .user_contribs("Foo", &options)
+            .await
+            .map(|c| c.unwrap())
+            .collect()
+            .await;
+        assert_eq!(contribs.len(), 2);
+        assert_eq!(contribs[0].title(), &Title::new("Foo/Bar", 2));
+        assert_eq!(contribs[0].size_diff(), Some(-3));
+        assert_eq!(contribs[0].tags(), ["mobile edit".to_string()]);
+        assert!(contribs[0].is_minor());
+        assert_eq!(contribs[1].revid(), 5);
+        assert!(contribs[1].is_new());
+        assert_eq!(responses.requests()[1]["ucnamespace"], "0|2");
+    }
+}
@@ -0,0 +1,307 @@
+/*!
+The `watchlist` module reads and modifies the watchlist of the current user.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::{Api, TokenType};
+use crate::media_wiki_error::MediaWikiError;
+use crate::title::Title;
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// How an edit or other action changes the watchlist, for the `watchlist` parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Watchlist {
+    /// Add the page to the watchlist
+    Watch,
+    /// Remove the page from the watchlist
+    Unwatch,
+    /// Use the user preferences
+    #[default]
+    Preferences,
+    /// Don't change the watchlist
+    NoChange,
+}
+
+impl Watchlist {
+    /// Returns the value as used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Watch => "watch",
+            Self::Unwatch => "unwatch",
+            Self::Preferences => "preferences",
+            Self::NoChange => "nochange",
+        }
+    }
+}
+
+impl fmt::Display for Watchlist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Watchlist {
+    type Err = MediaWikiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "watch" => Ok(Self::Watch),
+            "unwatch" => Ok(Self::Unwatch),
+            "preferences" => Ok(Self::Preferences),
+            "nochange" => Ok(Self::NoChange),
+            other => Err(MediaWikiError::String(format!(
+                "Unknown watchlist option: {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// A recent change to a page on the watchlist, as returned by `list=watchlist`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchlistEntry {
+    title: Title,
+    kind: Option<String>,
+    page_id: Option<u64>,
+    revid: Option<u64>,
+    old_revid: Option<u64>,
+    user: Option<String>,
+    timestamp: Option<String>,
+    comment: Option<String>,
+    old_len: Option<u64>,
+    new_len: Option<u64>,
+    bot: bool,
+    minor: bool,
+    new: bool,
+}
+
+impl WatchlistEntry {
+    /// Creates a new entry from a `list=watchlist` result entry (`formatversion=2`)
+    pub fn from_json(j: &Value) -> Option<Self> {
+        j["title"].as_str()?;
+        Some(Self {
+            title: Title::new_from_api_result(j),
+            kind: j["type"].as_str().map(|s| s.to_string()),
+            page_id: j["pageid"].as_u64(),
+            revid: j["revid"].as_u64(),
+            old_revid: j["old_revid"].as_u64(),
+            user: j["user"].as_str().map(|s| s.to_string()),
+            timestamp: j["timestamp"].as_str().map(|s| s.to_string()),
+            comment: j["comment"].as_str().map(|s| s.to_string()),
+            old_len: j["oldlen"].as_u64(),
+            new_len: j["newlen"].as_u64(),
+            bot: j["bot"].as_bool().unwrap_or(false),
+            minor: j["minor"].as_bool().unwrap_or(false),
+            new: j["new"].as_bool().unwrap_or(false),
+        })
+    }
+
+    /// Returns the title of the changed page
+    pub fn title(&self) -> &Title {
+        &self.title
+    }
+
+    /// Returns the type of change: `edit`, `new`, `log`, `categorize` or `external`
+    pub fn kind(&self) -> Option<&str> {
+        self.kind.as_deref()
+    }
+
+    /// Returns the page ID
+    pub fn page_id(&self) -> Option<u64> {
+        self.page_id
+    }
+
+    /// Returns the revision ID of the change
+    pub fn revid(&self) -> Option<u64> {
+        self.revid
+    }
+
+    /// Returns the revision ID before the change
+    pub fn old_revid(&self) -> Option<u64> {
+        self.old_revid
+    }
+
+    /// Returns the user who made the change
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// Returns the timestamp of the change
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Returns the edit summary
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// Returns the page size before the change
+    pub fn old_len(&self) -> Option<u64> {
+        self.old_len
+    }
+
+    /// Returns the page size after the change
+    pub fn new_len(&self) -> Option<u64> {
+        self.new_len
+    }
+
+    /// Returns true for bot edits
+    pub fn is_bot(&self) -> bool {
+        self.bot
+    }
+
+    /// Returns true for minor edits
+    pub fn is_minor(&self) -> bool {
+        self.minor
+    }
+
+    /// Returns true if the change created the page
+    pub fn is_new(&self) -> bool {
+        self.new
+    }
+}
+
+impl Api {
+    /// Returns a stream of recent changes to pages on the watchlist of the current user, newest first,
+    /// via `list=watchlist`
+    pub async fn watchlist(
+        &self,
+    ) -> impl Stream<Item = Result<WatchlistEntry, MediaWikiError>> + '_ {
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("list", "watchlist"),
+            ("wlprop", "ids|title|flags|user|comment|timestamp|sizes"),
+            ("wllimit", "max"),
+            ("formatversion", "2"),
+        ]);
+        self.get_query_api_list_iter(&params, "watchlist", WatchlistEntry::from_json)
+            .await
+    }
+
+    /// Returns all titles on the watchlist of the current user, via `list=watchlistraw`
+    pub async fn watchlist_raw(&self) -> impl Stream<Item = Result<Title, MediaWikiError>> + '_ {
+        let params = self.params_into(&[
+            ("action", "query"),
+            ("list", "watchlistraw"),
+            ("wrlimit", "max"),
+        ]);
+        self.get_query_api_json_limit_iter(&params, None)
+            .await
+            .flat_map(|result| {
+                let items: Vec<Result<Title, MediaWikiError>> = match result {
+                    // `watchlistraw` is returned outside of `query`
+                    Ok(result) => Self::watchlist_raw_entries(&result)
+                        .iter()
+                        .map(Title::new_from_api_result)
+                        .map(Ok)
+                        .collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                futures::stream::iter(items)
+            })
+    }
+
+    /// Returns the `watchlistraw` entries of a result, wherever they are
+    fn watchlist_raw_entries(result: &Value) -> Vec<Value> {
+        result["watchlistraw"]
+            .as_array()
+            .or_else(|| result["query"]["watchlistraw"].as_array())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Adds `titles` to the watchlist, in batches of 50 (500 with `apihighlimits`).
+    /// `expiry` can be a timestamp or a relative time like `1 month`; `None` means infinite.
+    pub async fn watch_batch(
+        &self,
+        titles: &[Title],
+        expiry: Option<&str>,
+    ) -> Result<(), MediaWikiError> {
+        self.watch_or_unwatch_batch(titles, expiry, false).await
+    }
+
+    /// Removes `titles` from the watchlist, in batches of 50 (500 with `apihighlimits`)
+    pub async fn unwatch_batch(&self, titles: &[Title]) -> Result<(), MediaWikiError> {
+        self.watch_or_unwatch_batch(titles, None, true).await
+    }
+
+    async fn watch_or_unwatch_batch(
+        &self,
+        titles: &[Title],
+        expiry: Option<&str>,
+        unwatch: bool,
+    ) -> Result<(), MediaWikiError> {
+        let titles = titles
+            .iter()
+            .map(|t| {
+                t.full_pretty(self)
+                    .ok_or_else(|| MediaWikiError::BadTitle(t.clone()))
+            })
+            .collect::<Result<Vec<String>, MediaWikiError>>()?;
+        let token = self.get_token_of_type(TokenType::Watch).await?;
+        for chunk in titles.chunks(self.multi_value_limit()) {
+            let mut params = self.params_into(&[
+                ("action", "watch"),
+                ("titles", &chunk.join("|")),
+                ("token", &token),
+            ]);
+            if unwatch {
+                params.insert("unwatch".to_string(), "1".to_string());
+            }
+            if let Some(expiry) = expiry {
+                params.insert("expiry".to_string(), expiry.to_string());
+            }
+            let result = self.post_query_api_json(&params).await?;
+            if !result["error"].is_null() {
+                return Err(MediaWikiError::EditError(result));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watchlist_round_trip() {
+        for w in [
+            Watchlist::Watch,
+            Watchlist::Unwatch,
+            Watchlist::Preferences,
+            Watchlist::NoChange,
+        ] {
+            assert_eq!(w.to_string().parse::<Watchlist>().unwrap(), w);
+        }
+        assert!("sometimes".parse::<Watchlist>().is_err());
+    }
+
+    #[test]
+    fn watchlist_entry_from_json() {
+        let entry = WatchlistEntry::from_json(&json!({"type":"edit","ns":0,"title":"Foo","pageid":1,"revid":20,"old_revid":19,"user":"Bar","bot":false,"new":false,"minor":true,"oldlen":10,"newlen":12,"timestamp":"2024-01-01T00:00:00Z","comment":"typo"})).unwrap();
+        assert_eq!(entry.title(), &Title::new("Foo", 0));
+        assert_eq!(entry.kind(), Some("edit"));
+        assert_eq!(entry.revid(), Some(20));
+        assert!(entry.is_minor());
+        assert!(!entry.is_bot());
+        assert_eq!(entry.new_len(), Some(12));
+        assert!(WatchlistEntry::from_json(&json!({"type":"log"})).is_none());
+    }
+
+    #[test]
+    fn watchlist_raw_entries() {
+        let result = json!({"watchlistraw":[{"ns":0,"title":"Foo"},{"ns":1,"title":"Talk:Foo"}]});
+        let titles: Vec<Title> = Api::watchlist_raw_entries(&result)
+            .iter()
+            .map(Title::new_from_api_result)
+            .collect();
+        assert_eq!(titles, vec![Title::new("Foo", 0), Title::new("Foo", 1)]);
+        assert!(Api::watchlist_raw_entries(&json!({})).is_empty());
+    }
+}
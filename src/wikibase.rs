@@ -0,0 +1,412 @@
+/*!
+The `wikibase` module contains helpers for editing Wikibase entities, such as Wikidata items.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::wikibase_edit::WbEditOptions;
+use futures::Stream;
+use serde_json::{json, Value};
+
+/// A single statement operation, to be applied as part of a batch
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementOp {
+    /// Adds a new statement; the value is the claim JSON, with `mainsnak`
+    Add(Value),
+    /// Removes the statement with the given ID (GUID)
+    Remove(String),
+    /// Replaces a statement; the value is the claim JSON, including its `id`
+    Update(Value),
+}
+
+impl StatementOp {
+    /// Creates an `Add` operation for a statement with a snak of `datatype` and `datavalue`
+    pub fn add_value(property: &str, datatype: &str, datavalue: Value) -> Self {
+        Self::Add(json!({
+            "mainsnak": {
+                "snaktype": "value",
+                "property": property,
+                "datatype": datatype,
+                "datavalue": datavalue,
+            },
+            "type": "statement",
+            "rank": "normal",
+        }))
+    }
+
+    /// Creates an `Add` operation for an item-valued statement, e.g. `P31` → `Q5`
+    pub fn add_item(property: &str, item: &str) -> Self {
+        let datavalue =
+            json!({"value": {"entity-type": "item", "id": item}, "type": "wikibase-entityid"});
+        Self::add_value(property, "wikibase-item", datavalue)
+    }
+
+    /// Creates an `Add` operation for a string-valued statement
+    pub fn add_string(property: &str, value: &str) -> Self {
+        let datavalue = json!({"value": value, "type": "string"});
+        Self::add_value(property, "string", datavalue)
+    }
+
+    /// Returns the property of this operation, if known
+    pub fn property(&self) -> Option<&str> {
+        match self {
+            Self::Add(claim) | Self::Update(claim) => claim["mainsnak"]["property"].as_str(),
+            Self::Remove(_) => None,
+        }
+    }
+
+    /// Returns the claim JSON for `wbeditentity`
+    fn to_claim_json(&self) -> Value {
+        match self {
+            Self::Add(claim) | Self::Update(claim) => claim.to_owned(),
+            Self::Remove(id) => json!({"id": id, "remove": ""}),
+        }
+    }
+}
+
+/// Returns the `wbeditentity` data for a batch of statement operations
+pub fn statement_batch_data(ops: &[StatementOp]) -> Value {
+    let claims: Vec<Value> = ops.iter().map(|op| op.to_claim_json()).collect();
+    json!({ "claims": claims })
+}
+
+/// Returns an aggregated edit summary for a batch of statement operations,
+/// e.g. `added 2 statements (P31, P18); removed 1 statement`
+pub fn statement_batch_summary(ops: &[StatementOp]) -> String {
+    let part = |verb: &str, filter: fn(&StatementOp) -> bool| {
+        let matching: Vec<&StatementOp> = ops.iter().filter(|op| filter(op)).collect();
+        if matching.is_empty() {
+            return None;
+        }
+        let mut properties: Vec<&str> = vec![];
+        for property in matching.iter().filter_map(|op| op.property()) {
+            if !properties.contains(&property) {
+                properties.push(property);
+            }
+        }
+        let noun = if matching.len() == 1 {
+            "statement"
+        } else {
+            "statements"
+        };
+        let mut ret = format!("{} {} {}", verb, matching.len(), noun);
+        if !properties.is_empty() {
+            ret += &format!(" ({})", properties.join(", "));
+        }
+        Some(ret)
+    };
+    let parts: Vec<String> = [
+        part("added", |op| matches!(op, StatementOp::Add(_))),
+        part("updated", |op| matches!(op, StatementOp::Update(_))),
+        part("removed", |op| matches!(op, StatementOp::Remove(_))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    parts.join("; ")
+}
+
+/// Returns the statement operations that turn the claims of `old` into those of `new`,
+/// both being entity JSON as returned by `wbgetentities`.
+/// Statements without an ID in `new` are added, statements missing from `new` are removed,
+/// and statements that differ (ignoring snak hashes) are updated.
+pub fn diff_claims(old: &Value, new: &Value) -> Vec<StatementOp> {
+    let claims = |entity: &Value| -> Vec<Value> {
+        entity["claims"]
+            .as_object()
+            .into_iter()
+            .flat_map(|claims| claims.values())
+            .filter_map(|v| v.as_array())
+            .flatten()
+            .cloned()
+            .collect()
+    };
+    let old_claims = claims(old);
+    let new_claims = claims(new);
+    let mut ops = vec![];
+    for c2 in &new_claims {
+        let c1 = c2["id"]
+            .as_str()
+            .and_then(|id| old_claims.iter().find(|c1| c1["id"].as_str() == Some(id)));
+        match c1 {
+            Some(c1) => {
+                if without_hashes(c1) != without_hashes(c2) {
+                    ops.push(StatementOp::Update(c2.to_owned()));
+                }
+            }
+            None => {
+                let mut claim = c2.to_owned();
+                if let Some(claim) = claim.as_object_mut() {
+                    claim.remove("id");
+                }
+                ops.push(StatementOp::Add(claim));
+            }
+        }
+    }
+    for c1 in &old_claims {
+        if let Some(id) = c1["id"].as_str() {
+            if !new_claims.iter().any(|c2| c2["id"].as_str() == Some(id)) {
+                ops.push(StatementOp::Remove(id.to_string()));
+            }
+        }
+    }
+    ops
+}
+
+/// Returns a copy of `v` without `hash` keys, which the API sets and ignores on edit
+fn without_hashes(v: &Value) -> Value {
+    match v {
+        Value::Object(o) => Value::Object(
+            o.iter()
+                .filter(|(k, _)| *k != "hash")
+                .map(|(k, v)| (k.to_owned(), without_hashes(v)))
+                .collect(),
+        ),
+        Value::Array(a) => Value::Array(a.iter().map(without_hashes).collect()),
+        _ => v.to_owned(),
+    }
+}
+
+/// A hit of `wbsearchentities`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntitySearchHit {
+    id: String,
+    label: Option<String>,
+    description: Option<String>,
+    concept_uri: Option<String>,
+    match_type: Option<String>,
+    match_language: Option<String>,
+    match_text: Option<String>,
+}
+
+impl EntitySearchHit {
+    /// Creates a hit from an entry of the `search` array
+    pub fn from_json(j: &Value) -> Option<Self> {
+        let as_string = |v: &Value| v.as_str().map(|s| s.to_string());
+        Some(Self {
+            id: j["id"].as_str()?.to_string(),
+            label: as_string(&j["label"]),
+            description: as_string(&j["description"]),
+            concept_uri: as_string(&j["concepturi"]),
+            match_type: as_string(&j["match"]["type"]),
+            match_language: as_string(&j["match"]["language"]),
+            match_text: as_string(&j["match"]["text"]),
+        })
+    }
+
+    /// Returns the entity ID, e.g. `Q42`
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the label in the search language
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns the description in the search language
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Returns the concept URI, e.g. `http://www.wikidata.org/entity/Q42`
+    pub fn concept_uri(&self) -> Option<&str> {
+        self.concept_uri.as_deref()
+    }
+
+    /// Returns what matched the query: `label`, `alias` or `entityId`
+    pub fn match_type(&self) -> Option<&str> {
+        self.match_type.as_deref()
+    }
+
+    /// Returns the language of the matching term
+    pub fn match_language(&self) -> Option<&str> {
+        self.match_language.as_deref()
+    }
+
+    /// Returns the matching term
+    pub fn match_text(&self) -> Option<&str> {
+        self.match_text.as_deref()
+    }
+}
+
+impl Api {
+    /// Searches entities by label or alias via `wbsearchentities`, loading more hits via `continue` as required.
+    /// `entity_type` is e.g. `item` or `property`; `None` for items.
+    pub fn wbsearchentities<'a>(
+        &'a self,
+        query: &str,
+        language: &str,
+        entity_type: Option<&str>,
+    ) -> impl Stream<Item = Result<EntitySearchHit, MediaWikiError>> + 'a {
+        let params = self.params_into(&[
+            ("action", "wbsearchentities"),
+            ("search", query),
+            ("language", language),
+            ("uselang", language),
+            ("type", entity_type.unwrap_or("item")),
+            ("limit", "max"),
+        ]);
+        let state = (Some(0_u64), std::collections::VecDeque::new());
+        futures::stream::unfold(state, move |(mut offset, mut pending)| {
+            let params = params.clone();
+            async move {
+                loop {
+                    if let Some(hit) = pending.pop_front() {
+                        return Some((Ok(hit), (offset, pending)));
+                    }
+                    let mut params = params.clone();
+                    params.insert("continue".to_string(), offset?.to_string());
+                    let result = match self.get_query_api_json(&params).await {
+                        Ok(result) => result,
+                        Err(e) => return Some((Err(e), (None, pending))),
+                    };
+                    if let Some(e) = MediaWikiError::from_api_result(&result) {
+                        return Some((Err(e), (None, pending)));
+                    }
+                    let (hits, next) = Self::search_hits_from_api_result(&result);
+                    pending.extend(hits);
+                    offset = next;
+                }
+            }
+        })
+    }
+
+    /// Returns the hits of a `wbsearchentities` result, and the offset to continue from
+    fn search_hits_from_api_result(result: &Value) -> (Vec<EntitySearchHit>, Option<u64>) {
+        let hits = result["search"]
+            .as_array()
+            .map(|arr| arr.iter().filter_map(EntitySearchHit::from_json).collect())
+            .unwrap_or_default();
+        (hits, result["search-continue"].as_u64())
+    }
+
+    /// Applies a batch of statement operations to `entity` in a single `wbeditentity` call,
+    /// with an aggregated summary; a summary in `options` is appended to it.
+    /// If `options` has a `baserevid`, the edit fails on a conflicting change.
+    pub async fn wb_apply_statement_batch(
+        &self,
+        entity: &str,
+        ops: Vec<StatementOp>,
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        if ops.is_empty() {
+            return Err(MediaWikiError::String(
+                "No statement operations to apply".to_string(),
+            ));
+        }
+        let data = statement_batch_data(&ops).to_string();
+        let summary = match options.summary() {
+            Some(summary) => format!("{}; {}", statement_batch_summary(&ops), summary),
+            None => statement_batch_summary(&ops),
+        };
+        let mut options = options.clone();
+        options.set_summary(Some(&summary));
+        self.wb_edit(
+            &[("action", "wbeditentity"), ("id", entity), ("data", &data)],
+            &options,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_hits() {
+        let result = json!({"searchinfo":{"search":"douglas adams"},"search":[{"id":"Q42","title":"Q42","pageid":138,
+            "concepturi":"http://www.wikidata.org/entity/Q42","label":"Douglas Adams","description":"English writer",
+            "match":{"type":"label","language":"en","text":"Douglas Adams"}}],"search-continue":1,"success":1});
+        let (hits, next) = Api::search_hits_from_api_result(&result);
+        assert_eq!(next, Some(1));
+        assert_eq!(hits[0].id(), "Q42");
+        assert_eq!(hits[0].match_type(), Some("label"));
+        assert_eq!(hits[0].description(), Some("English writer"));
+        let (hits, next) = Api::search_hits_from_api_result(&json!({"search":[],"success":1}));
+        assert!(hits.is_empty());
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn batch_data() {
+        let ops = vec![
+            StatementOp::add_item("P31", "Q5"),
+            StatementOp::Remove("Q42$ABC".to_string()),
+        ];
+        let data = statement_batch_data(&ops);
+        assert_eq!(data["claims"][0]["mainsnak"]["property"], "P31");
+        assert_eq!(
+            data["claims"][0]["mainsnak"]["datavalue"]["value"]["id"],
+            "Q5"
+        );
+        assert_eq!(data["claims"][1], json!({"id":"Q42$ABC","remove":""}));
+    }
+
+    #[test]
+    fn batch_summary() {
+        let ops = vec![
+            StatementOp::add_item("P31", "Q5"),
+            StatementOp::add_string("P213", "0000 0001"),
+            StatementOp::Remove("Q42$ABC".to_string()),
+        ];
+        assert_eq!(
+            statement_batch_summary(&ops),
+            "added 2 statements (P31, P213); removed 1 statement"
+        );
+    }
+
+    #[tokio::test]
+    async fn apply_statement_batch() {
+        let mut responses = crate::mock::MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "wbeditentity")],
+                json!({"entity":{"id":"Q42","lastrevid":2},"success":1}),
+            );
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let mut options = WbEditOptions::new();
+        options.set_summary(Some("cleanup"));
+        options.set_baserevid(Some(1));
+        options.set_tags(&["bot trial"]);
+        let ops = vec![StatementOp::add_item("P31", "Q5")];
+        let result = api
+            .wb_apply_statement_batch("Q42", ops, &options)
+            .await
+            .unwrap();
+        assert_eq!(result["entity"]["lastrevid"], 2);
+        let requests = responses.requests();
+        let edit = requests.last().unwrap();
+        assert_eq!(edit["summary"], "added 1 statement (P31); cleanup");
+        assert_eq!(edit["baserevid"], "1");
+        assert_eq!(edit["tags"], "bot trial");
+        assert_eq!(edit["token"], "abc+\\");
+        assert!(api
+            .wb_apply_statement_batch("Q42", vec![], &options)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn claims_diff() {
+        let p31 = |id: &str, item: &str, hash: &str| {
+            json!({"id":id,"type":"statement","rank":"normal","mainsnak":{"snaktype":"value","property":"P31","hash":hash,
+                "datavalue":{"value":{"entity-type":"item","id":item},"type":"wikibase-entityid"}}})
+        };
+        let old = json!({"claims":{"P31":[p31("Q1$a","Q5","h1"),p31("Q1$b","Q6","h2"),p31("Q1$c","Q7","h3")]}});
+        let new = json!({"claims":{"P31":[p31("Q1$a","Q5","other"),p31("Q1$b","Q8","h2")],"P18":[{"mainsnak":{"property":"P18"}}]}});
+        let ops = diff_claims(&old, &new);
+        assert_eq!(ops.len(), 3);
+        assert!(ops.contains(&StatementOp::Update(p31("Q1$b", "Q8", "h2"))));
+        assert!(ops.contains(&StatementOp::Add(json!({"mainsnak":{"property":"P18"}}))));
+        assert!(ops.contains(&StatementOp::Remove("Q1$c".to_string())));
+        assert!(diff_claims(&old, &old).is_empty());
+    }
+}
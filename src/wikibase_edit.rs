@@ -0,0 +1,637 @@
+/*!
+The `wikibase_edit` module offers high-level helpers for common Wikibase edits, such as creating items
+and adding statements. All helpers fetch the edit token, and set `bot`, the summary and `baserevid` as required.
+*/
+
+#![deny(missing_docs)]
+
+use crate::api::Api;
+use crate::media_wiki_error::MediaWikiError;
+use crate::wikibase::{statement_batch_data, StatementOp};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Options shared by all `wikibase_edit` helpers
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WbEditOptions {
+    summary: Option<String>,
+    baserevid: Option<u64>,
+    tags: Vec<String>,
+}
+
+impl WbEditOptions {
+    /// Creates options without summary and base revision
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the edit summary
+    pub fn summary(&self) -> Option<&str> {
+        self.summary.as_deref()
+    }
+
+    /// Sets the edit summary, appended to the automatic summary
+    pub fn set_summary(&mut self, summary: Option<&str>) {
+        self.summary = summary.map(|s| s.to_string());
+    }
+
+    /// Returns the base revision ID
+    pub fn baserevid(&self) -> Option<u64> {
+        self.baserevid
+    }
+
+    /// Sets the revision the edit is based on; the edit fails on a conflicting change
+    pub fn set_baserevid(&mut self, baserevid: Option<u64>) {
+        self.baserevid = baserevid;
+    }
+
+    /// Returns the change tags to apply to the edit
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Sets the change tags to apply to the edit; see `Api::list_change_tags`
+    pub fn set_tags(&mut self, tags: &[&str]) {
+        self.tags = tags.iter().map(|s| s.to_string()).collect();
+    }
+}
+
+/// The value of a snak, for statement, qualifier and reference edits
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnakValue {
+    /// An item, e.g. `Q5`
+    Item(String),
+    /// A string, such as an external identifier or URL
+    String(String),
+    /// A datavalue of another type (e.g. `time` or `quantity`) with its `value` JSON
+    Other {
+        /// The datavalue type, e.g. `time`
+        value_type: String,
+        /// The `value` of the datavalue
+        value: Value,
+    },
+    /// An unknown value
+    SomeValue,
+    /// No value
+    NoValue,
+}
+
+impl SnakValue {
+    /// Returns the snak type: `value`, `somevalue` or `novalue`
+    pub fn snaktype(&self) -> &'static str {
+        match self {
+            Self::SomeValue => "somevalue",
+            Self::NoValue => "novalue",
+            _ => "value",
+        }
+    }
+
+    /// Returns the `value` of the datavalue, if any, as used by `wbsetclaimvalue` and `wbsetqualifier`
+    pub fn value_json(&self) -> Option<Value> {
+        match self {
+            Self::Item(id) => Some(json!({"entity-type": "item", "id": id})),
+            Self::String(s) => Some(json!(s)),
+            Self::Other { value, .. } => Some(value.to_owned()),
+            Self::SomeValue | Self::NoValue => None,
+        }
+    }
+
+    /// Returns the datavalue, with `type` and `value`, if any
+    pub fn datavalue(&self) -> Option<Value> {
+        let value_type = match self {
+            Self::Item(_) => "wikibase-entityid",
+            Self::String(_) => "string",
+            Self::Other { value_type, .. } => value_type,
+            Self::SomeValue | Self::NoValue => return None,
+        };
+        Some(json!({"type": value_type, "value": self.value_json()?}))
+    }
+
+    /// Returns the snak JSON for `property`
+    pub fn snak_json(&self, property: &str) -> Value {
+        let mut snak = json!({"snaktype": self.snaktype(), "property": property});
+        if let Some(datavalue) = self.datavalue() {
+            snak["datavalue"] = datavalue;
+        }
+        snak
+    }
+
+    /// Adds `snaktype` and, for values, `value` to `params`
+    fn add_params(&self, params: &mut Vec<(String, String)>) {
+        params.push(("snaktype".to_string(), self.snaktype().to_string()));
+        if let Some(value) = self.value_json() {
+            params.push(("value".to_string(), value.to_string()));
+        }
+    }
+}
+
+/// Placeholder item ID returned by `Api::create_item` for a simulated dry-run edit
+pub const DRY_RUN_ITEM_ID: &str = "Q0";
+
+/// Placeholder statement ID (GUID) suffix returned by `Api::add_claim` for a simulated dry-run edit
+pub const DRY_RUN_GUID: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Placeholder reference hash returned by `Api::set_reference` for a simulated dry-run edit
+pub const DRY_RUN_REFERENCE_HASH: &str = "0000000000000000000000000000000000000000";
+
+/// Returns the string at `pointer` in an edit result, or `placeholder` if the edit was simulated in dry-run mode
+fn edit_result_str(
+    result: Value,
+    pointer: &str,
+    placeholder: impl FnOnce() -> String,
+) -> Result<String, MediaWikiError> {
+    if Api::is_dry_run_result(&result) {
+        return Ok(placeholder());
+    }
+    match result.pointer(pointer).and_then(|v| v.as_str()) {
+        Some(s) => Ok(s.to_string()),
+        None => Err(MediaWikiError::BadResponse(result)),
+    }
+}
+
+/// Returns a term map like `{"en":{"language":"en","value":"Foo"}}`, as used for labels and descriptions
+fn terms_json(terms: &[(&str, &str)]) -> Value {
+    Value::Object(
+        terms
+            .iter()
+            .map(|(language, value)| {
+                (
+                    language.to_string(),
+                    json!({"language": language, "value": value}),
+                )
+            })
+            .collect(),
+    )
+}
+
+impl Api {
+    /// Posts a Wikibase edit action, with token, `bot`, summary and `baserevid`
+    pub(crate) async fn wb_edit(
+        &self,
+        params: &[(&str, &str)],
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        let mut params: HashMap<String, String> = self.params_into(params);
+        params.insert("token".to_string(), self.get_edit_token().await?);
+        let summary = self
+            .edit_summary()
+            .apply(options.summary().unwrap_or_default());
+        if !summary.is_empty() {
+            params.insert("summary".to_string(), summary);
+        }
+        if let Some(baserevid) = options.baserevid() {
+            params.insert("baserevid".to_string(), baserevid.to_string());
+        }
+        let tags = match options.tags().is_empty() {
+            true => self.edit_tags(),
+            false => options.tags(),
+        };
+        if !tags.is_empty() {
+            params.insert("tags".to_string(), tags.join("|"));
+        }
+        if self.user().is_bot() {
+            params.insert("bot".to_string(), "1".to_string());
+        }
+        let result = self.post_query_api_json(&params).await?;
+        match result["success"].as_u64() {
+            Some(1) => Ok(result),
+            _ => Err(MediaWikiError::from_api_result(&result)
+                .unwrap_or(MediaWikiError::EditError(result))),
+        }
+    }
+
+    /// Creates a new item with labels, descriptions and statements, and returns its ID.
+    /// In dry-run mode, returns `DRY_RUN_ITEM_ID`.
+    pub async fn create_item(
+        &self,
+        labels: &[(&str, &str)],
+        descriptions: &[(&str, &str)],
+        claims: &[StatementOp],
+        options: &WbEditOptions,
+    ) -> Result<String, MediaWikiError> {
+        let mut data = statement_batch_data(claims);
+        data["labels"] = terms_json(labels);
+        data["descriptions"] = terms_json(descriptions);
+        let data = data.to_string();
+        let result = self
+            .wb_edit(
+                &[("action", "wbeditentity"), ("new", "item"), ("data", &data)],
+                options,
+            )
+            .await?;
+        edit_result_str(result, "/entity/id", || DRY_RUN_ITEM_ID.to_string())
+    }
+
+    /// Adds a statement with the `value` of a datavalue (e.g. `{"entity-type":"item","id":"Q5"}` or `"some string"`),
+    /// and returns the statement ID (GUID). In dry-run mode, returns a GUID ending in `DRY_RUN_GUID`.
+    pub async fn add_claim(
+        &self,
+        entity: &str,
+        property: &str,
+        value: &Value,
+        options: &WbEditOptions,
+    ) -> Result<String, MediaWikiError> {
+        let value = value.to_string();
+        let result = self
+            .wb_edit(
+                &[
+                    ("action", "wbcreateclaim"),
+                    ("entity", entity),
+                    ("property", property),
+                    ("snaktype", "value"),
+                    ("value", &value),
+                ],
+                options,
+            )
+            .await?;
+        edit_result_str(result, "/claim/id", || {
+            format!("{}${}", entity, DRY_RUN_GUID)
+        })
+    }
+
+    /// Removes the statement with the given ID (GUID)
+    pub async fn remove_claim(
+        &self,
+        guid: &str,
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        self.wb_edit(&[("action", "wbremoveclaims"), ("claim", guid)], options)
+            .await
+    }
+
+    /// Returns the statements of an entity via `wbgetclaims`, optionally only those for `property`
+    pub async fn get_claims(
+        &self,
+        entity: &str,
+        property: Option<&str>,
+    ) -> Result<Vec<Value>, MediaWikiError> {
+        let mut params = self.params_into(&[("action", "wbgetclaims"), ("entity", entity)]);
+        if let Some(property) = property {
+            params.insert("property".to_string(), property.to_string());
+        }
+        let result = self.get_query_api_json(&params).await?;
+        match result["claims"].as_object() {
+            Some(claims) => Ok(claims
+                .values()
+                .filter_map(|v| v.as_array())
+                .flatten()
+                .cloned()
+                .collect()),
+            None => Err(MediaWikiError::from_api_result(&result)
+                .unwrap_or(MediaWikiError::BadResponse(result))),
+        }
+    }
+
+    /// Sets the main value of the statement with the given ID (GUID)
+    pub async fn set_claim_value(
+        &self,
+        guid: &str,
+        value: &SnakValue,
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        let mut params = vec![
+            ("action".to_string(), "wbsetclaimvalue".to_string()),
+            ("claim".to_string(), guid.to_string()),
+        ];
+        value.add_params(&mut params);
+        self.wb_edit(&Self::str_pairs(&params), options).await
+    }
+
+    /// Adds a qualifier to the statement with the given ID (GUID), and returns the changed statement
+    pub async fn set_qualifier(
+        &self,
+        guid: &str,
+        property: &str,
+        value: &SnakValue,
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        let mut params = vec![
+            ("action".to_string(), "wbsetqualifier".to_string()),
+            ("claim".to_string(), guid.to_string()),
+            ("property".to_string(), property.to_string()),
+        ];
+        value.add_params(&mut params);
+        let result = self.wb_edit(&Self::str_pairs(&params), options).await?;
+        Ok(result["claim"].to_owned())
+    }
+
+    /// Adds a reference with `snaks` (property and value) to the statement with the given ID (GUID),
+    /// and returns the reference hash. In dry-run mode, returns `DRY_RUN_REFERENCE_HASH`.
+    pub async fn set_reference(
+        &self,
+        guid: &str,
+        snaks: &[(&str, SnakValue)],
+        options: &WbEditOptions,
+    ) -> Result<String, MediaWikiError> {
+        let mut snaks_json = serde_json::Map::new();
+        let mut order = vec![];
+        for (property, value) in snaks {
+            if !snaks_json.contains_key(*property) {
+                order.push(json!(property));
+            }
+            let entry = snaks_json
+                .entry(property.to_string())
+                .or_insert_with(|| json!([]));
+            if let Some(arr) = entry.as_array_mut() {
+                arr.push(value.snak_json(property));
+            }
+        }
+        let snaks_json = Value::Object(snaks_json).to_string();
+        let order = Value::Array(order).to_string();
+        let result = self
+            .wb_edit(
+                &[
+                    ("action", "wbsetreference"),
+                    ("statement", guid),
+                    ("snaks", &snaks_json),
+                    ("snaks-order", &order),
+                ],
+                options,
+            )
+            .await?;
+        edit_result_str(result, "/reference/hash", || {
+            DRY_RUN_REFERENCE_HASH.to_string()
+        })
+    }
+
+    /// Removes the references with the given hashes from the statement with the given ID (GUID)
+    pub async fn remove_references(
+        &self,
+        guid: &str,
+        hashes: &[&str],
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        let references = hashes.join("|");
+        self.wb_edit(
+            &[
+                ("action", "wbremovereferences"),
+                ("statement", guid),
+                ("references", &references),
+            ],
+            options,
+        )
+        .await
+    }
+
+    /// Returns `&str` pairs for owned parameter pairs
+    fn str_pairs(params: &[(String, String)]) -> Vec<(&str, &str)> {
+        params
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    /// Sets the label of an entity in a language; an empty value removes the label
+    pub async fn set_label(
+        &self,
+        entity: &str,
+        language: &str,
+        value: &str,
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        self.wb_edit(
+            &[
+                ("action", "wbsetlabel"),
+                ("id", entity),
+                ("language", language),
+                ("value", value),
+            ],
+            options,
+        )
+        .await
+    }
+
+    /// Sets the description of an entity in a language; an empty value removes the description
+    pub async fn set_description(
+        &self,
+        entity: &str,
+        language: &str,
+        value: &str,
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        self.wb_edit(
+            &[
+                ("action", "wbsetdescription"),
+                ("id", entity),
+                ("language", language),
+                ("value", value),
+            ],
+            options,
+        )
+        .await
+    }
+
+    /// Adds and removes aliases of an entity in a language
+    pub async fn set_aliases(
+        &self,
+        entity: &str,
+        language: &str,
+        add: &[&str],
+        remove: &[&str],
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        let add = add.join("|");
+        let remove = remove.join("|");
+        let mut params = vec![
+            ("action", "wbsetaliases"),
+            ("id", entity),
+            ("language", language),
+        ];
+        if !add.is_empty() {
+            params.push(("add", &add));
+        }
+        if !remove.is_empty() {
+            params.push(("remove", &remove));
+        }
+        self.wb_edit(&params, options).await
+    }
+
+    /// Sets the sitelink of an entity for a site (e.g. `enwiki`); an empty title removes the sitelink
+    pub async fn set_sitelink(
+        &self,
+        entity: &str,
+        site: &str,
+        title: &str,
+        options: &WbEditOptions,
+    ) -> Result<Value, MediaWikiError> {
+        self.wb_edit(
+            &[
+                ("action", "wbsetsitelink"),
+                ("id", entity),
+                ("linksite", site),
+                ("linktitle", title),
+            ],
+            options,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit_summary::EditSummary;
+    use crate::mock::MockResponses;
+
+    fn mocked_api() -> (Api, MockResponses) {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "wbeditentity")],
+                json!({"entity":{"id":"Q123","type":"item"},"success":1}),
+            )
+            .add(
+                &[("action", "wbcreateclaim")],
+                json!({"pageinfo":{"lastrevid":5},"success":1,"claim":{"id":"Q42$abc"}}),
+            )
+            .add(
+                &[("action", "wbremoveclaims")],
+                json!({"error":{"code":"invalid-guid","info":"Invalid claim guid"}}),
+            );
+        (Api::new_mocked(responses.clone()).unwrap(), responses)
+    }
+
+    #[tokio::test]
+    async fn claim_level_edits() {
+        let mut responses = MockResponses::new();
+        responses
+            .add(
+                &[("meta", "tokens")],
+                json!({"query":{"tokens":{"csrftoken":"abc+\\"}}}),
+            )
+            .add(
+                &[("action", "wbgetclaims")],
+                json!({"claims":{"P31":[{"id":"Q42$a","mainsnak":{"property":"P31"}}],
+                    "P21":[{"id":"Q42$b","mainsnak":{"property":"P21"}}]}}),
+            )
+            .add(
+                &[("action", "wbsetqualifier")],
+                json!({"pageinfo":{"lastrevid":6},"success":1,"claim":{"id":"Q42$a","qualifiers":{}}}),
+            )
+            .add(
+                &[("action", "wbsetreference")],
+                json!({"pageinfo":{"lastrevid":7},"success":1,"reference":{"hash":"d4e5"}}),
+            )
+            .add(&[("action", "wbsetclaimvalue")], json!({"success":1}))
+            .add(&[("action", "wbremovereferences")], json!({"success":1}));
+        let api = Api::new_mocked(responses.clone()).unwrap();
+        let options = WbEditOptions::new();
+        assert_eq!(api.get_claims("Q42", None).await.unwrap().len(), 2);
+
+        let claim = api
+            .set_qualifier("Q42$a", "P580", &SnakValue::NoValue, &options)
+            .await
+            .unwrap();
+        assert_eq!(claim["id"], "Q42$a");
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["snaktype"], "novalue");
+        assert!(!request.contains_key("value"));
+
+        let snaks = [
+            ("P854", SnakValue::String("https://example.org".to_string())),
+            ("P248", SnakValue::Item("Q36578".to_string())),
+        ];
+        let hash = api.set_reference("Q42$a", &snaks, &options).await.unwrap();
+        assert_eq!(hash, "d4e5");
+        let request = responses.requests().pop().unwrap();
+        let sent: Value = serde_json::from_str(&request["snaks"]).unwrap();
+        assert_eq!(sent["P854"][0]["datavalue"]["value"], "https://example.org");
+        assert_eq!(sent["P248"][0]["datavalue"]["type"], "wikibase-entityid");
+        assert_eq!(request["snaks-order"], r#"["P854","P248"]"#);
+
+        api.set_claim_value("Q42$a", &SnakValue::Item("Q5".to_string()), &options)
+            .await
+            .unwrap();
+        let request = responses.requests().pop().unwrap();
+        assert_eq!(request["value"], r#"{"entity-type":"item","id":"Q5"}"#);
+        api.remove_references("Q42$a", &["d4e5", "f6"], &options)
+            .await
+            .unwrap();
+        assert_eq!(responses.requests().pop().unwrap()["references"], "d4e5|f6");
+    }
+
+    #[tokio::test]
+    async fn create_item() {
+        let (mut api, responses) = mocked_api();
+        api.set_edit_summary(EditSummary::with_tool("mytool", Some("1.2")));
+        let mut options = WbEditOptions::new();
+        options.set_summary(Some("new item"));
+        options.set_tags(&["bot-edit", "mytool"]);
+        let id = api
+            .create_item(
+                &[("en", "Foo")],
+                &[("en", "a foo")],
+                &[StatementOp::add_item("P31", "Q5")],
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(id, "Q123");
+        let request = responses.requests().pop().unwrap();
+        let data: Value = serde_json::from_str(&request["data"]).unwrap();
+        assert_eq!(data["labels"]["en"]["value"], "Foo");
+        assert_eq!(data["claims"][0]["mainsnak"]["property"], "P31");
+        assert_eq!(request["token"], "abc+\\");
+        assert_eq!(request["summary"], "new item #mytool v1.2");
+        assert_eq!(request["tags"], "bot-edit|mytool");
+    }
+
+    #[tokio::test]
+    async fn dry_run_placeholders() {
+        let (mut api, responses) = mocked_api();
+        api.set_dry_run(true);
+        let options = WbEditOptions::new();
+        let id = api
+            .create_item(&[("en", "Foo")], &[], &[], &options)
+            .await
+            .unwrap();
+        assert_eq!(id, DRY_RUN_ITEM_ID);
+        let guid = api
+            .add_claim(
+                &id,
+                "P31",
+                &json!({"entity-type":"item","id":"Q5"}),
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(guid, format!("Q0${}", DRY_RUN_GUID));
+        let snaks = [("P248", SnakValue::Item("Q36578".to_string()))];
+        let hash = api.set_reference(&guid, &snaks, &options).await.unwrap();
+        assert_eq!(hash, DRY_RUN_REFERENCE_HASH);
+        let actions: Vec<String> = api
+            .dry_run_requests()
+            .iter()
+            .map(|r| r["action"].to_string())
+            .collect();
+        assert_eq!(actions, ["wbeditentity", "wbcreateclaim", "wbsetreference"]);
+        // Only the token was fetched from the wiki
+        assert_eq!(responses.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn add_and_remove_claim() {
+        let (api, responses) = mocked_api();
+        let mut options = WbEditOptions::new();
+        options.set_baserevid(Some(4));
+        let guid = api
+            .add_claim(
+                "Q42",
+                "P31",
+                &json!({"entity-type":"item","id":"Q5"}),
+                &options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(guid, "Q42$abc");
+        assert_eq!(responses.requests().pop().unwrap()["baserevid"], "4");
+        assert!(matches!(
+            api.remove_claim("nope", &WbEditOptions::new()).await,
+            Err(MediaWikiError::Api { .. })
+        ));
+    }
+}